@@ -0,0 +1,133 @@
+/* combustion: chess rules and engine. generated by cbindgen from src/ffi.rs. */
+
+#ifndef COMBUSTION_H
+#define COMBUSTION_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/*
+ * Boards and engines are opaque handles, made and freed by the functions below.
+ *
+ * Strings passed in are nul terminated UTF-8 and only read during the call. Strings
+ * returned are owned by the caller and must be freed with combustion_string_free.
+ *
+ * Moves are in coordinate notation, like "e2e4", "e1g1" or "e7e8q". Moves passed in
+ * may also be in SAN, like "Nf3" or "O-O".
+ *
+ * A function that fails returns NULL or -1, and combustion_last_error says why. Passing
+ * a NULL handle is a failure, not undefined behaviour; passing a freed one is.
+ *
+ * A board or engine may be used from any thread, but not from two at once, except that
+ * combustion_engine_stop may be called while combustion_engine_search runs.
+ *
+ * The engine functions need the crate's default "engine" feature.
+ */
+
+typedef enum CombustionStatus {
+  COMBUSTION_STATUS_IN_PROGRESS = 0,
+  COMBUSTION_STATUS_CHECKMATE = 1,
+  COMBUSTION_STATUS_STALEMATE = 2,
+  COMBUSTION_STATUS_CHECK = 3,
+  COMBUSTION_STATUS_DRAW_BY_FIFTY_MOVE = 4,
+  COMBUSTION_STATUS_DRAW_BY_INSUFFICIENT_MATERIAL = 5,
+} CombustionStatus;
+
+typedef struct CombustionBoard CombustionBoard;
+
+/*
+ * A search engine keeps its transposition table between searches, and can be stopped
+ * from another thread while it searches.
+ */
+typedef struct CombustionEngine CombustionEngine;
+
+/*
+ * When to stop searching, at whichever comes first. Zero means no limit, and with no
+ * limits at all the search goes on until combustion_engine_stop.
+ */
+typedef struct CombustionLimits {
+  uint32_t depth;
+  uint64_t nodes;
+  uint64_t movetime_ms;
+  /* look only for a mate in at most this many moves */
+  uint32_t mate;
+} CombustionLimits;
+
+/*
+ * The progress of a search after each iteration. pv is only valid during the callback.
+ */
+typedef struct CombustionInfo {
+  uint32_t depth;
+  uint32_t seldepth;
+  /* centipawns for the side to move, or +-(100000 + n) for mate in n moves */
+  int32_t score;
+  uint64_t nodes;
+  uint64_t time_ms;
+  uint64_t nps;
+  const char *pv;
+} CombustionInfo;
+
+typedef void (*CombustionInfoCallback)(const CombustionInfo *info, void *user);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ * The message for the last call on this thread that failed, or NULL. It stays valid
+ * until the next failure on this thread.
+ */
+const char *combustion_last_error(void);
+
+void combustion_string_free(char *s);
+
+/* the initial position */
+CombustionBoard *combustion_board_new(void);
+
+CombustionBoard *combustion_board_from_fen(const char *fen);
+
+CombustionBoard *combustion_board_clone(const CombustionBoard *board);
+
+void combustion_board_free(CombustionBoard *board);
+
+char *combustion_board_fen(const CombustionBoard *board);
+
+/* the legal moves, in coordinate notation separated by spaces */
+char *combustion_board_legal_moves(const CombustionBoard *board);
+
+/* plays a legal move on the board, returning 0, or -1 if it isn't legal */
+int combustion_board_make_move(CombustionBoard *board, const char *mv);
+
+/* a legal move in the board, given in coordinate notation or SAN, written in SAN */
+char *combustion_board_san(const CombustionBoard *board, const char *mv);
+
+CombustionStatus combustion_board_status(const CombustionBoard *board);
+
+CombustionEngine *combustion_engine_new(void);
+
+void combustion_engine_free(CombustionEngine *engine);
+
+/* forget everything learned in earlier searches, as for a new game */
+void combustion_engine_clear(const CombustionEngine *engine);
+
+/*
+ * The best move in the board, in coordinate notation, or NULL if the game is over.
+ * info, if not NULL, is called with user after each iteration. Blocks until a limit is
+ * reached or combustion_engine_stop. limits may be NULL to search until stopped.
+ */
+char *combustion_engine_search(const CombustionEngine *engine,
+                               const CombustionBoard *board,
+                               const CombustionLimits *limits,
+                               CombustionInfoCallback info,
+                               void *user);
+
+/* makes a search running on another thread return its best move so far */
+void combustion_engine_stop(const CombustionEngine *engine);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* COMBUSTION_H */
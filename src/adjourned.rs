@@ -0,0 +1,173 @@
+use crate::engine_options::{EngineOptions, OptionValue};
+use crate::game::Game;
+use crate::moves::Move;
+use crate::piece::Color;
+use crate::time_manager::TimeManager;
+use crate::util::ChessError;
+
+use std::fmt;
+use std::fs;
+
+// everything the engine needs to carry on with a game after a restart: the game from its
+// start, the side we play, both clocks in centiseconds, the time control and the
+// options. saved as name = value lines, like the weights files.
+#[derive(Debug, Clone)]
+pub struct Adjourned {
+    pub game: Game,
+    pub color: Color,
+    pub clocks: [isize; 2],
+    pub time_manager: TimeManager,
+    pub options: EngineOptions,
+}
+
+impl Adjourned {
+    pub fn parse(s: &str) -> Result<Adjourned, ChessError> {
+        let mut game = None;
+        let mut moves = Vec::new();
+        let mut color = Color::Black;
+        let mut clocks = [0, 0];
+        let mut time_manager = TimeManager::new();
+        let mut options = EngineOptions::new();
+        for line in s.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let (name, value) = match line.split_once('=') {
+                Some((name, value)) => (name.trim(), value.trim()),
+                None => parse_error!("[Adjourned::parse] no value in \"{}\"", line),
+            };
+            let numbers = value
+                .split_whitespace()
+                .map(|n| n.parse::<isize>())
+                .collect::<Result<Vec<isize>, _>>();
+            match (name, numbers.as_deref()) {
+                ("fen", _) => game = Some(Game::from_fen(value)?),
+                ("moves", _) => moves = value.split_whitespace().collect(),
+                ("color", _) => {
+                    color = match value {
+                        "white" => Color::White,
+                        "black" => Color::Black,
+                        _ => parse_error!("[Adjourned::parse] no color \"{}\"", value),
+                    }
+                }
+                ("time", Ok(&[white, black])) => clocks = [white, black],
+                ("level", Ok(&[mps, inc])) if mps >= 0 => time_manager.set_level(mps as usize, inc),
+                ("st", Ok(&[csecs])) => time_manager.set_move_time(csecs),
+                _ => match name.strip_prefix("option ") {
+                    Some(option) => options.set(option.trim(), value)?,
+                    None => parse_error!("[Adjourned::parse] can't read \"{}\"", line),
+                },
+            }
+        }
+        let mut game = match game {
+            Some(game) => game,
+            None => parse_error!("[Adjourned::parse] no fen"),
+        };
+        for mv in moves {
+            let mv = Move::from_xboard_format(mv, game.board())?;
+            game.make_move(&mv)?;
+        }
+        Ok(Adjourned {
+            game,
+            color,
+            clocks,
+            time_manager,
+            options,
+        })
+    }
+
+    pub fn load(path: &str) -> Result<Adjourned, ChessError> {
+        match fs::read_to_string(path) {
+            Ok(s) => Adjourned::parse(&s),
+            Err(e) => parse_error!("[Adjourned::load] can't read {}: {}", path, e),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), ChessError> {
+        match fs::write(path, self.to_string()) {
+            Ok(()) => Ok(()),
+            Err(e) => parse_error!("[Adjourned::save] can't write {}: {}", path, e),
+        }
+    }
+}
+
+impl fmt::Display for Adjourned {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "fen = {}", self.game.start().to_fen())?;
+        let moves: Vec<String> = self
+            .game
+            .moves()
+            .iter()
+            .zip(self.game.positions())
+            .map(|(mv, b)| mv.to_xboard_format(b.color_to_move))
+            .collect();
+        writeln!(f, "moves = {}", moves.join(" "))?;
+        writeln!(f, "color = {}", self.color)?;
+        writeln!(f, "time = {} {}", self.clocks[0], self.clocks[1])?;
+        let (mps, inc) = self.time_manager.level();
+        writeln!(f, "level = {} {}", mps, inc)?;
+        if let Some(csecs) = self.time_manager.move_time() {
+            writeln!(f, "st = {}", csecs)?;
+        }
+        for option in self.options.iter() {
+            match &option.value {
+                OptionValue::Check(b) => writeln!(f, "option {} = {}", option.name, b)?,
+                OptionValue::Spin { value, .. } => {
+                    writeln!(f, "option {} = {}", option.name, value)?
+                }
+                OptionValue::Text(s) => writeln!(f, "option {} = {}", option.name, s)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::adjourned::Adjourned;
+    use crate::engine_options::{EngineOptions, CONTEMPT, SKILL_LEVEL};
+    use crate::game::Game;
+    use crate::moves::Move;
+    use crate::piece::Color;
+    use crate::time_manager::TimeManager;
+
+    #[test]
+    fn adjourn_and_resume() {
+        let mut game = Game::new();
+        for mv in [
+            "e2e4", "d7d5", "e4d5", "c7c5", "d5c6e.p.", "g8f6", "c6b7", "f6d5", "b7a8q",
+        ]
+        .iter()
+        {
+            let mv = Move::from_xboard_format(mv, game.board()).unwrap();
+            game.make_move(&mv).unwrap();
+        }
+        let mut time_manager = TimeManager::new();
+        time_manager.set_level(40, 200);
+        let mut options = EngineOptions::new();
+        options.set(CONTEMPT, "-25").unwrap();
+        options.set(SKILL_LEVEL, "7").unwrap();
+        let adjourned = Adjourned {
+            game,
+            color: Color::White,
+            clocks: [12000, 9050],
+            time_manager,
+            options,
+        };
+
+        let s = adjourned.to_string();
+        assert!(s.contains("moves = e2e4 d7d5 e4d5 c7c5 d5c6e.p. g8f6 c6b7 f6d5 b7a8q\n"));
+        assert!(s.contains("option Skill Level = 7\n"));
+        let resumed = Adjourned::parse(&s).unwrap();
+        assert_eq!(resumed.game.board(), adjourned.game.board());
+        assert_eq!(resumed.game.moves(), adjourned.game.moves());
+        assert_eq!(resumed.color, Color::White);
+        assert_eq!(resumed.clocks, [12000, 9050]);
+        assert_eq!(resumed.time_manager.level(), (40, 200));
+        assert_eq!(resumed.options, adjourned.options);
+        assert_eq!(resumed.to_string(), s);
+
+        assert!(Adjourned::parse("moves = e2e4").is_err());
+        assert!(Adjourned::parse("fen = 4k3/8/8/8/8/8/8/4K3 w - - 0 1\ncolor = green").is_err());
+        let start = "fen = rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1\n";
+        assert!(Adjourned::parse(&format!("{}moves = e2e5", start)).is_err());
+        assert!(Adjourned::parse(&format!("{}option Hash = -1", start)).is_err());
+    }
+}
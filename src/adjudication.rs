@@ -0,0 +1,172 @@
+use crate::game::Game;
+use crate::pgn::PgnGame;
+use crate::piece::Color;
+
+use std::fmt;
+
+// how an engine game was stopped before it ended by the rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Adjudication {
+    // the loser, who resigned
+    Resign(Color),
+    Draw,
+}
+
+impl Adjudication {
+    // "1-0", "0-1" or "1/2-1/2"
+    pub fn score(&self) -> &'static str {
+        match *self {
+            Adjudication::Resign(Color::Black) => "1-0",
+            Adjudication::Resign(Color::White) => "0-1",
+            Adjudication::Draw => "1/2-1/2",
+        }
+    }
+
+    pub fn reason(&self) -> &'static str {
+        match *self {
+            Adjudication::Resign(Color::White) => "White resigns",
+            Adjudication::Resign(Color::Black) => "Black resigns",
+            Adjudication::Draw => "Draw by adjudication",
+        }
+    }
+
+    // the game as pgn with this result, and a termination tag saying it was adjudicated
+    pub fn to_pgn(&self, game: Game, headers: Vec<(String, String)>) -> PgnGame {
+        let mut headers: Vec<(String, String)> = headers
+            .into_iter()
+            .filter(|(name, _)| name != "Result")
+            .collect();
+        headers.push(("Result".to_string(), self.score().to_string()));
+        headers.push(("Termination".to_string(), "adjudication".to_string()));
+        let mut pgn = PgnGame::from_game(game, headers, self.score());
+        if let Some(last) = pgn.line.moves.last_mut() {
+            last.comments.push(self.reason().to_string());
+        }
+        pgn
+    }
+}
+
+// as xboard wants it: 1-0 {Black resigns}
+impl fmt::Display for Adjudication {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {{{}}}", self.score(), self.reason())
+    }
+}
+
+// watches the scores the engines give for their moves in a match, to stop games whose
+// result is clear. a side resigns once both engines have agreed it is lost by at least
+// resign_score for resign_moves moves each, and a game is drawn once both have scored it
+// within draw_score of even for draw_moves moves each, from move draw_after on.
+#[derive(Debug, Clone)]
+pub struct Adjudicator {
+    pub resign_score: isize,
+    pub resign_moves: usize,
+    pub draw_score: isize,
+    pub draw_moves: usize,
+    pub draw_after: usize,
+    // every score so far, for white
+    scores: Vec<isize>,
+}
+
+impl Adjudicator {
+    pub fn new() -> Adjudicator {
+        Adjudicator {
+            resign_score: 600,
+            resign_moves: 4,
+            draw_score: 10,
+            draw_moves: 8,
+            draw_after: 40,
+            scores: Vec::new(),
+        }
+    }
+
+    // for a new game
+    pub fn clear(&mut self) {
+        self.scores.clear();
+    }
+
+    // the score the engine that moved gave, for its own side, after the move made
+    // move_number. returns the adjudication once there is one.
+    pub fn record(
+        &mut self,
+        mover: Color,
+        score: isize,
+        move_number: usize,
+    ) -> Option<Adjudication> {
+        self.scores.push(match mover {
+            Color::White => score,
+            Color::Black => -score,
+        });
+        let last = |n: usize| match self.scores.len().checked_sub(2 * n) {
+            Some(start) if n > 0 => &self.scores[start..],
+            _ => &[],
+        };
+
+        let resign = last(self.resign_moves);
+        if !resign.is_empty() {
+            if resign.iter().all(|&s| s <= -self.resign_score) {
+                return Some(Adjudication::Resign(Color::White));
+            }
+            if resign.iter().all(|&s| s >= self.resign_score) {
+                return Some(Adjudication::Resign(Color::Black));
+            }
+        }
+        let draw = last(self.draw_moves);
+        if move_number >= self.draw_after
+            && !draw.is_empty()
+            && draw.iter().all(|&s| s.abs() <= self.draw_score)
+        {
+            return Some(Adjudication::Draw);
+        }
+        None
+    }
+}
+
+impl Default for Adjudicator {
+    fn default() -> Adjudicator {
+        Adjudicator::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::adjudication::{Adjudication, Adjudicator};
+    use crate::game::Game;
+    use crate::moves::Move;
+    use crate::piece::Color;
+
+    #[test]
+    fn adjudication() {
+        let mut adj = Adjudicator::new();
+        // white thinks it is winning, but black doesn't agree until later
+        for n in 1..=4 {
+            assert_eq!(adj.record(Color::White, 700, n), None);
+            assert_eq!(adj.record(Color::Black, 0, n), None);
+        }
+        for n in 5..=7 {
+            assert_eq!(adj.record(Color::White, 700, n), None);
+            assert_eq!(adj.record(Color::Black, -650, n), None);
+        }
+        assert_eq!(adj.record(Color::White, 800, 8), None);
+        assert_eq!(
+            adj.record(Color::Black, -900, 8),
+            Some(Adjudication::Resign(Color::Black))
+        );
+
+        // dead even for long enough, but only late in the game
+        adj.clear();
+        for n in 1..=39 {
+            assert_eq!(adj.record(Color::White, 5, n), None);
+            assert_eq!(adj.record(Color::Black, -5, n), None);
+        }
+        assert_eq!(adj.record(Color::White, 0, 40), Some(Adjudication::Draw));
+
+        let mut game = Game::new();
+        game.make_move(&mv!("e2-e4")).unwrap();
+        let headers = vec![("Result".to_string(), "*".to_string())];
+        let pgn = Adjudication::Resign(Color::White).to_pgn(game, headers);
+        assert_eq!(pgn.header("Result"), Some("0-1"));
+        assert_eq!(pgn.header("Termination"), Some("adjudication"));
+        assert!(pgn.to_string().ends_with("1. e4 {White resigns} 0-1\n"));
+    }
+}
@@ -0,0 +1,117 @@
+use crate::board::Board;
+use crate::board_alpha_beta::{SearchResult, SearchStats};
+use crate::search_limits::SearchLimits;
+use crate::transposition_table::TranspositionTable;
+use crate::util::ChessError;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// plies from the root, as xboard's sd counts them
+pub const DEFAULT_DEPTH: usize = 5;
+
+// openings, middlegames and endings, some quiet and some sharp
+pub const POSITIONS: [&str; 40] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 10",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 11",
+    "4rrk1/pp1n3p/3q2pQ/2p1pb2/2PP4/2P3N1/P2B2PP/4RRK1 b - - 7 19",
+    "rq3rk1/ppp2ppp/1bnpb3/3N2B1/3NP3/7P/PPPQ1PP1/2KR3R w - - 7 14",
+    "r1bq1r1k/1pp1n1pp/1p1p4/4p2Q/4Pp2/1BNP4/PPP2PPP/3R1RK1 w - - 2 14",
+    "r3r1k1/2p2ppp/p1p1bn2/8/1q2P3/2NPQN2/PPP3PP/R4RK1 b - - 2 15",
+    "r1bbk1nr/pp3p1p/2n5/1N4p1/2Np1B2/8/PPP2PPP/2KR1B1R w kq - 0 13",
+    "r1bq1rk1/ppp1nppp/4n3/3p3Q/3P4/1BP1B3/PP1N2PP/R4RK1 w - - 1 16",
+    "4r1k1/r1q2ppp/ppp2n2/4P3/5Rb1/1N1BQ3/PPP3PP/R5K1 w - - 1 17",
+    "2rqkb1r/ppp2p2/2npb1p1/1N1Nn2p/2P1PP2/8/PP2B1PP/R1BQK2R b KQ - 0 11",
+    "r1bq1r1k/b1p1npp1/p2p3p/1p6/3PP3/1B2NN2/PP3PPP/R2Q1RK1 w - - 1 16",
+    "3r1rk1/p5pp/bpp1pp2/8/q1PP1P2/b3P3/P2NQRPP/1R2B1K1 b - - 6 22",
+    "r1q2rk1/2p1bppp/2Pp4/p6b/Q1PNp3/4B3/PP1R1PPP/2K4R w - - 2 18",
+    "4k2r/1pb2ppp/1p2p3/1R1p4/3P4/2r1PN2/P4PPP/1R4K1 b - - 3 22",
+    "3q2k1/pb3p1p/4pbp1/2r5/PpN2N2/1P2P2P/5PP1/Q2R2K1 b - - 4 26",
+    "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+    "rnbqkb1r/pp2pppp/3p1n2/8/3NP3/8/PPP2PPP/RNBQKB1R w KQkq - 1 5",
+    "r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/3P1N2/PPP2PPP/RNBQK2R w KQkq - 1 5",
+    "rnbqkb1r/ppp1pppp/5n2/3p4/2PP4/8/PP2PPPP/RNBQKBNR w KQkq - 1 3",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "2r2rk1/1bqnbpp1/1p1ppn1p/pP6/N1P1P3/P2B1N1P/1B2QPP1/R2R2K1 b - - 0 1",
+    "3rr1k1/pp3pp1/1qn2np1/8/3p4/PP1R1P2/2P1NQPP/R1B3K1 b - - 0 1",
+    "6k1/3b3r/1p1p4/p1n2p2/1PPNpP1q/P3Q1p1/1R1RB1P1/5K2 b - - 0 1",
+    "r2r1n2/pp2bk2/2p1p2p/3q4/3PN1QP/2P3R1/P4PP1/5RK1 w - - 0 1",
+    "1K6/2P5/1p3P2/1k2P3/1qnP1B2/3Q4/8/8 b - - 0 1",
+    "6k1/6p1/6Pp/ppp5/3pn2P/1P3K2/1PP2P2/8 b - - 0 1",
+    "8/8/8/8/5kp1/P7/8/1K1N4 w - - 0 1",
+    "8/8/8/5N2/8/p7/8/2NK3k w - - 0 1",
+    "8/3k4/8/8/8/4BN2/4K3/8 w - - 0 1",
+    "8/8/1P6/5pr1/8/4R3/7k/2K5 w - - 0 1",
+    "8/2p4P/8/kr6/6R1/8/8/1K6 w - - 0 1",
+    "8/8/3P3k/8/1p6/8/1P6/1K3n2 b - - 0 1",
+    "8/R7/2q5/8/6k1/8/1P5p/K6R w - - 0 124",
+    "1K1k4/1P6/8/8/8/8/r7/2R5 w - - 0 1",
+    "8/8/8/8/4k3/8/3KP3/8 w - - 0 1",
+    "8/k7/3p4/p2P1p2/P2P1P2/8/8/K7 w - - 0 1",
+    "8/p7/8/1P6/K1k3p1/6P1/7P/8 w - - 0 1",
+    "8/8/4k3/8/2p5/8/B2K4/8 w - - 0 1",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Bench {
+    // the same on every run and machine for the same code, so it signs what the search
+    // does: a refactor that changes it has changed the search
+    pub nodes: u64,
+    pub time: Duration,
+    // summed over the positions
+    pub stats: SearchStats,
+}
+
+impl Bench {
+    pub fn nps(&self) -> u64 {
+        let micros = self.time.as_micros() as u64;
+        (self.nodes * 1_000_000).checked_div(micros).unwrap_or(0)
+    }
+}
+
+// search each of the positions to depth plies, on one thread with a fresh table each
+// time so nothing carries over, calling report with each result
+pub fn bench(
+    depth: usize,
+    report: &mut dyn FnMut(usize, &SearchResult),
+) -> Result<Bench, ChessError> {
+    let limits = SearchLimits::depth(depth.saturating_sub(1));
+    let mut total = Bench::default();
+    for (i, fen) in POSITIONS.iter().enumerate() {
+        let mut b = Board::from_fen(fen)?;
+        let tt = Arc::new(TranspositionTable::new());
+        let start = Instant::now();
+        let res = b.search(&limits, None, Some(tt))?;
+        total.time += start.elapsed();
+        total.nodes += res.nodes;
+        total.stats.add(&res.stats);
+        report(i, &res);
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bench::{bench, POSITIONS};
+    use crate::board::Board;
+
+    #[test]
+    fn bench_positions() {
+        for fen in POSITIONS.iter() {
+            let b = Board::from_fen(fen).unwrap();
+            assert!(!b.status().is_over(), "{} is over", fen);
+        }
+
+        let mut moves = Vec::new();
+        let first = bench(2, &mut |_, res| moves.push(res.mv)).unwrap();
+        assert_eq!(moves.len(), POSITIONS.len());
+        let second = bench(2, &mut |_, _| {}).unwrap();
+        assert_eq!(first.nodes, second.nodes);
+        assert!(first.nodes > 0);
+        assert_eq!(first.stats, second.stats);
+        assert_eq!(first.stats.nodes, first.nodes);
+        assert!(first.stats.first_move_cutoffs <= first.stats.cutoffs);
+    }
+}
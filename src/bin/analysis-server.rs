@@ -0,0 +1,275 @@
+extern crate combustion;
+
+use combustion::board::Board;
+use combustion::board_alpha_beta::SearchResult;
+use combustion::moves::coordinates;
+use combustion::search_limits::{SearchLimits, MAX_DEPTH};
+use combustion::transposition_table::{TranspositionTable, DEFAULT_MEGABYTES};
+
+use std::env;
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::process::exit;
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use getopts::Options;
+use tungstenite::error::ProtocolError;
+use tungstenite::{accept, Error, Message};
+
+// how often a connection looks for new analysis to send while waiting for messages
+const POLL: Duration = Duration::from_millis(20);
+
+// the most lines multipv may ask for
+const MAX_LINES: usize = 64;
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!(
+        "Usage: {} [OPTIONS]\n\n\
+         Analyses positions for web pages over websockets, one session to a connection.\n\
+         Send \"start FEN\" to analyse a position until \"stop\", or until another start.\n\
+         Each deeper iteration comes back as a line of json with the depth, score, nodes,\n\
+         nps and pv, and the end of the analysis as {{\"bestmove\":\"e2e4\"}}.\n\
+         Send \"multipv N\" before a start to see the best N moves, each with its own\n\
+         line of json a depth, numbered from 1 by \"multipv\".",
+        program
+    );
+    print!("{}", opts.usage(&brief));
+    exit(0);
+}
+
+fn json_error(msg: &str) -> String {
+    format!(
+        "{{\"error\":\"{}\"}}",
+        msg.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+// the analysis for one connection. the transposition table is kept between positions,
+// since an analysis board mostly steps through a single game.
+struct Session {
+    table: Arc<TranspositionTable>,
+    abort: Arc<RwLock<bool>>,
+    search: Option<JoinHandle<()>>,
+    // the best moves to show each with its line
+    lines: usize,
+}
+
+impl Session {
+    fn new(megabytes: usize) -> Session {
+        Session {
+            table: Arc::new(TranspositionTable::with_megabytes(megabytes)),
+            abort: Arc::new(RwLock::new(false)),
+            search: None,
+            lines: 1,
+        }
+    }
+
+    fn start(&mut self, fen: &str, out: &Sender<String>) -> Result<(), String> {
+        let mut b = Board::from_fen(fen).map_err(|e| e.to_string())?;
+        self.stop();
+        // a fresh flag, so a search still winding down can't be restarted by it
+        self.abort = Arc::new(RwLock::new(false));
+        let (abort, table, out) = (self.abort.clone(), self.table.clone(), out.clone());
+        if self.lines > 1 {
+            let lines = self.lines;
+            self.search = Some(thread::spawn(move || {
+                multipv(b, lines, abort, table, out);
+            }));
+            return Ok(());
+        }
+        self.search = Some(thread::spawn(move || {
+            let root = b.clone();
+            let mut report = |res: &SearchResult| {
+                let _ = out.send(res.to_json(&root));
+            };
+            let done = match b.search_with(
+                &SearchLimits::infinite(),
+                Some(abort),
+                Some(table),
+                &mut report,
+            ) {
+                Ok(res) => format!("{{\"bestmove\":\"{}\"}}", coordinates(&res.mv, &root)),
+                Err(e) => json_error(&e.to_string()),
+            };
+            let _ = out.send(done);
+        }));
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        *self.abort.write().unwrap() = true;
+        if let Some(search) = self.search.take() {
+            let _ = search.join();
+        }
+    }
+
+    fn handle(&mut self, msg: &str, out: &Sender<String>) -> Result<(), String> {
+        let msg = msg.trim();
+        let (command, rest) = msg.split_once(' ').unwrap_or((msg, ""));
+        match command {
+            "start" => self.start(rest.trim(), out),
+            "stop" => {
+                self.stop();
+                Ok(())
+            }
+            "multipv" => match rest.trim().parse::<usize>() {
+                Ok(n) if (1..=MAX_LINES).contains(&n) => {
+                    self.lines = n;
+                    Ok(())
+                }
+                _ => Err(format!("multipv takes a number from 1 to {}", MAX_LINES)),
+            },
+            _ => Err(format!("unknown command: {}", command)),
+        }
+    }
+}
+
+// analyse b until aborted, a depth at a time, finding each of the best lines moves in
+// turn by searching again without the moves already found. the table makes the repeated
+// shallower iterations cheap.
+fn multipv(
+    mut b: Board,
+    lines: usize,
+    abort: Arc<RwLock<bool>>,
+    table: Arc<TranspositionTable>,
+    out: Sender<String>,
+) {
+    let root = b.clone();
+    let legal = root.legal_moves();
+    let mut best = None;
+    'deepening: for depth in 0..=MAX_DEPTH {
+        let mut found = Vec::new();
+        for line in 1..=lines.min(legal.len()).max(1) {
+            let limits = SearchLimits {
+                depth: Some(depth),
+                search_moves: legal
+                    .iter()
+                    .filter(|&mv| !found.contains(mv))
+                    .cloned()
+                    .collect(),
+                ..SearchLimits::default()
+            };
+            let res = b.search_with(
+                &limits,
+                Some(abort.clone()),
+                Some(table.clone()),
+                &mut |_| {},
+            );
+            // an aborted search's line is cut short, so it isn't shown
+            if *abort.read().unwrap() {
+                break 'deepening;
+            }
+            match res {
+                Ok(res) => {
+                    let json = res.to_json(&root);
+                    let _ = out.send(format!("{{\"multipv\":{},{}", line, &json[1..]));
+                    if line == 1 {
+                        best = Some(res.mv);
+                    }
+                    found.push(res.mv);
+                }
+                Err(e) => {
+                    let _ = out.send(json_error(&e.to_string()));
+                    return;
+                }
+            }
+        }
+    }
+    if let Some(mv) = best {
+        let _ = out.send(format!("{{\"bestmove\":\"{}\"}}", coordinates(&mv, &root)));
+    }
+}
+
+fn timed_out(e: &Error) -> bool {
+    match e {
+        Error::Io(e) => {
+            e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut
+        }
+        _ => false,
+    }
+}
+
+// waiting for messages only as long as POLL, so analysis goes out as it comes in
+fn serve(stream: TcpStream, megabytes: usize) -> Result<(), String> {
+    let mut socket = accept(stream).map_err(|e| e.to_string())?;
+    socket
+        .get_ref()
+        .set_read_timeout(Some(POLL))
+        .map_err(|e| e.to_string())?;
+    let (out, analysis) = channel();
+    let mut session = Session::new(megabytes);
+    let served = 'session: loop {
+        let mut replies: Vec<String> = analysis.try_iter().collect();
+        match socket.read() {
+            Ok(Message::Text(msg)) => {
+                if let Err(e) = session.handle(&msg, &out) {
+                    replies.push(json_error(&e));
+                }
+            }
+            Ok(_) => (),
+            Err(ref e) if timed_out(e) => (),
+            Err(Error::ConnectionClosed)
+            | Err(Error::Protocol(ProtocolError::ResetWithoutClosingHandshake)) => break Ok(()),
+            Err(e) => break Err(e.to_string()),
+        }
+        for reply in replies {
+            if let Err(e) = socket.send(Message::Text(reply)) {
+                break 'session Err(e.to_string());
+            }
+        }
+    };
+    session.stop();
+    served
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let mut options = Options::new();
+    options.optflag("h", "help", "Print this help menu.");
+    options.optopt(
+        "a",
+        "address",
+        "Where to listen for connections (default: 127.0.0.1:8080).",
+        "ADDRESS",
+    );
+    options.optopt(
+        "m",
+        "hash",
+        "Megabytes of transposition table for each session.",
+        "MB",
+    );
+    let opts = options.parse(&args[1..]).unwrap();
+    if opts.opt_present("h") {
+        print_usage(&args[0], options);
+    }
+
+    let address = opts
+        .opt_str("a")
+        .unwrap_or_else(|| "127.0.0.1:8080".to_string());
+    let megabytes = opts.opt_str("m").map_or(DEFAULT_MEGABYTES, |s| {
+        s.parse::<usize>().expect("MB should be a number!")
+    });
+    let listener = TcpListener::bind(&address).unwrap_or_else(|e| {
+        eprintln!("can't listen on {}: {}", address, e);
+        exit(1);
+    });
+    println!("listening on ws://{}", address);
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                thread::spawn(move || {
+                    let peer = stream
+                        .peer_addr()
+                        .map_or("?".to_string(), |addr| addr.to_string());
+                    if let Err(e) = serve(stream, megabytes) {
+                        eprintln!("{}: {}", peer, e);
+                    }
+                });
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+}
@@ -1,28 +1,62 @@
 extern crate combustion;
-extern crate num_cpus;
 
-use combustion::board::Board;
-use combustion::threadpool::Threadpool;
+use combustion::bench::{bench, DEFAULT_DEPTH, POSITIONS};
 
-use std::sync::{Arc, Condvar, Mutex};
-use std::time::Instant;
+use std::env;
+use std::process::exit;
 
-// sees how long it takes to solve this tactic
+use getopts::Options;
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!("Usage: {} [OPTIONS] [DEPTH]", program);
+    print!("{}", opts.usage(&brief));
+    exit(0);
+}
+
+// searches a fixed set of positions to a fixed depth. the node count is the same on
+// every machine, so it checks that a refactor didn't change the search, and the speed
+// compares builds.
 fn main() {
-    let b = Board::from_fen("1K6/2P5/1p3P2/1k2P3/1qnP1B2/3Q4/8/8 b - - 0 1").unwrap();
-    println!("{}", b);
-    let main_signal = Arc::new(Condvar::new());
-    let main_mutex = Mutex::new(());
-    let mut pool = Threadpool::new(num_cpus::get(), main_signal.clone());
-    let start = Instant::now();
-    pool.find_best_move(&b, 6);
-    println!("started search...");
-    let _ = main_signal.wait(main_mutex.lock().unwrap()).unwrap();
-    let (mv, score) = pool.take_result().unwrap().unwrap();
+    let args: Vec<String> = env::args().collect();
+    let mut options = Options::new();
+    options.optflag("h", "help", "Print this help menu.");
+    options.optflag("v", "verbose", "Print the result of each position.");
+    options.optflag(
+        "s",
+        "stats",
+        "Print how the move ordering and pruning did over all the positions.",
+    );
+    let opts = options.parse(&args[1..]).unwrap();
+    if opts.opt_present("h") || opts.free.len() > 1 {
+        print_usage(&args[0], options);
+    }
+
+    let depth = match opts.free.first() {
+        Some(s) => s.parse::<usize>().expect("DEPTH should be a number!"),
+        None => DEFAULT_DEPTH,
+    };
+    let verbose = opts.opt_present("v");
+    let total = bench(depth, &mut |i, res| {
+        if verbose {
+            println!(
+                "{:>2}/{} {} score={} nodes={}",
+                i + 1,
+                POSITIONS.len(),
+                res.mv,
+                res.score,
+                res.nodes
+            );
+        }
+    })
+    .unwrap();
     println!(
-        "finished search: move={} score={} took={}s",
-        mv,
-        score,
-        start.elapsed().as_secs()
+        "depth={} nodes={} took={:.3}s nps={}",
+        depth,
+        total.nodes,
+        total.time.as_secs_f64(),
+        total.nps()
     );
+    if opts.opt_present("s") {
+        println!("{}", total.stats);
+    }
 }
@@ -6,16 +6,37 @@
 
 use combustion::*;
 
-use combustion::board::Board;
+use combustion::adjourned::Adjourned;
+use combustion::board::{Board, GameStatus};
+use combustion::board_alpha_beta::{is_mate_score, SearchResult};
+use combustion::board_render::RenderOptions;
+use combustion::book::Book;
 use combustion::clock::Clock;
+use combustion::engine_options::{
+    EngineOptions, CONTEMPT, DETERMINISTIC, ELO, HASH, LIMIT_STRENGTH, MTDF, OWN_BOOK, PONDER,
+    SEARCH_STATS, SKILL_LEVEL, TEMPERATURE,
+};
+use combustion::eval_graph::{evaluate_game, CSV_HEADER};
+use combustion::eval_params::{set_eval_params, EvalParams};
+use combustion::game::Game;
 use combustion::moves::Move;
-use combustion::piece::Color;
+use combustion::persona::Persona;
+use combustion::pgn::{read_pgn, PgnGame};
+use combustion::piece::{Color, Piece, PieceType};
+use combustion::position::Pos;
+use combustion::search_limits::{SearchLimits, Skill};
+use combustion::search_trace::SearchTrace;
+use combustion::tablebase::{set_tablebase, Tablebase};
 use combustion::threadpool::Threadpool;
+use combustion::time_manager::TimeManager;
+use combustion::transposition_table::TranspositionTable;
 use combustion::util::ChessError;
 
 use std::cell::RefCell;
 use std::env;
+use std::fs::{self, OpenOptions};
 use std::io::Write;
+use std::path::Path;
 use std::process::exit;
 use std::rc::Rc;
 use std::sync::mpsc::{channel, Sender, TryRecvError};
@@ -33,58 +54,487 @@ fn print_usage(program: &str, opts: Options) {
     exit(0);
 }
 
+// what --fen prints: a line each for the move, score, depth, nodes and pv, so scripts can
+// pick out what they need. mate scores read "mate N", negative when being mated.
+fn analysis(res: &SearchResult) -> String {
+    let score = if is_mate_score(res.score) {
+        let xboard = res.xboard_score();
+        format!("mate {}", xboard.signum() * (xboard.abs() - 100_000))
+    } else {
+        res.score.to_string()
+    };
+    let pv: Vec<String> = res.pv.iter().map(|mv| mv.to_string()).collect();
+    format!(
+        "bestmove {}\nscore {}\ndepth {}\nnodes {}\npv {}",
+        res.mv,
+        score,
+        res.depth + 1,
+        res.nodes,
+        pv.join(" ")
+    )
+}
+
 fn ignore() {
     combustion::debug!("ignoring message");
 }
 
+// scores within this many centipawns of 0 are a dead draw
+const DRAWISH: isize = 10;
+
+// take a draw when the last search found us no better off than a draw is worth, which
+// is -contempt. with no score yet, as in the book, play on.
+fn accept_draw(scores: &[isize], contempt: isize) -> bool {
+    scores.last().is_some_and(|&score| score <= -contempt)
+}
+
+// offer a draw in a late endgame when the last few searches all found it level
+fn offer_draw(scores: &[isize], b: &Board, contempt: isize) -> bool {
+    scores.len() >= 5
+        && b.iter_pieces().count() <= 10
+        && scores[scores.len() - 5..]
+            .iter()
+            .all(|&score| score.abs() <= DRAWISH && score <= DRAWISH - contempt)
+}
+
+// against a much weaker opponent play on for the win, and against a much stronger one
+// take the draw. ratings are (ours, theirs), with 0 for unknown.
+fn rating_contempt(ratings: (isize, isize)) -> isize {
+    match ratings {
+        (mine, theirs) if mine > 0 && theirs > 0 && (mine - theirs).abs() >= 200 => {
+            ((mine - theirs) / 4).clamp(-100, 100)
+        }
+        _ => 0,
+    }
+}
+
+// the seven tag roster and the players' ratings and types, for a game on server or
+// played locally. players are (name, is a program, rating) for white and black.
+fn pgn_headers(
+    server: Option<&str>,
+    players: [(&str, bool, isize); 2],
+    score: &str,
+) -> Vec<(String, String)> {
+    let event = server.map_or("Computer chess game".to_string(), |s| format!("{} game", s));
+    let mut headers = vec![
+        ("Event".to_string(), event),
+        ("Site".to_string(), server.unwrap_or("?").to_string()),
+        ("Date".to_string(), "????.??.??".to_string()),
+        ("Round".to_string(), "-".to_string()),
+        ("White".to_string(), players[0].0.to_string()),
+        ("Black".to_string(), players[1].0.to_string()),
+        ("Result".to_string(), score.to_string()),
+    ];
+    for (color, &(_, program, rating)) in ["White", "Black"].iter().zip(players.iter()) {
+        if rating > 0 {
+            headers.push((format!("{}Elo", color), rating.to_string()));
+        }
+        let kind = if program { "program" } else { "human" };
+        headers.push((format!("{}Type", color), kind.to_string()));
+    }
+    headers
+}
+
+// a message for whoever sits at the interface. on a server there may be nobody to close
+// the popup, so it only goes to the log.
+fn tell_user(on_server: bool, msg: &str) {
+    if on_server {
+        debug!("{}", msg);
+    } else {
+        send!("telluser {}", msg);
+    }
+}
+
+// one command of protocol 1's edit mode, setting up b with pieces of color c: "c" to
+// change color, "#" to clear the board, and "Pa4" or "xa4" to put a piece on or clear a
+// square. false if s isn't one of them.
+fn edit_board(b: &mut Board, c: &mut Color, s: &str) -> bool {
+    let mut chars = s.chars();
+    match (chars.next(), Pos::from_algebra(chars.as_str())) {
+        (Some('c'), _) if s.len() == 1 => *c = c.other(),
+        (Some('#'), _) if s.len() == 1 => {
+            for pos in Pos::all() {
+                b.get_piece_at(&pos.to_string());
+            }
+        }
+        (Some('x'), Ok(pos)) => {
+            b.get_piece_at(&pos.to_string());
+        }
+        (Some(letter), Ok(pos)) => match Piece::from_char(letter.to_ascii_uppercase()) {
+            Some(p) => {
+                b.get_piece_at(&pos.to_string());
+                b.put_piece_at(
+                    Piece {
+                        kind: p.kind,
+                        color: *c,
+                    },
+                    &pos.to_string(),
+                );
+            }
+            None => return false,
+        },
+        _ => return false,
+    }
+    true
+}
+
+// the position set up in edit mode, which can castle wherever the king and rook are
+// still at home
+fn finish_edit(mut b: Board) -> Result<Board, ChessError> {
+    let home = |pos, kind, color| b.piece(pos) == Some(Piece { kind, color });
+    let castles = [
+        (Color::White, Pos::E1, Pos::H1),
+        (Color::White, Pos::E1, Pos::A1),
+        (Color::Black, Pos::E8, Pos::H8),
+        (Color::Black, Pos::E8, Pos::A8),
+    ];
+    let mut rights = [false; 4];
+    for (i, &(color, king, rook)) in castles.iter().enumerate() {
+        rights[i] = home(king, PieceType::King, color) && home(rook, PieceType::Rook, color);
+    }
+    b.set_castle_rights(rights);
+    b.set_en_passant_target(None);
+    // through fen, to check the position
+    Board::from_fen(&b.to_fen())
+}
+
+// the game as it stands, to carry on with after a restart
+fn adjournment(
+    game: &Game,
+    my_color: Color,
+    clocks: [&Clock; 2],
+    time_manager: &TimeManager,
+    options: &EngineOptions,
+) -> Adjourned {
+    Adjourned {
+        game: game.clone(),
+        color: my_color,
+        clocks: [clocks[0].time_remaining(), clocks[1].time_remaining()],
+        time_manager: time_manager.clone(),
+        options: options.clone(),
+    }
+}
+
 #[allow(unused_variables, unused_assignments)]
 fn main() {
     let args: Vec<String> = env::args().collect();
     let mut options = Options::new();
     options.optflag("h", "help", "Print this help menu.");
-    options.optflag("r", "random", "Choose moves randomly.");
-    options.optflag("d", "depth", "Set max search depth.");
+    options.optflagopt(
+        "r",
+        "random",
+        "Play without searching: random, capturer, greedy or careful.",
+        "PERSONA",
+    );
+    options.optopt(
+        "d",
+        "depth",
+        "Search at most this many plies, as xboard's sd does.",
+        "PLIES",
+    );
+    options.optflag("n", "no-book", "Don't play moves from the built-in opening book.");
+    options.optflag(
+        "D",
+        "deterministic",
+        "Search on one thread and never pick a move at random, to reproduce a game.",
+    );
+    options.optopt(
+        "e",
+        "eval",
+        "Print the evaluation of a position, term by term, and exit.",
+        "FEN",
+    );
+    options.optopt(
+        "f",
+        "fen",
+        "Search a position, print the best move, score and pv, and exit.",
+        "FEN",
+    );
+    options.optflag(
+        "j",
+        "json",
+        "With --fen or --graph, print json instead: a line for each iteration or game.",
+    );
+    options.optopt(
+        "T",
+        "trace",
+        "With --fen, search on one thread and write the tree it searched to a file, as \
+         json with --json.",
+        "FILE",
+    );
+    options.optopt(
+        "",
+        "trace-plies",
+        "With --trace, record this many plies below the root. Defaults to 3.",
+        "PLIES",
+    );
+    options.optopt(
+        "",
+        "trace-nodes",
+        "With --trace, stop recording after this many nodes. Defaults to 100000.",
+        "NODES",
+    );
+    options.optopt(
+        "g",
+        "graph",
+        "Print the evaluation after every move of the games in a pgn file as csv, and exit.",
+        "PGN",
+    );
+    options.optopt(
+        "w",
+        "weights",
+        "Read evaluation weights from a file of name = value lines.",
+        "FILE",
+    );
+    options.optopt(
+        "c",
+        "contempt",
+        "Centipawns to give up to avoid a draw, or negative to prefer one.",
+        "CP",
+    );
+    options.optopt(
+        "s",
+        "skill",
+        "Play weaker, at a level from 0 to 20, which is full strength.",
+        "LEVEL",
+    );
+    options.optopt(
+        "p",
+        "pgn",
+        "Append each game to a pgn file when it ends.",
+        "FILE",
+    );
+    options.optopt(
+        "t",
+        "tablebases",
+        "Play endings from the tables tbgen wrote to a directory.",
+        "DIR",
+    );
+    options.optopt(
+        "a",
+        "adjourn",
+        "Save the game to a file when it is adjourned, for resume to carry on with.",
+        "FILE",
+    );
     let opts = options.parse(&args[1..]).unwrap();
     if opts.opt_present("h") {
         print_usage(&args[0], options);
     }
+    if let Some(dir) = opts.opt_str("t") {
+        match Tablebase::load_dir(Path::new(&dir)) {
+            Ok(tb) => set_tablebase(tb),
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        }
+    }
+    if let Some(path) = opts.opt_str("w") {
+        match EvalParams::load(&path) {
+            Ok(params) => set_eval_params(params),
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        }
+    }
+    if let Some(fen) = opts.opt_str("e") {
+        match Board::from_fen(&fen) {
+            Ok(b) => println!("{}\n{}", b, b.evaluate_detailed()),
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        }
+        exit(0);
+    }
 
-    let mut max_depth = match opts.opt_str("d") {
-        Some(s) => s.parse::<usize>().unwrap(),
-        None => 5,
-    };
+    // otherwise the clock decides how deep we go. like sd, counted in plies from the
+    // root, so 1 looks only at our own moves.
+    let depth_flag = opts.opt_str("d").map(|s| {
+        s.parse::<usize>().unwrap_or_else(|_| {
+            eprintln!("-d should be a number");
+            exit(1);
+        })
+    });
+    let mut max_depth = depth_flag;
+
+    // the command line sets the options' starting values, and the gui may change them
+    let mut engine_options = EngineOptions::new();
+    if opts.opt_present("n") {
+        engine_options.set(OWN_BOOK, "false").unwrap();
+    }
+    if opts.opt_present("D") {
+        engine_options.set(DETERMINISTIC, "true").unwrap();
+    }
+    for (flag, name) in [("c", CONTEMPT), ("s", SKILL_LEVEL)].iter() {
+        if let Some(value) = opts.opt_str(flag) {
+            if let Err(e) = engine_options.set(name, &value) {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        }
+    }
+    let starting_options = engine_options.clone();
+
+    // search one position and exit, for scripts. without a depth it thinks for five
+    // seconds. with --json each finished iteration is printed as it comes.
+    if let Some(fen) = opts.opt_str("f") {
+        let mut b = match Board::from_fen(&fen) {
+            Ok(b) => b,
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        };
+        let limits = match max_depth {
+            Some(plies) => SearchLimits::depth(plies.saturating_sub(1)),
+            None => SearchLimits::move_time(Duration::from_secs(5)),
+        };
+        let tt = TranspositionTable::with_megabytes(engine_options.spin(HASH) as usize);
+        let root = b.clone();
+        let json = opts.opt_present("j");
+        let mut report = |res: &SearchResult| {
+            if json {
+                println!("{}", res.to_json(&root));
+            }
+        };
+        let tt = Some(Arc::new(tt));
+        let res = match opts.opt_str("T") {
+            Some(path) => {
+                let plies = opts.opt_get_default("trace-plies", 3).unwrap();
+                let nodes = opts.opt_get_default("trace-nodes", 100_000).unwrap();
+                let mut trace = SearchTrace::new(plies, nodes);
+                let res = b.search_traced(&limits, tt, &mut trace, &mut report);
+                let dump = if json {
+                    trace.to_json()
+                } else {
+                    trace.to_text()
+                };
+                if let Err(e) = fs::write(&path, dump) {
+                    eprintln!("{}: {}", path, e);
+                    exit(1);
+                }
+                res
+            }
+            None => b.search_with(&limits, None, tt, &mut report),
+        };
+        match res {
+            Ok(_) if json => (),
+            Ok(res) => println!("{}", analysis(&res)),
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        }
+        exit(0);
+    }
+
+    // the evaluation after each move of each game, for plotting. with --json a game is a
+    // line, with its moves in an array.
+    if let Some(path) = opts.opt_str("g") {
+        let pgn = fs::read_to_string(&path).unwrap_or_else(|e| {
+            eprintln!("can't read {}: {}", path, e);
+            exit(1);
+        });
+        let limits = match max_depth {
+            Some(plies) => SearchLimits::depth(plies.saturating_sub(1)),
+            None => SearchLimits::move_time(Duration::from_secs(1)),
+        };
+        let json = opts.opt_present("j");
+        if !json {
+            println!("game,{}", CSV_HEADER);
+        }
+        for (i, pgn_game) in read_pgn(&pgn).enumerate() {
+            let evals = pgn_game.and_then(|pgn_game| {
+                evaluate_game(&pgn_game.game, &limits, &mut |eval| {
+                    if !json {
+                        println!("{},{}", i + 1, eval.to_csv());
+                    }
+                })
+            });
+            match evals {
+                Ok(evals) if json => {
+                    let moves: Vec<String> = evals.iter().map(|eval| eval.to_json()).collect();
+                    println!("{{\"game\":{},\"moves\":[{}]}}", i + 1, moves.join(","));
+                }
+                Ok(_) => (),
+                Err(e) => eprintln!("game {}: {}", i + 1, e),
+            }
+        }
+        exit(0);
+    }
 
     unsafe {
         signal(SIGINT, SIG_IGN); // ignore SIGINT!!!! xboard sends SIGINT WTF
     }
 
-    let engine_random_choice = opts.opt_present("r");
+    // play without searching, for beginners and as a baseline
+    let persona = if opts.opt_present("r") {
+        match Persona::parse(&opts.opt_str("r").unwrap_or_else(|| "random".to_string())) {
+            Ok(p) => Some(p),
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        }
+    } else {
+        None
+    };
+    let book = Book::builtin();
 
     // main loop- recieving and sending messages to xboard
-    debug!("combustion started! random={}", engine_random_choice);
+    debug!("combustion started! persona={:?}", persona);
 
     // precompile regexes
     let re_level = Regex::new(r"^level (\d+) (\d+)(:\d+)? (\d+)$").unwrap();
     let re_st = Regex::new(r"^st (\d+)$").unwrap();
     let re_sd = Regex::new(r"^sd (\d+)$").unwrap();
+    let re_mate = Regex::new(r"^mate (\d+)$").unwrap();
     let re_time = Regex::new(r"^time (\d+)$").unwrap();
     let re_otim = Regex::new(r"^otim (\d+)$").unwrap();
     let re_protover = Regex::new(r"^protover (\d+)$").unwrap();
     let re_variant = Regex::new(r"^variant (\w+)$").unwrap();
     let re_ping = Regex::new(r"^ping (\d+)$").unwrap();
-    let re_result = Regex::new(r"^result ([012/]+-[012/]+) (\{.*\})$").unwrap();
+    let re_result = Regex::new(r"^result ([012/]+-[012/]+|\*) (\{.*\})$").unwrap();
     let re_setboard = Regex::new(r"^setboard (.+)$").unwrap();
     let re_accepted = Regex::new(r"^accepted (\w+)$").unwrap();
     let re_rejected = Regex::new(r"^rejected (\w+)$").unwrap();
+    let re_option = Regex::new(r"^option (.+)$").unwrap();
+    let re_exclude = Regex::new(r"^(exclude|include) (\w+)$").unwrap();
+    let re_memory = Regex::new(r"^memory (\d+)$").unwrap();
+    let re_cores = Regex::new(r"^cores (\d+)$").unwrap();
+    let re_ics = Regex::new(r"^ics (\S+)$").unwrap();
     let re_nps = Regex::new(r"^nps (\d+)$").unwrap();
     let re_name = Regex::new(r"^name (.+)$").unwrap();
     let re_rating = Regex::new(r"^rating (\d+) (\d+)$").unwrap();
     let re_usermove = Regex::new(r"^usermove ([\w\d]+)$").unwrap();
+    let re_adjourn = Regex::new(r"^(adjourn|resume)(?: (.+))?$").unwrap();
 
-    let mut b = Board::initial();
+    let mut game = Game::new();
     let mut force_mode = true;
     let mut my_color = Color::Black;
-    let mut history: Vec<Move> = Vec::new();
+    // our search scores for the moves we made this game, and the move we last offered a
+    // draw on, to not keep offering
+    let mut scores: Vec<isize> = Vec::new();
+    let mut offered_draw = 0;
+    // root moves xboard told us not to search, and the key of the position they are
+    // excluded in. they no longer apply once the position changes.
+    let mut excluded: Vec<Move> = Vec::new();
+    let mut excluded_in = 0;
+    // whether the search running is pondering, whose result nobody wants
+    let mut pondering = false;
+    // the board being set up in edit mode, and the color of the pieces being placed
+    let mut editing: Option<(Board, Color)> = None;
+    // what xboard tells us about the opponent: on a chess server, its name, their name,
+    // whether they are a program, and our ratings as (ours, theirs)
+    let mut server: Option<String> = None;
+    let mut opponent = "?".to_string();
+    let mut opponent_computer = false;
+    let mut ratings = (0, 0);
+    // from xboard's nps: our clock runs on nodes searched at this rate, and the time on
+    // it when we started thinking
+    let mut nps: Option<u64> = None;
+    let mut clock_at_start = 0;
 
     let main_signal = Arc::new(Condvar::new());
     let main_mutex = Mutex::new(());
@@ -93,6 +543,8 @@ fn main() {
     let white_clock = Rc::new(RefCell::new(Clock::new(30000, main_signal.clone()))); // init 5m
     let black_clock = Rc::new(RefCell::new(Clock::new(30000, main_signal.clone())));
 
+    let mut time_manager = TimeManager::new();
+
     let mut my_clock = white_clock.clone();
     let mut their_clock = black_clock.clone();
 
@@ -119,35 +571,103 @@ fn main() {
                     }
                 }
 
-                if (engine_random_choice || pool.has_result())
+                // a draw by rule can be claimed without moving, as after setboard or
+                // moves made in force mode
+                if !force_mode && !pool.thinking() && game.board().color_to_move() == my_color {
+                    if let Some(result) = game.result() {
+                        send!("{}", result);
+                        force_mode = true;
+                        continue;
+                    }
+                }
+
+                // no need to search while in the book
+                let book_move = if engine_options.check(OWN_BOOK)
+                    && !force_mode
+                    && !pool.thinking()
+                    && game.board().color_to_move() == my_color
+                {
+                    if engine_options.check(DETERMINISTIC) {
+                        book.pick_heaviest(game.board())
+                    } else {
+                        book.pick(game.board())
+                    }
+                } else {
+                    None
+                };
+
+                if (persona.is_some() || book_move.is_some() || pool.has_result())
                     && !force_mode
-                    && b.color_to_move == my_color
+                    && game.board().color_to_move() == my_color
                 {
                     debug!("getting result");
 
                     let mv_result;
-                    if engine_random_choice {
-                        mv_result = b.random_move();
+                    // how long the search took, counted in nodes
+                    let mut node_time = None;
+                    if let Some(mv) = book_move {
+                        debug!("book move {}", mv);
+                        mv_result = Ok((mv, 0));
+                    } else if let Some(persona) = persona {
+                        mv_result = persona.pick(game.board());
                         thread::sleep(Duration::from_millis(500));
                     } else {
-                        mv_result = pool.take_result().unwrap();
+                        mv_result = pool.take_result().unwrap().map(|res| {
+                            let pv: Vec<String> = res.pv.iter().map(|mv| mv.to_string()).collect();
+                            debug!("expecting {}", pv.join(" "));
+                            if engine_options.check(SEARCH_STATS) {
+                                send!("# {}", res.stats);
+                            }
+                            node_time = nps.map(|_| res.time);
+                            let (mv, score) = res.pick_near_best();
+                            if mv != res.mv {
+                                debug!("playing {} instead, {} worse", mv, res.score - score);
+                            }
+                            (mv, score)
+                        });
                     }
 
                     match mv_result {
                         Ok((mv, score)) => {
-                            b = b.make_move(&mv).unwrap();
-                            history.push(mv);
+                            game.make_move(&mv).unwrap();
                             debug!("moving {} with score {}", mv, score);
-                            debug!("new board:\n{}", b);
+                            debug!(
+                                "new board:\n{}",
+                                game.board()
+                                    .render(&RenderOptions::last_move(&mv, my_color))
+                            );
+                            // the search doesn't know the game's history, so it will
+                            // repeat positions when it sees nothing better. offer the draw
+                            // the game is heading for.
+                            if book_move.is_none() {
+                                scores.push(score);
+                            }
+                            let late = game.board().move_number >= offered_draw + 10;
+                            let contempt = engine_options.spin(CONTEMPT) + rating_contempt(ratings);
+                            if game.result().is_none()
+                                && (game.repetitions() > 0 && score <= 0
+                                    || late && offer_draw(&scores, game.board(), contempt))
+                            {
+                                send!("offer draw");
+                                offered_draw = game.board().move_number;
+                            }
                             send!("move {}", mv.to_xboard_format(my_color));
                             my_clock.borrow().stop();
+                            if let Some(t) = node_time {
+                                let csecs = t.as_millis() as isize / 10;
+                                my_clock.borrow_mut().correct(clock_at_start - csecs);
+                            }
                             their_clock.borrow().start();
+                            if let Some(result) = game.result() {
+                                send!("{}", result);
+                                force_mode = true;
+                            }
                         }
-                        Err(ChessError::Stalemate) => {
+                        Err(ChessError::GameOver(GameStatus::Stalemate)) => {
                             send!("1/2-1/2 {{Stalemate}}");
                             force_mode = true;
                         }
-                        Err(ChessError::Checkmate) => {
+                        Err(ChessError::GameOver(GameStatus::Checkmate)) => {
                             match my_color {
                                 Color::White => send!("0-1 {{Checkmate}}"),
                                 Color::Black => send!("1-0 {{Checkmate}}"),
@@ -158,17 +678,62 @@ fn main() {
                     }
                 }
                 // find a move if it is my turn
-                else if !engine_random_choice
+                else if persona.is_none()
                     && !pool.thinking()
                     && !force_mode
-                    && b.color_to_move == my_color
+                    && game.board().color_to_move() == my_color
                 {
                     debug!("finding best move");
-                    pool.find_best_move(&b, max_depth);
+                    let budget = time_manager
+                        .budget(my_clock.borrow().time_remaining(), game.board().move_number - 1);
+                    debug!("thinking for {:?} (at most {:?})", budget.soft, budget.hard);
+                    let mut search_moves = Vec::new();
+                    if !excluded.is_empty() && excluded_in == game.board().zobrist() {
+                        search_moves = game.board().legal_moves();
+                        search_moves.retain(|mv| !excluded.contains(mv));
+                        // with everything excluded we still have to move
+                        if search_moves.is_empty() {
+                            debug!("every move is excluded, searching them all");
+                        }
+                    }
+                    let skill = if engine_options.check(LIMIT_STRENGTH) {
+                        Skill::from_elo(engine_options.spin(ELO) as usize)
+                    } else {
+                        Skill::level(engine_options.spin(SKILL_LEVEL) as usize)
+                    };
+                    let limits = SearchLimits {
+                        // the search counts plies below the root moves
+                        depth: max_depth.map(|plies| plies.saturating_sub(1)),
+                        time: Some(budget),
+                        search_moves,
+                        nps,
+                        margin: engine_options.spin(TEMPERATURE),
+                        mtdf: engine_options.check(MTDF),
+                        deterministic: engine_options.check(DETERMINISTIC),
+                        ..SearchLimits::default()
+                    };
+                    clock_at_start = my_clock.borrow().time_remaining();
+                    pool.find_best_move(game.board(), &skill.limit(limits));
+                }
+                // ponder: search the opponent's position until they move, filling the
+                // table with what we will need to answer them
+                else if engine_options.check(PONDER)
+                    && persona.is_none()
+                    && !pool.thinking()
+                    && !pool.has_result()
+                    && !force_mode
+                    && game.board().color_to_move() != my_color
+                    && game.result().is_none()
+                    // not on the first move, as after new
+                    && !game.moves().is_empty()
+                {
+                    debug!("pondering");
+                    pondering = true;
+                    pool.find_best_move(game.board(), &SearchLimits::infinite());
                 } else {
                     // no input, no moves => wait
                     // debug!("sleep...");
-                    let _ = main_signal.wait(main_mutex.lock().unwrap()).unwrap();
+                    let _guard = main_signal.wait(main_mutex.lock().unwrap()).unwrap();
                     // let _ = main_signal.wait_timeout(main_mutex.lock().unwrap(), Duration::from_millis(500)).unwrap();
                 }
             }
@@ -176,11 +741,44 @@ fn main() {
             Ok(s) => {
                 debug!("received message: \"{}\"", s);
 
-                if s == "exit" || s == "q" || s == "quit" {
+                // whatever comes in, pondering stops, and starts over after if it still
+                // should
+                if pondering {
+                    pool.abort_and_clear();
+                    pondering = false;
+                }
+
+                if is_quit(&s) {
                     debug!("exiting");
-                    return;
+                    pool.abort_and_clear();
+                    pool.close();
+                    white_clock.borrow().stop();
+                    black_clock.borrow().stop();
+                    input_watcher_thread.join().unwrap();
+                    exit(0);
+                } else if let Some((b, c)) = editing.as_mut() {
+                    if s == "." {
+                        match finish_edit(b.clone()) {
+                            Ok(new_board) => {
+                                debug!("set board to edited position\n{}", new_board);
+                                pool.clear_table();
+                                game = Game::from_board(new_board);
+                                scores.clear();
+                                offered_draw = 0;
+                            }
+                            Err(e) => {
+                                tell_user(server.is_some(), &format!("Illegal position: {}", e))
+                            }
+                        }
+                        editing = None;
+                    } else if !edit_board(b, c, &s) {
+                        send!("Error (unknown edit command): {}", s);
+                    }
                 } else if re_protover.is_match(&s) {
-                    send!("feature usermove=1 sigint=0 ping=1 colors=0 playother=1 setboard=1 analyze=0 done=1");
+                    for feature in engine_options.xboard_features() {
+                        send!("{}", feature);
+                    }
+                    send!("feature usermove=1 sigint=0 ping=1 nps=1 name=1 ics=1 exclude=1 memory=1 smp=1 colors=0 playother=1 setboard=1 analyze=0 done=1");
                 } else if re_ping.is_match(&s) {
                     let n = re_ping.captures(&s).unwrap()[1].parse::<usize>().unwrap();
                     // check that all previous commands are finished
@@ -190,8 +788,13 @@ fn main() {
                     send!("pong {}", n);
                 } else if s == "new" {
                     pool.abort_and_clear();
+                    pool.clear_table();
                     force_mode = false;
-                    b = Board::initial();
+                    game = Game::new();
+                    scores.clear();
+                    offered_draw = 0;
+                    // xboard says again if the next opponent is a program
+                    opponent_computer = false;
                     my_color = Color::Black;
                     // my clock is Black's
                     my_clock = black_clock.clone();
@@ -200,9 +803,23 @@ fn main() {
                     // reset clocks (stops clocks)
                     black_clock.borrow().reset();
                     white_clock.borrow().reset();
-                    // use wall clock for time measurement.
-                    // do not ponder now.
-                    debug!("created new board:\n{}", b);
+                    // use wall clock for time measurement
+                    nps = None;
+                    // sd only lasts the game
+                    max_depth = depth_flag;
+                    excluded.clear();
+                    engine_options.new_game(&starting_options);
+                    debug!("created new board:\n{}", game.board());
+                } else if s == "random" {
+                    engine_options.toggle_random();
+                } else if s == "hard" {
+                    engine_options.set(PONDER, "true").unwrap();
+                } else if s == "easy" {
+                    engine_options.set(PONDER, "false").unwrap();
+                } else if s == "post" {
+                    pool.set_post(true);
+                } else if s == "nopost" {
+                    pool.set_post(false);
                 } else if s == "force" {
                     // accept moves from both sides, stop calculating
                     pool.abort_and_clear();
@@ -214,7 +831,7 @@ fn main() {
                     // leave force mode
                     force_mode = false;
                     // play as the color that is on move
-                    my_color = b.color_to_move;
+                    my_color = game.board().color_to_move();
                     // that color's clock is mine
                     // opponent's clock is the other color
                     match my_color {
@@ -234,7 +851,7 @@ fn main() {
                     // leave force mode
                     force_mode = false;
                     // play the color that is not on the move
-                    my_color = b.color_to_move.other();
+                    my_color = game.board().color_to_move().other();
                     // opponent's clock is for the color on move
                     // my clock is clock for color not on move
                     match my_color {
@@ -256,14 +873,161 @@ fn main() {
                 else if re_level.is_match(&s) {
                     // setting the clock mode
                     let caps = re_level.captures(&s).unwrap();
-                    ignore();
+                    let mps = caps[1].parse::<usize>().unwrap();
+                    let mut base = caps[2].parse::<isize>().unwrap() * 60;
+                    if let Some(secs) = caps.get(3) {
+                        base += secs.as_str()[1..].parse::<isize>().unwrap();
+                    }
+                    let inc = caps[4].parse::<isize>().unwrap();
+                    white_clock.borrow_mut().set(base * 100);
+                    black_clock.borrow_mut().set(base * 100);
+                    time_manager.set_level(mps, inc * 100);
                 } else if re_st.is_match(&s) {
                     // set the delay
-                    ignore();
+                    let secs = re_st.captures(&s).unwrap()[1].parse::<isize>().unwrap();
+                    time_manager.set_move_time(secs * 100);
                 } else if re_sd.is_match(&s) {
                     // set the max-depth
-                    max_depth = re_sd.captures(&s).unwrap()[1].parse::<usize>().unwrap();
-                    debug!("set max search depth to {}", max_depth);
+                    let depth = re_sd.captures(&s).unwrap()[1].parse::<usize>().unwrap();
+                    max_depth = Some(depth);
+                    debug!("set max search depth to {}", depth);
+                } else if re_option.is_match(&s) {
+                    match engine_options.set_xboard(&re_option.captures(&s).unwrap()[1]) {
+                        Ok(HASH) => {
+                            pool.abort_and_clear();
+                            pool.set_hash_size(engine_options.spin(HASH) as usize);
+                        }
+                        Ok(name) => debug!("set option {}", name),
+                        Err(e) => send!("Error ({}): {}", e, s),
+                    }
+                } else if re_exclude.is_match(&s) {
+                    let caps = re_exclude.captures(&s).unwrap();
+                    if excluded_in != game.board().zobrist() {
+                        excluded.clear();
+                        excluded_in = game.board().zobrist();
+                    }
+                    match (&caps[1], &caps[2]) {
+                        ("exclude", "all") => excluded = game.board().legal_moves(),
+                        ("include", "all") => excluded.clear(),
+                        (command, mv_str) => match Move::from_xboard_format(mv_str, game.board()) {
+                            Ok(mv) if command == "exclude" => {
+                                if !excluded.contains(&mv) {
+                                    excluded.push(mv);
+                                }
+                            }
+                            Ok(mv) => excluded.retain(|&m| m != mv),
+                            Err(e) => send!("Illegal move: ({}) {}", e, s),
+                        },
+                    }
+                    debug!("excluding {} moves", excluded.len());
+                } else if re_memory.is_match(&s) {
+                    // the hash table gets all the memory we are allowed, up to the most
+                    // the Hash option takes
+                    let caps = re_memory.captures(&s).unwrap();
+                    let mb = caps[1].parse::<usize>().unwrap_or(usize::MAX);
+                    engine_options.set_clamped(HASH, mb);
+                    let mb = engine_options.spin(HASH) as usize;
+                    pool.abort_and_clear();
+                    pool.set_hash_size(mb);
+                    debug!("set hash size to {} MB", mb);
+                } else if re_cores.is_match(&s) {
+                    // a search thread per core we may use
+                    let n = re_cores.captures(&s).unwrap()[1].parse::<usize>().unwrap();
+                    pool.abort_and_clear();
+                    pool.set_threads(n);
+                    debug!("searching with {} threads", pool.threads());
+                } else if re_adjourn.is_match(&s) {
+                    // not part of xboard either: save the game to carry on with after a
+                    // restart, to the file given or the one from --adjourn
+                    let caps = re_adjourn.captures(&s).unwrap();
+                    let path = match caps.get(2) {
+                        Some(path) => path.as_str().to_string(),
+                        None => match opts.opt_str("a") {
+                            Some(path) => path,
+                            None => {
+                                send!("Error (no file to {} with): {}", &caps[1], s);
+                                continue;
+                            }
+                        },
+                    };
+                    pool.abort_and_clear();
+                    white_clock.borrow().stop();
+                    black_clock.borrow().stop();
+                    if &caps[1] == "adjourn" {
+                        let clocks = [&*white_clock.borrow(), &*black_clock.borrow()];
+                        let adjourned =
+                            adjournment(&game, my_color, clocks, &time_manager, &engine_options);
+                        match adjourned.save(&path) {
+                            Ok(()) => debug!("adjourned the game to {}", path),
+                            Err(e) => tell_user(server.is_some(), &e.to_string()),
+                        }
+                        force_mode = true;
+                    } else {
+                        match Adjourned::load(&path) {
+                            Ok(adjourned) => {
+                                pool.clear_table();
+                                game = adjourned.game;
+                                scores.clear();
+                                offered_draw = 0;
+                                excluded.clear();
+                                white_clock.borrow_mut().correct(adjourned.clocks[0]);
+                                black_clock.borrow_mut().correct(adjourned.clocks[1]);
+                                time_manager = adjourned.time_manager;
+                                engine_options = adjourned.options;
+                                pool.set_hash_size(engine_options.spin(HASH) as usize);
+                                // carry on playing our side, with the clock of whoever is
+                                // to move running
+                                my_color = adjourned.color;
+                                match my_color {
+                                    Color::White => {
+                                        my_clock = white_clock.clone();
+                                        their_clock = black_clock.clone();
+                                    }
+                                    Color::Black => {
+                                        my_clock = black_clock.clone();
+                                        their_clock = white_clock.clone();
+                                    }
+                                }
+                                force_mode = false;
+                                if game.board().color_to_move() == my_color {
+                                    my_clock.borrow().start();
+                                } else {
+                                    their_clock.borrow().start();
+                                }
+                                debug!("resumed the game from {}\n{}", path, game.board());
+                            }
+                            Err(e) => tell_user(server.is_some(), &e.to_string()),
+                        }
+                    }
+                } else if re_mate.is_match(&s) {
+                    // not part of xboard: look for a forced mate in the current position,
+                    // for solving puzzles. the answer pops up for the user.
+                    let moves = re_mate.captures(&s).unwrap()[1].parse::<usize>().unwrap();
+                    if pool.thinking() {
+                        send!("Error (busy): {}", s);
+                    } else {
+                        pool.find_best_move(game.board(), &SearchLimits::mate(moves));
+                        while pool.thinking() {
+                            thread::sleep(Duration::from_millis(50));
+                        }
+                        match pool.take_result() {
+                            Some(Ok(res)) if is_mate_score(res.score) => {
+                                let pv: Vec<String> =
+                                    res.pv.iter().map(|mv| mv.to_string()).collect();
+                                let msg = format!(
+                                    "mate in {}: {}",
+                                    res.xboard_score() - 100_000,
+                                    pv.join(" ")
+                                );
+                                tell_user(server.is_some(), &msg);
+                            }
+                            Some(Ok(_)) => {
+                                tell_user(server.is_some(), &format!("no mate in {}", moves))
+                            }
+                            Some(Err(e)) => tell_user(server.is_some(), &e.to_string()),
+                            None => {}
+                        }
+                    }
                 }
                 // clocks always remain with color
                 // which one to update is determined by which side i play
@@ -281,12 +1045,54 @@ fn main() {
                     // move now with best result
                     pool.abort();
                 } else if s == "draw" {
-                    // to accept: send "offer draw"
-                    ignore();
+                    // offering back accepts
+                    let contempt = engine_options.spin(CONTEMPT) + rating_contempt(ratings);
+                    if !force_mode && accept_draw(&scores, contempt) {
+                        send!("offer draw");
+                    } else {
+                        debug!("declining the draw");
+                    }
                 }
                 // ^result ([012/]+-[012/]+|\*) (\{.*\})$
                 else if re_result.is_match(&s) {
                     pool.abort_and_clear();
+                    let caps = re_result.captures(&s).unwrap();
+                    let score = &caps[1];
+                    // the server adjourned the game, to be resumed later
+                    if score == "*" && caps[2].to_lowercase().contains("adjourn") {
+                        if let Some(path) = opts.opt_str("a") {
+                            let clocks = [&*white_clock.borrow(), &*black_clock.borrow()];
+                            let adjourned = adjournment(
+                                &game,
+                                my_color,
+                                clocks,
+                                &time_manager,
+                                &engine_options,
+                            );
+                            match adjourned.save(&path) {
+                                Ok(()) => debug!("adjourned the game to {}", path),
+                                Err(e) => tell_user(server.is_some(), &e.to_string()),
+                            }
+                        }
+                    }
+                    if let Some(path) = opts.opt_str("p").filter(|_| score != "*") {
+                        let me = ("combustion", true, ratings.0);
+                        let them = (opponent.as_str(), opponent_computer, ratings.1);
+                        let players = match my_color {
+                            Color::White => [me, them],
+                            Color::Black => [them, me],
+                        };
+                        let headers = pgn_headers(server.as_deref(), players, score);
+                        let pgn = PgnGame::from_game(game.clone(), headers, score);
+                        let written = OpenOptions::new()
+                            .create(true)
+                            .append(true)
+                            .open(&path)
+                            .and_then(|mut f| writeln!(f, "{}", pgn));
+                        if let Err(e) = written {
+                            debug!("can't write the game to {}: {}", path, e);
+                        }
+                    }
                 }
                 // ^setboard (.+)$
                 else if re_setboard.is_match(&s) {
@@ -294,51 +1100,48 @@ fn main() {
                     match Board::from_fen(fen) {
                         Ok(new_board) => {
                             debug!("set board to new position\n{}", new_board);
-                            b = new_board;
+                            pool.clear_table();
+                            game = Game::from_board(new_board);
+                            scores.clear();
+                            offered_draw = 0;
                         }
                         Err(e) => {
                             debug!("{}", e.msg());
+                            tell_user(server.is_some(), &format!("Illegal position: {}", e));
                         }
                     }
+                } else if s == "edit" {
+                    // protocol 1 interfaces set up positions piece by piece, starting
+                    // with white's
+                    editing = Some((game.board().clone(), Color::White));
                 } else if s == "undo" {
-                    if history.len() > 0 {
-                        history.pop();
-                    }
-                    b = Board::initial();
-                    for mv in history.iter() {
-                        b = b.make_move(mv).unwrap();
-                    }
+                    game.undo();
                 } else if s == "remove" {
-                    if history.len() > 0 {
-                        history.pop();
-                    }
-                    if history.len() > 0 {
-                        history.pop();
-                    }
-                    b = Board::initial();
-                    for mv in history.iter() {
-                        b = b.make_move(mv).unwrap();
-                    }
+                    game.undo();
+                    game.undo();
                 } else if re_usermove.is_match(&s) {
                     let ref mv_str = re_usermove.captures(&s).unwrap()[1];
-                    match Move::from_xboard_format(mv_str, &b) {
+                    match Move::from_xboard_format(mv_str, game.board()) {
                         Ok(mv) => {
                             debug!("got move {}", mv);
-                            match b.make_move(&mv) {
+                            match game.make_move(&mv) {
                                 Err(e) => {
                                     send!("Illegal move: ({}) {}", e, s);
                                 }
-                                Ok(new_board) => {
+                                Ok(()) => {
                                     if !force_mode {
                                         // stop opponent's clock
                                         their_clock.borrow().stop();
                                         // start my clock
                                         my_clock.borrow().start();
                                     }
-                                    // debug!("current board:\n{}", new_board);
-                                    history.push(mv);
-                                    b = new_board;
-                                    // debug!("new board\n{}", b);
+                                    // debug!("new board\n{}", game.board());
+                                    if !force_mode {
+                                        if let Some(result) = game.result() {
+                                            send!("{}", result);
+                                            force_mode = true;
+                                        }
+                                    }
                                 }
                             }
                         }
@@ -347,6 +1150,22 @@ fn main() {
                             send!("Error (unknown command): {}", s);
                         }
                     }
+                } else if re_nps.is_match(&s) {
+                    // 0 goes back to the wall clock
+                    let n = re_nps.captures(&s).unwrap()[1].parse::<u64>().unwrap();
+                    nps = Some(n).filter(|&n| n > 0);
+                } else if re_ics.is_match(&s) {
+                    // "ics -" when not on a server
+                    let host = &re_ics.captures(&s).unwrap()[1];
+                    server = Some(host.to_string()).filter(|host| host != "-");
+                } else if re_name.is_match(&s) {
+                    opponent = re_name.captures(&s).unwrap()[1].to_string();
+                } else if s == "computer" {
+                    opponent_computer = true;
+                } else if re_rating.is_match(&s) {
+                    let caps = re_rating.captures(&s).unwrap();
+                    ratings = (caps[1].parse().unwrap(), caps[2].parse().unwrap());
+                    debug!("contempt {} for the ratings", rating_contempt(ratings));
                 } else {
                     ignore();
                 }
@@ -355,20 +1174,30 @@ fn main() {
     }
 }
 
-fn next_input_line() -> String {
+// None once the input is closed
+fn next_input_line() -> Option<String> {
     let mut input = String::new();
     match std::io::stdin().read_line(&mut input) {
         Err(e) => panic!("[next_input_line]: {}", e),
-        _ => {}
+        Ok(0) => None,
+        Ok(_) => Some(input.trim().to_string()),
     }
-    input.trim().to_string()
 }
 
-// fn stdin_watcher(strings: Arc<Mutex<Vec<String>>>, main_signal: Arc<Condvar>)
+fn is_quit(s: &str) -> bool {
+    s == "exit" || s == "q" || s == "quit"
+}
+
+// passes each line of input to the main loop, until quit or the end of the input, which
+// counts as quit
 fn stdin_watcher(tx: Sender<String>, main_signal: Arc<Condvar>) -> thread::JoinHandle<()> {
     thread::spawn(move || loop {
-        let s = next_input_line();
+        let s = next_input_line().unwrap_or_else(|| "quit".to_string());
+        let quit = is_quit(&s);
         tx.send(s).unwrap();
         main_signal.notify_all();
+        if quit {
+            break;
+        }
     })
 }
@@ -0,0 +1,237 @@
+extern crate combustion;
+
+use combustion::adjudication::Adjudicator;
+use combustion::board::Board;
+use combustion::board_alpha_beta::is_mate_score;
+use combustion::game::Game;
+use combustion::persona::Persona;
+use combustion::piece::Color;
+use combustion::search_limits::SearchLimits;
+use combustion::transposition_table::TranspositionTable;
+
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Write};
+use std::process::exit;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::channel;
+use std::sync::Arc;
+use std::thread;
+
+use getopts::Options;
+
+// a game still going after this many moves is drawn
+const MAX_MOVES: usize = 300;
+
+// megabytes of transposition table for each thread
+const HASH: usize = 16;
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!(
+        "Usage: {} [OPTIONS]\n\n\
+         Plays fast games against itself and appends the positions, the search's score and\n\
+         the result of the game to a file, for tuning the evaluation. Positions in check,\n\
+         or where the best move captures or promotes, are left out, as are mate scores.\n\
+         In text format a line is \"FEN | SCORE | RESULT\", the score in centipawns for\n\
+         white and the result 1.0, 0.5 or 0.0 for white. In epd format it is the position\n\
+         with a ce operation, for the side to move, and a c9 operation with the result.",
+        program
+    );
+    print!("{}", opts.usage(&brief));
+    exit(0);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Text,
+    Epd,
+}
+
+// a position worth learning from, with the score for white
+struct Sample {
+    board: Board,
+    score: isize,
+}
+
+impl Format {
+    fn line(&self, sample: &Sample, result: &str) -> String {
+        match self {
+            Format::Text => {
+                let result = match result {
+                    "1-0" => "1.0",
+                    "0-1" => "0.0",
+                    _ => "0.5",
+                };
+                format!("{} | {} | {}", sample.board.to_fen(), sample.score, result)
+            }
+            Format::Epd => {
+                let b = &sample.board;
+                let fields: Vec<String> = b.to_fen().split(' ').take(4).map(String::from).collect();
+                let ce = match b.color_to_move() {
+                    Color::White => sample.score,
+                    Color::Black => -sample.score,
+                };
+                format!("{} ce {}; c9 \"{}\";", fields.join(" "), ce, result)
+            }
+        }
+    }
+}
+
+// one game from random_plies random moves, the positions to keep from it and its score
+fn play(
+    limits: &SearchLimits,
+    random_plies: usize,
+    tt: &Arc<TranspositionTable>,
+    adjudicator: &mut Adjudicator,
+) -> (Vec<Sample>, &'static str) {
+    adjudicator.clear();
+    tt.clear();
+    let mut game = Game::new();
+    for _ in 0..random_plies {
+        match Persona::Random.pick(game.board()) {
+            Ok((mv, _)) => game.make_move(&mv).unwrap(),
+            Err(_) => break,
+        }
+    }
+    let mut samples = Vec::new();
+    loop {
+        if let Some(result) = game.result() {
+            return (samples, result.score());
+        }
+        let move_number = game.board().move_number;
+        if move_number > MAX_MOVES {
+            return (samples, "1/2-1/2");
+        }
+        let b = game.board().clone();
+        let res = b.clone().search(limits, None, Some(tt.clone())).unwrap();
+        let quiet = !res.mv.takes && res.mv.promotion.is_none();
+        if quiet && !b.in_check(b.color_to_move()) && !is_mate_score(res.score) {
+            let score = match b.color_to_move() {
+                Color::White => res.score,
+                Color::Black => -res.score,
+            };
+            samples.push(Sample {
+                board: b.clone(),
+                score,
+            });
+        }
+        game.make_move(&res.mv).unwrap();
+        if let Some(adjudication) = adjudicator.record(b.color_to_move(), res.score, move_number) {
+            return (samples, adjudication.score());
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let mut options = Options::new();
+    options.optflag("h", "help", "Print this help menu.");
+    options.optopt(
+        "o",
+        "output",
+        "The file to append positions to (default: data.txt).",
+        "FILE",
+    );
+    options.optopt("g", "games", "Games to play (default: 1000).", "N");
+    options.optopt(
+        "n",
+        "nodes",
+        "Nodes to search for each move (default: 5000).",
+        "N",
+    );
+    options.optopt(
+        "d",
+        "depth",
+        "Plies to search for each move, instead of nodes.",
+        "PLIES",
+    );
+    options.optopt(
+        "r",
+        "random",
+        "Random moves to open each game with (default: 8).",
+        "PLIES",
+    );
+    options.optopt(
+        "t",
+        "threads",
+        "Games to play at once (default: the number of cores).",
+        "N",
+    );
+    options.optopt("f", "format", "text or epd (default: text).", "FORMAT");
+    let opts = options.parse(&args[1..]).unwrap();
+    if opts.opt_present("h") {
+        print_usage(&args[0], options);
+    }
+
+    let number = |name: &str, default: usize| {
+        opts.opt_str(name).map_or(default, |s| {
+            s.parse::<usize>().unwrap_or_else(|_| {
+                eprintln!("-{} should be a number", name);
+                exit(1);
+            })
+        })
+    };
+    let games = number("g", 1000);
+    let random_plies = number("r", 8);
+    let threads = number("t", num_cpus::get()).max(1);
+    let limits = match opts.opt_str("d") {
+        Some(_) => SearchLimits::depth(number("d", 0).saturating_sub(1)),
+        None => SearchLimits::nodes(number("n", 5000) as u64),
+    };
+    let format = match opts.opt_str("f").as_deref() {
+        None | Some("text") => Format::Text,
+        Some("epd") => Format::Epd,
+        Some(other) => {
+            eprintln!("no format {}", other);
+            exit(1);
+        }
+    };
+    let path = opts.opt_str("o").unwrap_or_else(|| "data.txt".to_string());
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .unwrap_or_else(|e| {
+            eprintln!("can't open {}: {}", path, e);
+            exit(1);
+        });
+    let mut out = BufWriter::new(file);
+
+    // each thread takes the next game until there are none left
+    let next_game = Arc::new(AtomicUsize::new(0));
+    let (tx, rx) = channel();
+    for _ in 0..threads {
+        let (next_game, tx, limits) = (next_game.clone(), tx.clone(), limits.clone());
+        thread::spawn(move || {
+            let tt = Arc::new(TranspositionTable::with_megabytes(HASH));
+            let mut adjudicator = Adjudicator::new();
+            while next_game.fetch_add(1, Ordering::SeqCst) < games {
+                let (samples, result) = play(&limits, random_plies, &tt, &mut adjudicator);
+                let lines: Vec<String> = samples.iter().map(|s| format.line(s, result)).collect();
+                if tx.send((lines, result)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    drop(tx);
+
+    let (mut played, mut positions) = (0, 0);
+    for (lines, result) in rx {
+        played += 1;
+        positions += lines.len();
+        let written = lines.iter().try_for_each(|line| writeln!(out, "{}", line));
+        if let Err(e) = written.and_then(|_| out.flush()) {
+            eprintln!("can't write to {}: {}", path, e);
+            exit(1);
+        }
+        println!(
+            "game {} of {}: {} with {} positions, {} in all",
+            played,
+            games,
+            result,
+            lines.len(),
+            positions
+        );
+    }
+}
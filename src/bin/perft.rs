@@ -0,0 +1,84 @@
+use combustion::board::Board;
+use combustion::threadpool::Threadpool;
+
+use std::env;
+use std::process::exit;
+use std::sync::{Arc, Condvar};
+use std::time::Instant;
+
+use getopts::Options;
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!("Usage: {} [OPTIONS] DEPTH", program);
+    print!("{}", opts.usage(&brief));
+    exit(0);
+}
+
+// counts the leaf nodes of the move tree, to check the move generator against known results
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let mut options = Options::new();
+    options.optflag("h", "help", "Print this help menu.");
+    options.optflag(
+        "d",
+        "divide",
+        "Print the count below each move from the position.",
+    );
+    options.optopt(
+        "f",
+        "fen",
+        "Position to count from (default: the initial position).",
+        "FEN",
+    );
+    options.optopt(
+        "t",
+        "threads",
+        "Number of threads to count with (default: number of cores).",
+        "N",
+    );
+    let opts = options.parse(&args[1..]).unwrap();
+    if opts.opt_present("h") || opts.free.len() != 1 {
+        print_usage(&args[0], options);
+    }
+
+    let depth = opts.free[0]
+        .parse::<usize>()
+        .expect("DEPTH should be a number!");
+    let nthreads = match opts.opt_str("t") {
+        Some(s) => s.parse::<usize>().expect("N should be a number!"),
+        None => num_cpus::get(),
+    };
+    let b = match opts.opt_str("f") {
+        Some(fen) => Board::from_fen(&fen).unwrap(),
+        None => Board::initial(),
+    };
+    println!("{}", b);
+
+    let start = Instant::now();
+    let divide = if nthreads > 1 {
+        let pool = Threadpool::new(nthreads, Arc::new(Condvar::new()));
+        pool.perft_divide(&b, depth)
+    } else {
+        b.perft_divide(depth)
+    };
+    let nodes: u64 = if depth == 0 {
+        1
+    } else {
+        divide.iter().map(|&(_, n)| n).sum()
+    };
+    let secs = start.elapsed().as_secs_f64();
+
+    if opts.opt_present("d") {
+        for (mv, n) in divide.iter() {
+            println!("{}: {}", mv.to_xboard_format(b.color_to_move()), n);
+        }
+        println!("moves: {}", divide.len());
+    }
+    println!(
+        "perft({}) = {} took={:.3}s nps={:.0}",
+        depth,
+        nodes,
+        secs,
+        nodes as f64 / secs
+    );
+}
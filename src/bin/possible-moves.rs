@@ -14,7 +14,7 @@ fn main() {
         board = board.make_move(&mv).unwrap();
     }
 
-    for mv in &board.legal_moves().unwrap() {
-        println!("{}", mv.to_xboard_format(board.color_to_move));
+    for mv in &board.legal_moves() {
+        println!("{}", mv.to_xboard_format(board.color_to_move()));
     }
 }
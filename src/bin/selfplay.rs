@@ -0,0 +1,351 @@
+extern crate combustion;
+
+use combustion::adjudication::{Adjudication, Adjudicator};
+use combustion::board_alpha_beta::SearchResult;
+use combustion::book::Book;
+use combustion::epd::EpdReader;
+use combustion::eval_params::{set_eval_params, EvalParams};
+use combustion::game::{Game, GameResult};
+use combustion::pgn::PgnGame;
+use combustion::piece::Color;
+use combustion::search_limits::{SearchLimits, Skill, MAX_SKILL};
+use combustion::sprt::{Hypothesis, MatchScore, Sprt};
+use combustion::transposition_table::{TranspositionTable, DEFAULT_MEGABYTES};
+
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use std::process::exit;
+use std::sync::Arc;
+use std::time::Duration;
+
+use getopts::Options;
+
+// a game still going after this many moves is drawn
+const MAX_MOVES: usize = 300;
+
+// book moves each opening starts with, when there is no file of openings
+const OPENING_PLIES: usize = 8;
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!(
+        "Usage: {} [OPTIONS]\n\n\
+         Each engine is a comma separated list of settings, like depth=6,weights=new.toml.\n\
+         The settings are name, depth (plies, as xboard's sd), nodes, movetime (ms),\n\
+         weights (a file of evaluation weights), skill (0 to 20), hash (MB) and mtdf (1 to\n\
+         search with mtd(f) instead of aspiration windows).",
+        program
+    );
+    print!("{}", opts.usage(&brief));
+    exit(0);
+}
+
+// one side of the match
+struct Engine {
+    name: String,
+    limits: SearchLimits,
+    params: EvalParams,
+    skill: Skill,
+    megabytes: usize,
+}
+
+impl Engine {
+    fn parse(spec: &str, name: &str) -> Result<Engine, String> {
+        let mut engine = Engine {
+            name: name.to_string(),
+            limits: SearchLimits::default(),
+            params: EvalParams::default(),
+            skill: Skill::level(MAX_SKILL),
+            megabytes: DEFAULT_MEGABYTES,
+        };
+        for setting in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (key, value) = setting
+                .split_once('=')
+                .ok_or_else(|| format!("no value in {}", setting))?;
+            let number = || {
+                value
+                    .parse::<u64>()
+                    .map_err(|_| format!("{} should be a number", key))
+            };
+            match key {
+                "name" => engine.name = value.to_string(),
+                "depth" => engine.limits.depth = Some((number()? as usize).saturating_sub(1)),
+                "nodes" => engine.limits.nodes = Some(number()?),
+                "movetime" => {
+                    let t = SearchLimits::move_time(Duration::from_millis(number()?));
+                    engine.limits.time = t.time;
+                }
+                "weights" => engine.params = EvalParams::load(value).map_err(|e| e.to_string())?,
+                "skill" => engine.skill = Skill::level(number()? as usize),
+                "hash" => engine.megabytes = number()? as usize,
+                "mtdf" => engine.limits.mtdf = number()? != 0,
+                _ => return Err(format!("no setting named {}", key)),
+            }
+        }
+        let limits = &engine.limits;
+        if limits.depth.is_none() && limits.nodes.is_none() && limits.time.is_none() {
+            // four plies from the root
+            engine.limits.depth = Some(3);
+        }
+        Ok(engine)
+    }
+
+    // the weights are set for the whole process, so each move sets its own
+    fn think(&self, game: &Game, tt: &Arc<TranspositionTable>) -> SearchResult {
+        set_eval_params(self.params);
+        let limits = self.skill.limit(self.limits.clone());
+        game.board()
+            .clone()
+            .search(&limits, None, Some(tt.clone()))
+            .unwrap()
+    }
+}
+
+enum Ending {
+    Rules(GameResult),
+    Adjudicated(Adjudication),
+}
+
+impl Ending {
+    fn score(&self) -> &'static str {
+        match self {
+            Ending::Rules(result) => result.score(),
+            Ending::Adjudicated(adjudication) => adjudication.score(),
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self {
+            Ending::Rules(result) => result.reason(),
+            Ending::Adjudicated(adjudication) => adjudication.reason(),
+        }
+    }
+}
+
+fn play(
+    white: &Engine,
+    black: &Engine,
+    mut game: Game,
+    adjudicator: &mut Adjudicator,
+) -> (Game, Ending) {
+    adjudicator.clear();
+    let tables = [
+        Arc::new(TranspositionTable::with_megabytes(white.megabytes)),
+        Arc::new(TranspositionTable::with_megabytes(black.megabytes)),
+    ];
+    loop {
+        if let Some(result) = game.result() {
+            return (game, Ending::Rules(result));
+        }
+        let move_number = game.board().move_number;
+        if move_number > MAX_MOVES {
+            return (game, Ending::Adjudicated(Adjudication::Draw));
+        }
+        let mover = game.board().color_to_move();
+        let (engine, tt) = if mover == Color::White {
+            (white, &tables[0])
+        } else {
+            (black, &tables[1])
+        };
+        let (mv, score) = engine.think(&game, tt).pick_near_best();
+        game.make_move(&mv).unwrap();
+        if let Some(adjudication) = adjudicator.record(mover, score, move_number) {
+            return (game, Ending::Adjudicated(adjudication));
+        }
+    }
+}
+
+// random lines from the book, so the games don't all repeat each other
+fn book_openings(n: usize) -> Vec<Game> {
+    let book = Book::builtin();
+    (0..n)
+        .map(|_| {
+            let mut game = Game::new();
+            for _ in 0..OPENING_PLIES {
+                match book.pick(game.board()) {
+                    Some(mv) => game.make_move(&mv).unwrap(),
+                    None => break,
+                }
+            }
+            game
+        })
+        .collect()
+}
+
+fn read_openings(path: &str) -> Result<Vec<Game>, String> {
+    let reader = EpdReader::open(Path::new(path)).map_err(|e| e.to_string())?;
+    let openings = reader
+        .map(|epd| epd.map(|epd| Game::from_board(epd.board)))
+        .collect::<Result<Vec<Game>, _>>()
+        .map_err(|e| e.to_string())?;
+    if openings.is_empty() {
+        return Err(format!("no positions in {}", path));
+    }
+    Ok(openings)
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let mut options = Options::new();
+    options.optflag("h", "help", "Print this help menu.");
+    options.optopt(
+        "a",
+        "first",
+        "The first engine, the one being tested.",
+        "SETTINGS",
+    );
+    options.optopt("b", "second", "The engine it plays against.", "SETTINGS");
+    options.optopt(
+        "g",
+        "games",
+        "Most games to play, in pairs with colors swapped (default: 100).",
+        "N",
+    );
+    options.optopt(
+        "o",
+        "openings",
+        "Start from the positions in an epd file rather than book lines.",
+        "FILE",
+    );
+    options.optopt("p", "pgn", "Append each game to a pgn file.", "FILE");
+    options.optopt(
+        "",
+        "elo0",
+        "Stop when the first is at most this much stronger (default: 0).",
+        "ELO",
+    );
+    options.optopt(
+        "",
+        "elo1",
+        "Stop when the first is at least this much stronger (default: 5).",
+        "ELO",
+    );
+    let opts = options.parse(&args[1..]).unwrap();
+    if opts.opt_present("h") {
+        print_usage(&args[0], options);
+    }
+
+    let or_exit = |res: Result<Engine, String>| {
+        res.unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            exit(1);
+        })
+    };
+    let first = or_exit(Engine::parse(
+        &opts.opt_str("a").unwrap_or_default(),
+        "first",
+    ));
+    let second = or_exit(Engine::parse(
+        &opts.opt_str("b").unwrap_or_default(),
+        "second",
+    ));
+    let games = opts
+        .opt_str("g")
+        .map_or(100, |s| s.parse::<usize>().expect("N should be a number!"));
+    let elo = |name: &str, default: f64| {
+        opts.opt_str(name).map_or(default, |s| {
+            s.parse::<f64>().expect("ELO should be a number!")
+        })
+    };
+    let sprt = Sprt::new(elo("elo0", 0.0), elo("elo1", 5.0));
+
+    let pairs = games.div_ceil(2);
+    let openings = match opts.opt_str("o") {
+        Some(path) => read_openings(&path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            exit(1);
+        }),
+        None => book_openings(pairs),
+    };
+
+    let mut adjudicator = Adjudicator::new();
+    let mut score = MatchScore::default();
+    'pairs: for round in 0..pairs {
+        let opening = &openings[round % openings.len()];
+        for &first_is_white in [true, false].iter() {
+            let (white, black) = if first_is_white {
+                (&first, &second)
+            } else {
+                (&second, &first)
+            };
+            let (game, ending) = play(white, black, opening.clone(), &mut adjudicator);
+            let first_won = match ending.score() {
+                "1-0" => Some(first_is_white),
+                "0-1" => Some(!first_is_white),
+                _ => None,
+            };
+            match first_won {
+                Some(true) => score.wins += 1,
+                Some(false) => score.losses += 1,
+                None => score.draws += 1,
+            }
+            println!(
+                "game {}: {} - {} {} {{{}}}",
+                score.games(),
+                white.name,
+                black.name,
+                ending.score(),
+                ending.reason()
+            );
+
+            if let Some(path) = opts.opt_str("p") {
+                let headers = vec![
+                    ("Event".to_string(), "combustion selfplay".to_string()),
+                    ("Site".to_string(), "?".to_string()),
+                    ("Date".to_string(), "????.??.??".to_string()),
+                    ("Round".to_string(), score.games().to_string()),
+                    ("White".to_string(), white.name.clone()),
+                    ("Black".to_string(), black.name.clone()),
+                    ("Result".to_string(), ending.score().to_string()),
+                ];
+                let pgn = match ending {
+                    Ending::Adjudicated(adjudication) => adjudication.to_pgn(game, headers),
+                    Ending::Rules(result) => PgnGame::from_game(game, headers, result.score()),
+                };
+                let written = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .and_then(|mut f| writeln!(f, "{}", pgn));
+                if let Err(e) = written {
+                    eprintln!("can't write the game to {}: {}", path, e);
+                }
+            }
+            if score.games() as usize >= games {
+                break 'pairs;
+            }
+        }
+
+        let (lower, upper) = sprt.bounds();
+        println!(
+            "{}: {}  elo {:.1} +/- {:.1}  los {:.1}%  llr {:.2} ({:.2}, {:.2})",
+            first.name,
+            score,
+            score.elo(),
+            score.elo_margin(),
+            100.0 * score.los(),
+            sprt.llr(&score),
+            lower,
+            upper
+        );
+        if sprt.result(&score).is_some() {
+            break;
+        }
+    }
+
+    println!(
+        "{} vs {}: {}  elo {:.1} +/- {:.1}  los {:.1}%",
+        first.name,
+        second.name,
+        score,
+        score.elo(),
+        score.elo_margin(),
+        100.0 * score.los()
+    );
+    match sprt.result(&score) {
+        Some(Hypothesis::H0) => println!("h0 accepted: at most {} elo", sprt.elo0),
+        Some(Hypothesis::H1) => println!("h1 accepted: at least {} elo", sprt.elo1),
+        None => println!("no sprt result after {} games", score.games()),
+    }
+}
@@ -0,0 +1,186 @@
+extern crate combustion;
+
+use combustion::board_alpha_beta::{is_mate_score, MATE};
+use combustion::epd::{Epd, EpdReader};
+use combustion::moves::Move;
+use combustion::pgn::san_line;
+use combustion::search_limits::SearchLimits;
+use combustion::transposition_table::TranspositionTable;
+
+use std::env;
+use std::path::Path;
+use std::process::exit;
+use std::sync::Arc;
+use std::time::Duration;
+
+use getopts::Options;
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!(
+        "Usage: {} [OPTIONS] [FEN]\n\n\
+         Solves each position for mate when given -m or a dm operation, otherwise finds the\n\
+         best move. Positions with bm or am operations are checked against them. With -p,\n\
+         mates of any length are looked for with proof-number search instead, though the\n\
+         mate found isn't always the shortest.",
+        program
+    );
+    print!("{}", opts.usage(&brief));
+    exit(0);
+}
+
+// pawns for the side to move, or #n for mate in n moves, negative when being mated
+fn format_score(score: isize) -> String {
+    if is_mate_score(score) {
+        let moves = (MATE - score.abs() + 1) / 2;
+        format!("#{}", score.signum() * moves)
+    } else {
+        format!("{:+.2}", score as f64 / 100.0)
+    }
+}
+
+// the moves in an operand like "Qxf7+ Nd5", ignoring any that don't parse
+fn moves_in(epd: &Epd, op: &str) -> Vec<Move> {
+    epd.op(op).map_or(Vec::new(), |sans| {
+        sans.split_whitespace()
+            .filter_map(|san| Move::from_san(&epd.board, san).ok())
+            .collect()
+    })
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let mut options = Options::new();
+    options.optflag("h", "help", "Print this help menu.");
+    options.optopt(
+        "f",
+        "file",
+        "Solve the positions in an epd file, one to a line.",
+        "FILE",
+    );
+    options.optopt("m", "mate", "Look for a mate in at most N moves.", "N");
+    options.optopt(
+        "p",
+        "proof",
+        "Prove a mate of any length, expanding at most NODES positions.",
+        "NODES",
+    );
+    options.optopt(
+        "t",
+        "time",
+        "Milliseconds to look for the best move (default: 5000).",
+        "MS",
+    );
+    options.optopt(
+        "d",
+        "depth",
+        "Plies to look for the best move, instead of a time.",
+        "PLIES",
+    );
+    let opts = options.parse(&args[1..]).unwrap();
+    if opts.opt_present("h") || (opts.free.is_empty() == opts.opt_str("f").is_none()) {
+        print_usage(&args[0], options);
+    }
+
+    let or_exit = |e: String| -> ! {
+        eprintln!("{}", e);
+        exit(1);
+    };
+    let positions: Vec<Epd> = match opts.opt_str("f") {
+        Some(path) => EpdReader::open(Path::new(&path))
+            .and_then(|reader| reader.collect())
+            .unwrap_or_else(|e| or_exit(e.to_string())),
+        None => vec![Epd::parse(&opts.free.join(" ")).unwrap_or_else(|e| or_exit(e.to_string()))],
+    };
+    let mate = opts
+        .opt_str("m")
+        .map(|s| s.parse::<usize>().expect("N should be a number!"));
+    let proof = opts
+        .opt_str("p")
+        .map(|s| s.parse::<usize>().expect("NODES should be a number!"));
+    let limits = match opts.opt_str("d") {
+        Some(s) => {
+            let plies = s.parse::<usize>().expect("PLIES should be a number!");
+            SearchLimits::depth(plies.saturating_sub(1))
+        }
+        None => {
+            let ms = opts
+                .opt_str("t")
+                .map_or(5000, |s| s.parse::<u64>().expect("MS should be a number!"));
+            SearchLimits::move_time(Duration::from_millis(ms))
+        }
+    };
+
+    let (mut checked, mut solved) = (0, 0);
+    for (i, epd) in positions.iter().enumerate() {
+        let id = epd
+            .op("id")
+            .map_or_else(|| (i + 1).to_string(), |id| id.to_string());
+        let b = &epd.board;
+        let mate = mate.or_else(|| epd.op("dm").and_then(|n| n.parse::<usize>().ok()));
+
+        if let Some(nodes) = proof {
+            checked += 1;
+            match b.prove_mate(nodes) {
+                Some(line) => {
+                    solved += 1;
+                    println!(
+                        "{}: mate in {}: {}",
+                        id,
+                        line.len().div_ceil(2),
+                        san_line(b, &line)
+                    );
+                }
+                None => println!("{}: no mate proven in {} positions", id, nodes),
+            }
+            continue;
+        }
+
+        if let Some(n) = mate {
+            checked += 1;
+            match b.find_mate(n) {
+                Some(line) => {
+                    solved += 1;
+                    println!(
+                        "{}: mate in {}: {}",
+                        id,
+                        line.len().div_ceil(2),
+                        san_line(b, &line)
+                    );
+                }
+                None => println!("{}: no mate in {}", id, n),
+            }
+            continue;
+        }
+
+        let tt = Arc::new(TranspositionTable::new());
+        let res = match b.clone().search(&limits, None, Some(tt)) {
+            Ok(res) => res,
+            Err(e) => {
+                println!("{}: {}", id, e);
+                continue;
+            }
+        };
+        let (best, avoid) = (moves_in(epd, "bm"), moves_in(epd, "am"));
+        let verdict = if best.is_empty() && avoid.is_empty() {
+            ""
+        } else if (best.is_empty() || best.contains(&res.mv)) && !avoid.contains(&res.mv) {
+            checked += 1;
+            solved += 1;
+            " ok"
+        } else {
+            checked += 1;
+            " wrong"
+        };
+        println!(
+            "{}: {} {}{}: {}",
+            id,
+            res.mv.to_san(b),
+            format_score(res.score),
+            verdict,
+            san_line(b, &res.pv)
+        );
+    }
+    if checked > 0 {
+        println!("solved {} of {}", solved, checked);
+    }
+}
@@ -0,0 +1,71 @@
+use combustion::tablebase::{Table, Tablebase, ENDINGS};
+
+use std::env;
+use std::path::Path;
+use std::process::exit;
+use std::time::Instant;
+
+use getopts::Options;
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!("Usage: {} [OPTIONS] [ENDING...]", program);
+    print!("{}", opts.usage(&brief));
+    exit(0);
+}
+
+// generates distance to mate tables for endings like KQK or KBNK, for combustion -t
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let mut options = Options::new();
+    options.optflag("h", "help", "Print this help menu.");
+    options.optopt(
+        "o",
+        "output",
+        "Directory to write NAME.tb files to, and read the tables needed for promotions \
+         from (default: the current directory).",
+        "DIR",
+    );
+    let opts = options.parse(&args[1..]).unwrap();
+    if opts.opt_present("h") {
+        print_usage(&args[0], options);
+    }
+    let dir = opts.opt_str("o").unwrap_or_else(|| ".".to_string());
+    let dir = Path::new(&dir);
+    let endings: Vec<String> = if opts.free.is_empty() {
+        ENDINGS.iter().map(|s| s.to_string()).collect()
+    } else {
+        opts.free.clone()
+    };
+
+    let mut tb = match Tablebase::load_dir(dir) {
+        Ok(tb) => tb,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(1);
+        }
+    };
+    for ending in endings {
+        let start = Instant::now();
+        let table = match Table::generate(&ending, &tb) {
+            Ok(table) => table,
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        };
+        let (wins, longest) = table.wins();
+        println!(
+            "{}: {} won positions, longest mate {} plies, in {:.1}s",
+            table.name(),
+            wins,
+            longest,
+            start.elapsed().as_secs_f64()
+        );
+        let path = dir.join(format!("{}.tb", table.name()));
+        if let Err(e) = table.save(&path) {
+            eprintln!("{}", e);
+            exit(1);
+        }
+        tb.insert(table);
+    }
+}
@@ -0,0 +1,648 @@
+extern crate combustion;
+
+use combustion::adjudication::{Adjudication, Adjudicator};
+use combustion::board::Board;
+use combustion::board_alpha_beta::MATE;
+use combustion::book::Book;
+use combustion::game::{Game, GameResult};
+use combustion::moves::{coordinates, Move};
+use combustion::pgn::PgnGame;
+use combustion::piece::Color;
+use combustion::search_limits::{SearchLimits, Skill, MAX_SKILL};
+use combustion::time_manager::TimeManager;
+use combustion::transposition_table::{TranspositionTable, DEFAULT_MEGABYTES};
+
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{exit, Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use getopts::Options;
+
+// a game still going after this many moves is drawn
+const MAX_MOVES: usize = 300;
+
+// book moves each opening starts with
+const OPENING_PLIES: usize = 8;
+
+// how long an engine gets to start up and answer isready
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+// how long past its clock an engine may take to answer before it loses on time, for
+// the pipes and the process scheduler
+const GRACE: Duration = Duration::from_millis(200);
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!(
+        "Usage: {} [OPTIONS]\n\n\
+         Each engine is a comma separated list of settings. One with cmd is a uci engine run\n\
+         as a process, like cmd=/usr/bin/stockfish,option.Hash=64, and one without is\n\
+         combustion. Both take name, depth, nodes and movetime (ms), which replace the clock.\n\
+         Combustion also takes skill (0 to 20), hash (MB) and mtdf (1 to search with mtd(f)).\n\
+         Without a combustion of its own the list starts with one at full strength.",
+        program
+    );
+    print!("{}", opts.usage(&brief));
+    exit(0);
+}
+
+// uci writes moves like xboard, except that en passant has no suffix
+fn from_uci(s: &str, b: &Board) -> Option<Move> {
+    b.legal_moves()
+        .into_iter()
+        .find(|mv| coordinates(mv, b) == s)
+}
+
+// both sides' time in centiseconds, like the clock, and the increment each move adds
+#[derive(Debug, Clone, Copy)]
+struct Clocks {
+    time: [isize; 2],
+    increment: isize,
+}
+
+impl Clocks {
+    // from seconds and an optional increment, like 60+0.5
+    fn parse(s: &str) -> Option<Clocks> {
+        let (base, increment) = s.split_once('+').unwrap_or((s, "0"));
+        let csecs = |t: &str| t.parse::<f64>().ok().map(|t| (t * 100.0) as isize);
+        let base = csecs(base)?;
+        Some(Clocks {
+            time: [base, base],
+            increment: csecs(increment)?,
+        })
+    }
+
+    fn left(&self, c: Color) -> isize {
+        self.time[c as usize]
+    }
+}
+
+// what one side of a game needs
+trait Player {
+    fn name(&self) -> &str;
+
+    fn new_game(&mut self) -> Result<(), String>;
+
+    // the move to play in the game's position, with its score for the side to move when
+    // the player says
+    fn think(&mut self, game: &Game, clocks: &Clocks) -> Result<(Move, Option<isize>), String>;
+}
+
+// the limits a spec gives instead of the clock
+fn fixed_limit(limits: &mut SearchLimits, key: &str, value: &str) -> Result<bool, String> {
+    let number = || {
+        value
+            .parse::<u64>()
+            .map_err(|_| format!("{} should be a number", key))
+    };
+    match key {
+        "depth" => limits.depth = Some((number()? as usize).saturating_sub(1)),
+        "nodes" => limits.nodes = Some(number()?),
+        "movetime" => limits.time = SearchLimits::move_time(Duration::from_millis(number()?)).time,
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+struct Combustion {
+    name: String,
+    // fixed limits for each move, or the default to play on the clock
+    limits: SearchLimits,
+    skill: Skill,
+    tt: Arc<TranspositionTable>,
+    time_manager: TimeManager,
+}
+
+impl Combustion {
+    fn parse(spec: &str) -> Result<Combustion, String> {
+        let mut player = Combustion {
+            name: "combustion".to_string(),
+            limits: SearchLimits::default(),
+            skill: Skill::level(MAX_SKILL),
+            tt: Arc::new(TranspositionTable::with_megabytes(DEFAULT_MEGABYTES)),
+            time_manager: TimeManager::new(),
+        };
+        for (key, value) in settings(spec)? {
+            if fixed_limit(&mut player.limits, key, value)? {
+                continue;
+            }
+            let number = value
+                .parse::<usize>()
+                .map_err(|_| format!("{} should be a number", key));
+            match key {
+                "name" => player.name = value.to_string(),
+                "skill" => player.skill = Skill::level(number?),
+                "hash" => player.tt = Arc::new(TranspositionTable::with_megabytes(number?)),
+                "mtdf" => player.limits.mtdf = number? != 0,
+                _ => return Err(format!("no setting named {}", key)),
+            }
+        }
+        Ok(player)
+    }
+}
+
+impl Player for Combustion {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn new_game(&mut self) -> Result<(), String> {
+        self.tt.clear();
+        Ok(())
+    }
+
+    fn think(&mut self, game: &Game, clocks: &Clocks) -> Result<(Move, Option<isize>), String> {
+        let b = game.board();
+        let mut limits = self.limits.clone();
+        if limits.depth.is_none() && limits.nodes.is_none() && limits.time.is_none() {
+            self.time_manager.set_level(0, clocks.increment);
+            let budget = self
+                .time_manager
+                .budget(clocks.left(b.color_to_move()), b.move_number - 1);
+            limits.time = Some(budget);
+        }
+        let limits = self.skill.limit(limits);
+        let res = b
+            .clone()
+            .search(&limits, None, Some(self.tt.clone()))
+            .map_err(|e| e.to_string())?;
+        let (mv, score) = res.pick_near_best();
+        Ok((mv, Some(score)))
+    }
+}
+
+// an engine in another process, spoken to over its stdin and stdout
+struct UciEngine {
+    name: String,
+    // what follows go when the clock isn't used, like "depth 10"
+    go: Option<String>,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl UciEngine {
+    fn start(spec: &str) -> Result<UciEngine, String> {
+        let mut cmd = None;
+        let mut name = None;
+        let mut options = Vec::new();
+        let mut limits = SearchLimits::default();
+        let mut go = Vec::new();
+        for (key, value) in settings(spec)? {
+            if fixed_limit(&mut limits, key, value)? {
+                go.push(format!("{} {}", key, value));
+            } else if let Some(option) = key.strip_prefix("option.") {
+                options.push((option, value));
+            } else {
+                match key {
+                    "cmd" => cmd = Some(value),
+                    "name" => name = Some(value.to_string()),
+                    _ => return Err(format!("no setting named {}", key)),
+                }
+            }
+        }
+        let cmd = cmd.ok_or("no cmd to run")?;
+
+        let mut words = cmd.split_whitespace();
+        let program = words.next().ok_or("no cmd to run")?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("can't run {}: {}", cmd, e))?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (tx, rx) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = UciEngine {
+            name: cmd.to_string(),
+            go: if go.is_empty() {
+                None
+            } else {
+                Some(go.join(" "))
+            },
+            child,
+            stdin,
+            lines: rx,
+        };
+        engine.send("uci")?;
+        let deadline = Instant::now() + STARTUP_TIMEOUT;
+        loop {
+            let line = engine.read_line(deadline)?;
+            if let Some(id) = line.strip_prefix("id name ") {
+                engine.name = id.trim().to_string();
+            } else if line.trim() == "uciok" {
+                break;
+            }
+        }
+        if let Some(name) = name {
+            engine.name = name;
+        }
+        for (option, value) in options {
+            engine.send(&format!("setoption name {} value {}", option, value))?;
+        }
+        engine.ready()?;
+        Ok(engine)
+    }
+
+    fn send(&mut self, line: &str) -> Result<(), String> {
+        writeln!(self.stdin, "{}", line)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|e| format!("{} stopped: {}", self.name, e))
+    }
+
+    fn read_line(&self, deadline: Instant) -> Result<String, String> {
+        let wait = deadline.saturating_duration_since(Instant::now());
+        match self.lines.recv_timeout(wait) {
+            Ok(line) => Ok(line),
+            Err(RecvTimeoutError::Timeout) => Err(format!("{} took too long", self.name)),
+            Err(RecvTimeoutError::Disconnected) => Err(format!("{} stopped", self.name)),
+        }
+    }
+
+    fn ready(&mut self) -> Result<(), String> {
+        self.send("isready")?;
+        let deadline = Instant::now() + STARTUP_TIMEOUT;
+        while self.read_line(deadline)?.trim() != "readyok" {}
+        Ok(())
+    }
+}
+
+impl Player for UciEngine {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn new_game(&mut self) -> Result<(), String> {
+        self.send("ucinewgame")?;
+        self.ready()
+    }
+
+    fn think(&mut self, game: &Game, clocks: &Clocks) -> Result<(Move, Option<isize>), String> {
+        let start = game.start();
+        let mut position = if *start == Board::initial() {
+            "position startpos".to_string()
+        } else {
+            format!("position fen {}", start.to_fen())
+        };
+        if !game.moves().is_empty() {
+            position.push_str(" moves");
+            for (mv, b) in game.moves().iter().zip(game.positions()) {
+                position.push(' ');
+                position.push_str(&coordinates(mv, b));
+            }
+        }
+        self.send(&position)?;
+
+        let b = game.board();
+        let deadline = match &self.go {
+            Some(go) => {
+                let go = format!("go {}", go);
+                self.send(&go)?;
+                // fixed limits have no clock to lose on
+                Instant::now() + Duration::from_secs(24 * 60 * 60)
+            }
+            None => {
+                let ms = |csecs: isize| csecs.max(0) * 10;
+                let go = format!(
+                    "go wtime {} btime {} winc {} binc {}",
+                    ms(clocks.left(Color::White)),
+                    ms(clocks.left(Color::Black)),
+                    ms(clocks.increment),
+                    ms(clocks.increment)
+                );
+                self.send(&go)?;
+                let left = clocks.left(b.color_to_move()).max(0) as u64 * 10;
+                Instant::now() + Duration::from_millis(left) + GRACE
+            }
+        };
+
+        let mut score = None;
+        loop {
+            let line = self.read_line(deadline)?;
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                ["bestmove", mv, ..] => {
+                    return match from_uci(mv, b) {
+                        Some(mv) => Ok((mv, score)),
+                        None => Err(format!("{} played the illegal move {}", self.name, mv)),
+                    };
+                }
+                ["info", rest @ ..] => {
+                    if let Some(i) = rest.iter().position(|&w| w == "score") {
+                        score = match (rest.get(i + 1), rest.get(i + 2)) {
+                            (Some(&"cp"), Some(cp)) => cp.parse::<isize>().ok().or(score),
+                            (Some(&"mate"), Some(n)) => match n.parse::<isize>() {
+                                Ok(n) if n > 0 => Some(MATE - (2 * n - 1)),
+                                Ok(n) => Some(-(MATE + 2 * n)),
+                                Err(_) => score,
+                            },
+                            _ => score,
+                        };
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        thread::sleep(Duration::from_millis(100));
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// the key=value pairs of a player spec
+fn settings(spec: &str) -> Result<Vec<(&str, &str)>, String> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            s.split_once('=')
+                .ok_or_else(|| format!("no value in {}", s))
+        })
+        .collect()
+}
+
+enum Ending {
+    Rules(GameResult),
+    Adjudicated(Adjudication),
+    // the loser, for running out of time, an illegal move or its process failing
+    Forfeit(Color, String),
+}
+
+impl Ending {
+    fn score(&self) -> &'static str {
+        match self {
+            Ending::Rules(result) => result.score(),
+            Ending::Adjudicated(adjudication) => adjudication.score(),
+            Ending::Forfeit(Color::White, _) => "0-1",
+            Ending::Forfeit(Color::Black, _) => "1-0",
+        }
+    }
+
+    fn reason(&self) -> String {
+        match self {
+            Ending::Rules(result) => result.reason().to_string(),
+            Ending::Adjudicated(adjudication) => adjudication.reason().to_string(),
+            Ending::Forfeit(_, why) => why.clone(),
+        }
+    }
+}
+
+fn play(
+    mut players: [&mut dyn Player; 2],
+    mut game: Game,
+    mut clocks: Clocks,
+    adjudicator: &mut Adjudicator,
+) -> (Game, Ending) {
+    adjudicator.clear();
+    for (player, c) in players.iter_mut().zip(Color::both()) {
+        if let Err(e) = player.new_game() {
+            return (game, Ending::Forfeit(c, e));
+        }
+    }
+    loop {
+        if let Some(result) = game.result() {
+            return (game, Ending::Rules(result));
+        }
+        let move_number = game.board().move_number;
+        if move_number > MAX_MOVES {
+            return (game, Ending::Adjudicated(Adjudication::Draw));
+        }
+        let mover = game.board().color_to_move();
+        let start = Instant::now();
+        let thought = players[mover as usize].think(&game, &clocks);
+        clocks.time[mover as usize] -= (start.elapsed().as_millis() / 10) as isize;
+        let (mv, score) = match thought {
+            Ok(thought) => thought,
+            Err(e) => return (game, Ending::Forfeit(mover, e)),
+        };
+        if clocks.left(mover) < 0 {
+            let why = match mover {
+                Color::White => "White loses on time",
+                Color::Black => "Black loses on time",
+            };
+            return (game, Ending::Forfeit(mover, why.to_string()));
+        }
+        clocks.time[mover as usize] += clocks.increment;
+        game.make_move(&mv).unwrap();
+        if let Some(score) = score {
+            if let Some(adjudication) = adjudicator.record(mover, score, move_number) {
+                return (game, Ending::Adjudicated(adjudication));
+            }
+        }
+    }
+}
+
+fn opening() -> Game {
+    let book = Book::builtin();
+    let mut game = Game::new();
+    for _ in 0..OPENING_PLIES {
+        match book.pick(game.board()) {
+            Some(mv) => game.make_move(&mv).unwrap(),
+            None => break,
+        }
+    }
+    game
+}
+
+// points out of games for each player against each other
+fn crosstable(names: &[String], points: &[Vec<f64>], games: &[Vec<usize>]) -> String {
+    let totals: Vec<(f64, usize)> = points
+        .iter()
+        .zip(games)
+        .map(|(p, g)| (p.iter().sum(), g.iter().sum()))
+        .collect();
+    let mut order: Vec<usize> = (0..names.len()).collect();
+    order.sort_by(|&a, &b| totals[b].0.partial_cmp(&totals[a].0).unwrap());
+
+    let width = names.iter().map(|n| n.len()).max().unwrap_or(0).max(6);
+    let mut s = format!(
+        "{:>3}  {:<width$}  {:>11}",
+        "",
+        "engine",
+        "score",
+        width = width
+    );
+    for rank in 1..=names.len() {
+        s.push_str(&format!("  {:>9}", rank));
+    }
+    for (rank, &i) in order.iter().enumerate() {
+        let (total, played) = totals[i];
+        s.push_str(&format!(
+            "\n{:>3}  {:<width$}  {:>11}",
+            rank + 1,
+            names[i],
+            format!("{}/{}", total, played),
+            width = width
+        ));
+        for &j in &order {
+            let cell = if i == j || games[i][j] == 0 {
+                "-".to_string()
+            } else {
+                format!("{}/{}", points[i][j], games[i][j])
+            };
+            s.push_str(&format!("  {:>9}", cell));
+        }
+    }
+    s
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let mut options = Options::new();
+    options.optflag("h", "help", "Print this help menu.");
+    options.optmulti(
+        "e",
+        "engine",
+        "An engine to play, by its settings.",
+        "SETTINGS",
+    );
+    options.optflag(
+        "",
+        "gauntlet",
+        "Only the first engine plays the others, rather than all playing all.",
+    );
+    options.optopt(
+        "t",
+        "tc",
+        "Seconds on each clock and the increment (default: 10+0.1).",
+        "SECS[+INC]",
+    );
+    options.optopt(
+        "g",
+        "games",
+        "Games between each pair, with colors alternating (default: 2).",
+        "N",
+    );
+    options.optopt("p", "pgn", "Append each game to a pgn file.", "FILE");
+    let opts = options.parse(&args[1..]).unwrap();
+    if opts.opt_present("h") {
+        print_usage(&args[0], options);
+    }
+
+    let or_exit = |e: String| -> ! {
+        eprintln!("{}", e);
+        exit(1);
+    };
+    let specs = opts.opt_strs("e");
+    let mut players: Vec<Box<dyn Player>> = Vec::new();
+    if specs.iter().all(|spec| spec.contains("cmd=")) {
+        players.push(Box::new(Combustion::parse("").unwrap()));
+    }
+    for spec in &specs {
+        if spec.contains("cmd=") {
+            players.push(Box::new(
+                UciEngine::start(spec).unwrap_or_else(|e| or_exit(e)),
+            ));
+        } else {
+            players.push(Box::new(
+                Combustion::parse(spec).unwrap_or_else(|e| or_exit(e)),
+            ));
+        }
+    }
+    if players.len() < 2 {
+        or_exit("a tournament needs at least two engines".to_string());
+    }
+    let clocks = Clocks::parse(&opts.opt_str("t").unwrap_or_else(|| "10+0.1".to_string()))
+        .unwrap_or_else(|| or_exit("the time control should be like 60+0.5".to_string()));
+    let games = opts
+        .opt_str("g")
+        .map_or(2, |s| s.parse::<usize>().expect("N should be a number!"));
+
+    let n = players.len();
+    let pairings: Vec<(usize, usize)> = if opts.opt_present("gauntlet") {
+        (1..n).map(|j| (0, j)).collect()
+    } else {
+        (0..n)
+            .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+            .collect()
+    };
+    let names: Vec<String> = players.iter().map(|p| p.name().to_string()).collect();
+    let mut points = vec![vec![0.0; n]; n];
+    let mut played = vec![vec![0; n]; n];
+    let mut adjudicator = Adjudicator::new();
+    let mut round = 0;
+    for &(i, j) in &pairings {
+        let mut start = opening();
+        for g in 0..games {
+            // each opening is played twice, once from each side
+            if g % 2 == 0 && g > 0 {
+                start = opening();
+            }
+            let (white, black) = if g % 2 == 0 { (i, j) } else { (j, i) };
+            let (first, second) = players.split_at_mut(j);
+            let (pi, pj) = (&mut *first[i], &mut *second[0]);
+            let sides: [&mut dyn Player; 2] = if white == i { [pi, pj] } else { [pj, pi] };
+            let (game, ending) = play(sides, start.clone(), clocks, &mut adjudicator);
+
+            let white_points = match ending.score() {
+                "1-0" => 1.0,
+                "0-1" => 0.0,
+                _ => 0.5,
+            };
+            points[white][black] += white_points;
+            points[black][white] += 1.0 - white_points;
+            played[white][black] += 1;
+            played[black][white] += 1;
+            round += 1;
+            println!(
+                "game {}: {} - {} {} {{{}}}",
+                round,
+                names[white],
+                names[black],
+                ending.score(),
+                ending.reason()
+            );
+
+            if let Some(path) = opts.opt_str("p") {
+                let headers = vec![
+                    ("Event".to_string(), "combustion tournament".to_string()),
+                    ("Site".to_string(), "?".to_string()),
+                    ("Date".to_string(), "????.??.??".to_string()),
+                    ("Round".to_string(), round.to_string()),
+                    ("White".to_string(), names[white].clone()),
+                    ("Black".to_string(), names[black].clone()),
+                    ("Result".to_string(), ending.score().to_string()),
+                ];
+                let pgn = match ending {
+                    Ending::Adjudicated(adjudication) => adjudication.to_pgn(game, headers),
+                    _ => {
+                        let mut pgn = PgnGame::from_game(game, headers, ending.score());
+                        if let Some(last) = pgn.line.moves.last_mut() {
+                            last.comments.push(ending.reason());
+                        }
+                        pgn
+                    }
+                };
+                let written = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&path)
+                    .and_then(|mut f| writeln!(f, "{}", pgn));
+                if let Err(e) = written {
+                    eprintln!("can't write the game to {}: {}", path, e);
+                }
+            }
+        }
+    }
+    println!("\n{}", crosstable(&names, &points, &played));
+}
@@ -1,26 +1,92 @@
+use crate::board_render::RenderOptions;
+use crate::board_threatens::ThreatCache;
+use crate::mailbox::Mailbox;
 use crate::piece::{Color, Piece, PieceType};
 use crate::position::Pos;
 
-use std::fmt;
+use core::fmt;
+use core::hash::{Hash, Hasher};
 
+#[cfg(not(feature = "std"))]
+use crate::alloc_prelude::*;
+
+// where the game stands for the side to move. a game that is drawn may also be check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameStatus {
+    InProgress,
+    Check,
+    Checkmate,
+    Stalemate,
+    DrawBy(Draw),
+}
+
+// why a game that isn't over by checkmate or stalemate is drawn. fifty moves and
+// repetition are draws either side may claim, insufficient material is automatic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Draw {
+    FiftyMove,
+    // only a game knows its earlier positions, see Game::status
+    Repetition,
+    InsufficientMaterial,
+}
+
+impl GameStatus {
+    // checkmate, stalemate or a draw
+    pub fn is_over(&self) -> bool {
+        !matches!(*self, GameStatus::InProgress | GameStatus::Check)
+    }
+}
+
+impl fmt::Display for GameStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GameStatus::InProgress => write!(f, "in progress"),
+            GameStatus::Check => write!(f, "check"),
+            GameStatus::Checkmate => write!(f, "checkmate"),
+            GameStatus::Stalemate => write!(f, "stalemate"),
+            GameStatus::DrawBy(draw) => write!(f, "draw by {}", draw),
+        }
+    }
+}
+
+impl fmt::Display for Draw {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Draw::FiftyMove => write!(f, "the fifty move rule"),
+            Draw::Repetition => write!(f, "repetition"),
+            Draw::InsufficientMaterial => write!(f, "insufficient material"),
+        }
+    }
+}
+
+// the fields that go into the zobrist hash are only written here in the crate, where
+// the key is kept up to date. the setters below do the same for everyone else.
+#[derive(Clone)]
 pub struct Board {
-    pub board: [Option<Piece>; 64],
-    pub color_to_move: Color,
-    pub castle_rights: [bool; 4], // [ white K, white Q, black k, black q ]
-    pub en_passant_target: Option<Pos>,
+    pub(crate) board: Mailbox,
+    pub(crate) color_to_move: Color,
+    pub(crate) castle_rights: [bool; 4], // [ white K, white Q, black k, black q ]
+    pub(crate) en_passant_target: Option<Pos>,
     pub halfmove_clock: usize,
     pub move_number: usize,
+    // what each side attacks, for as long as the position stays the same. code that
+    // changes the board in place must clear it.
+    pub(crate) threats: ThreatCache,
+    // the zobrist hash, updated along with the board
+    pub(crate) key: u64,
 }
 
 impl Board {
     pub fn new() -> Self {
         Board {
-            board: [None; 64],
+            board: Mailbox::empty(),
             color_to_move: Color::White,
             castle_rights: [false, false, false, false],
             en_passant_target: None,
             halfmove_clock: 0,
             move_number: 1,
+            threats: ThreatCache::default(),
+            key: 0,
         }
     }
 
@@ -29,43 +95,87 @@ impl Board {
     }
 
     pub fn piece(&self, loc: Pos) -> Option<Piece> {
-        self.board[loc.index()]
+        self.board.get(loc)
     }
 
     // ignores bad formating of the string!
     pub fn get_piece_at(&mut self, s: &str) -> Option<Piece> {
-        self.board[pos!(s).index()].take()
+        self.threats.clear();
+        self.take_piece(pos!(s))
     }
 
     // ignores bad formating of the string!
     pub fn put_piece_at(&mut self, p: Piece, s: &str) {
         let pos = Pos::from_algebra(s).unwrap();
         assert!(self.piece(pos).is_none());
-        self.board[pos.index()] = Some(p);
+        self.threats.clear();
+        self.set_piece(pos, Some(p));
     }
 
     pub fn pieces(&self, f: &dyn Fn(Piece) -> bool) -> Vec<(Pos, Piece)> {
-        let mut res = Vec::new();
-        for ix in 0..64 {
-            self.board[ix].map(|p| {
-                if f(p) {
-                    res.push((Pos::from_index(ix), p));
-                }
-            });
-        }
-        res
+        self.iter_pieces().filter(|&(_, p)| f(p)).collect()
     }
 
     pub fn get_pieces_by_type_and_color(&self, k: PieceType, c: Color) -> Vec<Pos> {
+        self.iter_pieces_by_type_and_color(k, c).collect()
+    }
+
+    pub fn get_pieces_by_color(&self, c: Color) -> Vec<(Pos, Piece)> {
+        self.iter_pieces_by_color(c).collect()
+    }
+
+    // the pieces on the board, in order of square from a8 to h1. unlike the functions
+    // above these don't allocate, for the loops in move generation and evaluation.
+    pub fn iter_pieces(&self) -> impl Iterator<Item = (Pos, Piece)> + '_ {
+        Pos::all().filter_map(move |pos| self.piece(pos).map(|p| (pos, p)))
+    }
+
+    pub fn iter_pieces_by_color(&self, c: Color) -> impl Iterator<Item = (Pos, Piece)> + '_ {
+        self.iter_pieces().filter(move |&(_, p)| p.color == c)
+    }
+
+    pub fn iter_pieces_by_type_and_color(
+        &self,
+        k: PieceType,
+        c: Color,
+    ) -> impl Iterator<Item = Pos> + '_ {
         let q = Piece { kind: k, color: c };
-        self.pieces(&|p| p == q)
-            .into_iter()
+        self.iter_pieces()
+            .filter(move |&(_, p)| p == q)
             .map(|(pos, _)| pos)
-            .collect()
     }
 
-    pub fn get_pieces_by_color(&self, c: Color) -> Vec<(Pos, Piece)> {
-        self.pieces(&|p| p.color == c)
+    pub fn color_to_move(&self) -> Color {
+        self.color_to_move
+    }
+
+    pub fn castle_rights(&self) -> [bool; 4] {
+        self.castle_rights
+    }
+
+    pub fn en_passant_target(&self) -> Option<Pos> {
+        self.en_passant_target
+    }
+
+    // these change the position without a move, as an editor does, so they don't check
+    // that it is legal afterwards
+    pub fn set_color_to_move(&mut self, c: Color) {
+        self.key ^= self.state_key();
+        self.color_to_move = c;
+        self.key ^= self.state_key();
+    }
+
+    pub fn set_castle_rights(&mut self, rights: [bool; 4]) {
+        self.key ^= self.state_key();
+        self.castle_rights = rights;
+        self.key ^= self.state_key();
+    }
+
+    pub fn set_en_passant_target(&mut self, ep: Option<Pos>) {
+        self.key ^= self.state_key();
+        self.en_passant_target = ep;
+        self.key ^= self.state_key();
+        self.threats.clear();
     }
 
     pub fn occupied(&self, pos: Pos) -> bool {
@@ -76,6 +186,30 @@ impl Board {
         self.en_passant_target.map_or(false, |q| p == q)
     }
 
+    // fifty moves by each side without a capture or pawn move: either side may claim a draw
+    pub fn fifty_move_draw(&self) -> bool {
+        self.halfmove_clock >= 100
+    }
+
+    // neither side can ever mate: only kings, a single knight or bishop, or bishops that
+    // are all on squares of one color
+    pub fn insufficient_material(&self) -> bool {
+        let mut knights = 0;
+        // by the color of their squares
+        let mut bishops = [0; 2];
+        for (pos, p) in self.board.iter() {
+            match p.map(|p| p.kind) {
+                Some(PieceType::Pawn) | Some(PieceType::Rook) | Some(PieceType::Queen) => {
+                    return false;
+                }
+                Some(PieceType::Knight) => knights += 1,
+                Some(PieceType::Bishop) => bishops[pos.is_light() as usize] += 1,
+                _ => {}
+            }
+        }
+        matches!((knights, bishops), (0, [_, 0]) | (0, [0, _]) | (1, [0, 0]))
+    }
+
     pub fn castle_kingside_rights(&self, c: Color) -> bool {
         match c {
             Color::White => self.castle_rights[0],
@@ -89,73 +223,65 @@ impl Board {
             Color::Black => self.castle_rights[3],
         }
     }
-}
 
-impl PartialEq for Board {
-    fn eq(&self, other: &Board) -> bool {
-        let mut eq = true;
-        eq &= self.color_to_move == other.color_to_move;
-        eq &= self.castle_rights == other.castle_rights;
-        eq &= self.en_passant_target == other.en_passant_target;
-        // // these properties dont affect what the next move could be
-        eq &= self.halfmove_clock == other.halfmove_clock;
-        eq &= self.move_number == other.move_number;
-        for i in 0..64 {
-            match (self.board[i], other.board[i]) {
-                (Some(p), Some(q)) => eq &= p == q,
-                (None, None) => {}
-                _ => return false,
-            }
+    // the same position with the board turned upside down and the colors swapped, so it
+    // is the other side to move with the same choices
+    pub fn flipped(&self) -> Board {
+        let flip = |pos: Pos| Pos::at(pos.file(), pos.rank().flip());
+        let mut b = self.clone();
+        for pos in Pos::all() {
+            b.set_piece(
+                flip(pos),
+                self.piece(pos).map(|p| Piece {
+                    color: p.color.other(),
+                    ..p
+                }),
+            );
         }
-        eq
+        b.set_color_to_move(self.color_to_move.other());
+        let rights = self.castle_rights;
+        b.set_castle_rights([rights[2], rights[3], rights[0], rights[1]]);
+        b.set_en_passant_target(self.en_passant_target.map(flip));
+        b
     }
-}
 
-impl fmt::Display for Board {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for i in 0..8 {
-            write!(f, "{} [ ", 8 - i)?;
-            for j in 0..8 {
-                match self.board[i * 8 + j] {
-                    Some(x) => write!(f, "{} ", x)?,
-                    None => write!(f, "_ ")?,
-                }
-            }
-            write!(f, "]\n")?;
-        }
-        write!(f, "    a b c d e f g h\n")?;
-        write!(f, "{}.", self.move_number)?;
-
-        match self.color_to_move {
-            Color::White => write!(f, " White to move.")?,
-            Color::Black => write!(f, " Black to move.")?,
+    // the position reflected between the a and h files. castling rights are dropped,
+    // since the kings and rooks end up on the wrong sides for them.
+    pub fn mirrored(&self) -> Board {
+        let mirror = |pos: Pos| Pos::at(pos.file().flip(), pos.rank());
+        let mut b = self.clone();
+        for pos in Pos::all() {
+            b.set_piece(mirror(pos), self.piece(pos));
         }
+        b.set_castle_rights([false; 4]);
+        b.set_en_passant_target(self.en_passant_target.map(mirror));
+        b
+    }
+}
 
-        if self.castle_rights.iter().any(|&x| x) {
-            write!(f, " [")?;
-            if self.castle_rights[0] {
-                write!(f, "K")?;
-            }
-            if self.castle_rights[1] {
-                write!(f, "Q")?;
-            }
-            if self.castle_rights[2] {
-                write!(f, "k")?;
-            }
-            if self.castle_rights[3] {
-                write!(f, "q")?;
-            }
-            write!(f, "]")?;
-        }
+// boards are equal when they hold the same position: the same pieces, side to move,
+// castling rights and en passant target, whatever the move counters. this agrees with
+// the zobrist hash, so a board can key a map of positions, as for counting repetitions.
+impl PartialEq for Board {
+    fn eq(&self, other: &Board) -> bool {
+        self.board == other.board
+            && self.color_to_move == other.color_to_move
+            && self.castle_rights == other.castle_rights
+            && self.en_passant_target == other.en_passant_target
+    }
+}
 
-        write!(f, " ({})", self.halfmove_clock)?;
+impl Eq for Board {}
 
-        match self.en_passant_target {
-            None => {}
-            Some(pos) => write!(f, " ({})", pos)?,
-        }
+impl Hash for Board {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.zobrist());
+    }
+}
 
-        write!(f, "\n")
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.draw(f, &RenderOptions::default())
     }
 }
 
@@ -1,183 +1,1456 @@
 use crate::board::Board;
-use crate::moves::Move;
-use crate::piece::{Color, Piece, PieceType};
-use crate::position::Pos;
-use crate::transposition_table::TranspositionTable;
+use crate::eval_params::{eval_params, EvalParams};
+use crate::move_list::{MoveList, MAX_MOVES};
+use crate::moves::{coordinates, Move};
+use crate::persona::Persona;
+use crate::piece::{Color, PieceType};
+use crate::position::Rank;
+use crate::search_limits::SearchLimits;
+use crate::search_trace::{Reason, SearchTrace};
+use crate::tablebase::{tablebase, Dtm, Tablebase};
+use crate::time_manager::{Budget, Instant};
+use crate::transposition_table::{Bound, TranspositionTable};
 use crate::util::ChessError;
 use rand::{self, Rng};
 
-use std::cmp::{max, min};
+use std::cell::RefCell;
+use std::cmp::{max, min, Reverse};
+use std::fmt;
+use std::mem;
 use std::sync::Arc;
 use std::sync::RwLock;
+use std::time::Duration;
 
-impl Board {
-    pub fn random_move(&self) -> Result<(Move, isize), ChessError> {
-        let mut rng = rand::thread_rng();
-        let ms = self.legal_moves()?;
-        let i = rng.gen::<usize>() % ms.len();
-        Ok((ms[i], 0))
-    }
-
-    fn piece_score(&self, pos: Pos, piece: Piece) -> isize {
-        let mut score = 0;
-        score += self.nthreats(pos, piece);
-        score += pos.value();
-        match piece.kind {
-            PieceType::Pawn => score += 100,
-            PieceType::Knight => score += 300,
-            PieceType::Bishop => score += 300,
-            PieceType::Rook => score += 500,
-            PieceType::Queen => score += 900,
-            PieceType::King => score += isize::max_value() / 2,
-        }
+// larger than any score, and safe to negate
+const INFINITY: isize = isize::MAX;
+
+// score for giving checkmate at the root. a mate n plies from the root is worth
+// MATE - n, so shorter mates are preferred.
+pub const MATE: isize = INFINITY - 1;
+
+// no search goes deeper than this, so anything closer to MATE is a mate score
+pub const MAX_PLY: isize = 1000;
+
+pub fn is_mate_score(score: isize) -> bool {
+    score.abs() >= MATE - MAX_PLY
+}
+
+// the moves to mate a mate score is, negative when being mated, or None for other scores
+pub fn mate_in(score: isize) -> Option<isize> {
+    if is_mate_score(score) {
+        Some(score.signum() * ((MATE - score.abs() + 1) / 2))
+    } else {
+        None
+    }
+}
+
+// a score in json, as {"cp":n} or {"mate":n} in moves
+pub(crate) fn json_score(score: isize) -> String {
+    match mate_in(score) {
+        Some(moves) => format!("{{\"mate\":{}}}", moves),
+        None => format!("{{\"cp\":{}}}", score),
+    }
+}
+
+// the table holds mate scores as distance from the stored node rather than the root,
+// since the node can be reached at different plies
+fn to_tt(score: isize, ply: isize) -> isize {
+    if score >= MATE - MAX_PLY {
+        score + ply
+    } else if score <= -(MATE - MAX_PLY) {
+        score - ply
+    } else {
+        score
+    }
+}
+
+fn from_tt(score: isize, ply: isize) -> isize {
+    if score >= MATE - MAX_PLY {
+        score - ply
+    } else if score <= -(MATE - MAX_PLY) {
+        score + ply
+    } else {
         score
     }
+}
+
+// half width of the first window tried around the previous iteration's score
+const ASPIRATION_WINDOW: isize = 50;
 
-    // get score of board in centipawns
-    pub fn score(&self, color: Color) -> isize {
-        let mut score = 0;
-        for (pos, piece) in self.get_pieces_by_color(color) {
-            score += self.piece_score(pos, piece);
+// how much a quiet move might gain, by remaining depth, before it's not worth searching
+const FUTILITY_MARGIN: [isize; 3] = [0, 200, 500];
+
+// how far below alpha the static score must be, by remaining depth, to try razoring
+const RAZOR_MARGIN: [isize; 3] = [0, 300, 600];
+
+// plies taken off the null move search, one more at high depths
+const NULL_MOVE_REDUCTION: usize = 2;
+
+// probcut: from this depth, a capture that beats beta by the margin in a search this
+// many plies shallower very likely beats beta in the full one too
+const PROBCUT_DEPTH: usize = 5;
+const PROBCUT_REDUCTION: usize = 4;
+const PROBCUT_MARGIN: isize = 200;
+
+// extensions come in quarters of a ply, carried down the line until they add up to one
+const PLY: usize = 4;
+
+// for a passed pawn pushed to the sixth and seventh ranks, so promotion races play out
+// before the horizon
+const PASSED_PAWN_EXTENSION: [usize; 2] = [2, 3];
+
+// history scores stay within this either way, old results fading as new ones come in
+const HISTORY_MAX: i32 = 16_384;
+
+// the most one cutoff can move a history score
+const HISTORY_BONUS: i32 = 1_200;
+
+// captures and promotions are searched before quiet moves, the best victims first. the
+// last is for promotions that take nothing
+const NOISY: i32 = 1 << 20;
+const VICTIM: [i32; 7] = [100, 300, 300, 500, 900, 0, 0];
+
+// moves are known to history by the piece and the square it goes to
+const PIECE_TO: usize = 6 * 64;
+
+// counts of how the search went, for judging changes to move ordering and pruning
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    // nodes that failed high, and those that did on the first move searched
+    pub cutoffs: u64,
+    pub first_move_cutoffs: u64,
+    // transposition table lookups, and those that found the position
+    pub tt_probes: u64,
+    pub tt_hits: u64,
+    pub null_moves: u64,
+    pub null_move_cutoffs: u64,
+    // nodes in all, and those in quiescence
+    pub nodes: u64,
+    pub quiescence_nodes: u64,
+}
+
+impl SearchStats {
+    pub fn add(&mut self, other: &SearchStats) {
+        self.cutoffs += other.cutoffs;
+        self.first_move_cutoffs += other.first_move_cutoffs;
+        self.tt_probes += other.tt_probes;
+        self.tt_hits += other.tt_hits;
+        self.null_moves += other.null_moves;
+        self.null_move_cutoffs += other.null_move_cutoffs;
+        self.nodes += other.nodes;
+        self.quiescence_nodes += other.quiescence_nodes;
+    }
+}
+
+fn percent(part: u64, all: u64) -> f64 {
+    if all == 0 {
+        0.0
+    } else {
+        100.0 * part as f64 / all as f64
+    }
+}
+
+impl fmt::Display for SearchStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "cutoffs {} ({:.1}% on the first move), tt hits {:.1}% of {}, \
+             null move cutoffs {:.1}% of {}, quiescence {:.1}% of {} nodes",
+            self.cutoffs,
+            percent(self.first_move_cutoffs, self.cutoffs),
+            percent(self.tt_hits, self.tt_probes),
+            self.tt_probes,
+            percent(self.null_move_cutoffs, self.null_moves),
+            self.null_moves,
+            percent(self.quiescence_nodes, self.nodes),
+            self.nodes
+        )
+    }
+}
+
+// the outcome of the last finished iteration of a search
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult {
+    pub mv: Move,
+    // for the side to move
+    pub score: isize,
+    // plies searched below the root moves
+    pub depth: usize,
+    // the most plies from the root reached by any line, counting quiescence
+    pub seldepth: usize,
+    // the expected line of play, starting with mv
+    pub pv: Vec<Move>,
+    // positions searched, and the time taken, up to the end of the iteration
+    pub nodes: u64,
+    pub time: Duration,
+    pub stats: SearchStats,
+    // the root moves scoring within the search's margin of the best, best first, when
+    // there is a margin
+    pub near_best: Vec<(Move, isize)>,
+}
+
+impl SearchResult {
+    // one of the moves near the best at random, the closer to the best the likelier,
+    // with its score. the best move if there are no others, and always a mate.
+    pub fn pick_near_best(&self) -> (Move, isize) {
+        if self.near_best.len() < 2 || is_mate_score(self.score) {
+            return (self.mv, self.score);
         }
-        for (pos, piece) in self.get_pieces_by_color(color.other()) {
-            score -= self.piece_score(pos, piece);
+        // each move's weight is one more than the centipawns it is above the worst
+        let worst = self.near_best.iter().map(|&(_, s)| s).min().unwrap();
+        let total: isize = self.near_best.iter().map(|&(_, s)| s - worst + 1).sum();
+        let mut pick = rand::thread_rng().gen_range(0, total);
+        for &(mv, score) in &self.near_best {
+            pick -= score - worst + 1;
+            if pick < 0 {
+                return (mv, score);
+            }
         }
-        score
+        (self.mv, self.score)
+    }
+
+    // nodes per second
+    pub fn nps(&self) -> u64 {
+        let micros = self.time.as_micros() as u64;
+        (self.nodes * 1_000_000).checked_div(micros).unwrap_or(0)
+    }
+
+    // the score as xboard wants it: centipawns, or 100000 + n for mate in n moves
+    pub fn xboard_score(&self) -> isize {
+        match mate_in(self.score) {
+            Some(moves) => self.score.signum() * 100_000 + moves,
+            None => self.score,
+        }
+    }
+
+    // a line of thinking output for xboard's post mode, in the extended format:
+    // ply score time nodes seldepth nps, a tab, then the pv. time is in centiseconds.
+    pub fn to_xboard_post(&self) -> String {
+        let pv: Vec<String> = self.pv.iter().map(|mv| mv.to_string()).collect();
+        format!(
+            "{} {} {} {} {} {}\t{}",
+            self.depth + 1,
+            self.xboard_score(),
+            self.time.as_millis() / 10,
+            self.nodes,
+            self.seldepth,
+            self.nps(),
+            pv.join(" ")
+        )
+    }
+
+    // the result as one line of json for scripts: the position searched from, plies from
+    // the root, the score as {"cp":n} or {"mate":n} in moves, nodes, nps, milliseconds,
+    // and the pv both in coordinates and in san
+    pub fn to_json(&self, b: &Board) -> String {
+        let mut pos = b.clone();
+        let (mut coords, mut sans) = (Vec::new(), Vec::new());
+        for mv in &self.pv {
+            coords.push(format!("\"{}\"", coordinates(mv, &pos)));
+            sans.push(format!("\"{}\"", mv.to_san(&pos)));
+            pos = match pos.make_move(mv) {
+                Ok(next) => next,
+                Err(_) => break,
+            };
+        }
+        format!(
+            "{{\"fen\":\"{}\",\"depth\":{},\"seldepth\":{},\"score\":{},\"nodes\":{},\"nps\":{},\
+             \"time\":{},\"bestmove\":\"{}\",\"pv\":[{}],\"san\":[{}]}}",
+            b.to_fen(),
+            self.depth + 1,
+            self.seldepth,
+            json_score(self.score),
+            self.nodes,
+            self.nps(),
+            self.time.as_millis(),
+            coordinates(&self.mv, b),
+            coords.join(","),
+            sans.join(",")
+        )
+    }
+}
+
+fn piece_to(mv: &Move) -> usize {
+    mv.kind as usize * 64 + mv.to.index()
+}
+
+// move a history score by bonus, less as it nears HISTORY_MAX
+fn gravity(entry: &mut i32, bonus: i32) {
+    *entry += bonus - *entry * bonus.abs() / HISTORY_MAX;
+}
+
+// the capture and continuation histories are a couple of megabytes, too much to
+// allocate for every search. each thread keeps the last search's, cleared for the next.
+type Histories = (Vec<i32>, [Vec<i32>; 2]);
+
+thread_local! {
+    static HISTORIES: RefCell<Option<Histories>> = const { RefCell::new(None) };
+}
+
+fn histories() -> Histories {
+    match HISTORIES.with(|h| h.borrow_mut().take()) {
+        Some((mut capture, mut continuation)) => {
+            capture.fill(0);
+            for table in continuation.iter_mut() {
+                table.fill(0);
+            }
+            (capture, continuation)
+        }
+        None => (
+            vec![0; PIECE_TO * 7],
+            [vec![0; PIECE_TO * PIECE_TO], vec![0; PIECE_TO * PIECE_TO]],
+        ),
+    }
+}
+
+// what every node of one search shares
+struct Search<'a> {
+    abort: &'a Option<Arc<RwLock<bool>>>,
+    tt: &'a Option<Arc<TranspositionTable>>,
+    // triangular pv table: pv[ply] is the best line found from the node at ply
+    pv: Vec<Vec<Move>>,
+    nodes: u64,
+    max_nodes: Option<u64>,
+    seldepth: isize,
+    // which of the lazy smp threads this is, 0 for the main one
+    thread: usize,
+    start: Instant,
+    // time goes by nodes at this rate, if set
+    nps: Option<u64>,
+    // root moves this far below the best get exact scores, left in root_scores
+    margin: isize,
+    noise: isize,
+    noise_seed: u64,
+    root_scores: Vec<(isize, Move)>,
+    budget: Option<Budget>,
+    // set once the hard time limit has passed
+    out_of_time: bool,
+    tablebase: Option<Arc<Tablebase>>,
+    // the evaluation weights, read once for the whole search
+    params: EvalParams,
+    // how well captures have done, by the piece, its square and what it took
+    capture_history: Vec<i32>,
+    // how well quiet moves have done after the move one and two plies before
+    continuation_history: [Vec<i32>; 2],
+    // the move made at each ply, None for a null move
+    played: Vec<Option<Move>>,
+    // quarter plies of extension carried into the node at each ply
+    extension: Vec<usize>,
+    // nodes aren't counted here, search.nodes has them
+    stats: SearchStats,
+    // the tree so far, when it is being recorded, and why the node just searched
+    // returned, if not for its window
+    trace: Option<&'a mut SearchTrace>,
+    reason: Option<Reason>,
+}
+
+// hand the histories back for the thread's next search
+impl Drop for Search<'_> {
+    fn drop(&mut self) {
+        let capture = mem::take(&mut self.capture_history);
+        let continuation = mem::take(&mut self.continuation_history);
+        HISTORIES.with(|h| *h.borrow_mut() = Some((capture, continuation)));
+    }
+}
+
+impl<'a> Search<'a> {
+    fn new(
+        abort: &'a Option<Arc<RwLock<bool>>>,
+        tt: &'a Option<Arc<TranspositionTable>>,
+    ) -> Search<'a> {
+        let (capture_history, continuation_history) = histories();
+        Search {
+            abort,
+            tt,
+            pv: Vec::new(),
+            nodes: 0,
+            max_nodes: None,
+            seldepth: 0,
+            thread: 0,
+            start: Instant::now(),
+            nps: None,
+            margin: 0,
+            noise: 0,
+            noise_seed: 0,
+            root_scores: Vec::new(),
+            budget: None,
+            out_of_time: false,
+            tablebase: tablebase(),
+            params: eval_params(),
+            capture_history,
+            continuation_history,
+            played: Vec::new(),
+            extension: Vec::new(),
+            stats: SearchStats::default(),
+            trace: None,
+            reason: None,
+        }
+    }
+
+    fn limit(&mut self, limits: &SearchLimits) {
+        self.max_nodes = limits.nodes;
+        self.budget = limits.budget();
+        self.nps = limits.nps;
+        self.margin = if limits.deterministic {
+            0
+        } else {
+            limits.margin.max(0)
+        };
+        self.noise = limits.noise.max(0);
+        self.noise_seed = limits.noise_seed;
+    }
+
+    fn stats(&self) -> SearchStats {
+        SearchStats {
+            nodes: self.nodes,
+            ..self.stats
+        }
+    }
+
+    // count a node, and look at the clock every so often
+    fn visit(&mut self, ply: isize) {
+        self.nodes += 1;
+        self.seldepth = max(self.seldepth, ply);
+        if self.nodes.is_multiple_of(1024) {
+            if let Some(budget) = self.budget {
+                self.out_of_time |= self.elapsed() >= budget.hard;
+            }
+        }
+    }
+
+    fn play(&mut self, ply: isize, mv: Option<&Move>) {
+        let ply = ply as usize;
+        if self.played.len() <= ply {
+            self.played.resize(ply + 1, None);
+        }
+        self.played[ply] = mv.cloned();
+    }
+
+    // the move that led to the node at ply
+    fn last_move(&self, ply: isize) -> Option<Move> {
+        match ply - 1 {
+            p if p >= 0 => self.played.get(p as usize).cloned().flatten(),
+            _ => None,
+        }
+    }
+
+    // whole plies to extend the search below mv, about to be made in b at ply, passing
+    // on what is left over. a null move passes on what it was given.
+    fn extend(&mut self, b: &Board, mv: Option<&Move>, ply: isize) -> usize {
+        let color = b.color_to_move;
+        let push = match mv {
+            Some(mv) if mv.kind == PieceType::Pawn && b.is_passed_pawn(mv.to, color) => {
+                match mv.to.rank().relative_to(color) {
+                    Rank::Sixth => PASSED_PAWN_EXTENSION[0],
+                    Rank::Seventh => PASSED_PAWN_EXTENSION[1],
+                    _ => 0,
+                }
+            }
+            _ => 0,
+        };
+        let ply = ply as usize;
+        if self.extension.len() <= ply + 1 {
+            self.extension.resize(ply + 2, 0);
+        }
+        let carried = self.extension[ply] + push;
+        self.extension[ply + 1] = carried % PLY;
+        carried / PLY
+    }
+
+    // the pieces and squares of the moves one and two plies before ply, where there were
+    // any
+    fn previous(&self, ply: isize) -> [Option<usize>; 2] {
+        let before = |back: isize| self.last_move(ply + 1 - back).map(|mv| piece_to(&mv));
+        [before(1), before(2)]
+    }
+
+    fn capture_index(b: &Board, mv: &Move) -> usize {
+        let victim = if mv.en_passant {
+            Some(PieceType::Pawn)
+        } else {
+            b.piece(mv.to).map(|p| p.kind)
+        };
+        piece_to(mv) * 7 + victim.map_or(6, |kind| kind as usize)
+    }
+
+    // searched first are captures and promotions, by victim and then capture history,
+    // and then quiet moves by how they did after the last two moves
+    fn order_moves(&self, b: &Board, moves: &mut MoveList, ply: isize) {
+        let previous = self.previous(ply);
+        moves.sort_by_cached_key(|mv| {
+            let score = if mv.takes || mv.promotion.is_some() {
+                let index = Search::capture_index(b, mv);
+                NOISY + VICTIM[index % 7] * 32 + self.capture_history[index]
+            } else {
+                (0..2)
+                    .filter_map(|i| previous[i].map(|prev| (i, prev)))
+                    .map(|(i, prev)| self.continuation_history[i][prev * PIECE_TO + piece_to(mv)])
+                    .sum()
+            };
+            Reverse(score)
+        });
+    }
+
+    // mv caused a cutoff after the moves in tried failed to: reward it and mark the
+    // others down, more so the deeper the search
+    fn update_history(&mut self, b: &Board, mv: &Move, tried: &[Move], depth: usize, ply: isize) {
+        let bonus = min(depth * depth * 16, HISTORY_BONUS as usize) as i32;
+        let previous = self.previous(ply);
+        let noisy = |mv: &Move| mv.takes || mv.promotion.is_some();
+        for (other, bonus) in tried.iter().map(|m| (m, -bonus)).chain([(mv, bonus)]) {
+            if noisy(other) {
+                let index = Search::capture_index(b, other);
+                gravity(&mut self.capture_history[index], bonus);
+            } else if !noisy(mv) {
+                for (i, prev) in previous.iter().enumerate() {
+                    if let Some(prev) = prev {
+                        let entry =
+                            &mut self.continuation_history[i][prev * PIECE_TO + piece_to(other)];
+                        gravity(entry, bonus);
+                    }
+                }
+            }
+        }
+    }
+
+    // how long the search has taken, by the clock or in nodes
+    fn elapsed(&self) -> Duration {
+        match self.nps {
+            Some(nps) => Duration::from_micros(self.nodes * 1_000_000 / nps.max(1)),
+            None => self.start.elapsed(),
+        }
+    }
+
+    // the static score for the side to move
+    fn static_score(&self, b: &Board) -> isize {
+        b.score_with(&self.params, b.color_to_move)
+    }
+
+    // the static score, with the noise for b if there is any
+    fn evaluate(&self, b: &Board) -> isize {
+        let score = self.static_score(b);
+        if self.noise == 0 {
+            return score;
+        }
+        let hash = (b.zobrist() ^ self.noise_seed).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        score + (hash >> 32) as isize % (2 * self.noise + 1) - self.noise
+    }
+
+    fn aborted(&self) -> bool {
+        if self.out_of_time || self.max_nodes.is_some_and(|max| self.nodes >= max) {
+            return true;
+        }
+        match self.abort {
+            Some(mutex) => *mutex.read().unwrap(),
+            None => false,
+        }
+    }
+
+    fn clear_pv(&mut self, ply: isize) {
+        let ply = ply as usize;
+        if self.pv.len() <= ply {
+            self.pv.resize(ply + 1, Vec::new());
+        }
+        self.pv[ply].clear();
+    }
+
+    // mv is the new best move at ply: its line is mv then the child's line
+    fn update_pv(&mut self, ply: isize, mv: Move) {
+        let ply = ply as usize;
+        if self.pv.len() <= ply + 1 {
+            self.pv.resize(ply + 2, Vec::new());
+        }
+        let (parent, child) = self.pv.split_at_mut(ply + 1);
+        let line = &mut parent[ply];
+        line.clear();
+        line.push(mv);
+        line.extend_from_slice(&child[0]);
+    }
+}
+
+impl Board {
+    pub fn random_move(&self) -> Result<(Move, isize), ChessError> {
+        Persona::Random.pick(self)
     }
 
     // find the move with the weakest response - single threaded
-    pub fn best_move(&self, max_depth: usize) -> Result<(Move, isize), ChessError> {
-        let mut rng = rand::thread_rng();
-        let mut best_score = isize::min_value();
-        let mut best_move = None;
-        let moves = self.legal_moves()?;
-        for mv in moves {
-            let score;
-            if max_depth == 0 {
-                score = match self.make_move(&mv) {
-                    Err(ChessError::Checkmate) => return Ok((mv, isize::max_value() - 1)),
-                    Err(ChessError::Stalemate) => 0,
-                    Err(e) => return Err(e),
-                    Ok(new_board) => new_board.score(self.color_to_move),
-                };
+    pub fn best_move(&self, limits: &SearchLimits) -> Result<(Move, isize), ChessError> {
+        let tt = Arc::new(TranspositionTable::new());
+        let res = self.clone().search(limits, None, Some(tt))?;
+        Ok((res.mv, res.score))
+    }
+
+    // the mating line, if the side to move can force mate in at most moves moves
+    pub fn find_mate(&self, moves: usize) -> Option<Vec<Move>> {
+        let res = self
+            .clone()
+            .search(&SearchLimits::mate(moves), None, None)
+            .ok()?;
+        if is_mate_score(res.score) {
+            Some(res.pv)
+        } else {
+            None
+        }
+    }
+
+    // iterative deepening: search the root moves one more ply below them each iteration
+    // until a limit is reached. each iteration starts with a narrow window around the
+    // previous score, and the best moves so far are searched first. if stopped early,
+    // the result of the last finished iteration is returned.
+    pub fn search(
+        &mut self,
+        limits: &SearchLimits,
+        abort: Option<Arc<RwLock<bool>>>,
+        transposition_table: Option<Arc<TranspositionTable>>,
+    ) -> Result<SearchResult, ChessError> {
+        self.search_with(limits, abort, transposition_table, &mut |_| {})
+    }
+
+    // search, calling report with the result of each finished iteration
+    pub fn search_with(
+        &mut self,
+        limits: &SearchLimits,
+        abort: Option<Arc<RwLock<bool>>>,
+        transposition_table: Option<Arc<TranspositionTable>>,
+        report: &mut dyn FnMut(&SearchResult),
+    ) -> Result<SearchResult, ChessError> {
+        self.search_thread(0, limits, abort, transposition_table, report)
+    }
+
+    // one of several threads searching the same position and sharing a transposition
+    // table (lazy smp). helpers, thread > 0, differ from the main thread in the order they
+    // try root moves and the depths they iterate over, so they fill the table with
+    // different parts of the tree.
+    pub fn search_thread(
+        &mut self,
+        thread: usize,
+        limits: &SearchLimits,
+        abort: Option<Arc<RwLock<bool>>>,
+        transposition_table: Option<Arc<TranspositionTable>>,
+        report: &mut dyn FnMut(&SearchResult),
+    ) -> Result<SearchResult, ChessError> {
+        let mut search = Search::new(&abort, &transposition_table);
+        search.thread = thread;
+        search.limit(limits);
+        self.iterative_deepening(&mut search, limits, report)
+    }
+
+    // search on one thread, recording the tree in trace as it goes, for working out why
+    // the search chose what it did
+    pub fn search_traced(
+        &mut self,
+        limits: &SearchLimits,
+        transposition_table: Option<Arc<TranspositionTable>>,
+        trace: &mut SearchTrace,
+        report: &mut dyn FnMut(&SearchResult),
+    ) -> Result<SearchResult, ChessError> {
+        let abort = None;
+        let mut search = Search::new(&abort, &transposition_table);
+        search.limit(limits);
+        search.trace = Some(trace);
+        self.iterative_deepening(&mut search, limits, report)
+    }
+
+    fn iterative_deepening(
+        &mut self,
+        search: &mut Search,
+        limits: &SearchLimits,
+        report: &mut dyn FnMut(&SearchResult),
+    ) -> Result<SearchResult, ChessError> {
+        let max_depth = limits.max_depth();
+        let mut moves = MoveList::new();
+        self.generate_legal_moves(&mut moves);
+        if moves.is_empty() {
+            return Err(ChessError::GameOver(self.status_with(&moves)));
+        }
+        if !limits.search_moves.is_empty() {
+            moves.retain(|mv| limits.search_moves.contains(mv));
+            if moves.is_empty() {
+                illegal_move_error!("[search] none of the search moves are legal");
+            }
+        }
+        let n = moves.len();
+        moves.rotate_left(search.thread % n);
+        if let Some(mate_in) = limits.mate {
+            return Ok(self.mate_search(&moves, mate_in, search, report));
+        }
+        let mut best = SearchResult {
+            mv: moves[0],
+            score: 0,
+            depth: 0,
+            seldepth: 0,
+            pv: vec![moves[0]],
+            nodes: 0,
+            time: Duration::from_secs(0),
+            near_best: Vec::new(),
+            stats: SearchStats::default(),
+        };
+
+        let first_depth = min(search.thread % 2, max_depth);
+        for depth in first_depth..=max_depth {
+            // mtd(f) needs a first guess, which the first iteration gives it
+            let (score, pv) = if limits.mtdf && depth > first_depth {
+                match self.mtdf(&mut moves, depth, best.score, search) {
+                    Some(found) => found,
+                    None => return Ok(best),
+                }
             } else {
-                let tt = Arc::new(TranspositionTable::new(max_depth + 1));
-                score = self
-                    .make_move(&mv)?
-                    .alpha_beta(max_depth, None, Some(tt.clone()));
+                let mut delta = ASPIRATION_WINDOW;
+                let (mut alpha, mut beta) = if depth == first_depth {
+                    (-INFINITY, INFINITY)
+                } else {
+                    (
+                        best.score.saturating_sub(delta).max(-INFINITY),
+                        best.score.saturating_add(delta),
+                    )
+                };
+                loop {
+                    let score = self.search_root(&mut moves, depth, alpha, beta, search);
+                    if search.aborted() {
+                        return Ok(best);
+                    }
+                    // outside the window the score is only a bound: widen that side and
+                    // retry
+                    if score <= alpha {
+                        alpha = alpha.saturating_sub(delta).max(-INFINITY);
+                    } else if score >= beta {
+                        beta = beta.saturating_add(delta);
+                    } else {
+                        break (score, search.pv[0].clone());
+                    }
+                    delta = delta.saturating_mul(2);
+                }
+            };
+            best = SearchResult {
+                mv: pv[0],
+                score,
+                depth,
+                seldepth: search.seldepth as usize,
+                pv,
+                nodes: search.nodes,
+                time: search.elapsed(),
+                stats: search.stats(),
+                // mtd(f)'s root scores are only bounds
+                near_best: search
+                    .root_scores
+                    .iter()
+                    .filter(|&&(s, _)| {
+                        search.margin > 0 && !limits.mtdf && s >= score - search.margin
+                    })
+                    .map(|&(s, mv)| (mv, s))
+                    .collect(),
+            };
+            report(&best);
+            // the next iteration would likely not finish in time. with only one move
+            // there is nothing to think about.
+            if let Some(budget) = search.budget {
+                if n == 1 || search.elapsed() >= budget.soft {
+                    break;
+                }
             }
-            if score > best_score || (score == best_score && rng.gen()) {
-                best_move = Some(mv);
+        }
+        Ok(best)
+    }
+
+    // search each root move with depth plies below it, leaving the best line in the pv
+    // table. the moves are then sorted by score for the next iteration, so the best move
+    // and any that failed high come first; moves cut off keep their old order behind
+    // them.
+    fn search_root(
+        &mut self,
+        moves: &mut MoveList,
+        depth: usize,
+        alpha_in: isize,
+        beta: isize,
+        search: &mut Search,
+    ) -> isize {
+        let mut alpha = alpha_in;
+        let mut best_score = -INFINITY;
+        let mut scores = [-INFINITY; MAX_MOVES];
+        search.clear_pv(0);
+        if let Some(trace) = search.trace.as_mut() {
+            trace.enter(None, depth + 1, alpha_in, beta);
+        }
+        for (i, mv) in moves.iter().enumerate() {
+            // with a margin, moves nearly as good as the best get exact scores too
+            let floor = alpha.saturating_sub(search.margin).max(-INFINITY);
+            search.play(0, Some(mv));
+            let extension = search.extend(self, Some(mv), 0);
+            let undo = self.do_move(mv);
+            let score = -self.negamax(depth + extension, 1, -beta, -floor, true, search);
+            self.undo_move(undo);
+            scores[i] = score;
+            if score > best_score {
                 best_score = score;
+                search.update_pv(0, *mv);
+            }
+            alpha = max(alpha, score);
+            if alpha >= beta || search.aborted() {
+                break;
             }
         }
-        Ok((best_move.unwrap(), best_score))
+
+        let mut scored: Vec<(isize, Move)> =
+            scores.iter().cloned().zip(moves.iter().cloned()).collect();
+        scored.sort_by_key(|&(score, _)| Reverse(score));
+        for (slot, &(_, mv)) in moves.iter_mut().zip(scored.iter()) {
+            *slot = mv;
+        }
+        search.root_scores = scored;
+        if let Some(trace) = search.trace.as_mut() {
+            trace.leave(best_score, None);
+        }
+        best_score
+    }
+
+    // mtd(f): close in on the score with zero window searches, starting from a guess like
+    // the last iteration's score. a search that fails high proves its move at least that
+    // good, so the line kept is from the last one that did. the null move fails hard, so
+    // a bound may only move by one a pass: while the passes fail the same way, each probe
+    // goes twice as far past the last. None if the search was aborted.
+    fn mtdf(
+        &mut self,
+        moves: &mut MoveList,
+        depth: usize,
+        guess: isize,
+        search: &mut Search,
+    ) -> Option<(isize, Vec<Move>)> {
+        let (mut lower, mut upper) = (-INFINITY, INFINITY);
+        let (mut probe, mut step, mut last_high) = (guess, 1isize, None);
+        let mut score = guess;
+        let mut pv = vec![moves[0]];
+        while lower < upper {
+            let beta = probe.clamp(lower + 1, upper);
+            score = self.search_root(moves, depth, beta - 1, beta, search);
+            if search.aborted() {
+                return None;
+            }
+            let high = score >= beta;
+            if high {
+                lower = score;
+                pv = search.pv[0].clone();
+            } else {
+                upper = score;
+            }
+            step = if last_high == Some(high) {
+                step.saturating_mul(2)
+            } else {
+                1
+            };
+            last_high = Some(high);
+            probe = if high {
+                score.saturating_add(step)
+            } else {
+                score.saturating_sub(step - 1)
+            };
+        }
+        Some((score, pv))
+    }
+
+    // look only for a forced mate in at most mate_in moves, shortest first. there is no
+    // evaluation, and moves that can't mate in time are pruned. without a mate the
+    // result scores 0.
+    fn mate_search(
+        &mut self,
+        moves: &MoveList,
+        mate_in: usize,
+        search: &mut Search,
+        report: &mut dyn FnMut(&SearchResult),
+    ) -> SearchResult {
+        let mut best = SearchResult {
+            mv: moves[0],
+            score: 0,
+            depth: 0,
+            seldepth: 0,
+            pv: vec![moves[0]],
+            nodes: 0,
+            time: Duration::from_secs(0),
+            near_best: Vec::new(),
+            stats: SearchStats::default(),
+        };
+        for n in 1..=mate_in {
+            let plies = 2 * n - 1;
+            let mut found = false;
+            search.clear_pv(0);
+            for mv in moves.iter() {
+                let undo = self.do_move(mv);
+                found = self.mated_within(plies - 1, 1, search);
+                self.undo_move(undo);
+                if found {
+                    search.update_pv(0, *mv);
+                    best.mv = *mv;
+                    best.score = MATE - plies as isize;
+                    best.pv = search.pv[0].clone();
+                }
+                if found || search.aborted() {
+                    break;
+                }
+            }
+            if !found && search.aborted() {
+                break;
+            }
+            best.depth = plies - 1;
+            best.seldepth = search.seldepth as usize;
+            best.nodes = search.nodes;
+            best.time = search.elapsed();
+            best.stats = search.stats();
+            if found {
+                report(&best);
+                break;
+            }
+        }
+        best
+    }
+
+    // whether the side to move can force mate within plies, an odd number. checks are
+    // tried first, and on the last move nothing else.
+    fn mates_within(&mut self, plies: usize, ply: isize, search: &mut Search) -> bool {
+        search.visit(ply);
+        search.clear_pv(ply);
+        if self.fifty_move_draw() || search.aborted() {
+            return false;
+        }
+        let mut moves = MoveList::new();
+        self.generate_legal_moves(&mut moves);
+        for checks in [true, false] {
+            if !checks && plies == 1 {
+                break;
+            }
+            for mv in moves.iter() {
+                if self.gives_check(mv) != checks {
+                    continue;
+                }
+                let undo = self.do_move(mv);
+                let mated = self.mated_within(plies - 1, ply + 1, search);
+                self.undo_move(undo);
+                if mated {
+                    search.update_pv(ply, *mv);
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    // whether the side to move is mated within plies, whatever it does
+    fn mated_within(&mut self, plies: usize, ply: isize, search: &mut Search) -> bool {
+        search.visit(ply);
+        search.clear_pv(ply);
+        let mut moves = MoveList::new();
+        self.generate_legal_moves(&mut moves);
+        if moves.is_empty() {
+            return self.in_check(self.color_to_move);
+        }
+        if plies == 0 || self.fifty_move_draw() || search.aborted() {
+            return false;
+        }
+        for (i, mv) in moves.iter().enumerate() {
+            let undo = self.do_move(mv);
+            let mated = self.mates_within(plies - 1, ply + 1, search);
+            self.undo_move(undo);
+            if !mated {
+                return false;
+            }
+            // every defence loses, the first stands in for them in the pv
+            if i == 0 {
+                search.update_pv(ply, *mv);
+            }
+        }
+        true
     }
 
     pub fn alpha_beta(
-        &self,
+        &mut self,
         max_depth: usize,
         abort: Option<Arc<RwLock<bool>>>,
         transposition_table: Option<Arc<TranspositionTable>>,
     ) -> isize {
-        self.alpha_beta_rec(
-            self.color_to_move.other(),
-            0,
-            max_depth,
-            isize::min_value(),
-            isize::max_value(),
-            &abort,
-            &transposition_table,
-        )
+        let mut search = Search::new(&abort, &transposition_table);
+        // scored for the player who moved into this position
+        -self.negamax(max_depth, 1, -INFINITY, INFINITY, true, &mut search)
     }
 
-    fn alpha_beta_rec(
-        &self,
-        my_color: Color,
+    // score for the side to move, searching depth plies further. ply is the distance
+    // from the root, for scoring mates.
+    fn negamax(
+        &mut self,
         depth: usize,
-        max_depth: usize,
+        ply: isize,
+        alpha: isize,
+        beta: isize,
+        allow_null: bool,
+        search: &mut Search,
+    ) -> isize {
+        if search.trace.is_none() {
+            return self.search_node(depth, ply, alpha, beta, allow_null, search);
+        }
+        let mv = search.last_move(ply);
+        if let Some(trace) = search.trace.as_mut() {
+            trace.enter(mv, depth, alpha, beta);
+        }
+        let score = self.search_node(depth, ply, alpha, beta, allow_null, search);
+        let reason = search.reason.take();
+        if let Some(trace) = search.trace.as_mut() {
+            trace.leave(score, reason);
+        }
+        score
+    }
+
+    fn search_node(
+        &mut self,
+        depth: usize,
+        ply: isize,
         alpha_in: isize,
-        beta_in: isize,
-        abort: &Option<Arc<RwLock<bool>>>,
-        tt: &Option<Arc<TranspositionTable>>,
+        beta: isize,
+        allow_null: bool,
+        search: &mut Search,
     ) -> isize {
+        search.visit(ply);
+        search.clear_pv(ply);
+
+        // the table outlives this search, so store scores from white's point of view
+        let perspective = match self.color_to_move {
+            Color::White => 1,
+            Color::Black => -1,
+        };
+
+        // a draw can be claimed, unless the last move was mate
+        if self.fifty_move_draw() {
+            let mut moves = MoveList::new();
+            self.generate_legal_moves(&mut moves);
+            return if moves.is_empty() && self.in_check(self.color_to_move) {
+                search.reason = Some(Reason::GameOver);
+                -MATE + ply
+            } else {
+                search.reason = Some(Reason::Draw);
+                0
+            };
+        }
+
+        // endings in the tablebase are solved
+        if let Some(ref tb) = search.tablebase {
+            let score = match tb.probe(self) {
+                Some(Dtm::Win(plies)) => Some(MATE - ply - plies as isize),
+                Some(Dtm::Loss(plies)) => Some(-MATE + ply + plies as isize),
+                Some(Dtm::Draw) => Some(0),
+                None => None,
+            };
+            if let Some(score) = score {
+                search.reason = Some(Reason::Tablebase);
+                return score;
+            }
+        }
+
         // if the transposition table includes this board state at this depth,
-        // return the previous value
-        if let Some(ref table) = *tt {
-            if let Some(result) = table.get(self, depth) {
-                return result;
+        // return the previous value if it is good enough for this window
+        if let Some(ref table) = *search.tt {
+            search.stats.tt_probes += 1;
+            if let Some((result, bound)) = table.get(self, depth) {
+                search.stats.tt_hits += 1;
+                let result = from_tt(perspective * result, ply);
+                let enough = match bound {
+                    Bound::Exact => true,
+                    Bound::Lower => result >= beta,
+                    Bound::Upper => result <= alpha_in,
+                };
+                if enough {
+                    search.reason = Some(Reason::Table);
+                    return result;
+                }
+            }
+        }
+
+        if depth == 0 || search.aborted() {
+            search.reason = Some(Reason::Horizon);
+            return search.evaluate(self);
+        }
+
+        let in_check = self.in_check(self.color_to_move);
+
+        // razoring: far below alpha near the horizon, only captures could save us. see
+        // if they do, and if not don't bother with a full search.
+        if depth <= 2 && !in_check && alpha_in > -INFINITY {
+            let static_eval = search.static_score(self);
+            if static_eval.saturating_add(RAZOR_MARGIN[depth]) <= alpha_in {
+                let v = self.quiescence(ply, alpha_in, alpha_in + 1, search);
+                if v <= alpha_in {
+                    search.reason = Some(Reason::Razor);
+                    return v;
+                }
+            }
+        }
+
+        // null move: if passing still leaves us above beta after a reduced search, a real
+        // move will too. unsound in zugzwang, so not when only pawns are left.
+        if allow_null
+            && depth > NULL_MOVE_REDUCTION
+            && beta < INFINITY
+            && !in_check
+            && self.has_non_pawn_material(self.color_to_move)
+        {
+            let r = if depth > 6 {
+                NULL_MOVE_REDUCTION + 1
+            } else {
+                NULL_MOVE_REDUCTION
+            };
+            search.play(ply, None);
+            search.extend(self, None, ply);
+            search.stats.null_moves += 1;
+            let en_passant_target = self.do_null_move();
+            let score = -self.negamax(depth - 1 - r, ply + 1, -beta, -beta + 1, false, search);
+            self.undo_null_move(en_passant_target);
+            if score >= beta {
+                search.stats.null_move_cutoffs += 1;
+                search.reason = Some(Reason::NullMove);
+                return beta;
+            }
+        }
+
+        let mut moves = MoveList::new();
+        self.generate_legal_moves(&mut moves);
+        if moves.is_empty() {
+            search.reason = Some(Reason::GameOver);
+            return if in_check { -MATE + ply } else { 0 };
+        }
+        search.order_moves(self, &mut moves, ply);
+
+        if depth >= PROBCUT_DEPTH && !in_check && !is_mate_score(beta) {
+            if let Some(score) = self.probcut(&moves, depth, ply, beta, search) {
+                search.reason = Some(Reason::ProbCut);
+                return score;
             }
         }
 
+        // futility: close to the horizon, a quiet move can't make up a big deficit
+        let futility_limit = match depth {
+            1 | 2 if !in_check => Some(search.static_score(self) + FUTILITY_MARGIN[depth]),
+            _ => None,
+        };
+
         let mut alpha = alpha_in;
-        let mut beta = beta_in;
-        if depth == max_depth || abort.as_ref().map_or(false, |mutex| *mutex.read().unwrap()) {
-            return self.score(my_color);
-        }
-
-        let ret;
-        if self.color_to_move == my_color {
-            // maximizing player
-            let mut v = isize::min_value();
-            match self.legal_moves() {
-                Err(ChessError::Checkmate) => return isize::min_value() + 1,
-                Err(ChessError::Stalemate) => return 0,
-                Err(e) => panic!("{}", e),
-                Ok(moves) => {
-                    for mv in moves {
-                        let b = self.make_move(&mv).unwrap();
-                        let score = b.alpha_beta_rec(
-                            my_color,
-                            depth + 1,
-                            max_depth,
-                            alpha,
-                            beta,
-                            abort,
-                            tt,
-                        );
-                        v = max(v, score);
-                        alpha = max(alpha, v);
-                        if beta <= alpha {
-                            break;
-                        }
+        let mut v = -INFINITY;
+        let mut tried = MoveList::new();
+        for mv in moves.iter() {
+            let quiet = !mv.takes && mv.promotion.is_none();
+            search.play(ply, Some(mv));
+            let extension = search.extend(self, Some(mv), ply);
+            let undo = self.do_move(mv);
+            if let Some(limit) = futility_limit {
+                // moves that give check stay, they may be forcing, as do extended ones
+                if quiet && extension == 0 && limit <= alpha && !self.in_check(self.color_to_move) {
+                    self.undo_move(undo);
+                    if let Some(trace) = search.trace.as_mut() {
+                        trace.pruned(*mv, depth - 1, -limit, Reason::Futility);
                     }
+                    v = max(v, limit);
+                    continue;
                 }
             }
-            ret = v;
-        } else {
-            let mut v = isize::max_value();
-            match self.legal_moves() {
-                Err(ChessError::Checkmate) => return isize::max_value() - 1,
-                Err(ChessError::Stalemate) => return 0,
-                Err(e) => panic!("{}", e),
-                Ok(moves) => {
-                    for mv in moves {
-                        let b = self.make_move(&mv).unwrap();
-                        let score = b.alpha_beta_rec(
-                            my_color,
-                            depth + 1,
-                            max_depth,
-                            alpha,
-                            beta,
-                            abort,
-                            tt,
-                        );
-                        v = min(v, score);
-                        beta = min(beta, v);
-                        if beta <= alpha {
-                            break;
-                        }
-                    }
+            let score = -self.negamax(depth - 1 + extension, ply + 1, -beta, -alpha, true, search);
+            self.undo_move(undo);
+            if score > v {
+                v = score;
+                if v > alpha {
+                    alpha = v;
+                    search.update_pv(ply, *mv);
+                }
+            }
+            if alpha >= beta {
+                search.stats.cutoffs += 1;
+                if tried.is_empty() {
+                    search.stats.first_move_cutoffs += 1;
                 }
+                if !search.aborted() {
+                    search.update_history(self, mv, &tried, depth, ply);
+                }
+                break;
             }
-            ret = v;
+            tried.push(*mv);
+        }
+
+        // an aborted search leaves a garbage value, keep it out of the table
+        if search.aborted() {
+            return v;
         }
 
         // update the transposition table with the result
-        if let Some(ref table) = *tt {
-            table.insert(self, depth, ret);
+        if let Some(ref table) = *search.tt {
+            let bound = if v <= alpha_in {
+                Bound::Upper
+            } else if v >= beta {
+                Bound::Lower
+            } else {
+                Bound::Exact
+            };
+            table.insert(self, depth, perspective * to_tt(v, ply), bound);
+        }
+
+        v
+    }
+
+    // probcut: see if a capture clears a raised beta cheaply, first in quiescence and
+    // then with a shallow search, and if one does take the cut with its score
+    fn probcut(
+        &mut self,
+        moves: &MoveList,
+        depth: usize,
+        ply: isize,
+        beta: isize,
+        search: &mut Search,
+    ) -> Option<isize> {
+        let probcut_beta = beta + PROBCUT_MARGIN;
+        for mv in moves.iter().filter(|mv| mv.takes || mv.promotion.is_some()) {
+            search.play(ply, Some(mv));
+            let extension = search.extend(self, Some(mv), ply);
+            let undo = self.do_move(mv);
+            let mut score = -self.quiescence(ply + 1, -probcut_beta, -probcut_beta + 1, search);
+            if score >= probcut_beta {
+                score = -self.negamax(
+                    depth - 1 - PROBCUT_REDUCTION + extension,
+                    ply + 1,
+                    -probcut_beta,
+                    -probcut_beta + 1,
+                    true,
+                    search,
+                );
+            }
+            self.undo_move(undo);
+            if score >= probcut_beta && !search.aborted() {
+                return Some(score);
+            }
+        }
+        None
+    }
+
+    // search only captures and promotions until the position is quiet, so the static
+    // score isn't taken in the middle of an exchange. the side to move may stand pat
+    // unless in check.
+    fn quiescence(
+        &mut self,
+        ply: isize,
+        alpha_in: isize,
+        beta: isize,
+        search: &mut Search,
+    ) -> isize {
+        search.visit(ply);
+        search.stats.quiescence_nodes += 1;
+        let mut moves = MoveList::new();
+        self.generate_legal_moves(&mut moves);
+        let in_check = self.in_check(self.color_to_move);
+        if moves.is_empty() {
+            return if in_check { -MATE + ply } else { 0 };
+        }
+        search.order_moves(self, &mut moves, ply);
+
+        let mut alpha = alpha_in;
+        let mut v = -INFINITY;
+        if !in_check {
+            v = search.evaluate(self);
+            if v >= beta || search.aborted() {
+                return v;
+            }
+            alpha = max(alpha, v);
+        }
+
+        for mv in moves.iter() {
+            if !in_check && !mv.takes && mv.promotion.is_none() {
+                continue;
+            }
+            let undo = self.do_move(mv);
+            let score = -self.quiescence(ply + 1, -beta, -alpha, search);
+            self.undo_move(undo);
+            v = max(v, score);
+            alpha = max(alpha, v);
+            if alpha >= beta {
+                break;
+            }
         }
+        v
+    }
+
+    fn has_non_pawn_material(&self, c: Color) -> bool {
+        self.iter_pieces_by_color(c)
+            .any(|(_, p)| p.kind != PieceType::Pawn && p.kind != PieceType::King)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Search, SearchResult, SearchStats, MATE};
+    use crate::board::Board;
+    use crate::move_list::MoveList;
+    use crate::moves::Move;
+
+    use std::time::Duration;
+
+    #[test]
+    fn xboard_post() {
+        let mut res = SearchResult {
+            mv: mv!("Qg5-e7"),
+            score: MATE - 3,
+            depth: 3,
+            seldepth: 7,
+            pv: vec![mv!("Qg5-e7")],
+            nodes: 1234,
+            time: Duration::from_millis(1500),
+            near_best: Vec::new(),
+            stats: SearchStats::default(),
+        };
+        assert_eq!(res.to_xboard_post(), "4 100002 150 1234 7 822\tQg5e7");
+        assert_eq!(res.nps(), 822);
+        res.score = -(MATE - 4);
+        assert_eq!(res.xboard_score(), -100002);
+        res.score = -35;
+        assert_eq!(res.xboard_score(), -35);
+
+        let b = Board::from_fen("4k3/8/3P4/6Q1/8/8/8/K7 w - - 0 1").unwrap();
+        res.score = MATE - 1;
+        assert_eq!(
+            res.to_json(&b),
+            "{\"fen\":\"4k3/8/3P4/6Q1/8/8/8/K7 w - - 0 1\",\"depth\":4,\"seldepth\":7,\
+             \"score\":{\"mate\":1},\"nodes\":1234,\"nps\":822,\"time\":1500,\
+             \"bestmove\":\"g5e7\",\"pv\":[\"g5e7\"],\"san\":[\"Qe7#\"]}"
+        );
+    }
+
+    #[test]
+    fn root_moves_sorted_by_score() {
+        let mut search = Search::new(&None, &None);
+        let mut b = Board::from_fen("4k3/8/3P4/6Q1/8/8/8/K7 w - - 0 1").unwrap();
+        let mut moves = MoveList::new();
+        b.generate_legal_moves(&mut moves);
+        let mut sorted = moves;
+        let best = b.search_root(
+            &mut sorted,
+            1,
+            -super::INFINITY,
+            super::INFINITY,
+            &mut search,
+        );
+        assert_eq!(best, MATE - 1);
+        assert_eq!(sorted[0], mv!("Qg5-e7"));
+        assert_eq!(search.pv[0], vec![mv!("Qg5-e7")]);
+
+        // nothing lost, and the best move stays first. the others only have bounds
+        // from the window they were searched with, so they may move around.
+        let mut again = sorted;
+        b.search_root(
+            &mut again,
+            1,
+            -super::INFINITY,
+            super::INFINITY,
+            &mut search,
+        );
+        assert_eq!(again[0], sorted[0]);
+        assert_eq!(sorted.len(), moves.len());
+        assert!(moves.iter().all(|mv| sorted.contains(mv)));
+    }
+
+    #[test]
+    fn quiescence_resolves_captures() {
+        let mut search = Search::new(&None, &None);
+        // white's queen takes the rook for free
+        let mut b = Board::from_fen("4k3/8/8/3r4/8/8/3Q4/4K3 w - - 0 1").unwrap();
+        let static_eval = b.score(b.color_to_move);
+        let q = b.quiescence(0, -super::INFINITY, super::INFINITY, &mut search);
+        assert!(q > static_eval + 400, "{} vs {}", q, static_eval);
+
+        // the queen is defended, but still worth the rook
+        let mut b = Board::from_fen("4k3/8/8/3r4/8/8/3QK3/8 b - - 0 1").unwrap();
+        let static_eval = b.score(b.color_to_move);
+        let q = b.quiescence(0, -super::INFINITY, super::INFINITY, &mut search);
+        assert!(q > static_eval + 250, "{} vs {}", q, static_eval);
+    }
+
+    #[test]
+    fn history_orders_moves() {
+        let mut search = Search::new(&None, &None);
+        let b = Board::from_fen("r3k3/8/8/3p4/8/2N5/8/R3K3 w - - 0 1").unwrap();
+        let mut moves = MoveList::new();
+        b.generate_legal_moves(&mut moves);
+
+        // the rook is worth more than the pawn, whatever takes it
+        search.order_moves(&b, &mut moves, 0);
+        assert_eq!(moves[0], mv!("Ra1xa8"));
+        assert_eq!(moves[1], mv!("Nc3xd5"));
+
+        // a quiet move that cut off after e2-e4 comes first after e2-e4 again, but not
+        // after some other move
+        search.play(0, Some(&mv!("e2-e4")));
+        let tried = [mv!("Ke1-f1"), mv!("Nc3-b5")];
+        search.update_history(&b, &mv!("Ke1-d2"), &tried, 4, 1);
+        search.order_moves(&b, &mut moves, 1);
+        assert_eq!(moves[2], mv!("Ke1-d2"));
+        assert!(moves[moves.len() - 2..].contains(&mv!("Nc3-b5")));
+        search.play(0, Some(&mv!("d2-d4")));
+        b.generate_legal_moves(&mut moves);
+        search.order_moves(&b, &mut moves, 1);
+        assert_ne!(moves[2], mv!("Ke1-d2"));
+    }
+
+    #[test]
+    fn histories_are_reused() {
+        let b = Board::from_fen("r3k3/8/8/3p4/8/2N5/8/R3K3 w - - 0 1").unwrap();
+        let mut search = Search::new(&None, &None);
+        search.play(0, Some(&mv!("e2-e4")));
+        let tried = [mv!("Nc3xd5")];
+        search.update_history(&b, &mv!("Ke1-d2"), &tried, 4, 1);
+        assert!(search.continuation_history[0].iter().any(|&h| h != 0));
+        let tables = (
+            search.capture_history.as_ptr(),
+            search.continuation_history[0].as_ptr(),
+        );
+        drop(search);
+
+        // the next search on this thread gets the same tables back, cleared
+        let search = Search::new(&None, &None);
+        let again = (
+            search.capture_history.as_ptr(),
+            search.continuation_history[0].as_ptr(),
+        );
+        assert_eq!(tables, again);
+        assert!(search.capture_history.iter().all(|&h| h == 0));
+        assert!(search.continuation_history[0].iter().all(|&h| h == 0));
+    }
+
+    #[test]
+    fn passed_pawn_extensions() {
+        let mut search = Search::new(&None, &None);
+        let b = Board::from_fen("4k3/8/8/4P3/8/8/1P6/4K3 w - - 0 1").unwrap();
+
+        // half a ply for the sixth rank, carried past a move that earns nothing, and
+        // three quarters for the seventh tip it over a whole ply
+        assert_eq!(search.extend(&b, Some(&mv!("e5-e6")), 0), 0);
+        let b = b.make_move(&mv!("e5-e6")).unwrap();
+        assert_eq!(search.extend(&b, Some(&mv!("Ke8-d8")), 1), 0);
+        let b = b.make_move(&mv!("Ke8-d8")).unwrap();
+        assert_eq!(search.extend(&b, Some(&mv!("e6-e7")), 2), 1);
+        assert_eq!(search.extension[3], 1);
+
+        // a pawn that isn't passed, or isn't far enough, earns nothing
+        let b = Board::from_fen("4k3/3p4/8/4P3/8/8/1P6/4K3 w - - 0 1").unwrap();
+        assert_eq!(search.extend(&b, Some(&mv!("e5-e6")), 0), 0);
+        assert_eq!(search.extension[1], 0);
+        assert_eq!(search.extend(&b, Some(&mv!("b2-b4")), 0), 0);
+        assert_eq!(search.extension[1], 0);
+    }
+
+    #[test]
+    fn probcut_ignores_earlier_lines() {
+        // the queen takes the knight, and black's passed pawn can answer from c4
+        let mut b = Board::from_fen("4k3/8/8/8/2pn4/8/8/3QK3 w - - 0 1").unwrap();
+        let mut moves = MoveList::new();
+        b.generate_legal_moves(&mut moves);
+
+        let mut fresh = Search::new(&None, &None);
+        let first = b.probcut(&moves, 6, 2, 450, &mut fresh);
+        assert!(first.is_some());
 
-        ret
+        // a search that has been down other lines, which left extension carried below.
+        // none of it may reach the pawn's reply
+        let mut used = Search::new(&None, &None);
+        used.extension = vec![0, 3, 0, 3, 3, 3];
+        let again = b.probcut(&moves, 6, 2, 450, &mut used);
+        assert_eq!((first, fresh.nodes), (again, used.nodes));
     }
 }
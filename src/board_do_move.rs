@@ -0,0 +1,205 @@
+use crate::board::Board;
+use crate::moves::{Castle, Move};
+use crate::piece::{Color, Piece, PieceType};
+use crate::position::Pos;
+
+// everything do_move changes that can't be recovered from the move itself
+#[derive(Debug, Clone, Copy)]
+pub struct Undo {
+    pub mv: Move,
+    pub captured: Option<Piece>,
+    castle_rights: [bool; 4],
+    en_passant_target: Option<Pos>,
+    halfmove_clock: usize,
+    move_number: usize,
+    key: u64,
+}
+
+// (king from, king to, rook from, rook to)
+fn castle_squares(castle: Castle, color: Color) -> (Pos, Pos, Pos, Pos) {
+    match (castle, color) {
+        (Castle::Kingside, Color::White) => (Pos::E1, Pos::G1, Pos::H1, Pos::F1),
+        (Castle::Queenside, Color::White) => (Pos::E1, Pos::C1, Pos::A1, Pos::D1),
+        (Castle::Kingside, Color::Black) => (Pos::E8, Pos::G8, Pos::H8, Pos::F8),
+        (Castle::Queenside, Color::Black) => (Pos::E8, Pos::C8, Pos::A8, Pos::D8),
+    }
+}
+
+// the square a pawn pushed two squares passed over, where it can be taken en passant on
+// the next move, and only then
+pub(crate) fn en_passant_target_after(mv: &Move) -> Option<Pos> {
+    let (dv, dh) = mv.from.offset(mv.to);
+    if mv.kind == PieceType::Pawn && dv.abs() == 2 && dh == 0 {
+        mv.from.mv(dv / 2, 0)
+    } else {
+        None
+    }
+}
+
+impl Board {
+    // makes mv in place. unlike make_move there are no legality checks: mv must come from
+    // the move generator for this position, and may leave the mover's king in check.
+    pub fn do_move(&mut self, mv: &Move) -> Undo {
+        let color = self.color_to_move;
+        self.threats.clear();
+        let mut undo = Undo {
+            mv: *mv,
+            captured: None,
+            castle_rights: self.castle_rights,
+            en_passant_target: self.en_passant_target,
+            halfmove_clock: self.halfmove_clock,
+            move_number: self.move_number,
+            key: self.key,
+        };
+        let state = self.state_key();
+
+        if color == Color::Black {
+            self.move_number += 1;
+        }
+        if mv.takes || mv.kind == PieceType::Pawn {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+
+        if let Some(castle) = mv.castle {
+            let (king_from, king_to, rook_from, rook_to) = castle_squares(castle, color);
+            self.move_piece(king_from, king_to);
+            self.move_piece(rook_from, rook_to);
+            self.clear_castle_rights(color);
+        } else if mv.en_passant {
+            let target_piece_at = match color {
+                Color::White => mv.to.south(1).unwrap(),
+                Color::Black => mv.to.north(1).unwrap(),
+            };
+            undo.captured = self.take_piece(target_piece_at);
+            self.move_piece(mv.from, mv.to);
+        } else {
+            let p = self
+                .take_piece(mv.from)
+                .expect("[do_move] no piece to move!");
+            if p.kind == PieceType::King {
+                self.clear_castle_rights(color);
+            }
+            if p.kind == PieceType::Rook {
+                match color {
+                    Color::White => {
+                        if mv.from == Pos::H1 {
+                            self.castle_rights[0] = false;
+                        }
+                        if mv.from == Pos::A1 {
+                            self.castle_rights[1] = false;
+                        }
+                    }
+                    Color::Black => {
+                        if mv.from == Pos::H8 {
+                            self.castle_rights[2] = false;
+                        }
+                        if mv.from == Pos::A8 {
+                            self.castle_rights[3] = false;
+                        }
+                    }
+                }
+            }
+            undo.captured = self.take_piece(mv.to);
+            if undo.captured.is_some_and(|q| q.kind == PieceType::Rook) {
+                self.clear_rook_castle_right(mv.to);
+            }
+            let p = match mv.promotion {
+                Some(kind) => Piece { kind, ..p },
+                None => p,
+            };
+            self.set_piece(mv.to, Some(p));
+        }
+
+        self.color_to_move = color.other();
+        self.en_passant_target = en_passant_target_after(mv);
+        self.key ^= state ^ self.state_key();
+        undo
+    }
+
+    // takes back the move that produced undo, which must be the last one made
+    pub fn undo_move(&mut self, undo: Undo) {
+        let color = self.color_to_move.other();
+        self.threats.clear();
+        let mv = undo.mv;
+
+        if let Some(castle) = mv.castle {
+            let (king_from, king_to, rook_from, rook_to) = castle_squares(castle, color);
+            self.board.shift(king_to, king_from);
+            self.board.shift(rook_to, rook_from);
+        } else if mv.en_passant {
+            let target_piece_at = match color {
+                Color::White => mv.to.south(1).unwrap(),
+                Color::Black => mv.to.north(1).unwrap(),
+            };
+            self.board.shift(mv.to, mv.from);
+            self.board.set(target_piece_at, undo.captured);
+        } else {
+            let p = self
+                .board
+                .take(mv.to)
+                .expect("[undo_move] no piece to move back!");
+            let p = match mv.promotion {
+                Some(_) => Piece {
+                    kind: PieceType::Pawn,
+                    ..p
+                },
+                None => p,
+            };
+            self.board.set(mv.from, Some(p));
+            self.board.set(mv.to, undo.captured);
+        }
+
+        self.color_to_move = color;
+        self.castle_rights = undo.castle_rights;
+        self.en_passant_target = undo.en_passant_target;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.move_number = undo.move_number;
+        self.key = undo.key;
+    }
+
+    // passes the turn without moving, for null move pruning. returns the en passant
+    // target it cleared, which undo_null_move needs.
+    pub fn do_null_move(&mut self) -> Option<Pos> {
+        self.threats.clear();
+        let state = self.state_key();
+        self.color_to_move = self.color_to_move.other();
+        let ep = self.en_passant_target.take();
+        self.key ^= state ^ self.state_key();
+        ep
+    }
+
+    pub fn undo_null_move(&mut self, en_passant_target: Option<Pos>) {
+        self.threats.clear();
+        let state = self.state_key();
+        self.color_to_move = self.color_to_move.other();
+        self.en_passant_target = en_passant_target;
+        self.key ^= state ^ self.state_key();
+    }
+
+    // a rook taken in its corner can't castle any more
+    pub(crate) fn clear_rook_castle_right(&mut self, corner: Pos) {
+        let right = match corner {
+            Pos::H1 => 0,
+            Pos::A1 => 1,
+            Pos::H8 => 2,
+            Pos::A8 => 3,
+            _ => return,
+        };
+        self.castle_rights[right] = false;
+    }
+
+    fn clear_castle_rights(&mut self, c: Color) {
+        match c {
+            Color::White => {
+                self.castle_rights[0] = false;
+                self.castle_rights[1] = false;
+            }
+            Color::Black => {
+                self.castle_rights[2] = false;
+                self.castle_rights[3] = false;
+            }
+        }
+    }
+}
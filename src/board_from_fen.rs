@@ -1,7 +1,10 @@
 use crate::board::Board;
 use crate::piece::{Color, Piece, PieceType};
 use crate::position::Pos;
-use crate::util::ChessError;
+use crate::util::{ChessError, ParseError};
+
+#[cfg(not(feature = "std"))]
+use crate::alloc_prelude::*;
 
 impl Board {
     pub fn to_fen(&self) -> String {
@@ -79,18 +82,30 @@ impl Board {
         s
     }
 
+    // a position that could come up in a game, see validate()
     pub fn from_fen(fen: &str) -> Result<Board, ChessError> {
+        let b = Board::from_fen_unchecked(fen)?;
+        b.validate()?;
+        Ok(b)
+    }
+
+    // any arrangement of pieces, even without kings, as for testing move generation
+    pub fn from_fen_unchecked(fen: &str) -> Result<Board, ChessError> {
         let mut b = Board::new();
         let mut i = 0;
         let mut j = 0;
-        let tokens: Vec<&str> = fen.split(" ").collect();
+        // the halfmove clock and move number are often left off, as in epd
+        let tokens: Vec<&str> = fen.split_whitespace().collect();
+        if tokens.len() < 4 || tokens.len() > 6 {
+            parse_error!("[from_fen] expected 4 to 6 fields in \"{}\"", fen);
+        }
 
         let check = |i, j| {
-            if i * 8 + j >= 64 {
-                Err(ChessError::ParseError(format!(
+            if i >= 8 || j >= 8 {
+                Err(ChessError::ParseError(ParseError(format!(
                     "[from_fen] index out of bounds i={} j={}!",
                     i, j
-                )))
+                ))))
             } else {
                 Ok(())
             }
@@ -99,113 +114,151 @@ impl Board {
         // parse board
         for c in tokens[0].chars() {
             match c {
-                ' ' => break,
                 '/' => {
+                    if j != 8 {
+                        parse_error!("[from_fen] rank {} has {} squares", 8 - i, j);
+                    }
                     i += 1;
                     j = 0;
                 }
 
                 n @ '1'..='8' => {
-                    j += n
-                        .to_string()
-                        .parse::<usize>()
-                        .expect("couldn't read number!");
+                    j += n as usize - '0' as usize;
+                    if j > 8 {
+                        parse_error!("[from_fen] rank {} has {} squares", 8 - i, j);
+                    }
                 }
 
                 'P' => {
                     check(i, j)?;
-                    b.board[i * 8 + j] = Some(Piece {
-                        kind: PieceType::Pawn,
-                        color: Color::White,
-                    });
+                    b.board.set(
+                        Pos::new(i, j),
+                        Some(Piece {
+                            kind: PieceType::Pawn,
+                            color: Color::White,
+                        }),
+                    );
                     j += 1;
                 }
                 'p' => {
                     check(i, j)?;
-                    b.board[i * 8 + j] = Some(Piece {
-                        kind: PieceType::Pawn,
-                        color: Color::Black,
-                    });
+                    b.board.set(
+                        Pos::new(i, j),
+                        Some(Piece {
+                            kind: PieceType::Pawn,
+                            color: Color::Black,
+                        }),
+                    );
                     j += 1;
                 }
                 'B' => {
                     check(i, j)?;
-                    b.board[i * 8 + j] = Some(Piece {
-                        kind: PieceType::Bishop,
-                        color: Color::White,
-                    });
+                    b.board.set(
+                        Pos::new(i, j),
+                        Some(Piece {
+                            kind: PieceType::Bishop,
+                            color: Color::White,
+                        }),
+                    );
                     j += 1;
                 }
                 'b' => {
                     check(i, j)?;
-                    b.board[i * 8 + j] = Some(Piece {
-                        kind: PieceType::Bishop,
-                        color: Color::Black,
-                    });
+                    b.board.set(
+                        Pos::new(i, j),
+                        Some(Piece {
+                            kind: PieceType::Bishop,
+                            color: Color::Black,
+                        }),
+                    );
                     j += 1;
                 }
                 'N' => {
                     check(i, j)?;
-                    b.board[i * 8 + j] = Some(Piece {
-                        kind: PieceType::Knight,
-                        color: Color::White,
-                    });
+                    b.board.set(
+                        Pos::new(i, j),
+                        Some(Piece {
+                            kind: PieceType::Knight,
+                            color: Color::White,
+                        }),
+                    );
                     j += 1;
                 }
                 'n' => {
                     check(i, j)?;
-                    b.board[i * 8 + j] = Some(Piece {
-                        kind: PieceType::Knight,
-                        color: Color::Black,
-                    });
+                    b.board.set(
+                        Pos::new(i, j),
+                        Some(Piece {
+                            kind: PieceType::Knight,
+                            color: Color::Black,
+                        }),
+                    );
                     j += 1;
                 }
                 'R' => {
                     check(i, j)?;
-                    b.board[i * 8 + j] = Some(Piece {
-                        kind: PieceType::Rook,
-                        color: Color::White,
-                    });
+                    b.board.set(
+                        Pos::new(i, j),
+                        Some(Piece {
+                            kind: PieceType::Rook,
+                            color: Color::White,
+                        }),
+                    );
                     j += 1;
                 }
                 'r' => {
                     check(i, j)?;
-                    b.board[i * 8 + j] = Some(Piece {
-                        kind: PieceType::Rook,
-                        color: Color::Black,
-                    });
+                    b.board.set(
+                        Pos::new(i, j),
+                        Some(Piece {
+                            kind: PieceType::Rook,
+                            color: Color::Black,
+                        }),
+                    );
                     j += 1;
                 }
                 'Q' => {
                     check(i, j)?;
-                    b.board[i * 8 + j] = Some(Piece {
-                        kind: PieceType::Queen,
-                        color: Color::White,
-                    });
+                    b.board.set(
+                        Pos::new(i, j),
+                        Some(Piece {
+                            kind: PieceType::Queen,
+                            color: Color::White,
+                        }),
+                    );
                     j += 1;
                 }
                 'q' => {
                     check(i, j)?;
-                    b.board[i * 8 + j] = Some(Piece {
-                        kind: PieceType::Queen,
-                        color: Color::Black,
-                    });
+                    b.board.set(
+                        Pos::new(i, j),
+                        Some(Piece {
+                            kind: PieceType::Queen,
+                            color: Color::Black,
+                        }),
+                    );
                     j += 1;
                 }
                 'K' => {
                     check(i, j)?;
-                    b.board[i * 8 + j] = Some(Piece {
-                        kind: PieceType::King,
-                        color: Color::White,
-                    });
+                    b.board.set(
+                        Pos::new(i, j),
+                        Some(Piece {
+                            kind: PieceType::King,
+                            color: Color::White,
+                        }),
+                    );
                     j += 1;
                 }
                 'k' => {
                     check(i, j)?;
-                    b.board[i * 8 + j] = Some(Piece {
-                        kind: PieceType::King,
-                        color: Color::Black,
-                    });
+                    b.board.set(
+                        Pos::new(i, j),
+                        Some(Piece {
+                            kind: PieceType::King,
+                            color: Color::Black,
+                        }),
+                    );
                     j += 1;
                 }
 
@@ -213,6 +266,10 @@ impl Board {
             }
         }
 
+        if i != 7 || j != 8 {
+            parse_error!("[from_fen] \"{}\" isn't 8 ranks of 8 squares", tokens[0]);
+        }
+
         // parse turn
         match tokens[1] {
             "w" | "W" => b.color_to_move = Color::White,
@@ -238,16 +295,19 @@ impl Board {
             s => b.en_passant_target = Some(Pos::from_algebra(s)?),
         }
 
-        b.halfmove_clock = match tokens[4].parse() {
-            Ok(c) => c,
-            Err(_) => parse_error!("[from_fen] couldn't decode half move clock!"),
+        b.halfmove_clock = match tokens.get(4).map(|s| s.parse()) {
+            None => 0,
+            Some(Ok(c)) => c,
+            Some(Err(_)) => parse_error!("[from_fen] couldn't decode half move clock!"),
         };
 
-        b.move_number = match tokens[5].parse() {
-            Ok(c) => c,
-            Err(_) => parse_error!("[from_fen] couldn't decode move number!"),
+        b.move_number = match tokens.get(5).map(|s| s.parse()) {
+            None => 1,
+            Some(Ok(c)) => c,
+            Some(Err(_)) => parse_error!("[from_fen] couldn't decode move number!"),
         };
 
+        b.rehash();
         Ok(b)
     }
 }
@@ -255,23 +315,65 @@ impl Board {
 #[cfg(test)]
 mod tests {
     use crate::board::Board;
+    use crate::util::ChessError;
 
     #[test]
     fn fen_correct() {
         let p = "1K6/2P5/1p3P2/1k2P3/1qnP1B2/3Q4/8/8 b - - 0 1";
-        let q = &Board::from_fen(p).unwrap().to_fen();
+        let q = &Board::from_fen_unchecked(p).unwrap().to_fen();
         println!("\np={}\nq={}", p, q);
         assert_eq!(p, q);
 
         let p = "4k2r/8/5Q2/8/8/8/8/8 b kq - 0 1";
-        println!("\n{}", Board::from_fen(p).unwrap());
-        let q = &Board::from_fen(p).unwrap().to_fen();
+        println!("\n{}", Board::from_fen_unchecked(p).unwrap());
+        let q = &Board::from_fen_unchecked(p).unwrap().to_fen();
         println!("\np={}\nq={}", p, q);
         assert_eq!(p, q);
 
         let p = "r3k2r/8/8/8/8/8/8/8 b kq - 0 1";
-        let q = &Board::from_fen(p).unwrap().to_fen();
+        let q = &Board::from_fen_unchecked(p).unwrap().to_fen();
         println!("\np={}\nq={}", p, q);
         assert_eq!(p, q);
     }
+
+    #[test]
+    fn fen_missing_counters() {
+        let b = Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3").unwrap();
+        assert_eq!(
+            b.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+        let b = Board::from_fen("4k3/8/8/8/8/8/8/4K3  w -  -  12").unwrap();
+        assert_eq!(b.to_fen(), "4k3/8/8/8/8/8/8/4K3 w - - 12 1");
+    }
+
+    #[test]
+    fn fen_malformed() {
+        for fen in [
+            "",
+            "4k3/8/8/8/8/8/8/4K3",
+            "4k3/8/8/8/8/8/8/4K3 w -",
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1 extra",
+            "4k3/8/8/8/8/8/8/4K3 x - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w X - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w - e - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w - e33 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w - - x 1",
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 -1",
+            "4k3/8/8/8/8/8/8/4K3/8 w - - 0 1",
+            "4k3/8/8/8/8/8/4K3 w - - 0 1",
+            "4k4/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3p w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K2 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3/ w - - 0 1",
+            "4k3/8/8/8/8/8/8/4é3 w - - 0 1",
+        ]
+        .iter()
+        {
+            match Board::from_fen_unchecked(fen) {
+                Err(ChessError::ParseError(_)) => {}
+                res => panic!("{:?} gave {:?}", fen, res.map(|b| b.to_fen())),
+            }
+        }
+    }
 }
@@ -0,0 +1,347 @@
+use crate::board::Board;
+use crate::move_list::MoveList;
+use crate::moves::{Castle, Move};
+use crate::piece::{Color, Piece, PieceType};
+use crate::position::{Pos, Squares};
+
+#[cfg(not(feature = "std"))]
+use crate::alloc_prelude::*;
+
+pub(crate) const ORTHOGONAL: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, 1), (0, -1)];
+pub(crate) const DIAGONAL: [(isize, isize); 4] = [(-1, 1), (-1, -1), (1, 1), (1, -1)];
+pub(crate) const KNIGHT_JUMPS: [(isize, isize); 8] = [
+    (1, 2),
+    (1, -2),
+    (-1, -2),
+    (-1, 2),
+    (2, 1),
+    (2, -1),
+    (-2, -1),
+    (-2, 1),
+];
+pub(crate) const KING_STEPS: [(isize, isize); 8] = [
+    (1, 0),
+    (1, 1),
+    (1, -1),
+    (-1, 0),
+    (-1, 1),
+    (-1, -1),
+    (0, 1),
+    (0, -1),
+];
+
+// can a slider of this kind move along dir?
+pub(crate) fn slides_along(kind: PieceType, dir: (isize, isize)) -> bool {
+    let diagonal = dir.0 != 0 && dir.1 != 0;
+    match kind {
+        PieceType::Queen => true,
+        PieceType::Rook => !diagonal,
+        PieceType::Bishop => diagonal,
+        _ => false,
+    }
+}
+
+// unit step from a towards b, if they share a rank, file or diagonal
+fn direction(a: Pos, b: Pos) -> Option<(isize, isize)> {
+    let (dv, dh) = a.offset(b);
+    if a == b || (dv != 0 && dh != 0 && dv.abs() != dh.abs()) {
+        None
+    } else {
+        Some((dv.signum(), dh.signum()))
+    }
+}
+
+// a piece that cannot leave the line between its king and an enemy slider
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pin {
+    pub pos: Pos,
+    pub king: Pos,
+    // the enemy slider
+    pub pinner: Pos,
+    // step from the king towards the pinner, as Pos::mv takes it
+    pub dir: (isize, isize),
+}
+
+impl Pin {
+    // where the pinned piece may go: the squares between the king and the pinner, and
+    // the pinner
+    pub fn ray(&self) -> Squares {
+        let mut ray = self.king.between(self.pinner);
+        ray.insert(self.pinner);
+        ray
+    }
+}
+
+impl Board {
+    pub(crate) fn king_pos(&self, c: Color) -> Option<Pos> {
+        let mut kings = self.iter_pieces_by_type_and_color(PieceType::King, c);
+        match (kings.next(), kings.next()) {
+            (Some(king), None) => Some(king),
+            _ => None,
+        }
+    }
+
+    // is c's king attacked? false without exactly one king.
+    pub fn in_check(&self, c: Color) -> bool {
+        self.king_pos(c)
+            .is_some_and(|king| self.color_threatens(c.other(), king))
+    }
+
+    // does the move, legal in this position, check the other side's king? worked out
+    // without making it, except for castling, en passant and promotions.
+    pub fn gives_check(&self, mv: &Move) -> bool {
+        let c = self.color_to_move;
+        let king = match self.king_pos(c.other()) {
+            Some(k) => k,
+            None => return false,
+        };
+        if mv.castle.is_some() || mv.en_passant || mv.promotion.is_some() {
+            return self.make_move(mv).is_ok_and(|b| b.in_check(c.other()));
+        }
+        let p = Piece {
+            kind: mv.kind,
+            color: c,
+        };
+        self.attacks_from(p, mv.to, king, mv.from) || self.discovers_check(mv, king)
+    }
+
+    // would p on from attack target, if the square vacated were empty?
+    fn attacks_from(&self, p: Piece, from: Pos, target: Pos, vacated: Pos) -> bool {
+        let (dv, dh) = from.offset(target);
+        match p.kind {
+            PieceType::Pawn => {
+                let forward = match p.color {
+                    Color::White => -1,
+                    Color::Black => 1,
+                };
+                dv == forward && dh.abs() == 1
+            }
+            PieceType::Knight => KNIGHT_JUMPS.contains(&(dv, dh)),
+            PieceType::King => KING_STEPS.contains(&(dv, dh)),
+            _ => match direction(from, target) {
+                Some(dir) if slides_along(p.kind, dir) => from
+                    .between(target)
+                    .all(|pos| pos == vacated || !self.occupied(pos)),
+                _ => false,
+            },
+        }
+    }
+
+    // does moving the piece off its line to the king uncover one of our sliders?
+    fn discovers_check(&self, mv: &Move, king: Pos) -> bool {
+        let dir = match direction(king, mv.from) {
+            Some(dir) => dir,
+            None => return false,
+        };
+        if king.line(mv.from).contains(mv.to) {
+            return false;
+        }
+        let mut ray = king;
+        while let Some(pos) = ray.mv(dir.0, dir.1) {
+            ray = pos;
+            if pos == mv.from {
+                continue;
+            }
+            if let Some(p) = self.piece(pos) {
+                return p.color == self.color_to_move && slides_along(p.kind, dir);
+            }
+        }
+        false
+    }
+
+    // the enemy pieces giving check to c's king at king
+    fn checkers(&self, king: Pos, c: Color) -> Squares {
+        let enemy = c.other();
+        let mut res = Squares::default();
+        let is = |pos: Pos, kind| self.piece(pos) == Some(Piece { kind, color: enemy });
+
+        for &(v, h) in KNIGHT_JUMPS.iter() {
+            if let Some(pos) = king.mv(v, h) {
+                if is(pos, PieceType::Knight) {
+                    res.insert(pos);
+                }
+            }
+        }
+        for &(v, h) in KING_STEPS.iter() {
+            if let Some(pos) = king.mv(v, h) {
+                if is(pos, PieceType::King) {
+                    res.insert(pos);
+                }
+            }
+        }
+        // pawns attack diagonally forward, so look diagonally backward from the king
+        let forward = match c {
+            Color::White => -1,
+            Color::Black => 1,
+        };
+        for &h in [-1, 1].iter() {
+            if let Some(pos) = king.mv(forward, h) {
+                if is(pos, PieceType::Pawn) {
+                    res.insert(pos);
+                }
+            }
+        }
+        for &dir in ORTHOGONAL.iter().chain(DIAGONAL.iter()) {
+            let mut ray = king;
+            while let Some(pos) = ray.mv(dir.0, dir.1) {
+                ray = pos;
+                if let Some(p) = self.piece(pos) {
+                    if p.color == enemy && slides_along(p.kind, dir) {
+                        res.insert(pos);
+                    }
+                    break;
+                }
+            }
+        }
+        res
+    }
+
+    // c's pieces which can't move off the line to their king without exposing it to an
+    // enemy rook, bishop or queen. empty without exactly one king.
+    pub fn pinned(&self, c: Color) -> Vec<Pin> {
+        match self.king_pos(c) {
+            Some(king) => self.pins(king, c),
+            None => Vec::new(),
+        }
+    }
+
+    // c's pieces which are pinned to c's king at king
+    fn pins(&self, king: Pos, c: Color) -> Vec<Pin> {
+        let mut res = Vec::new();
+        self.for_each_pin(king, c, |pin| res.push(pin));
+        res
+    }
+
+    fn for_each_pin(&self, king: Pos, c: Color, mut f: impl FnMut(Pin)) {
+        for &dir in ORTHOGONAL.iter().chain(DIAGONAL.iter()) {
+            let mut ray = king;
+            let mut candidate = None;
+            while let Some(pos) = ray.mv(dir.0, dir.1) {
+                ray = pos;
+                match self.piece(pos) {
+                    None => {}
+                    Some(p) if p.color == c => {
+                        if candidate.is_some() {
+                            break;
+                        }
+                        candidate = Some(pos);
+                    }
+                    Some(p) => {
+                        if let Some(pinned) = candidate {
+                            if slides_along(p.kind, dir) {
+                                f(Pin {
+                                    pos: pinned,
+                                    king,
+                                    pinner: pos,
+                                    dir,
+                                });
+                            }
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    // the square of the pawn removed by an en passant capture
+    fn en_passant_victim(&self, mv: &Move) -> Pos {
+        match self.color_to_move {
+            Color::White => mv.to.south(1).unwrap(),
+            Color::Black => mv.to.north(1).unwrap(),
+        }
+    }
+
+    // en passant empties two squares and fills a third, so the king can be exposed in
+    // ways pins don't show: along the rank both pawns leave, or along a diagonal the
+    // taken pawn was blocking. follow each ray from the king as the board will be.
+    fn en_passant_exposes_king(&self, king: Pos, mv: &Move) -> bool {
+        let victim = self.en_passant_victim(mv);
+        let enemy = self.color_to_move.other();
+        for &dir in ORTHOGONAL.iter().chain(DIAGONAL.iter()) {
+            let mut ray = king;
+            while let Some(pos) = ray.mv(dir.0, dir.1) {
+                ray = pos;
+                if pos == mv.to {
+                    break;
+                }
+                if pos == mv.from || pos == victim {
+                    continue;
+                }
+                if let Some(p) = self.piece(pos) {
+                    if p.color == enemy && slides_along(p.kind, dir) {
+                        return true;
+                    }
+                    break;
+                }
+            }
+        }
+        false
+    }
+
+    // drop the pseudo-legal moves which leave the king in check, found using masks of
+    // the pinned pieces and of the squares that answer a check instead of trying each
+    // move. nothing is allocated.
+    pub(crate) fn retain_legal(&self, moves: &mut MoveList) {
+        let c = self.color_to_move;
+        let king = match self.king_pos(c) {
+            Some(k) => k,
+            // without exactly one king there is no check to worry about
+            None => return,
+        };
+        let enemy = c.other();
+        let checkers = self.checkers(king, c);
+        // a single check is answered by taking the checker or blocking it. only a slider
+        // can be blocked, and only a slider is ever aligned with the king at a distance.
+        let mut answers = Squares::default();
+        for checker in checkers {
+            answers = king.between(checker);
+            answers.insert(checker);
+        }
+        // a pinned piece may only move along the line through it and the king
+        let mut pinned = Squares::default();
+        self.for_each_pin(king, c, |pin| pinned.insert(pin.pos));
+
+        // the king may not step along the ray of a slider that is checking it
+        let mut kingless = self.clone();
+        kingless.take_piece(king);
+
+        moves.retain(|mv| {
+            if let Some(castle) = mv.castle {
+                // king_moves checked the king's square and the one it passes over
+                let (to, corner) = match castle {
+                    Castle::Kingside => (king.east(2), king.east(3)),
+                    Castle::Queenside => (king.west(2), king.west(4)),
+                };
+                let rook = Some(Piece {
+                    kind: PieceType::Rook,
+                    color: c,
+                });
+                return checkers.is_empty()
+                    && corner.is_some_and(|corner| self.piece(corner) == rook)
+                    && to.is_some_and(|to| !self.color_threatens(enemy, to));
+            }
+
+            if mv.kind == PieceType::King {
+                return !kingless.threats(enemy).contains(mv.to);
+            }
+
+            // only the king can get out of double check
+            if checkers.len() > 1 {
+                return false;
+            }
+            // otherwise capture the checker or block it
+            let answers_check = answers.contains(mv.to)
+                || (mv.en_passant && checkers.contains(self.en_passant_victim(mv)));
+            if !checkers.is_empty() && !answers_check {
+                return false;
+            }
+
+            if pinned.contains(mv.from) && !king.line(mv.from).contains(mv.to) {
+                return false;
+            }
+
+            !(mv.en_passant && self.en_passant_exposes_king(king, mv))
+        });
+    }
+}
@@ -1,99 +1,114 @@
-use crate::board::Board;
+use crate::board::{Board, Draw, GameStatus};
+use crate::board_do_move::en_passant_target_after;
+use crate::move_list::MoveList;
 use crate::moves::{Castle, Move};
 use crate::piece::{Color, Piece, PieceType};
-use crate::position::Pos;
+use crate::position::{Pos, Rank};
 use crate::util::ChessError;
 
+#[cfg(not(feature = "std"))]
+use crate::alloc_prelude::*;
+
+// the order promotions are generated in
+const PROMOTIONS: [PieceType; 4] = [
+    PieceType::Queen,
+    PieceType::Knight,
+    PieceType::Rook,
+    PieceType::Bishop,
+];
+
+// push a pawn move, or each promotion if it reaches the last rank
+fn push_pawn_move(moves: &mut MoveList, mv: Move) {
+    if mv.to.rank() == Rank::First || mv.to.rank() == Rank::Eighth {
+        for &kind in PROMOTIONS.iter() {
+            moves.push(mv.promoting(kind));
+        }
+    } else {
+        moves.push(mv);
+    }
+}
+
 impl Board {
+    // all pseudo-legal moves for the side to move
     pub fn moves(&self) -> Vec<Move> {
-        let c = self.color_to_move;
-        let mut moves = Vec::new();
-        for (loc, p) in self.get_pieces_by_color(c) {
-            match p.kind {
-                PieceType::Pawn => moves.extend(self.pawn_moves(loc, c)),
-                PieceType::Queen => moves.extend(self.queen_moves(loc, c)),
-                PieceType::Rook => moves.extend(self.rook_moves(loc, c)),
-                PieceType::Bishop => moves.extend(self.bishop_moves(loc, c)),
-                PieceType::Knight => moves.extend(self.knight_moves(loc, c)),
-                PieceType::King => moves.extend(self.king_moves(loc, c)),
-            }
+        let mut moves = MoveList::new();
+        self.generate_moves(&mut moves);
+        moves.to_vec()
+    }
+
+    // empty when the game is over, see status() for how it ended
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut moves = MoveList::new();
+        self.generate_legal_moves(&mut moves);
+        moves.to_vec()
+    }
+
+    pub fn status(&self) -> GameStatus {
+        let mut moves = MoveList::new();
+        self.generate_legal_moves(&mut moves);
+        self.status_with(&moves)
+    }
+
+    // the status, given the legal moves. mate comes before the fifty move rule, and a
+    // draw before check.
+    pub(crate) fn status_with(&self, moves: &MoveList) -> GameStatus {
+        let in_check = self.in_check(self.color_to_move);
+        if moves.is_empty() {
+            if in_check {
+                GameStatus::Checkmate
+            } else {
+                GameStatus::Stalemate
+            }
+        } else if self.insufficient_material() {
+            GameStatus::DrawBy(Draw::InsufficientMaterial)
+        } else if self.fifty_move_draw() {
+            GameStatus::DrawBy(Draw::FiftyMove)
+        } else if in_check {
+            GameStatus::Check
+        } else {
+            GameStatus::InProgress
         }
-        moves
     }
 
-    pub fn legal_moves(&self) -> Result<Vec<Move>, ChessError> {
-        // filter moves where the king is in check
-        let mut moves: Vec<Move> = self
-            .moves()
-            .into_iter()
-            .filter(|m| self.make_move(m).is_ok())
-            .collect();
-        moves.sort();
-        // check for checkmate, stalemate, no moves (when there are no kings, haha)
+    // push the pseudo-legal moves for the side to move onto moves
+    pub fn generate_moves(&self, moves: &mut MoveList) {
         let c = self.color_to_move;
-        let kings = self.get_pieces_by_type_and_color(PieceType::King, c);
-        if moves.len() == 0 {
-            if kings.len() == 1 && self.color_threatens(c.other(), kings[0]) {
-                Err(ChessError::Checkmate)
-            } else {
-                Err(ChessError::Stalemate)
+        for (loc, p) in self.iter_pieces_by_color(c) {
+            match p.kind {
+                PieceType::Pawn => self.pawn_moves(loc, c, moves),
+                PieceType::Queen => self.queen_moves(loc, c, moves),
+                PieceType::Rook => self.rook_moves(loc, c, moves),
+                PieceType::Bishop => self.bishop_moves(loc, c, moves),
+                PieceType::Knight => self.knight_moves(loc, c, moves),
+                PieceType::King => self.king_moves(loc, c, moves),
             }
-        } else {
-            Ok(moves)
         }
     }
 
-    fn pawn_moves(&self, loc: Pos, c: Color) -> Vec<Move> {
+    // replace the contents of moves with the sorted legal moves for the side to move
+    pub fn generate_legal_moves(&self, moves: &mut MoveList) {
+        moves.clear();
+        self.generate_moves(moves);
+        self.retain_legal(moves);
+        moves.sort_unstable();
+    }
+
+    fn pawn_moves(&self, loc: Pos, c: Color, moves: &mut MoveList) {
         match c {
-            Color::White => self.white_pawn_moves(loc),
-            Color::Black => self.black_pawn_moves(loc),
+            Color::White => self.white_pawn_moves(loc, moves),
+            Color::Black => self.black_pawn_moves(loc, moves),
         }
     }
 
-    fn white_pawn_moves(&self, old: Pos) -> Vec<Move> {
-        let mut moves = Vec::new();
-        let m = Move {
-            kind: PieceType::Pawn,
-            from: old,
-            to: old,
-            takes: false,
-            en_passant: false,
-            promotion: None,
-            castle: None,
-        };
-
+    fn white_pawn_moves(&self, old: Pos, moves: &mut MoveList) {
         // noncapturing reqular move
         old.north(1).map(|new| {
             if !self.occupied(new) {
-                // promotion
-                if new.rank_is(8) {
-                    moves.push(Move {
-                        to: new,
-                        promotion: Some(PieceType::Queen),
-                        ..m
-                    });
-                    moves.push(Move {
-                        to: new,
-                        promotion: Some(PieceType::Knight),
-                        ..m
-                    });
-                    moves.push(Move {
-                        to: new,
-                        promotion: Some(PieceType::Rook),
-                        ..m
-                    });
-                    moves.push(Move {
-                        to: new,
-                        promotion: Some(PieceType::Bishop),
-                        ..m
-                    });
-                } else {
-                    moves.push(Move { to: new, ..m });
-                }
-                if old.rank_is(2) {
+                push_pawn_move(moves, Move::normal(PieceType::Pawn, old, new));
+                if old.rank() == Rank::Second {
                     new.north(1).map(|double| {
                         if !self.occupied(double) {
-                            moves.push(Move { to: double, ..m });
+                            moves.push(Move::normal(PieceType::Pawn, old, double));
                         }
                     });
                 }
@@ -101,141 +116,24 @@ impl Board {
         });
 
         // capturing regular moves
-        old.northeast(1).map(|new| {
-            if self.occupied(new) {
-                if new.rank_is(8) {
-                    moves.push(Move {
-                        to: new,
-                        takes: true,
-                        promotion: Some(PieceType::Queen),
-                        ..m
-                    });
-                    moves.push(Move {
-                        to: new,
-                        takes: true,
-                        promotion: Some(PieceType::Knight),
-                        ..m
-                    });
-                    moves.push(Move {
-                        to: new,
-                        takes: true,
-                        promotion: Some(PieceType::Rook),
-                        ..m
-                    });
-                    moves.push(Move {
-                        to: new,
-                        takes: true,
-                        promotion: Some(PieceType::Bishop),
-                        ..m
-                    });
-                } else {
-                    moves.push(Move {
-                        to: new,
-                        takes: true,
-                        ..m
-                    });
-                }
-            } else if self.is_en_passant_target(new) {
-                moves.push(Move {
-                    to: new,
-                    takes: true,
-                    en_passant: true,
-                    ..m
-                });
-            }
-        });
-
-        old.northwest(1).map(|new| {
-            if self.occupied(new) {
-                if new.rank_is(8) {
-                    moves.push(Move {
-                        to: new,
-                        takes: true,
-                        promotion: Some(PieceType::Queen),
-                        ..m
-                    });
-                    moves.push(Move {
-                        to: new,
-                        takes: true,
-                        promotion: Some(PieceType::Knight),
-                        ..m
-                    });
-                    moves.push(Move {
-                        to: new,
-                        takes: true,
-                        promotion: Some(PieceType::Rook),
-                        ..m
-                    });
-                    moves.push(Move {
-                        to: new,
-                        takes: true,
-                        promotion: Some(PieceType::Bishop),
-                        ..m
-                    });
-                } else {
-                    moves.push(Move {
-                        to: new,
-                        takes: true,
-                        ..m
-                    });
-                }
+        for &new in [old.northeast(1), old.northwest(1)].iter().flatten() {
+            if self.piece(new).is_some_and(|p| p.color == Color::Black) {
+                push_pawn_move(moves, Move::capture(PieceType::Pawn, old, new));
             } else if self.is_en_passant_target(new) {
-                moves.push(Move {
-                    to: new,
-                    takes: true,
-                    en_passant: true,
-                    ..m
-                });
+                moves.push(Move::en_passant(old, new));
             }
-        });
-
-        moves
+        }
     }
 
-    fn black_pawn_moves(&self, old: Pos) -> Vec<Move> {
-        let mut moves = Vec::new();
-        let m = Move {
-            kind: PieceType::Pawn,
-            from: old,
-            to: old,
-            takes: false,
-            en_passant: false,
-            promotion: None,
-            castle: None,
-        };
-
+    fn black_pawn_moves(&self, old: Pos, moves: &mut MoveList) {
         // noncapturing reqular move
         old.south(1).map(|new| {
             if !self.occupied(new) {
-                // promotion
-                if new.rank_is(1) {
-                    moves.push(Move {
-                        to: new,
-                        promotion: Some(PieceType::Queen),
-                        ..m
-                    });
-                    moves.push(Move {
-                        to: new,
-                        promotion: Some(PieceType::Knight),
-                        ..m
-                    });
-                    moves.push(Move {
-                        to: new,
-                        promotion: Some(PieceType::Rook),
-                        ..m
-                    });
-                    moves.push(Move {
-                        to: new,
-                        promotion: Some(PieceType::Bishop),
-                        ..m
-                    });
-                } else {
-                    moves.push(Move { to: new, ..m });
-                }
-                if old.rank_is(7) {
+                push_pawn_move(moves, Move::normal(PieceType::Pawn, old, new));
+                if old.rank() == Rank::Seventh {
                     new.south(1).map(|double| {
                         if !self.occupied(double) {
-                            moves.push(Move { to: double, ..m });
+                            moves.push(Move::normal(PieceType::Pawn, old, double));
                         }
                     });
                 }
@@ -243,116 +141,24 @@ impl Board {
         });
 
         // capturing regular moves
-        old.southeast(1).map(|new| {
-            if self.occupied(new) {
-                if new.rank_is(1) {
-                    moves.push(Move {
-                        to: new,
-                        takes: true,
-                        promotion: Some(PieceType::Queen),
-                        ..m
-                    });
-                    moves.push(Move {
-                        to: new,
-                        takes: true,
-                        promotion: Some(PieceType::Knight),
-                        ..m
-                    });
-                    moves.push(Move {
-                        to: new,
-                        takes: true,
-                        promotion: Some(PieceType::Rook),
-                        ..m
-                    });
-                    moves.push(Move {
-                        to: new,
-                        takes: true,
-                        promotion: Some(PieceType::Bishop),
-                        ..m
-                    });
-                } else {
-                    moves.push(Move {
-                        to: new,
-                        takes: true,
-                        ..m
-                    });
-                }
-            } else if self.is_en_passant_target(new) {
-                moves.push(Move {
-                    to: new,
-                    takes: true,
-                    en_passant: true,
-                    ..m
-                });
-            }
-        });
-
-        old.southwest(1).map(|new| {
-            if self.occupied(new) {
-                if new.rank_is(1) {
-                    moves.push(Move {
-                        to: new,
-                        takes: true,
-                        promotion: Some(PieceType::Queen),
-                        ..m
-                    });
-                    moves.push(Move {
-                        to: new,
-                        takes: true,
-                        promotion: Some(PieceType::Knight),
-                        ..m
-                    });
-                    moves.push(Move {
-                        to: new,
-                        takes: true,
-                        promotion: Some(PieceType::Rook),
-                        ..m
-                    });
-                    moves.push(Move {
-                        to: new,
-                        takes: true,
-                        promotion: Some(PieceType::Bishop),
-                        ..m
-                    });
-                } else {
-                    moves.push(Move {
-                        to: new,
-                        takes: true,
-                        ..m
-                    });
-                }
+        for &new in [old.southeast(1), old.southwest(1)].iter().flatten() {
+            if self.piece(new).is_some_and(|p| p.color == Color::White) {
+                push_pawn_move(moves, Move::capture(PieceType::Pawn, old, new));
             } else if self.is_en_passant_target(new) {
-                moves.push(Move {
-                    to: new,
-                    takes: true,
-                    en_passant: true,
-                    ..m
-                });
+                moves.push(Move::en_passant(old, new));
             }
-        });
-
-        moves
+        }
     }
 
-    fn queen_moves(&self, old: Pos, c: Color) -> Vec<Move> {
+    fn queen_moves(&self, old: Pos, c: Color, moves: &mut MoveList) {
         let mut new = old;
-        let mut moves = Vec::new();
-        let m = Move {
-            kind: PieceType::Queen,
-            from: old,
-            to: new,
-            takes: false,
-            en_passant: false,
-            promotion: None,
-            castle: None,
-        };
 
         {
             let mut mv = |new, takes| {
-                moves.push(Move {
-                    to: new,
-                    takes: takes,
-                    ..m
+                moves.push(if takes {
+                    Move::capture(PieceType::Queen, old, new)
+                } else {
+                    Move::normal(PieceType::Queen, old, new)
                 })
             };
 
@@ -467,28 +273,17 @@ impl Board {
                 }
             }
         }
-        moves
     }
 
-    fn rook_moves(&self, old: Pos, c: Color) -> Vec<Move> {
+    fn rook_moves(&self, old: Pos, c: Color, moves: &mut MoveList) {
         let mut new = old;
-        let mut moves = Vec::new();
-        let m = Move {
-            kind: PieceType::Rook,
-            from: old,
-            to: new,
-            takes: false,
-            en_passant: false,
-            promotion: None,
-            castle: None,
-        };
 
         {
             let mut mv = |new, takes| {
-                moves.push(Move {
-                    to: new,
-                    takes: takes,
-                    ..m
+                moves.push(if takes {
+                    Move::capture(PieceType::Rook, old, new)
+                } else {
+                    Move::normal(PieceType::Rook, old, new)
                 })
             };
 
@@ -547,28 +342,17 @@ impl Board {
                 }
             }
         }
-        moves
     }
 
-    fn bishop_moves(&self, old: Pos, c: Color) -> Vec<Move> {
+    fn bishop_moves(&self, old: Pos, c: Color, moves: &mut MoveList) {
         let mut new = old;
-        let mut moves = Vec::new();
-        let m = Move {
-            kind: PieceType::Bishop,
-            from: old,
-            to: new,
-            takes: false,
-            en_passant: false,
-            promotion: None,
-            castle: None,
-        };
 
         {
             let mut mv = |new, takes| {
-                moves.push(Move {
-                    to: new,
-                    takes: takes,
-                    ..m
+                moves.push(if takes {
+                    Move::capture(PieceType::Bishop, old, new)
+                } else {
+                    Move::normal(PieceType::Bishop, old, new)
                 })
             };
 
@@ -627,35 +411,16 @@ impl Board {
                 }
             }
         }
-        moves
     }
 
-    fn knight_moves(&self, old: Pos, c: Color) -> Vec<Move> {
-        let mut moves = Vec::new();
-        let m = Move {
-            kind: PieceType::Knight,
-            from: old,
-            to: old,
-            takes: false,
-            en_passant: false,
-            promotion: None,
-            castle: None,
-        };
+    fn knight_moves(&self, old: Pos, c: Color, moves: &mut MoveList) {
         {
             let mut mv = |vert, horiz| {
                 old.mv(vert, horiz).map(|new| match self.piece(new) {
-                    None => moves.push(Move {
-                        to: new,
-                        takes: false,
-                        ..m
-                    }),
+                    None => moves.push(Move::normal(PieceType::Knight, old, new)),
                     Some(p) => {
                         if p.color != c {
-                            moves.push(Move {
-                                to: new,
-                                takes: true,
-                                ..m
-                            })
+                            moves.push(Move::capture(PieceType::Knight, old, new))
                         }
                     }
                 });
@@ -669,20 +434,9 @@ impl Board {
             mv(-2, -1);
             mv(-2, 1);
         }
-        moves
     }
 
-    fn king_moves(&self, old: Pos, c: Color) -> Vec<Move> {
-        let mut moves = Vec::new();
-        let m = Move {
-            kind: PieceType::King,
-            from: old,
-            to: old,
-            takes: false,
-            en_passant: false,
-            promotion: None,
-            castle: None,
-        };
+    fn king_moves(&self, old: Pos, c: Color, moves: &mut MoveList) {
         let king_moves = [
             (1, 0),
             (1, 1),
@@ -695,33 +449,15 @@ impl Board {
         ];
         for &(vert, horiz) in king_moves.iter() {
             old.mv(vert, horiz).map(|new| match self.piece(new) {
-                None => moves.push(Move {
-                    to: new,
-                    takes: false,
-                    ..m
-                }),
+                None => moves.push(Move::normal(PieceType::King, old, new)),
                 Some(p) => {
                     if p.color != c {
-                        moves.push(Move {
-                            to: new,
-                            takes: true,
-                            ..m
-                        })
+                        moves.push(Move::capture(PieceType::King, old, new))
                     }
                 }
             });
         }
 
-        let castle = Move {
-            kind: PieceType::King,
-            from: Pos::zero(),
-            to: Pos::zero(),
-            takes: false,
-            en_passant: false,
-            promotion: None,
-            castle: None,
-        };
-
         if self.castle_kingside_rights(c)
             && !self.occupied(
                 old.east(1)
@@ -731,13 +467,10 @@ impl Board {
                 old.east(2)
                     .expect("[Board::king_moves] confusing castling rights!"),
             )
-            && !self.color_threatens(c.other(), old)
-            && !self.color_threatens(c.other(), old.east(1).unwrap())
+            && !self.threats(c.other()).contains(old)
+            && !self.threats(c.other()).contains(old.east(1).unwrap())
         {
-            moves.push(Move {
-                castle: Some(Castle::Kingside),
-                ..castle
-            });
+            moves.push(Move::castle(Castle::Kingside));
         }
 
         if self.castle_queenside_rights(c)
@@ -753,15 +486,11 @@ impl Board {
                 old.west(3)
                     .expect("[Board::king_moves] confusing castling rights!"),
             )
-            && !self.color_threatens(c.other(), old)
-            && !self.color_threatens(c.other(), old.west(1).unwrap())
+            && !self.threats(c.other()).contains(old)
+            && !self.threats(c.other()).contains(old.west(1).unwrap())
         {
-            moves.push(Move {
-                castle: Some(Castle::Queenside),
-                ..castle
-            });
+            moves.push(Move::castle(Castle::Queenside));
         }
-        moves
     }
 
     // checks for legality
@@ -769,7 +498,7 @@ impl Board {
         let color = self.color_to_move;
         let mut b = Board {
             color_to_move: color.other(),
-            ..*self
+            ..self.clone()
         };
         if color == Color::Black {
             b.move_number += 1;
@@ -787,16 +516,16 @@ impl Board {
                     }
                     match color {
                         Color::White => {
-                            if self.color_threatens(color.other(), pos!("e1"))
-                                || self.color_threatens(color.other(), pos!("f1"))
-                                || self.color_threatens(color.other(), pos!("g1"))
+                            if self.color_threatens(color.other(), Pos::E1)
+                                || self.color_threatens(color.other(), Pos::F1)
+                                || self.color_threatens(color.other(), Pos::G1)
                             {
                                 illegal_move_error!(
                                     "[make_move] {}: white cannot castle kingside through check!",
                                     mv
                                 );
                             }
-                            if self.occupied(pos!("f1")) || self.occupied(pos!("g1")) {
+                            if self.occupied(Pos::F1) || self.occupied(Pos::G1) {
                                 illegal_move_error!("[make_move] {}: white cannot castle kingside: spaces occupied!", mv);
                             }
                             let k = match b.get_piece_at("e1") {
@@ -831,16 +560,16 @@ impl Board {
                             b.castle_rights[1] = false;
                         }
                         Color::Black => {
-                            if self.color_threatens(color.other(), pos!("e8"))
-                                || self.color_threatens(color.other(), pos!("f8"))
-                                || self.color_threatens(color.other(), pos!("g8"))
+                            if self.color_threatens(color.other(), Pos::E8)
+                                || self.color_threatens(color.other(), Pos::F8)
+                                || self.color_threatens(color.other(), Pos::G8)
                             {
                                 illegal_move_error!(
                                     "[make_move] {} black cannot castle kingside through check!",
                                     mv
                                 );
                             }
-                            if self.occupied(pos!("f8")) || self.occupied(pos!("g8")) {
+                            if self.occupied(Pos::F8) || self.occupied(Pos::G8) {
                                 illegal_move_error!("[make_move] {}: black cannot castle kingside: spaces occupied!", mv);
                             }
                             let k = match b.get_piece_at("e8") {
@@ -882,18 +611,18 @@ impl Board {
                     }
                     match color {
                         Color::White => {
-                            if self.color_threatens(color.other(), pos!("e1"))
-                                || self.color_threatens(color.other(), pos!("d1"))
-                                || self.color_threatens(color.other(), pos!("c1"))
+                            if self.color_threatens(color.other(), Pos::E1)
+                                || self.color_threatens(color.other(), Pos::D1)
+                                || self.color_threatens(color.other(), Pos::C1)
                             {
                                 illegal_move_error!(
                                     "[make_move] {}: white cannot castle queenside through check!",
                                     mv
                                 );
                             }
-                            if self.occupied(pos!("b1"))
-                                || self.occupied(pos!("c1"))
-                                || self.occupied(pos!("d1"))
+                            if self.occupied(Pos::B1)
+                                || self.occupied(Pos::C1)
+                                || self.occupied(Pos::D1)
                             {
                                 illegal_move_error!("[make_move] {}: white cannot castle queenside: spaces occupied!", mv);
                             }
@@ -929,18 +658,18 @@ impl Board {
                             b.castle_rights[1] = false;
                         }
                         Color::Black => {
-                            if self.color_threatens(color.other(), pos!("e8"))
-                                || self.color_threatens(color.other(), pos!("d8"))
-                                || self.color_threatens(color.other(), pos!("c8"))
+                            if self.color_threatens(color.other(), Pos::E8)
+                                || self.color_threatens(color.other(), Pos::D8)
+                                || self.color_threatens(color.other(), Pos::C8)
                             {
                                 illegal_move_error!(
                                     "[make_move] {}: black cannot castle queenside through check!",
                                     mv
                                 );
                             }
-                            if self.occupied(pos!("b8"))
-                                || self.occupied(pos!("c8"))
-                                || self.occupied(pos!("d8"))
+                            if self.occupied(Pos::B8)
+                                || self.occupied(Pos::C8)
+                                || self.occupied(Pos::D8)
                             {
                                 illegal_move_error!("[make_move] {}: black cannot castle queenside: spaces occupied!", mv);
                             }
@@ -988,7 +717,7 @@ impl Board {
                 illegal_move_error!("[make_move] {}: illegal en passant!", mv);
             }
             // check that some piece exists at mv.from
-            let p = match b.board[mv.from.index()].take() {
+            let p = match b.take_piece(mv.from) {
                 Some(p) => p,
                 None => illegal_move_error!("[make_move] {}: no piece at {}", mv, mv.from),
             };
@@ -1007,17 +736,16 @@ impl Board {
                 Color::Black => mv.to.north(1).unwrap(),
             };
             // there should be no peice at the en passant target
-            match b.board[mv.to.index()] {
-                Some(p) => board_state_error!(
+            if let Some(p) = b.board.get(mv.to) {
+                board_state_error!(
                     "[make_move] {}: there should be no piece at {} but I found {}",
                     mv,
                     mv.to,
                     p
-                ),
-                None => {}
+                );
             }
             // remove the target piece from the board
-            let q = b.board[target_piece_at.index()].take();
+            let q = b.take_piece(target_piece_at);
             // check that there was actually something there
             if q.is_none() {
                 illegal_move_error!(
@@ -1031,13 +759,11 @@ impl Board {
                 illegal_move_error!("[make_move] {}: taking a piece of the same color!", mv);
             }
             // place the capturing piece
-            b.board[mv.to.index()] = Some(p);
-            // reset en passant target
-            b.en_passant_target = None;
+            b.set_piece(mv.to, Some(p));
         } else {
             // grab the moving/capturing piece
-            let p = b.board[mv.from.index()]
-                .take()
+            let p = b
+                .take_piece(mv.from)
                 .expect(&format!("[make_move] {}: no piece at {}", mv, mv.from));
             // check that it is the right color
             if p.color != color {
@@ -1064,25 +790,25 @@ impl Board {
             if p.kind == PieceType::Rook {
                 match color {
                     Color::White => {
-                        if mv.from == pos!("h1") {
+                        if mv.from == Pos::H1 {
                             b.castle_rights[0] = false
                         }
-                        if mv.from == pos!("a1") {
+                        if mv.from == Pos::A1 {
                             b.castle_rights[1] = false
                         }
                     }
                     Color::Black => {
-                        if mv.from == pos!("h8") {
+                        if mv.from == Pos::H8 {
                             b.castle_rights[2] = false
                         }
-                        if mv.from == pos!("a8") {
+                        if mv.from == Pos::A8 {
                             b.castle_rights[3] = false
                         }
                     }
                 }
             }
             // grab the potentially nonexistant target piece
-            let q = b.board[mv.to.index()].take();
+            let q = b.take_piece(mv.to);
             // if the move is a capture, check that there actually was a piece there
             if mv.takes && q.is_none() {
                 illegal_move_error!("[make_move] {}: taking a nonexistent piece!", mv);
@@ -1091,19 +817,24 @@ impl Board {
             if q.map_or(false, |q| q.color == color) {
                 illegal_move_error!("[make_move] {}: {} cannot take its own pieces!", mv, color);
             }
+            // taking a rook in its corner takes away castling with it
+            if q.is_some_and(|q| q.kind == PieceType::Rook) {
+                b.clear_rook_castle_right(mv.to);
+            }
             // possibly promote, but only for pawns
             if let Some(prom) = mv.promotion {
                 if p.kind != PieceType::Pawn {
                     illegal_move_error!("[make_move] {}: only pawns can promote!", mv);
                 }
-                b.board[mv.to.index()] = Some(Piece { kind: prom, ..p });
+                b.set_piece(mv.to, Some(Piece { kind: prom, ..p }));
             } else {
                 // place moving/capturing piece
-                b.board[mv.to.index()] = Some(p);
+                b.set_piece(mv.to, Some(p));
             }
         }
-        let kings = b.get_pieces_by_type_and_color(PieceType::King, color);
-        if kings.len() == 1 && b.color_threatens(color.other(), kings[0]) {
+        b.en_passant_target = en_passant_target_after(mv);
+        b.key ^= self.state_key() ^ b.state_key();
+        if b.in_check(color) {
             illegal_move_error!("moving into check");
         }
         Ok(b)
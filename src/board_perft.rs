@@ -0,0 +1,132 @@
+use crate::board::Board;
+use crate::move_list::MoveList;
+use crate::moves::Move;
+
+#[cfg(not(feature = "std"))]
+use crate::alloc_prelude::*;
+
+impl Board {
+    // count the leaves of the legal move tree of the given depth, for checking the move
+    // generator against known results
+    pub fn perft(&self, depth: usize) -> u64 {
+        self.clone().perft_rec(depth)
+    }
+
+    // perft for each legal move from this position, to find which move a wrong total
+    // comes from
+    pub fn perft_divide(&self, depth: usize) -> Vec<(Move, u64)> {
+        let mut b = self.clone();
+        let mut moves = MoveList::new();
+        if depth == 0 {
+            return Vec::new();
+        }
+        b.generate_legal_moves(&mut moves);
+        moves
+            .iter()
+            .map(|mv| {
+                let undo = b.do_move(mv);
+                let nodes = b.perft_rec(depth - 1);
+                b.undo_move(undo);
+                (*mv, nodes)
+            })
+            .collect()
+    }
+
+    fn perft_rec(&mut self, depth: usize) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let mut moves = MoveList::new();
+        self.generate_legal_moves(&mut moves);
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        let mut nodes = 0;
+        for mv in moves.iter() {
+            let undo = self.do_move(mv);
+            nodes += self.perft_rec(depth - 1);
+            self.undo_move(undo);
+        }
+        nodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+
+    macro_rules! perft_is {
+        ($fen:expr, $($n:expr),*) => {{
+            let b = Board::from_fen($fen).expect("[perft_is] bad fen!");
+            let expected = [$($n),*];
+            for (i, &n) in expected.iter().enumerate() {
+                assert_eq!(b.perft(i + 1), n, "perft({}) of {}", i + 1, $fen);
+            }
+        }};
+    }
+
+    #[test]
+    fn perft_divide_sums_to_perft() {
+        let b =
+            Board::from_fen("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8").unwrap();
+        let divide = b.perft_divide(2);
+        assert_eq!(divide.len(), 44);
+        assert_eq!(divide.iter().map(|&(_, n)| n).sum::<u64>(), 1486);
+        for (mv, n) in divide {
+            let replies = b.make_move(&mv).unwrap().legal_moves();
+            assert_eq!(n, replies.len() as u64, "perft_divide count for {}", mv);
+        }
+    }
+
+    #[test]
+    fn perft_initial() {
+        perft_is!(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            20,
+            400,
+            8902,
+            197281
+        );
+    }
+
+    #[test]
+    fn perft_kiwipete() {
+        perft_is!(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            48,
+            2039,
+            97862
+        );
+    }
+
+    #[test]
+    fn perft_position_3() {
+        // the deeper counts need pawns taking en passant after a double push
+        perft_is!(
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            14,
+            191,
+            2812,
+            43238
+        );
+    }
+
+    #[test]
+    fn perft_position_4() {
+        perft_is!(
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            6,
+            264
+        );
+    }
+
+    #[test]
+    fn perft_position_5() {
+        perft_is!(
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            44,
+            1486,
+            62379
+        );
+    }
+}
@@ -0,0 +1,236 @@
+use crate::board::Board;
+use crate::move_list::MoveList;
+use crate::moves::Move;
+
+use std::sync::{Arc, RwLock};
+
+// proof and disproof numbers past this are as good as infinite
+const INFINITE: u32 = 1 << 30;
+
+// a position in the proof tree. its children are the positions after each legal move,
+// stored one after another from first_child once it has been expanded.
+struct Node {
+    mv: Option<Move>,
+    parent: usize,
+    // whether the attacker is to move, so one mate is enough rather than all replies
+    attacking: bool,
+    proof: u32,
+    disproof: u32,
+    first_child: usize,
+    children: usize,
+}
+
+impl Node {
+    fn proven(&self) -> bool {
+        self.proof == 0
+    }
+}
+
+fn sum(numbers: impl Iterator<Item = u32>) -> u32 {
+    numbers.fold(0, |total, n| total.saturating_add(n).min(INFINITE))
+}
+
+// proof-number search: grow the tree where the fewest positions are left to prove or
+// disprove a mate, so narrow forcing lines go as deep as they need without a depth
+// limit. good at long mates and studies, where alpha-beta runs out of depth long before
+// the mate. there is no evaluation, and a repetition or the fifty move rule counts as a
+// defence.
+struct ProofTree {
+    nodes: Vec<Node>,
+    max_nodes: usize,
+}
+
+impl ProofTree {
+    // the numbers for the position reached after a move, worked out from its replies
+    // without adding them to the tree. mobility makes a good first guess: each reply
+    // is another line to refute.
+    fn leaf(b: &Board, attacking: bool, repeated: bool) -> (u32, u32) {
+        let mut moves = MoveList::new();
+        b.generate_legal_moves(&mut moves);
+        let won = (0, INFINITE);
+        let lost = (INFINITE, 0);
+        if moves.is_empty() {
+            return if b.in_check(b.color_to_move) && !attacking {
+                won
+            } else {
+                lost
+            };
+        }
+        if repeated || b.fifty_move_draw() || b.insufficient_material() {
+            return lost;
+        }
+        let n = moves.len() as u32;
+        if attacking {
+            (1, n)
+        } else {
+            (n, 1)
+        }
+    }
+
+    // add the positions after each legal move in b, the position at index
+    fn expand(&mut self, b: &mut Board, index: usize, path: &[u64]) {
+        let mut moves = MoveList::new();
+        b.generate_legal_moves(&mut moves);
+        let attacking = !self.nodes[index].attacking;
+        let first_child = self.nodes.len();
+        for mv in moves.iter() {
+            let undo = b.do_move(mv);
+            let repeated = path.contains(&b.zobrist());
+            let (proof, disproof) = ProofTree::leaf(b, attacking, repeated);
+            b.undo_move(undo);
+            self.nodes.push(Node {
+                mv: Some(*mv),
+                parent: index,
+                attacking,
+                proof,
+                disproof,
+                first_child: 0,
+                children: 0,
+            });
+        }
+        let node = &mut self.nodes[index];
+        node.first_child = first_child;
+        node.children = moves.len();
+    }
+
+    fn children(&self, index: usize) -> &[Node] {
+        let node = &self.nodes[index];
+        &self.nodes[node.first_child..node.first_child + node.children]
+    }
+
+    fn update(&mut self, index: usize) {
+        let children = self.children(index);
+        let (proof, disproof) = if self.nodes[index].attacking {
+            let proof = children.iter().map(|c| c.proof).min().unwrap_or(INFINITE);
+            (proof, sum(children.iter().map(|c| c.disproof)))
+        } else {
+            let disproof = children
+                .iter()
+                .map(|c| c.disproof)
+                .min()
+                .unwrap_or(INFINITE);
+            (sum(children.iter().map(|c| c.proof)), disproof)
+        };
+        let node = &mut self.nodes[index];
+        node.proof = proof;
+        node.disproof = disproof;
+    }
+
+    // the child that decides the numbers: the easiest to prove for the attacker and the
+    // easiest to refute for the defender
+    fn most_proving(&self, index: usize) -> usize {
+        let node = &self.nodes[index];
+        let children = self.children(index);
+        let best = if node.attacking {
+            children.iter().position(|c| c.proof == node.proof)
+        } else {
+            children.iter().position(|c| c.disproof == node.disproof)
+        };
+        node.first_child + best.unwrap_or(0)
+    }
+
+    fn solve(&mut self, root: &mut Board, abort: &Option<Arc<RwLock<bool>>>) {
+        let mut path = Vec::new();
+        let mut undos = Vec::new();
+        while !self.nodes[0].proven()
+            && self.nodes[0].disproof != 0
+            && self.nodes.len() < self.max_nodes
+            && !abort.as_ref().is_some_and(|abort| *abort.read().unwrap())
+        {
+            let mut index = 0;
+            path.push(root.zobrist());
+            while self.nodes[index].children > 0 {
+                index = self.most_proving(index);
+                undos.push(root.do_move(&self.nodes[index].mv.unwrap()));
+                path.push(root.zobrist());
+            }
+            self.expand(root, index, &path);
+            loop {
+                self.update(index);
+                if index == 0 {
+                    break;
+                }
+                index = self.nodes[index].parent;
+            }
+            while let Some(undo) = undos.pop() {
+                root.undo_move(undo);
+            }
+            path.clear();
+        }
+    }
+
+    // plies to mate from a proven position, taking the quickest mate and the longest
+    // defence the tree knows of
+    fn plies(&self, index: usize) -> usize {
+        let node = &self.nodes[index];
+        if node.children == 0 {
+            return 0;
+        }
+        let proven = (node.first_child..node.first_child + node.children)
+            .filter(|&child| self.nodes[child].proven())
+            .map(|child| self.plies(child));
+        1 + if node.attacking {
+            proven.min().unwrap_or(0)
+        } else {
+            proven.max().unwrap_or(0)
+        }
+    }
+
+    fn line(&self) -> Vec<Move> {
+        let mut line = Vec::new();
+        let mut index = 0;
+        while self.nodes[index].children > 0 {
+            let node = &self.nodes[index];
+            let proven = (node.first_child..node.first_child + node.children)
+                .filter(|&child| self.nodes[child].proven());
+            index = if node.attacking {
+                proven.min_by_key(|&child| self.plies(child))
+            } else {
+                proven.max_by_key(|&child| self.plies(child))
+            }
+            .unwrap();
+            line.push(self.nodes[index].mv.unwrap());
+        }
+        line
+    }
+}
+
+impl Board {
+    // a mating line for the side to move, however long, if proof-number search can prove
+    // one in at most max_nodes positions. the defence is the longest the proof covers,
+    // and the mate isn't always the shortest there is.
+    pub fn prove_mate(&self, max_nodes: usize) -> Option<Vec<Move>> {
+        self.prove_mate_with(max_nodes, None)
+    }
+
+    // prove_mate, giving up when abort is set
+    pub fn prove_mate_with(
+        &self,
+        max_nodes: usize,
+        abort: Option<Arc<RwLock<bool>>>,
+    ) -> Option<Vec<Move>> {
+        let mut b = self.clone();
+        let mut tree = ProofTree {
+            nodes: vec![Node {
+                mv: None,
+                parent: 0,
+                attacking: true,
+                proof: 1,
+                disproof: 1,
+                first_child: 0,
+                children: 0,
+            }],
+            max_nodes,
+        };
+        let (proof, disproof) = ProofTree::leaf(&b, true, false);
+        if proof == 0 || disproof == 0 {
+            return None;
+        }
+        tree.solve(&mut b, &abort);
+        if tree.nodes[0].proven() {
+            Some(tree.line())
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use crate::board::Board;
+use crate::moves::Move;
+use crate::piece::{Color, Piece};
+use crate::position::Pos;
+use crate::util::ChessError;
+
+use core::fmt;
+
+#[cfg(not(feature = "std"))]
+use crate::alloc_prelude::*;
+
+// how to draw a board as text. the default is what Display prints.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderOptions {
+    // chess symbols like ♘ instead of letters like N
+    pub unicode: bool,
+    // from black's side, with rank 1 at the top
+    pub flipped: bool,
+    // rank numbers down the side and file letters along the bottom
+    pub coordinates: bool,
+    // squares to mark with parentheses, like the from and to squares of the last move
+    pub highlight: Vec<Pos>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> RenderOptions {
+        RenderOptions {
+            unicode: false,
+            flipped: false,
+            coordinates: true,
+            highlight: Vec::new(),
+        }
+    }
+}
+
+impl RenderOptions {
+    // highlight mv, just made by c
+    pub fn last_move(mv: &Move, c: Color) -> RenderOptions {
+        let (from, to) = mv.squares(c);
+        RenderOptions {
+            highlight: vec![from, to],
+            ..RenderOptions::default()
+        }
+    }
+}
+
+impl Board {
+    pub fn render(&self, opts: &RenderOptions) -> String {
+        let mut s = String::new();
+        // writing to a string can't fail
+        let _ = self.draw(&mut s, opts);
+        s
+    }
+
+    pub(crate) fn draw(&self, f: &mut dyn fmt::Write, opts: &RenderOptions) -> fmt::Result {
+        let order = |i: usize| if opts.flipped { 7 - i } else { i };
+        for i in 0..8 {
+            let rank = order(i);
+            if opts.coordinates {
+                write!(f, "{} ", 8 - rank)?;
+            }
+            // the gaps around the squares, opened into parentheses around highlights
+            let lit = |j: usize| opts.highlight.contains(&Pos::new(rank, order(j)));
+            let gap = |j: usize| match (j > 0 && lit(j - 1), j < 8 && lit(j)) {
+                (false, false) => ' ',
+                (false, true) => '(',
+                (true, false) => ')',
+                (true, true) => '|',
+            };
+            write!(f, "[")?;
+            for j in 0..8 {
+                write!(f, "{}", gap(j))?;
+                match (self.piece(Pos::new(rank, order(j))), opts.unicode) {
+                    (Some(p), false) => write!(f, "{}", p)?,
+                    (Some(p), true) => write!(f, "{}", p.glyph())?,
+                    (None, false) => write!(f, "_")?,
+                    (None, true) => write!(f, "·")?,
+                }
+            }
+            writeln!(f, "{}]", gap(8))?;
+        }
+        if opts.coordinates {
+            write!(f, "   ")?;
+            for j in 0..8 {
+                write!(f, " {}", (b'a' + order(j) as u8) as char)?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "{}.", self.move_number)?;
+
+        match self.color_to_move {
+            Color::White => write!(f, " White to move.")?,
+            Color::Black => write!(f, " Black to move.")?,
+        }
+
+        if self.castle_rights.iter().any(|&x| x) {
+            write!(f, " [")?;
+            for (i, c) in "KQkq".chars().enumerate() {
+                if self.castle_rights[i] {
+                    write!(f, "{}", c)?;
+                }
+            }
+            write!(f, "]")?;
+        }
+
+        write!(f, " ({})", self.halfmove_clock)?;
+
+        if let Some(pos) = self.en_passant_target {
+            write!(f, " ({})", pos)?;
+        }
+
+        writeln!(f)
+    }
+
+    // reads back a diagram as render draws it, with any of the options, so one can be
+    // pasted in place of a fen. without rank numbers or file letters it is read from
+    // white's side. the line under the board is optional, and without it white is to
+    // move with no castling rights. the position is checked as by from_fen.
+    pub fn from_diagram(s: &str) -> Result<Board, ChessError> {
+        let mut b = Board::new();
+        let mut ranks = Vec::new();
+        let mut labels = Vec::new();
+        let mut flipped = false;
+        for line in s.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            if line.contains("to move") {
+                b.read_diagram_status(line)?;
+                continue;
+            }
+            let (open, close) = match (line.find('['), line.rfind(']')) {
+                (Some(open), Some(close)) if open < close => (open, close),
+                _ if line.starts_with('a') => continue,
+                _ if line.starts_with('h') => {
+                    flipped = true;
+                    continue;
+                }
+                _ => parse_error!("[from_diagram] unexpected line \"{}\"", line),
+            };
+            match line[..open].trim() {
+                "" => {}
+                n => match n.parse::<usize>() {
+                    Ok(n) if (1..=8).contains(&n) => labels.push(n),
+                    _ => parse_error!("[from_diagram] bad rank \"{}\"", n),
+                },
+            }
+            let mut rank = Vec::with_capacity(8);
+            for c in line[open + 1..close]
+                .chars()
+                .filter(|c| !" ()|".contains(*c))
+            {
+                match c {
+                    '_' | '·' | '.' => rank.push(None),
+                    c => match Piece::from_char(c) {
+                        Some(p) => rank.push(Some(p)),
+                        None => parse_error!("[from_diagram] unexpected \"{}\"", c),
+                    },
+                }
+            }
+            if rank.len() != 8 {
+                parse_error!("[from_diagram] {} squares in \"{}\"", rank.len(), line);
+            }
+            ranks.push(rank);
+        }
+
+        if ranks.len() != 8 {
+            parse_error!("[from_diagram] expected 8 ranks, got {}", ranks.len());
+        }
+        if !labels.is_empty() {
+            flipped = labels[0] == 1;
+            let ok = labels.len() == 8
+                && labels
+                    .iter()
+                    .enumerate()
+                    .all(|(i, &n)| n == if flipped { i + 1 } else { 8 - i });
+            if !ok {
+                parse_error!("[from_diagram] ranks out of order: {:?}", labels);
+            }
+        }
+        let order = |i: usize| if flipped { 7 - i } else { i };
+        for (i, rank) in ranks.iter().enumerate() {
+            for (j, &p) in rank.iter().enumerate() {
+                b.board.set(Pos::new(order(i), order(j)), p);
+            }
+        }
+        b.rehash();
+        b.validate()?;
+        Ok(b)
+    }
+
+    // "12. Black to move. [Kq] (3) (e3)": the move number, side to move, castling
+    // rights, halfmove clock and en passant target
+    fn read_diagram_status(&mut self, line: &str) -> Result<(), ChessError> {
+        let (n, rest) = match line.split_once(". ") {
+            Some((n, rest)) => (n, rest),
+            None => parse_error!("[from_diagram] unexpected line \"{}\"", line),
+        };
+        self.move_number = match n.parse() {
+            Ok(n) => n,
+            Err(_) => parse_error!("[from_diagram] bad move number \"{}\"", n),
+        };
+        let rest = if let Some(rest) = rest.strip_prefix("White to move.") {
+            self.color_to_move = Color::White;
+            rest
+        } else if let Some(rest) = rest.strip_prefix("Black to move.") {
+            self.color_to_move = Color::Black;
+            rest
+        } else {
+            parse_error!("[from_diagram] unexpected line \"{}\"", line)
+        };
+        for token in rest.split_whitespace() {
+            if let Some(rights) = token.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+                for c in rights.chars() {
+                    match "KQkq".find(c) {
+                        Some(i) => self.castle_rights[i] = true,
+                        None => parse_error!("[from_diagram] unexpected \"{}\"", c),
+                    }
+                }
+            } else if let Some(t) = token.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+                match t.parse() {
+                    Ok(n) => self.halfmove_clock = n,
+                    Err(_) => self.en_passant_target = Some(Pos::from_algebra(t)?),
+                }
+            } else {
+                parse_error!("[from_diagram] unexpected \"{}\"", token);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::board_render::RenderOptions;
+    use crate::moves::Move;
+    use crate::piece::Color;
+    use crate::position::Pos;
+
+    #[test]
+    fn render() {
+        let b = Board::initial().make_move(&mv!("e2-e4")).unwrap();
+        assert_eq!(b.render(&RenderOptions::default()), b.to_string());
+        assert_eq!(
+            b.render(&RenderOptions::last_move(&mv!("e2-e4"), Color::White)),
+            "8 [ r n b q k b n r ]\n\
+             7 [ p p p p p p p p ]\n\
+             6 [ _ _ _ _ _ _ _ _ ]\n\
+             5 [ _ _ _ _ _ _ _ _ ]\n\
+             4 [ _ _ _ _(P)_ _ _ ]\n\
+             3 [ _ _ _ _ _ _ _ _ ]\n\
+             2 [ P P P P(_)P P P ]\n\
+             1 [ R N B Q K B N R ]\n    \
+             a b c d e f g h\n\
+             1. Black to move. [KQkq] (0) (e3)\n"
+        );
+
+        let b = Board::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        let after = b.make_move(&mv!("O-O")).unwrap();
+        let opts = RenderOptions {
+            unicode: true,
+            flipped: true,
+            ..RenderOptions::last_move(&mv!("O-O"), Color::White)
+        };
+        assert_eq!(
+            after.render(&opts),
+            "1 [ ·(♔)♖(·)· · · · ]\n\
+             2 [ · · · · · · · · ]\n\
+             3 [ · · · · · · · · ]\n\
+             4 [ · · · · · · · · ]\n\
+             5 [ · · · · · · · · ]\n\
+             6 [ · · · · · · · · ]\n\
+             7 [ · · · · · · · · ]\n\
+             8 [ · · · ♚ · · · · ]\n    \
+             h g f e d c b a\n\
+             1. Black to move. (1)\n"
+        );
+
+        let opts = RenderOptions {
+            coordinates: false,
+            highlight: vec![pos!("e1"), pos!("f1")],
+            ..RenderOptions::default()
+        };
+        assert!(b
+            .render(&opts)
+            .starts_with("[ _ _ _ _ k _ _ _ ]\n[ _ _ _ _ _ _ _ _ ]\n"));
+        assert!(b
+            .render(&opts)
+            .ends_with("[ _ _ _ _(K|_)_ R ]\n1. White to move. [K] (0)\n"));
+    }
+
+    #[test]
+    fn from_diagram() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b Kq - 3 17",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        ];
+        let options = [
+            RenderOptions::default(),
+            RenderOptions {
+                unicode: true,
+                flipped: true,
+                highlight: vec![pos!("e1"), pos!("d1"), pos!("a8")],
+                ..RenderOptions::default()
+            },
+            RenderOptions {
+                coordinates: false,
+                ..RenderOptions::last_move(&mv!("d7-d5"), Color::Black)
+            },
+        ];
+        for fen in fens.iter() {
+            let b = Board::from_fen(fen).unwrap();
+            for opts in options.iter() {
+                let diagram = b.render(opts);
+                let read = Board::from_diagram(&diagram).unwrap();
+                assert_eq!(read.to_fen(), *fen, "\n{}", diagram);
+            }
+        }
+
+        // just the squares, as pasted into a bug report
+        let b = Board::from_diagram(
+            "
+            [ _ _ _ _ k _ _ _ ]
+            [ _ _ _ _ _ _ _ _ ]
+            [ _ _ _ _ _ _ _ _ ]
+            [ _ _ _ _ _ _ _ _ ]
+            [ _ _ _ _ _ _ _ _ ]
+            [ _ _ _ _ _ _ _ _ ]
+            [ _ _ _ _ _ _ _ _ ]
+            [ _ _ _ _ K _ _ R ]
+            ",
+        )
+        .unwrap();
+        assert_eq!(b.to_fen(), "4k3/8/8/8/8/8/8/4K2R w - - 0 1");
+
+        let diagram = Board::initial().to_string();
+        for bad in [
+            diagram.replacen("r n b", "r n", 1),
+            diagram.replacen("r n b", "r x b", 1),
+            diagram.replacen("8 [", "1 [", 1),
+            diagram.replacen("7 [ p p p p p p p p ]\n", "", 1),
+            diagram.replacen("White to move", "Red to move", 1),
+            diagram.replacen("(0)", "(z9)", 1),
+            diagram.replacen("k b n r", "q b n r", 1),
+        ]
+        .iter()
+        {
+            assert!(Board::from_diagram(bad).is_err(), "\n{}", bad);
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use crate::board::Board;
+use crate::board_render::RenderOptions;
+use crate::piece::{Color, Piece};
+use crate::position::Pos;
+#[cfg(feature = "png")]
+use crate::util::ChessError;
+
+use core::fmt::Write;
+
+#[cfg(not(feature = "std"))]
+use crate::alloc_prelude::*;
+
+// pixels on a side of a square, and around the board when there are coordinates
+const SQUARE: usize = 45;
+const MARGIN: usize = 20;
+
+const LIGHT: &str = "#f0d9b5";
+const DARK: &str = "#b58863";
+const LIGHT_HIGHLIGHT: &str = "#cdd26a";
+const DARK_HIGHLIGHT: &str = "#aaa23a";
+
+// fonts with the chess symbols, on linux, windows and elsewhere
+const FONTS: &str = "DejaVu Sans, Segoe UI Symbol, Noto Sans Symbols2, sans-serif";
+
+impl Board {
+    // a diagram of the board as an svg image, drawn with the options the text rendering
+    // takes except unicode. the king of the side to move is marked red when in check.
+    // the pieces are font glyphs, which most systems have, so the image is small.
+    pub fn to_svg(&self, opts: &RenderOptions) -> String {
+        let margin = if opts.coordinates { MARGIN } else { 0 };
+        let size = 8 * SQUARE + 2 * margin;
+        let order = |i: usize| if opts.flipped { 7 - i } else { i };
+        let check = self
+            .king_pos(self.color_to_move)
+            .filter(|_| self.in_check(self.color_to_move));
+
+        let mut s = String::new();
+        // writing to a string can't fail
+        let _ = write!(
+            s,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{0}\" \
+             viewBox=\"0 0 {0} {0}\">\
+             <defs><radialGradient id=\"check\">\
+             <stop offset=\"0%\" stop-color=\"#ff0000\"/>\
+             <stop offset=\"50%\" stop-color=\"#e70000\"/>\
+             <stop offset=\"100%\" stop-color=\"#9e0000\" stop-opacity=\"0\"/>\
+             </radialGradient></defs>\
+             <rect width=\"{0}\" height=\"{0}\" fill=\"#212121\"/>",
+            size
+        );
+
+        for i in 0..8 {
+            for j in 0..8 {
+                let pos = Pos::new(order(i), order(j));
+                let (x, y) = (margin + j * SQUARE, margin + i * SQUARE);
+                let fill = match (pos.is_light(), opts.highlight.contains(&pos)) {
+                    (true, false) => LIGHT,
+                    (false, false) => DARK,
+                    (true, true) => LIGHT_HIGHLIGHT,
+                    (false, true) => DARK_HIGHLIGHT,
+                };
+                let _ = write!(
+                    s,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{2}\" height=\"{2}\" fill=\"{3}\"/>",
+                    x, y, SQUARE, fill
+                );
+                if check == Some(pos) {
+                    let _ = write!(
+                        s,
+                        "<rect x=\"{}\" y=\"{}\" width=\"{2}\" height=\"{2}\" \
+                         fill=\"url(#check)\"/>",
+                        x, y, SQUARE
+                    );
+                }
+                if let Some(p) = self.piece(pos) {
+                    // the solid glyph for both sides, filled white or black
+                    let glyph = Piece {
+                        kind: p.kind,
+                        color: Color::Black,
+                    }
+                    .glyph();
+                    let (fill, stroke) = match p.color {
+                        Color::White => ("#ffffff", "#000000"),
+                        Color::Black => ("#000000", "#ffffff"),
+                    };
+                    let _ = write!(
+                        s,
+                        "<text x=\"{}\" y=\"{}\" font-size=\"38\" text-anchor=\"middle\" \
+                         font-family=\"{}\" fill=\"{}\" stroke=\"{}\" stroke-width=\"1\">{}</text>",
+                        x + SQUARE / 2,
+                        y + SQUARE * 4 / 5,
+                        FONTS,
+                        fill,
+                        stroke,
+                        glyph
+                    );
+                }
+            }
+        }
+
+        if opts.coordinates {
+            let label = format!(
+                "font-size=\"14\" text-anchor=\"middle\" font-family=\"{}\" fill=\"#e5e5e5\"",
+                FONTS
+            );
+            for i in 0..8 {
+                let middle = margin + i * SQUARE + SQUARE / 2;
+                // files along the bottom, and ranks down the left
+                let _ = write!(
+                    s,
+                    "<text x=\"{}\" y=\"{}\" {}>{}</text>\
+                     <text x=\"{}\" y=\"{}\" {}>{}</text>",
+                    middle,
+                    size - 5,
+                    label,
+                    (b'a' + order(i) as u8) as char,
+                    margin / 2,
+                    middle + 5,
+                    label,
+                    8 - order(i)
+                );
+            }
+        }
+
+        s.push_str("</svg>");
+        s
+    }
+
+    // the svg diagram drawn as a png, with the system's fonts for the pieces
+    #[cfg(feature = "png")]
+    pub fn to_png(&self, opts: &RenderOptions) -> Result<Vec<u8>, ChessError> {
+        use resvg::{tiny_skia, usvg};
+
+        let mut options = usvg::Options::default();
+        options.fontdb_mut().load_system_fonts();
+        let tree = match usvg::Tree::from_str(&self.to_svg(opts), &options) {
+            Ok(tree) => tree,
+            Err(e) => parse_error!("[to_png] can't read the svg: {}", e),
+        };
+        let size = tree.size().to_int_size();
+        let mut pixmap = match tiny_skia::Pixmap::new(size.width(), size.height()) {
+            Some(pixmap) => pixmap,
+            None => board_state_error!("[to_png] can't draw a {:?} image", size),
+        };
+        resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+        match pixmap.encode_png() {
+            Ok(png) => Ok(png),
+            Err(e) => board_state_error!("[to_png] can't encode the image: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::board_render::RenderOptions;
+    use crate::moves::Move;
+    use crate::piece::Color;
+
+    #[test]
+    fn svg() {
+        let b = Board::initial();
+        let svg = b.to_svg(&RenderOptions::default());
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"400\""));
+        assert!(svg.ends_with("</svg>"));
+        assert_eq!(svg.matches("<rect").count(), 65);
+        assert_eq!(svg.matches("♟").count(), 16);
+        assert_eq!(svg.matches("fill=\"#ffffff\" stroke").count(), 16);
+        assert!(!svg.contains("url(#check)"));
+
+        // the last move highlighted, and black in check
+        let b = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+        let b = b.make_move(&mv!("Ra1-a8")).unwrap();
+        let svg = b.to_svg(&RenderOptions {
+            coordinates: false,
+            ..RenderOptions::last_move(&mv!("Ra1-a8"), Color::White)
+        });
+        assert!(svg.contains("width=\"360\""));
+        assert_eq!(svg.matches("#cdd26a").count(), 1);
+        assert_eq!(svg.matches("#aaa23a").count(), 1);
+        // the king on e8 is the fifth square of the top row
+        assert!(svg
+            .contains("<rect x=\"180\" y=\"0\" width=\"45\" height=\"45\" fill=\"url(#check)\"/>"));
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn png() {
+        let png = Board::initial().to_png(&RenderOptions::default()).unwrap();
+        assert_eq!(&png[1..4], b"PNG");
+    }
+}
@@ -1,24 +1,26 @@
 #[cfg(test)]
 mod tests {
-    use crate::board::Board;
+    use crate::board::{Board, Draw, GameStatus};
     use crate::moves::Move;
-    use crate::piece::Color;
+    use crate::piece::{Color, PieceType};
     use crate::position::Pos;
+    #[cfg(feature = "engine")]
+    use crate::search_limits::{SearchLimits, MAX_DEPTH};
 
     use std::collections::HashSet;
 
     macro_rules! legal_moves_are {
         ( $fen:expr, $($mv:expr),* ) => {{
-            let b = Board::from_fen($fen).expect("[legal_moves] bad fen!");
+            let b = Board::from_fen_unchecked($fen).expect("[legal_moves] bad fen!");
             println!("\n{}", b);
             let mut should_be = HashSet::new();
             $(
                 should_be.insert(Move::from_algebra($mv).expect("[legal_moves] bad move!"));
             )*
-            for mv in b.legal_moves().unwrap() {
+            for mv in b.legal_moves() {
                 println!("{}", mv);
             }
-            let res: HashSet<Move> = b.legal_moves().unwrap().into_iter().collect();
+            let res: HashSet<Move> = b.legal_moves().into_iter().collect();
             assert_eq!(should_be, res);
             b
         }};
@@ -26,13 +28,13 @@ mod tests {
 
     macro_rules! legal_moves_include {
         ( $fen:expr, $($mv:expr),* ) => {{
-            let b = Board::from_fen($fen).expect("[legal_moves] bad fen!");
+            let b = Board::from_fen_unchecked($fen).expect("[legal_moves] bad fen!");
             println!("\n{}", b);
             let mut should_be = HashSet::new();
             $(
                 should_be.insert(Move::from_algebra($mv).expect("[legal_moves] bad move!"));
             )*
-            let res: HashSet<Move> = b.legal_moves().unwrap().into_iter().collect();
+            let res: HashSet<Move> = b.legal_moves().into_iter().collect();
             assert!(should_be.is_subset(&res));
             b
         }};
@@ -40,11 +42,11 @@ mod tests {
 
     macro_rules! board_after_move_is {
         ($mv:expr, $initial:expr, $result:expr) => {{
-            let b = Board::from_fen($initial).expect("[board_after_move_is] bad fen!");
+            let b = Board::from_fen_unchecked($initial).expect("[board_after_move_is] bad fen!");
             assert_eq!(
                 b.make_move(&Move::from_algebra($mv).expect("[board_after_move_is] bad move"))
                     .unwrap(),
-                Board::from_fen($result).expect("[board_after_move_is] bad fen!")
+                Board::from_fen_unchecked($result).expect("[board_after_move_is] bad fen!")
             );
         }};
     }
@@ -54,7 +56,7 @@ mod tests {
         let b =
             Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
         println!("\n{}", b);
-        assert_eq!(b.legal_moves().unwrap().len(), 20);
+        assert_eq!(b.legal_moves().len(), 20);
     }
 
     #[test]
@@ -88,7 +90,7 @@ mod tests {
         board_after_move_is!(
             "c7-c5",
             "8/2p4p/1P1P4/7P/8/8/8/8 b - - 0 1",
-            "8/7p/1P1P4/2p4P/8/8/8/8 w - - 0 2"
+            "8/7p/1P1P4/2p4P/8/8/8/8 w - c6 0 2"
         );
     }
 
@@ -114,6 +116,59 @@ mod tests {
         );
     }
 
+    #[test]
+    fn en_passant_in_play() {
+        // white's double push can be taken on the next move, and only then
+        let mut b = Board::from_fen("4k3/8/8/8/3p4/8/4P1P1/4K3 w - - 0 1").unwrap();
+        b = b.make_move(&mv!("e2-e4")).unwrap();
+        assert_eq!(b.en_passant_target, Some(pos!("e3")));
+        assert!(b.legal_moves().contains(&mv!("d4xe3e.p.")));
+        b = b.make_move(&mv!("Ke8-d8")).unwrap();
+        assert_eq!(b.en_passant_target, None);
+        b = b.make_move(&mv!("g2-g3")).unwrap();
+        assert_eq!(b.en_passant_target, None);
+        assert!(!b.legal_moves().contains(&mv!("d4xe3e.p.")));
+
+        // and black's, made in place
+        let mut b = Board::from_fen("4k3/3p4/8/4P3/8/8/8/4K3 b - - 0 1").unwrap();
+        let undo = b.do_move(&mv!("d7-d5"));
+        assert_eq!(b.to_fen(), "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2");
+        assert!(b.legal_moves().contains(&mv!("e5xd6e.p.")));
+        let taken = b.do_move(&mv!("e5xd6e.p."));
+        assert_eq!(b.to_fen(), "4k3/8/3P4/8/8/8/8/4K3 b - - 0 2");
+        b.undo_move(taken);
+        b.undo_move(undo);
+        assert_eq!(b.to_fen(), "4k3/3p4/8/4P3/8/8/8/4K3 b - - 0 1");
+    }
+
+    #[test]
+    fn rook_captures_end_castling() {
+        let b = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        for &(mv, fen) in [
+            ("Ra1xa8", "R3k2r/8/8/8/8/8/8/4K2R b Kk - 0 1"),
+            ("Rh1xh8", "r3k2R/8/8/8/8/8/8/R3K3 b Qq - 0 1"),
+        ]
+        .iter()
+        {
+            let mv = Move::from_algebra(mv).unwrap();
+            let after = b.make_move(&mv).unwrap();
+            assert_eq!(after.to_fen(), fen);
+            assert_eq!(Board::from_fen(fen).unwrap(), after);
+
+            let mut c = b.clone();
+            let undo = c.do_move(&mv);
+            assert_eq!(c.to_fen(), fen);
+            c.undo_move(undo);
+            assert_eq!(c.to_fen(), b.to_fen());
+        }
+
+        board_after_move_is!(
+            "Rh8xh1",
+            "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1",
+            "r3k3/8/8/8/8/8/8/R3K2r w Qq - 0 2"
+        );
+    }
+
     #[test]
     fn white_promotion() {
         legal_moves_are!(
@@ -130,14 +185,14 @@ mod tests {
         board_after_move_is!(
             "e7-e8=N",
             "3n4/4P3/8/8/8/8/8/8 w - - 0 1",
-            "3nN4/8/8/8/8/8/8/8 b - - 0 1"
+            "3nN3/8/8/8/8/8/8/8 b - - 0 1"
         );
     }
 
     #[test]
     fn black_promotion() {
         legal_moves_are!(
-            "8/8/8/8/8/8/3p4/4N3/ b - - 0 1",
+            "8/8/8/8/8/8/3p4/4N3 b - - 0 1",
             "d2-d1=Q",
             "d2-d1=N",
             "d2-d1=R",
@@ -149,7 +204,7 @@ mod tests {
         );
         board_after_move_is!(
             "d2xe1=B",
-            "8/8/8/8/8/8/3p4/4N3/ b - - 0 1",
+            "8/8/8/8/8/8/3p4/4N3 b - - 0 1",
             "8/8/8/8/8/8/8/4b3 w - - 0 2"
         );
     }
@@ -157,7 +212,7 @@ mod tests {
     #[test]
     fn white_queen() {
         legal_moves_are!(
-            "3n1q3/4Q3/8/4p3/7P/p7/8/8 w - - 0 1",
+            "3n1q2/4Q3/8/4p3/7P/p7/8/8 w - - 0 1",
             "Qe7xd8",
             "Qe7xf8",
             "Qe7xe5",
@@ -180,15 +235,15 @@ mod tests {
         );
         board_after_move_is!(
             "Qe7-b4",
-            "3n1q3/4Q3/8/4p3/7P/p7/8/8 w - - 0 1",
-            "3n1q3/8/8/4p3/1Q5P/p7/8/8 b - - 1 1"
+            "3n1q2/4Q3/8/4p3/7P/p7/8/8 w - - 0 1",
+            "3n1q2/8/8/4p3/1Q5P/p7/8/8 b - - 1 1"
         );
     }
 
     #[test]
     fn black_queen() {
         legal_moves_are!(
-            "3N1Q3/4q3/8/4P3/7p/P7/8/8 b - - 0 1",
+            "3N1Q2/4q3/8/4P3/7p/P7/8/8 b - - 0 1",
             "Qe7xd8",
             "Qe7xf8",
             "Qe7xe5",
@@ -211,15 +266,15 @@ mod tests {
         );
         board_after_move_is!(
             "Qe7-b4",
-            "3N1Q3/4q3/8/4P3/7p/P7/8/8 b - - 0 1",
-            "3N1Q3/8/8/4P3/1q5p/P7/8/8 w - - 1 2"
+            "3N1Q2/4q3/8/4P3/7p/P7/8/8 b - - 0 1",
+            "3N1Q2/8/8/4P3/1q5p/P7/8/8 w - - 1 2"
         );
     }
 
     #[test]
     fn white_rook() {
         legal_moves_are!(
-            "3n1q3/4R3/8/4p3/7P/p7/8/8 w - - 0 1",
+            "3n1q2/4R3/8/4p3/7P/p7/8/8 w - - 0 1",
             "Re7xe5",
             "Re7-e6",
             "Re7-e8",
@@ -234,15 +289,15 @@ mod tests {
         );
         board_after_move_is!(
             "Re7-f7",
-            "3n1q3/4R3/8/4p3/7P/p7/8/8 w - - 0 1",
-            "3n1q3/5R2/8/4p3/7P/p7/8/8 b - - 1 1"
+            "3n1q2/4R3/8/4p3/7P/p7/8/8 w - - 0 1",
+            "3n1q2/5R2/8/4p3/7P/p7/8/8 b - - 1 1"
         );
     }
 
     #[test]
     fn black_rook() {
         legal_moves_are!(
-            "3N1Q3/4r3/8/4P3/7p/P7/8/8 b - - 0 1",
+            "3N1Q2/4r3/8/4P3/7p/P7/8/8 b - - 0 1",
             "Re7xe5",
             "Re7-e6",
             "Re7-e8",
@@ -257,15 +312,15 @@ mod tests {
         );
         board_after_move_is!(
             "Re7-f7",
-            "3N1Q3/4r3/8/4P3/7p/P7/8/8 b - - 0 1",
-            "3N1Q3/5r2/8/4P3/7p/P7/8/8 w - - 1 2"
+            "3N1Q2/4r3/8/4P3/7p/P7/8/8 b - - 0 1",
+            "3N1Q2/5r2/8/4P3/7p/P7/8/8 w - - 1 2"
         );
     }
 
     #[test]
     fn white_bishop() {
         legal_moves_are!(
-            "3n1q3/4B3/8/4p3/7P/p7/8/8 w - - 0 1",
+            "3n1q2/4B3/8/4p3/7P/p7/8/8 w - - 0 1",
             "Be7xd8",
             "Be7xf8",
             "Be7xa3",
@@ -278,15 +333,15 @@ mod tests {
         );
         board_after_move_is!(
             "Be7xd8",
-            "3n1q3/4B3/8/4p3/7P/p7/8/8 w - - 0 1",
-            "3B1q3/8/8/4p3/7P/p7/8/8 b - - 0 1"
+            "3n1q2/4B3/8/4p3/7P/p7/8/8 w - - 0 1",
+            "3B1q2/8/8/4p3/7P/p7/8/8 b - - 0 1"
         );
     }
 
     #[test]
     fn black_bishop() {
         legal_moves_are!(
-            "3N1Q3/4b3/8/4P3/7p/P7/8/8 b - - 0 1",
+            "3N1Q2/4b3/8/4P3/7p/P7/8/8 b - - 0 1",
             "Be7xd8",
             "Be7xf8",
             "Be7xa3",
@@ -299,8 +354,8 @@ mod tests {
         );
         board_after_move_is!(
             "Be7xd8",
-            "3N1Q3/4b3/8/4P3/7p/P7/8/8 b - - 0 1",
-            "3b1Q3/8/8/4P3/7p/P7/8/8 w - - 0 2"
+            "3N1Q2/4b3/8/4P3/7p/P7/8/8 b - - 0 1",
+            "3b1Q2/8/8/4P3/7p/P7/8/8 w - - 0 2"
         );
     }
 
@@ -351,7 +406,7 @@ mod tests {
     #[test]
     fn white_king() {
         legal_moves_are!(
-            "n/1p6/2KP5/1p6/8/8/8/8 w - - 0 1",
+            "n7/1p6/2KP4/1p6/8/8/8/8 w - - 0 1",
             "Kc6xb7",
             "Kc6-d7",
             "Kc6-d5",
@@ -360,15 +415,15 @@ mod tests {
         );
         board_after_move_is!(
             "Kc6xb7",
-            "n/1p6/2KP5/1p6/8/8/8/8 w - - 0 1",
-            "n/1K6/3P5/1p6/8/8/8/8 b - - 0 1"
+            "n7/1p6/2KP4/1p6/8/8/8/8 w - - 0 1",
+            "n7/1K6/3P4/1p6/8/8/8/8 b - - 0 1"
         );
     }
 
     #[test]
     fn black_king() {
         legal_moves_are!(
-            "N/1P6/2kp5/1P6/8/8/8/8 b - - 0 1",
+            "N7/1P6/2kp4/1P6/8/8/8/8 b - - 0 1",
             "Kc6xb7",
             "Kc6-d7",
             "Kc6-d5",
@@ -377,8 +432,8 @@ mod tests {
         );
         board_after_move_is!(
             "Kc6xb7",
-            "N/1P6/2kp5/1P6/8/8/8/8 b - - 0 1",
-            "N/1k6/3p5/1P6/8/8/8/8 w - - 0 2"
+            "N7/1P6/2kp4/1P6/8/8/8/8 b - - 0 1",
+            "N7/1k6/3p4/1P6/8/8/8/8 w - - 0 2"
         );
     }
 
@@ -395,9 +450,9 @@ mod tests {
             "8/8/8/8/8/8/8/R3K2R w KQ - 0 1",
             "8/8/8/8/8/8/8/2KR3R b - - 1 1"
         );
-        let b = Board::from_fen("8/8/8/8/8/8/8/RN2K2R w KQ - 0 1").unwrap();
+        let b = Board::from_fen_unchecked("8/8/8/8/8/8/8/RN2K2R w KQ - 0 1").unwrap();
         println!("\n{}", b);
-        let res: HashSet<Move> = b.legal_moves().unwrap().into_iter().collect();
+        let res: HashSet<Move> = b.legal_moves().into_iter().collect();
         assert!(!res.contains(&mv!("O-O-O")));
     }
 
@@ -420,7 +475,7 @@ mod tests {
     #[should_panic]
     fn white_castling_through_threat() {
         // shouldn't be able to castle through threatened square!
-        let b = Board::from_fen("8/8/8/8/8/5q3/8/4K2R w KQ - 0 1").unwrap();
+        let b = Board::from_fen_unchecked("8/8/8/8/8/5q2/8/4K2R w KQ - 0 1").unwrap();
         println!("\n{}", b);
         b.make_move(&mv!("O-O")).unwrap();
     }
@@ -429,17 +484,656 @@ mod tests {
     #[should_panic]
     fn black_castling_through_threat() {
         // shouldn't be able to castle through threatened square!
-        let b = Board::from_fen("4k2r/8/5Q2/8/8/8/8/8 b kq - 0 1").unwrap();
+        let b = Board::from_fen_unchecked("4k2r/8/5Q2/8/8/8/8/8 b kq - 0 1").unwrap();
         println!("\n{}", b);
         b.make_move(&mv!("O-O")).unwrap();
     }
 
     #[test]
+    fn do_and_undo_move() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 3 7",
+            "8/8/8/pP6/8/8/8/8 w - a6 0 1",
+            "8/8/8/8/pP6/8/8/8 b - b3 0 1",
+            "3n4/4P3/8/8/8/8/8/8 w - - 0 1",
+            "8/8/8/8/8/8/3p4/4N3 b - - 0 1",
+        ];
+        for fen in fens.iter() {
+            let mut b = Board::from_fen_unchecked(fen).unwrap();
+            println!("\n{}", b);
+            for mv in b.legal_moves() {
+                let undo = b.do_move(&mv);
+                let expected = Board::from_fen_unchecked(fen)
+                    .unwrap()
+                    .make_move(&mv)
+                    .unwrap();
+                assert_eq!(
+                    b.to_fen(),
+                    expected.to_fen(),
+                    "do_move {} differs from make_move",
+                    mv
+                );
+                b.undo_move(undo);
+                assert_eq!(b.to_fen(), *fen, "undo_move {} failed", mv);
+            }
+        }
+    }
+
+    #[test]
+    fn null_move() {
+        let fen = "8/8/8/8/pP6/8/8/8 b - b3 0 1";
+        let mut b = Board::from_fen_unchecked(fen).unwrap();
+        let ep = b.do_null_move();
+        assert_eq!(b.color_to_move, Color::White);
+        assert_eq!(b.en_passant_target, None);
+        b.undo_null_move(ep);
+        assert_eq!(b.to_fen(), fen);
+    }
+
+    #[test]
+    fn boards_as_keys() {
+        let b = Board::initial();
+        let mut seen = HashSet::new();
+        seen.insert(b.clone());
+
+        // the knights go out and back, so only the move number has changed
+        let mut a = b.clone();
+        for mv in ["Ng1-f3", "Ng8-f6", "Nf3-g1", "Nf6-g8"].iter() {
+            a = a.make_move(&Move::from_algebra(mv).unwrap()).unwrap();
+        }
+        assert_eq!(a.move_number, 3);
+        assert_eq!(a, b);
+        assert!(seen.contains(&a));
+
+        // en passant and castling rights make a different position
+        let ep = Board::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1").unwrap();
+        let no_ep = Board::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - - 0 1").unwrap();
+        assert_ne!(ep, no_ep);
+        let castle = Board::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        let no_castle = Board::from_fen("4k3/8/8/8/8/8/8/4K2R w - - 0 1").unwrap();
+        assert_ne!(castle, no_castle);
+        seen.extend(vec![ep, no_ep, castle, no_castle]);
+        assert_eq!(seen.len(), 5);
+    }
+
+    #[test]
+    fn legal_moves_match_trial_moves() {
+        use rand::{self, Rng};
+        let mut rng = rand::thread_rng();
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        ];
+        for fen in fens.iter() {
+            for _ in 0..20 {
+                let mut b = Board::from_fen(fen).unwrap();
+                for _ in 0..40 {
+                    let trial: HashSet<Move> = b
+                        .moves()
+                        .into_iter()
+                        .filter(|m| b.make_move(m).is_ok())
+                        .collect();
+                    let legal: HashSet<Move> = b.legal_moves().into_iter().collect();
+                    assert_eq!(trial, legal, "\n{}", b);
+                    if legal.is_empty() {
+                        break;
+                    }
+                    let ms: Vec<Move> = legal.into_iter().collect();
+                    b = b.make_move(&ms[rng.gen::<usize>() % ms.len()]).unwrap();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn en_passant_pin() {
+        // taking en passant would leave both pawns' rank open to the rook
+        legal_moves_are!(
+            "8/8/8/KpP4r/8/8/8/7k w - b6 0 1",
+            "c5-c6",
+            "Ka5-a6",
+            "Ka5-b4",
+            "Ka5xb5",
+            "Ka5-b6"
+        );
+    }
+
+    #[test]
+    fn en_passant_exposing_king() {
+        for &(fen, mv) in [
+            // the two pawns leave the rank open to a rook or queen, for either side
+            ("8/8/8/KpP4r/8/8/8/7k w - b6 0 1", "c5xb6e.p."),
+            ("8/8/8/8/k1pP3Q/8/8/7K b - d3 0 1", "c4xd3e.p."),
+            ("8/8/8/8/1k1Pp2R/8/8/4K3 b - d3 0 1", "e4xd3e.p."),
+            // the captured pawn was shielding the king along a diagonal
+            ("1k6/1b6/8/3pP3/8/8/6K1/8 w - d6 0 1", "e5xd6e.p."),
+            // the capturing pawn is pinned along a diagonal
+            ("2b4k/8/8/5Pp1/8/7K/8/8 w - g6 0 1", "f5xg6e.p."),
+        ]
+        .iter()
+        {
+            let b = Board::from_fen(fen).unwrap();
+            let mv = Move::from_algebra(mv).unwrap();
+            assert!(!b.legal_moves().contains(&mv), "{} {}", fen, mv);
+            assert!(b.make_move(&mv).is_err(), "{} {}", fen, mv);
+        }
+
+        // with the king elsewhere it's fine
+        let b = Board::from_fen("8/8/8/1pP4r/K7/8/8/7k w - b6 0 1").unwrap();
+        assert!(b.legal_moves().contains(&mv!("c5xb6e.p.")));
+        assert!(b.make_move(&mv!("c5xb6e.p.")).is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "engine")]
     fn checkmate() {
         let b = Board::from_fen("4k3/8/3P4/6Q1/8/8/8/K7 w - - 0 1").unwrap();
         println!("\n{}", b);
-        let (mv, _) = b.best_move(1).unwrap();
+        let (mv, _) = b.best_move(&SearchLimits::depth(1)).unwrap();
         println!("got {}, expected Qg5-e7", mv);
         assert_eq!(mv, mv!("Qg5-e7"));
     }
+
+    #[test]
+    #[cfg(feature = "engine")]
+    fn aspiration_windows_find_exact_score() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "1K6/2P5/1p3P2/1k2P3/1qnP1B2/3Q4/8/8 b - - 0 1",
+        ];
+        for fen in fens.iter() {
+            let mut b = Board::from_fen(fen).unwrap();
+            let score = b.search(&SearchLimits::depth(2), None, None).unwrap().score;
+            let full_window = b
+                .legal_moves()
+                .iter()
+                .map(|mv| b.make_move(mv).unwrap().alpha_beta(2, None, None))
+                .max()
+                .unwrap();
+            assert_eq!(score, full_window, "{}", fen);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "engine")]
+    fn shortest_mate() {
+        use crate::board_alpha_beta::MATE;
+        // deeper searches see slower mates too, but must still prefer the mate in one
+        let b = Board::from_fen("4k3/8/3P4/6Q1/8/8/8/K7 w - - 0 1").unwrap();
+        for depth in 1..4 {
+            let (mv, score) = b.best_move(&SearchLimits::depth(depth)).unwrap();
+            assert_eq!(mv, mv!("Qg5-e7"), "depth {}", depth);
+            assert_eq!(score, MATE - 1, "depth {}", depth);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "engine")]
+    fn fifty_move_rule() {
+        use crate::board_alpha_beta::MATE;
+        // a queen up, but every move reaches the hundredth halfmove
+        let b = Board::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 99 80").unwrap();
+        assert!(!b.fifty_move_draw());
+        assert!(b.make_move(&mv!("Qd1-d2")).unwrap().fifty_move_draw());
+        assert_eq!(b.best_move(&SearchLimits::depth(2)).unwrap().1, 0);
+
+        // checkmate on the last move still wins
+        let b = Board::from_fen("4k3/8/3P4/6Q1/8/8/8/K7 w - - 99 80").unwrap();
+        assert_eq!(
+            b.best_move(&SearchLimits::depth(2)).unwrap(),
+            (mv!("Qg5-e7"), MATE - 1)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "engine")]
+    fn principal_variation() {
+        let mut b = Board::from_fen("1K6/2P5/1p3P2/1k2P3/1qnP1B2/3Q4/8/8 b - - 0 1").unwrap();
+        let res = b.search(&SearchLimits::depth(3), None, None).unwrap();
+        assert_eq!(res.pv[0], res.mv);
+        assert!(res.pv.len() > 1);
+        // the line is playable
+        let mut pos = b.clone();
+        for mv in res.pv.iter() {
+            pos = pos.make_move(mv).unwrap();
+        }
+
+        let mut b = Board::from_fen("4k3/8/3P4/6Q1/8/8/8/K7 w - - 0 1").unwrap();
+        assert_eq!(
+            b.search(&SearchLimits::depth(3), None, None).unwrap().pv,
+            vec![mv!("Qg5-e7")]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "engine")]
+    fn node_limited_search() {
+        let b = Board::from_fen("1K6/2P5/1p3P2/1k2P3/1qnP1B2/3Q4/8/8 b - - 0 1").unwrap();
+        let search = |max_nodes| {
+            let mut iterations = Vec::new();
+            let res = b
+                .clone()
+                .search_with(&SearchLimits::nodes(max_nodes), None, None, &mut |res| {
+                    iterations.push(res.clone())
+                })
+                .unwrap();
+            (res, iterations)
+        };
+        let (res, iterations) = search(5000);
+        assert!(res.depth < 20);
+        assert!(res.nodes <= 5000);
+        assert!(iterations.iter().all(|it| it.seldepth > it.depth));
+        assert_eq!(iterations.last().map(|it| &it.pv), Some(&res.pv));
+        assert!(iterations.windows(2).all(|w| w[0].nodes < w[1].nodes));
+
+        // the same budget gives the same search
+        let (again, _) = search(5000);
+        assert_eq!(
+            (again.mv, again.score, again.nodes),
+            (res.mv, res.score, res.nodes)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "engine")]
+    fn timed_search() {
+        use crate::time_manager::Budget;
+        use std::time::{Duration, Instant};
+
+        let mut b = Board::initial();
+        let limits = SearchLimits {
+            time: Some(Budget {
+                soft: Duration::from_millis(50),
+                hard: Duration::from_millis(200),
+            }),
+            ..SearchLimits::default()
+        };
+        let start = Instant::now();
+        let res = b.search(&limits, None, None).unwrap();
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(res.depth < MAX_DEPTH);
+        assert!(b.make_move(&res.mv).is_ok());
+
+        let start = Instant::now();
+        let limits = SearchLimits::move_time(Duration::from_millis(100));
+        let (mv, _) = b.best_move(&limits).unwrap();
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(b.make_move(&mv).is_ok());
+
+        // with time counted in nodes, a second is nps nodes however long they take
+        let limits = SearchLimits {
+            nps: Some(20_000),
+            ..SearchLimits::move_time(Duration::from_secs(1))
+        };
+        let res = b.search(&limits, None, None).unwrap();
+        assert!(res.nodes < 20_000 && res.depth > 0);
+        assert_eq!(res.time, Duration::from_micros(res.nodes * 50));
+    }
+
+    #[test]
+    #[cfg(feature = "engine")]
+    fn restricted_search_moves() {
+        // without the mate, the best of what's left
+        let b = Board::from_fen("4k3/8/3P4/6Q1/8/8/8/K7 w - - 0 1").unwrap();
+        let limits = SearchLimits {
+            depth: Some(2),
+            search_moves: vec![mv!("Ka1-b1"), mv!("Ka1-b2")],
+            ..SearchLimits::default()
+        };
+        let (mv, _) = b.best_move(&limits).unwrap();
+        assert!(mv == mv!("Ka1-b1") || mv == mv!("Ka1-b2"));
+
+        // a single candidate is just evaluated
+        let limits = SearchLimits::search_moves(&[mv!("Qg5-e7")]);
+        let res = b.clone().search(
+            &SearchLimits {
+                depth: Some(1),
+                ..limits
+            },
+            None,
+            None,
+        );
+        assert_eq!(res.unwrap().pv, vec![mv!("Qg5-e7")]);
+
+        let limits = SearchLimits::search_moves(&[mv!("Ka1-a3")]);
+        assert!(b.best_move(&limits).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "engine")]
+    fn moves_near_the_best() {
+        let limits = SearchLimits {
+            depth: Some(2),
+            margin: 30,
+            ..SearchLimits::default()
+        };
+        let res = Board::initial().search(&limits, None, None).unwrap();
+        assert!(res.near_best.len() > 1);
+        assert_eq!(res.near_best[0], (res.mv, res.score));
+        for &(_, score) in &res.near_best {
+            assert!(score <= res.score && score >= res.score - 30);
+        }
+        // the margin doesn't change the best move
+        let plain = Board::initial().search(&SearchLimits::depth(2), None, None);
+        assert_eq!(plain.unwrap().score, res.score);
+        for _ in 0..10 {
+            let picked = res.pick_near_best();
+            assert!(res.near_best.contains(&picked));
+        }
+
+        // but a mate is always played
+        let b = Board::from_fen("4k3/8/3P4/6Q1/8/8/8/K7 w - - 0 1").unwrap();
+        let res = b.clone().search(&limits, None, None).unwrap();
+        assert_eq!(res.pick_near_best().0, mv!("Qg5-e7"));
+    }
+
+    #[test]
+    #[cfg(feature = "engine")]
+    fn mtdf_search() {
+        use crate::board_alpha_beta::MATE;
+
+        let mtdf = |depth| SearchLimits {
+            mtdf: true,
+            ..SearchLimits::depth(depth)
+        };
+        let b = Board::from_fen("4k3/8/3P4/6Q1/8/8/8/K7 w - - 0 1").unwrap();
+        let res = b.clone().search(&mtdf(3), None, None).unwrap();
+        assert_eq!((res.mv, res.score), (mv!("Qg5-e7"), MATE - 1));
+
+        for fen in [
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 11",
+        ]
+        .iter()
+        {
+            let b = Board::from_fen(fen).unwrap();
+            let res = b.clone().search(&mtdf(3), None, None).unwrap();
+            // the line comes from the last pass to fail high, and must still be playable
+            assert_eq!(res.pv[0], res.mv);
+            let mut pos = b.clone();
+            for mv in res.pv.iter() {
+                pos = pos.make_move(mv).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "engine")]
+    fn skill_levels() {
+        use crate::search_limits::{Skill, MAX_SKILL};
+
+        let full = Skill::level(MAX_SKILL + 5);
+        assert!(full.is_full_strength());
+        assert_eq!(full.limit(SearchLimits::depth(9)), SearchLimits::depth(9));
+        assert_eq!(Skill::from_elo(3000), full);
+        assert_eq!(Skill::from_elo(500), Skill::level(0));
+
+        let weakest = Skill::level(0).limit(SearchLimits::depth(9));
+        assert_eq!((weakest.depth, weakest.nodes), (Some(1), Some(1000)));
+        assert_eq!((weakest.margin, weakest.noise), (100, 200));
+        let middling = Skill::level(10).limit(SearchLimits::default());
+        assert_eq!((middling.depth, middling.noise), (Some(6), 100));
+        // a deterministic search keeps its seed
+        let fixed = SearchLimits {
+            noise_seed: 7,
+            deterministic: true,
+            ..SearchLimits::default()
+        };
+        assert_eq!(Skill::level(10).limit(fixed).noise_seed, 7);
+
+        // the noise is the same each time for the same seed
+        let b = Board::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3")
+            .unwrap();
+        let noisy = SearchLimits {
+            depth: Some(2),
+            noise_seed: 7,
+            ..weakest
+        };
+        let res = b.clone().search(&noisy, None, None).unwrap();
+        let again = b.clone().search(&noisy, None, None).unwrap();
+        assert_eq!((res.mv, res.score), (again.mv, again.score));
+        assert!(b.make_move(&res.mv).is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "engine")]
+    fn mate_search() {
+        use crate::board_alpha_beta::MATE;
+
+        // Rd8+ Rxd8 Rxd8#, but nothing mates at once
+        let b = Board::from_fen("r5k1/5ppp/8/8/8/8/3R1PPP/3R2K1 w - - 0 1").unwrap();
+        assert_eq!(b.find_mate(1), None);
+        let line = b.find_mate(3).unwrap();
+        assert_eq!(line.len(), 3);
+        assert_eq!(line[0], mv!("Rd2-d8"));
+
+        let res = b
+            .clone()
+            .search(&SearchLimits::mate(2), None, None)
+            .unwrap();
+        assert_eq!(res.score, MATE - 3);
+        assert_eq!(res.xboard_score(), 100_002);
+
+        // the defender to move has nothing
+        let b = Board::from_fen("r5k1/5ppp/8/8/8/8/3R1PPP/3R2K1 b - - 0 1").unwrap();
+        assert_eq!(b.find_mate(2), None);
+    }
+
+    #[test]
+    #[cfg(feature = "engine")]
+    fn proof_number_search() {
+        let b = Board::from_fen("r5k1/5ppp/8/8/8/8/3R1PPP/3R2K1 w - - 0 1").unwrap();
+        let line = b.prove_mate(10_000).unwrap();
+        assert_eq!(line, vec![mv!("Rd2-d8"), mv!("Ra8xd8"), mv!("Rd1xd8")]);
+
+        // far longer than alpha-beta could see, with the king driven to the edge first
+        let b = Board::from_fen("8/8/8/8/8/1k6/8/K1R5 w - - 0 1").unwrap();
+        let line = b.prove_mate(100_000).unwrap();
+        assert!(line.len() > 20);
+        let mut pos = b.clone();
+        for mv in line.iter() {
+            pos = pos.make_move(mv).unwrap();
+        }
+        assert_eq!(pos.status(), GameStatus::Checkmate);
+
+        // the defender to move, and nobody able to mate
+        let b = Board::from_fen("r5k1/5ppp/8/8/8/8/3R1PPP/3R2K1 b - - 0 1").unwrap();
+        assert_eq!(b.prove_mate(10_000), None);
+        let b = Board::from_fen("8/8/8/8/8/8/8/K1k5 w - - 0 1").unwrap();
+        assert_eq!(b.prove_mate(10_000), None);
+    }
+
+    #[test]
+    fn san_moves() {
+        let b = Board::from_fen("R7/4P3/7k/8/1N3N2/8/8/R3K1R1 w Q - 0 1").unwrap();
+        let san = |s| Move::from_san(&b, s);
+        assert_eq!(san("Nbd5").unwrap(), mv!("Nb4-d5"));
+        assert!(san("Nd5").is_err());
+        assert_eq!(san("R1a4").unwrap(), mv!("Ra1-a4"));
+        assert!(san("Ra4").is_err());
+        assert_eq!(san("R8a6+").unwrap(), mv!("Ra8-a6"));
+        assert_eq!(san("e8=Q").unwrap(), mv!("e7-e8=Q"));
+        assert_eq!(san("e8N").unwrap(), mv!("e7-e8=N"));
+        assert_eq!(san("0-0-0").unwrap().castle, mv!("O-O-O").castle);
+        assert!(san("O-O+").is_err());
+        assert!(san("e8").is_err());
+        assert!(san("Ke3").is_err());
+        assert!(san("Nf").is_err());
+
+        // and back, with only as much of the source square as needed
+        for (mv, s) in [
+            ("Nb4-d5", "Nbd5"),
+            ("Ra1-a4", "R1a4"),
+            ("Ra8-a6", "R8a6+"),
+            ("Rg1-g6", "Rg6+"),
+            ("e7-e8=Q", "e8=Q"),
+        ]
+        .iter()
+        {
+            assert_eq!(mv!(*mv).to_san(&b), *s);
+        }
+        assert_eq!(mv!("O-O-O").to_san(&b), "O-O-O");
+
+        let b = Board::from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3")
+            .unwrap();
+        let ep = Move::from_san(&b, "exf6").unwrap();
+        assert!(ep.en_passant);
+        assert_eq!(ep.to_san(&b), "exf6");
+        assert_eq!(Move::from_san(&b, "Qh5+").unwrap().to_san(&b), "Qh5+");
+    }
+
+    #[test]
+    fn statuses() {
+        let status = |fen| Board::from_fen(fen).unwrap().status();
+        assert_eq!(
+            status("4k3/8/8/8/8/8/8/R3K3 b - - 0 1"),
+            GameStatus::InProgress
+        );
+        assert_eq!(status("R3k3/8/8/8/8/8/8/4K3 b - - 0 1"), GameStatus::Check);
+        assert_eq!(
+            status("4k3/4Q3/3P4/8/8/8/8/K7 b - - 0 1"),
+            GameStatus::Checkmate
+        );
+        assert_eq!(
+            status("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1"),
+            GameStatus::Stalemate
+        );
+
+        // the fifty move rule comes after mate, and before check
+        let fifty = GameStatus::DrawBy(Draw::FiftyMove);
+        assert_eq!(status("R3k3/8/8/8/8/8/8/4K3 b - - 100 80"), fifty);
+        assert_eq!(status("4k3/R7/8/8/8/8/8/4K3 b - - 100 80"), fifty);
+        assert_eq!(
+            status("4k3/4Q3/3P4/8/8/8/8/K7 b - - 100 80"),
+            GameStatus::Checkmate
+        );
+
+        let dead = GameStatus::DrawBy(Draw::InsufficientMaterial);
+        for fen in [
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/3NK3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/2B1K3 b - - 0 1",
+            // bishops all on light squares, whoever has them
+            "2b1k3/8/8/8/8/8/8/3BKB2 w - - 0 1",
+        ]
+        .iter()
+        {
+            assert_eq!(status(fen), dead, "{}", fen);
+        }
+        for fen in [
+            "4k3/8/8/8/8/8/7p/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/2BBK3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/1N1NK3 w - - 0 1",
+            "4kn2/8/8/8/8/8/8/3NK3 w - - 0 1",
+            "4kb2/8/8/8/8/8/8/3NK3 w - - 0 1",
+        ]
+        .iter()
+        {
+            assert_eq!(status(fen), GameStatus::InProgress, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn checks() {
+        let b = Board::from_fen("R3k3/8/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert!(b.in_check(Color::Black));
+        assert!(!b.in_check(Color::White));
+
+        // direct and discovered checks, by every kind of move, two plies deep
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "4k3/8/8/3pP3/8/8/4K3/1Q2R1B1 w - d6 0 1",
+            "r3k3/1P4P1/8/8/8/8/8/4K2R w K - 0 1",
+        ]
+        .iter()
+        {
+            let b = Board::from_fen(fen).unwrap();
+            let mut positions = vec![b.clone()];
+            for mv in b.legal_moves() {
+                positions.push(b.make_move(&mv).unwrap());
+            }
+            for b in positions.iter() {
+                for mv in b.legal_moves() {
+                    let after = b.make_move(&mv).unwrap();
+                    assert_eq!(
+                        b.gives_check(&mv),
+                        after.in_check(after.color_to_move),
+                        "{} in {}",
+                        mv,
+                        b.to_fen()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pinned_pieces() {
+        // the knight is pinned, but neither rook is since they shield the king together
+        let b = Board::from_fen("4r2k/8/8/b7/4R3/2N5/4R3/4K2q w - - 0 1").unwrap();
+        let pins = b.pinned(Color::White);
+        assert_eq!(pins.len(), 1);
+        let pin = pins[0];
+        assert_eq!((pin.pos, pin.pinner), (pos!("c3"), pos!("a5")));
+        assert_eq!(
+            pin.ray().collect::<Vec<_>>(),
+            vec![pos!("a5"), pos!("b4"), pos!("c3"), pos!("d2")]
+        );
+        assert!(b.pinned(Color::Black).is_empty());
+
+        let b = Board::from_fen("4k3/4r3/8/b7/8/8/3BN3/4K3 w - - 0 1").unwrap();
+        let mut pinned: Vec<Pos> = b.pinned(Color::White).iter().map(|pin| pin.pos).collect();
+        pinned.sort_by_key(|pos| pos.index());
+        assert_eq!(pinned, vec![pos!("d2"), pos!("e2")]);
+    }
+
+    #[test]
+    fn piece_iterators() {
+        let b = Board::initial();
+        assert_eq!(b.iter_pieces().count(), 32);
+        assert_eq!(b.iter_pieces_by_color(Color::Black).count(), 16);
+        assert_eq!(
+            b.iter_pieces_by_color(Color::White).collect::<Vec<_>>(),
+            b.get_pieces_by_color(Color::White)
+        );
+        assert_eq!(
+            b.iter_pieces_by_type_and_color(PieceType::Knight, Color::White)
+                .collect::<Vec<_>>(),
+            vec![pos!("b1"), pos!("g1")]
+        );
+    }
+
+    #[test]
+    fn threat_maps() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ]
+        .iter()
+        {
+            let mut b = Board::from_fen(fen).unwrap();
+            for &c in [Color::White, Color::Black].iter() {
+                // a clone doesn't keep the cache, so color_threatens traces the pieces
+                let threats = b.threats(c);
+                for pos in Pos::all() {
+                    assert_eq!(threats.contains(pos), b.clone().color_threatens(c, pos));
+                    assert_eq!(threats.contains(pos), b.color_threatens(c, pos));
+                }
+            }
+
+            // making a move forgets the maps
+            let mv = b.legal_moves()[0];
+            let undo = b.do_move(&mv);
+            assert_eq!(b.threats(Color::White), b.clone().threats(Color::White));
+            b.undo_move(undo);
+            assert_eq!(b.threats(Color::Black), b.clone().threats(Color::Black));
+        }
+    }
 }
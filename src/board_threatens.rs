@@ -1,496 +1,146 @@
 use crate::board::Board;
+use crate::board_legal::{slides_along, DIAGONAL, KING_STEPS, KNIGHT_JUMPS, ORTHOGONAL};
 use crate::piece::{Color, Piece, PieceType};
-use crate::position::Pos;
+use crate::position::{Pos, Squares};
 
-use std::cell::RefCell;
+use core::cell::OnceCell;
 
-impl Board {
-    pub fn color_threatens(&self, c: Color, old: Pos) -> bool {
-        for (new, piece) in self.get_pieces_by_color(c) {
-            match piece.kind {
-                PieceType::Pawn => {
-                    match c {
-                        Color::White => {
-                            // omg en passant is complicated
-                            if new.northeast(1).map_or(false, |ray| {
-                                ray == old
-                                    || self.is_en_passant_target(ray)
-                                        && ray.south(1).unwrap() == old
-                            }) || new.northwest(1).map_or(false, |ray| {
-                                ray == old
-                                    || self.is_en_passant_target(ray)
-                                        && ray.south(1).unwrap() == old
-                            }) {
-                                return true;
-                            }
-                        }
-                        Color::Black => {
-                            if new.southeast(1).map_or(false, |ray| {
-                                ray == old
-                                    || self.is_en_passant_target(ray)
-                                        && ray.north(1).unwrap() == old
-                            }) || new.southwest(1).map_or(false, |ray| {
-                                ray == old
-                                    || self.is_en_passant_target(ray)
-                                        && ray.north(1).unwrap() == old
-                            }) {
-                                return true;
-                            }
-                        }
-                    }
-                }
+// the squares each side attacks, worked out the first time they are asked for in a
+// position. cloning a board is nearly always to change it, so the clone starts empty.
+#[derive(Default)]
+pub struct ThreatCache([OnceCell<Squares>; 2]);
 
-                PieceType::King => {
-                    if new.mv(1, 1).map_or(false, |ray| ray == old)
-                        || new.mv(1, 0).map_or(false, |ray| ray == old)
-                        || new.mv(1, -1).map_or(false, |ray| ray == old)
-                        || new.mv(0, 1).map_or(false, |ray| ray == old)
-                        || new.mv(0, -1).map_or(false, |ray| ray == old)
-                        || new.mv(-1, 1).map_or(false, |ray| ray == old)
-                        || new.mv(-1, 0).map_or(false, |ray| ray == old)
-                        || new.mv(-1, -1).map_or(false, |ray| ray == old)
-                    {
-                        return true;
-                    }
-                }
-
-                PieceType::Knight => {
-                    if new.mv(1, 2).map_or(false, |ray| ray == old)
-                        || new.mv(1, -2).map_or(false, |ray| ray == old)
-                        || new.mv(-1, -2).map_or(false, |ray| ray == old)
-                        || new.mv(-1, 2).map_or(false, |ray| ray == old)
-                        || new.mv(2, 1).map_or(false, |ray| ray == old)
-                        || new.mv(2, -1).map_or(false, |ray| ray == old)
-                        || new.mv(-2, -1).map_or(false, |ray| ray == old)
-                        || new.mv(-2, 1).map_or(false, |ray| ray == old)
-                    {
-                        return true;
-                    }
-                }
+impl ThreatCache {
+    fn side(&self, c: Color) -> &OnceCell<Squares> {
+        match c {
+            Color::White => &self.0[0],
+            Color::Black => &self.0[1],
+        }
+    }
 
-                PieceType::Rook => {
-                    let mut ray = new;
-                    while ray.north(1).is_some() {
-                        ray = ray.north(1).unwrap();
-                        if ray == old {
-                            return true;
-                        }
-                        if self.piece(ray).is_some() {
-                            break;
-                        }
-                    }
+    // forget the maps, after the pieces or en passant target change
+    pub fn clear(&mut self) {
+        *self = ThreatCache::default();
+    }
+}
 
-                    ray = new;
-                    while ray.south(1).is_some() {
-                        ray = ray.south(1).unwrap();
-                        if ray == old {
-                            return true;
-                        }
-                        if self.piece(ray).is_some() {
-                            break;
-                        }
-                    }
+impl Clone for ThreatCache {
+    fn clone(&self) -> ThreatCache {
+        ThreatCache::default()
+    }
+}
 
-                    ray = new;
-                    while ray.east(1).is_some() {
-                        ray = ray.east(1).unwrap();
-                        if ray == old {
-                            return true;
-                        }
-                        if self.piece(ray).is_some() {
-                            break;
-                        }
-                    }
+impl Board {
+    // every square c attacks, counting a pawn that can be taken en passant as attacked
+    // by the pawns that can take it, as color_threatens does. repeated questions about
+    // the same position are answered from the cache.
+    pub fn threats(&self, c: Color) -> Squares {
+        *self.threats.side(c).get_or_init(|| self.find_threats(c))
+    }
 
-                    ray = new;
-                    while ray.west(1).is_some() {
-                        ray = ray.west(1).unwrap();
-                        if ray == old {
-                            return true;
-                        }
-                        if self.piece(ray).is_some() {
-                            break;
+    fn find_threats(&self, c: Color) -> Squares {
+        let mut res = Squares::default();
+        let mut add = |pos: Option<Pos>| {
+            if let Some(pos) = pos {
+                res.insert(pos);
+            }
+        };
+        for (pos, piece) in self.iter_pieces_by_color(c) {
+            match piece.kind {
+                PieceType::Pawn => {
+                    let forward = match c {
+                        Color::White => -1,
+                        Color::Black => 1,
+                    };
+                    for &h in [-1, 1].iter() {
+                        let to = pos.mv(forward, h);
+                        add(to);
+                        if to.is_some_and(|to| self.is_en_passant_target(to)) {
+                            add(pos.mv(0, h));
                         }
                     }
                 }
-
-                PieceType::Bishop => {
-                    let mut ray = new;
-                    while ray.northeast(1).is_some() {
-                        ray = ray.northeast(1).unwrap();
-                        if ray == old {
-                            return true;
-                        }
-                        if self.piece(ray).is_some() {
-                            break;
-                        }
-                    }
-
-                    ray = new;
-                    while ray.northwest(1).is_some() {
-                        ray = ray.northwest(1).unwrap();
-                        if ray == old {
-                            return true;
-                        }
-                        if self.piece(ray).is_some() {
-                            break;
-                        }
-                    }
-
-                    ray = new;
-                    while ray.southeast(1).is_some() {
-                        ray = ray.southeast(1).unwrap();
-                        if ray == old {
-                            return true;
-                        }
-                        if self.piece(ray).is_some() {
-                            break;
-                        }
-                    }
-
-                    ray = new;
-                    while ray.southwest(1).is_some() {
-                        ray = ray.southwest(1).unwrap();
-                        if ray == old {
-                            return true;
-                        }
-                        if self.piece(ray).is_some() {
-                            break;
-                        }
+                PieceType::Knight => {
+                    for &(v, h) in KNIGHT_JUMPS.iter() {
+                        add(pos.mv(v, h));
                     }
                 }
-
-                PieceType::Queen => {
-                    let mut ray = new;
-                    while ray.north(1).is_some() {
-                        ray = ray.north(1).unwrap();
-                        if ray == old {
-                            return true;
-                        }
-                        if self.piece(ray).is_some() {
-                            break;
-                        }
-                    }
-
-                    ray = new;
-                    while ray.south(1).is_some() {
-                        ray = ray.south(1).unwrap();
-                        if ray == old {
-                            return true;
-                        }
-                        if self.piece(ray).is_some() {
-                            break;
-                        }
-                    }
-
-                    ray = new;
-                    while ray.east(1).is_some() {
-                        ray = ray.east(1).unwrap();
-                        if ray == old {
-                            return true;
-                        }
-                        if self.piece(ray).is_some() {
-                            break;
-                        }
-                    }
-
-                    ray = new;
-                    while ray.west(1).is_some() {
-                        ray = ray.west(1).unwrap();
-                        if ray == old {
-                            return true;
-                        }
-                        if self.piece(ray).is_some() {
-                            break;
-                        }
-                    }
-
-                    ray = new;
-                    while ray.northeast(1).is_some() {
-                        ray = ray.northeast(1).unwrap();
-                        if ray == old {
-                            return true;
-                        }
-                        if self.piece(ray).is_some() {
-                            break;
-                        }
-                    }
-
-                    ray = new;
-                    while ray.northwest(1).is_some() {
-                        ray = ray.northwest(1).unwrap();
-                        if ray == old {
-                            return true;
-                        }
-                        if self.piece(ray).is_some() {
-                            break;
-                        }
-                    }
-
-                    ray = new;
-                    while ray.southeast(1).is_some() {
-                        ray = ray.southeast(1).unwrap();
-                        if ray == old {
-                            return true;
-                        }
-                        if self.piece(ray).is_some() {
-                            break;
-                        }
+                PieceType::King => {
+                    for &(v, h) in KING_STEPS.iter() {
+                        add(pos.mv(v, h));
                     }
-
-                    ray = new;
-                    while ray.southwest(1).is_some() {
-                        ray = ray.southwest(1).unwrap();
-                        if ray == old {
-                            return true;
-                        }
-                        if self.piece(ray).is_some() {
-                            break;
+                }
+                kind => {
+                    for &dir in ORTHOGONAL.iter().chain(DIAGONAL.iter()) {
+                        if !slides_along(kind, dir) {
+                            continue;
+                        }
+                        let mut ray = pos;
+                        while let Some(next) = ray.mv(dir.0, dir.1) {
+                            add(Some(next));
+                            if self.occupied(next) {
+                                break;
+                            }
+                            ray = next;
                         }
                     }
                 }
             }
         }
-        false
+        res
     }
 
-    pub fn nthreats(&self, pos: Pos, piece: Piece) -> isize {
-        match piece.kind {
-            PieceType::Pawn => self.pawn_nthreats(pos, piece.color),
-            PieceType::Knight => self.knight_nthreats(pos, piece.color),
-            PieceType::Bishop => self.bishop_nthreats(pos, piece.color),
-            PieceType::Rook => self.rook_nthreats(pos, piece.color),
-            PieceType::Queen => self.queen_nthreats(pos, piece.color),
-            PieceType::King => self.king_nthreats(pos, piece.color),
+    // does c attack pos? worked out from pos outward: a knight a jump away, a king a step
+    // away, a pawn diagonally behind, or a slider at the end of a clear ray. a pawn that
+    // can be taken en passant counts as attacked by the pawns beside it.
+    pub fn color_threatens(&self, c: Color, pos: Pos) -> bool {
+        if let Some(threats) = self.threats.side(c).get() {
+            return threats.contains(pos);
         }
-    }
-
-    fn pawn_nthreats(&self, pos: Pos, color: Color) -> isize {
-        let n = RefCell::new(0);
-        let ref f = |ray| {
-            match self.piece(ray) {
-                Some(p) => {
-                    if p.color == color.other() {
-                        *n.borrow_mut() += 2
-                    }
-                }
-                None => *n.borrow_mut() += 1,
-            }
-            if self.is_en_passant_target(ray) {
-                *n.borrow_mut() += 2
-            }
+        let is = |sq: Option<Pos>, kind| {
+            sq.is_some_and(|sq| self.piece(sq) == Some(Piece { kind, color: c }))
         };
 
-        match color {
-            Color::White => {
-                pos.northeast(1).map(f);
-                pos.northwest(1).map(f);
-            }
-            Color::Black => {
-                pos.southeast(1).map(f);
-                pos.southwest(1).map(f);
-            }
+        if KNIGHT_JUMPS
+            .iter()
+            .any(|&(v, h)| is(pos.mv(v, h), PieceType::Knight))
+        {
+            return true;
         }
-
-        // TODO: on an airplane, done know how to do this correctly
-        let n = n.borrow_mut().clone();
-        n
-    }
-
-    fn king_nthreats(&self, pos: Pos, color: Color) -> isize {
-        let n = RefCell::new(0);
-
-        let ref f = |ray| match self.piece(ray) {
-            Some(p) => {
-                if p.color == color.other() {
-                    *n.borrow_mut() += 2
-                }
-            }
-            None => *n.borrow_mut() += 1,
-        };
-
-        pos.mv(1, 1).map(f);
-        pos.mv(1, 0).map(f);
-        pos.mv(1, -1).map(f);
-        pos.mv(0, 1).map(f);
-        pos.mv(0, -1).map(f);
-        pos.mv(-1, 1).map(f);
-        pos.mv(-1, 0).map(f);
-        pos.mv(-1, -1).map(f);
-
-        // TODO: on an airplane, done know how to do this correctly
-        let n = n.borrow_mut().clone();
-        n
-    }
-
-    fn knight_nthreats(&self, pos: Pos, color: Color) -> isize {
-        let n = RefCell::new(0);
-
-        let ref f = |ray| match self.piece(ray) {
-            Some(p) => {
-                if p.color == color.other() {
-                    *n.borrow_mut() += 2
-                }
-            }
-            None => *n.borrow_mut() += 1,
-        };
-
-        pos.mv(1, 2).map(f);
-        pos.mv(1, -2).map(f);
-        pos.mv(-1, -2).map(f);
-        pos.mv(-1, 2).map(f);
-        pos.mv(2, 1).map(f);
-        pos.mv(2, -1).map(f);
-        pos.mv(-2, -1).map(f);
-        pos.mv(-2, 1).map(f);
-
-        // TODO: on an airplane, done know how to do this correctly
-        let n = n.borrow_mut().clone();
-        n
-    }
-
-    fn bishop_nthreats(&self, pos: Pos, color: Color) -> isize {
-        let n = RefCell::new(0);
-
-        let ref f = |ray| match self.piece(ray) {
-            Some(p) => {
-                if p.color == color.other() {
-                    *n.borrow_mut() += 2
-                }
-            }
-            None => *n.borrow_mut() += 1,
-        };
-
-        let mut ray = pos;
-        while ray.northeast(1).is_some() {
-            ray = ray.northeast(1).unwrap();
-            f(ray);
-        }
-
-        ray = pos;
-        while ray.northwest(1).is_some() {
-            ray = ray.northwest(1).unwrap();
-            f(ray);
+        if KING_STEPS
+            .iter()
+            .any(|&(v, h)| is(pos.mv(v, h), PieceType::King))
+        {
+            return true;
         }
 
-        ray = pos;
-        while ray.southeast(1).is_some() {
-            ray = ray.southeast(1).unwrap();
-            f(ray);
-        }
-
-        ray = pos;
-        while ray.southwest(1).is_some() {
-            ray = ray.southwest(1).unwrap();
-            f(ray);
-        }
-
-        // TODO: on an airplane, done know how to do this correctly
-        let n = n.borrow_mut().clone();
-        n
-    }
-
-    fn rook_nthreats(&self, pos: Pos, color: Color) -> isize {
-        let n = RefCell::new(0);
-
-        let ref f = |ray| match self.piece(ray) {
-            Some(p) => {
-                if p.color == color.other() {
-                    *n.borrow_mut() += 2
-                }
-            }
-            None => *n.borrow_mut() += 1,
+        let forward = match c {
+            Color::White => -1,
+            Color::Black => 1,
         };
-
-        let mut ray = pos;
-        while ray.north(1).is_some() {
-            ray = ray.north(1).unwrap();
-            f(ray);
-        }
-
-        ray = pos;
-        while ray.south(1).is_some() {
-            ray = ray.south(1).unwrap();
-            f(ray);
-        }
-
-        ray = pos;
-        while ray.east(1).is_some() {
-            ray = ray.east(1).unwrap();
-            f(ray);
-        }
-
-        ray = pos;
-        while ray.west(1).is_some() {
-            ray = ray.west(1).unwrap();
-            f(ray);
+        let en_passant = pos
+            .mv(forward, 0)
+            .is_some_and(|sq| self.is_en_passant_target(sq));
+        for &h in [-1, 1].iter() {
+            if is(pos.mv(-forward, h), PieceType::Pawn)
+                || (en_passant && is(pos.mv(0, h), PieceType::Pawn))
+            {
+                return true;
+            }
         }
 
-        let n = n.borrow_mut().clone();
-        n
-    }
-
-    fn queen_nthreats(&self, pos: Pos, color: Color) -> isize {
-        let n = RefCell::new(0);
-
-        let ref f = |ray| match self.piece(ray) {
-            Some(p) => {
-                if p.color == color.other() {
-                    *n.borrow_mut() += 2
+        for &dir in ORTHOGONAL.iter().chain(DIAGONAL.iter()) {
+            let mut ray = pos;
+            while let Some(sq) = ray.mv(dir.0, dir.1) {
+                if let Some(p) = self.piece(sq) {
+                    if p.color == c && slides_along(p.kind, dir) {
+                        return true;
+                    }
+                    break;
                 }
+                ray = sq;
             }
-            None => *n.borrow_mut() += 1,
-        };
-
-        let mut ray = pos;
-        while ray.north(1).is_some() {
-            ray = ray.north(1).unwrap();
-            f(ray);
-        }
-
-        ray = pos;
-        while ray.south(1).is_some() {
-            ray = ray.south(1).unwrap();
-            f(ray);
-        }
-
-        ray = pos;
-        while ray.east(1).is_some() {
-            ray = ray.east(1).unwrap();
-            f(ray);
-        }
-
-        ray = pos;
-        while ray.west(1).is_some() {
-            ray = ray.west(1).unwrap();
-            f(ray);
         }
-
-        ray = pos;
-        while ray.northeast(1).is_some() {
-            ray = ray.northeast(1).unwrap();
-            f(ray);
-        }
-
-        ray = pos;
-        while ray.northwest(1).is_some() {
-            ray = ray.northwest(1).unwrap();
-            f(ray);
-        }
-
-        ray = pos;
-        while ray.southeast(1).is_some() {
-            ray = ray.southeast(1).unwrap();
-            f(ray);
-        }
-
-        ray = pos;
-        while ray.southwest(1).is_some() {
-            ray = ray.southwest(1).unwrap();
-            f(ray);
-        }
-
-        let n = n.borrow_mut().clone();
-        n
+        false
     }
 }
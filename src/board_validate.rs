@@ -0,0 +1,122 @@
+use crate::board::Board;
+use crate::piece::{Color, Piece, PieceType};
+use crate::position::{Pos, Rank};
+use crate::util::ChessError;
+
+#[cfg(not(feature = "std"))]
+use crate::alloc_prelude::*;
+
+impl Board {
+    // checks that the position could come up in a game, as far as can be told without
+    // its history: one king each, no pawns on the back ranks, the side that just moved not
+    // left in check, and en passant and castling rights that fit the pieces
+    pub fn validate(&self) -> Result<(), ChessError> {
+        for c in Color::both() {
+            let kings = self
+                .iter_pieces_by_type_and_color(PieceType::King, c)
+                .count();
+            if kings != 1 {
+                board_state_error!("[validate] {} has {} kings", c, kings);
+            }
+        }
+
+        for (pos, _) in self
+            .iter_pieces()
+            .filter(|(_, p)| p.kind == PieceType::Pawn)
+        {
+            if pos.rank() == Rank::First || pos.rank() == Rank::Eighth {
+                board_state_error!("[validate] pawn on {}", pos);
+            }
+        }
+
+        let moved = self.color_to_move.other();
+        if self.in_check(moved) {
+            board_state_error!("[validate] {} is in check, but it's not their move", moved);
+        }
+
+        if let Some(target) = self.en_passant_target {
+            // the pawn that just moved two squares passed over target, from behind it
+            let (rank, pushed, start) = match moved {
+                Color::White => (Rank::Third, target.north(1), target.south(1)),
+                Color::Black => (Rank::Sixth, target.south(1), target.north(1)),
+            };
+            let pawn = Some(Piece {
+                kind: PieceType::Pawn,
+                color: moved,
+            });
+            if target.rank() != rank
+                || self.occupied(target)
+                || start.is_none_or(|start| self.occupied(start))
+                || pushed.is_none_or(|pushed| self.piece(pushed) != pawn)
+            {
+                board_state_error!("[validate] no pawn just passed over {}", target);
+            }
+        }
+
+        // rights to castle with the rook in the corner, as [white K, white Q, black k, black q]
+        let castles = [
+            (Color::White, Pos::E1, Pos::H1),
+            (Color::White, Pos::E1, Pos::A1),
+            (Color::Black, Pos::E8, Pos::H8),
+            (Color::Black, Pos::E8, Pos::A8),
+        ];
+        for (i, &(color, king, rook)) in castles.iter().enumerate() {
+            let on = |pos, kind| self.piece(pos) == Some(Piece { kind, color });
+            if self.castle_rights[i] && !(on(king, PieceType::King) && on(rook, PieceType::Rook)) {
+                board_state_error!(
+                    "[validate] {} can't castle with the rook on {}",
+                    color,
+                    rook
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::util::ChessError;
+
+    #[test]
+    fn validate() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            "4k3/8/8/8/8/8/8/R3K3 b Q - 0 1",
+        ]
+        .iter()
+        {
+            assert_eq!(Board::from_fen(fen).unwrap().validate(), Ok(()), "{}", fen);
+        }
+
+        for fen in [
+            // kings
+            "8/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K1K1 w - - 0 1",
+            // pawns on the back ranks
+            "4k2P/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/p3K3 w - - 0 1",
+            // black to move, but white in check
+            "4k3/8/8/8/8/8/8/r3K3 b - - 0 1",
+            // no pawn just moved past the en passant square
+            "4k3/8/8/8/8/8/4P3/4K3 b - e3 0 1",
+            "4k3/4p3/8/4p3/8/8/8/4K3 w - e6 0 1",
+            "4k3/8/8/8/3P4/8/8/4K3 w - d3 0 1",
+            // castling with missing rooks or kings
+            "4k3/8/8/8/8/8/8/4K3 w K - 0 1",
+            "r3k3/8/8/8/8/8/8/4K3 w k - 0 1",
+            "r6k/8/8/8/8/8/8/4K3 w q - 0 1",
+        ]
+        .iter()
+        {
+            match Board::from_fen(fen) {
+                Err(ChessError::BadBoardState(_)) => {}
+                res => panic!("{} gave {:?}", fen, res.map(|b| b.to_fen())),
+            }
+            assert!(Board::from_fen_unchecked(fen).is_ok());
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use crate::board::Board;
+use crate::moves::Move;
+use crate::util::ChessError;
+
+use rand::{self, Rng};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+// the repertoire compiled into the binary, so the engine varies its openings without any
+// files next to it
+const BUILTIN: &str = include_str!("book.txt");
+
+// opening moves by position, each with a weight: how many book lines play it
+#[derive(Debug, Clone, Default)]
+pub struct Book {
+    moves: HashMap<u64, Vec<(Move, u32)>>,
+}
+
+impl Book {
+    pub fn builtin() -> &'static Book {
+        static BOOK: OnceLock<Book> = OnceLock::new();
+        BOOK.get_or_init(|| Book::from_lines(BUILTIN).unwrap())
+    }
+
+    // lines of san moves from the initial position, like "e4 e5 Nf3 Nc6". move numbers,
+    // blank lines and # comments are skipped.
+    pub fn from_lines(s: &str) -> Result<Book, ChessError> {
+        let mut book = Book::default();
+        for (n, line) in s.lines().enumerate() {
+            let mut b = Board::initial();
+            let line = line.split('#').next().unwrap();
+            for san in line.split_whitespace().filter(|w| !w.ends_with('.')) {
+                let mv = match Move::from_san(&b, san) {
+                    Ok(mv) => mv,
+                    Err(e) => parse_error!("[book] line {}: {}", n + 1, e),
+                };
+                book.add(&b, mv);
+                b = b.make_move(&mv)?;
+            }
+        }
+        Ok(book)
+    }
+
+    fn add(&mut self, b: &Board, mv: Move) {
+        let moves = self.moves.entry(b.zobrist()).or_default();
+        match moves.iter_mut().find(|(m, _)| *m == mv) {
+            Some((_, weight)) => *weight += 1,
+            None => moves.push((mv, 1)),
+        }
+    }
+
+    // the number of positions with book moves
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    // the book moves in b with their weights, if any
+    pub fn moves(&self, b: &Board) -> &[(Move, u32)] {
+        self.moves
+            .get(&b.zobrist())
+            .map_or(&[], |moves| moves.as_slice())
+    }
+
+    // a book move for b, chosen at random in proportion to the weights, as long as it is
+    // legal there: a hash collision could find moves for some other position
+    pub fn pick(&self, b: &Board) -> Option<Move> {
+        let moves = self.moves(b);
+        let total: u32 = moves.iter().map(|(_, w)| w).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut r = rand::thread_rng().gen_range(0, total);
+        let (mv, _) = moves.iter().find(|(_, w)| {
+            if r < *w {
+                true
+            } else {
+                r -= w;
+                false
+            }
+        })?;
+        let legal = b.legal_moves();
+        legal.into_iter().find(|m| m == mv)
+    }
+
+    // the legal book move for b with the most weight, the first of them on a tie, so the
+    // same position always gets the same move
+    pub fn pick_heaviest(&self, b: &Board) -> Option<Move> {
+        let legal = b.legal_moves();
+        let mut heaviest: Option<(Move, u32)> = None;
+        for &(mv, w) in self.moves(b) {
+            if legal.contains(&mv) && heaviest.is_none_or(|(_, most)| w > most) {
+                heaviest = Some((mv, w));
+            }
+        }
+        heaviest.map(|(mv, _)| mv)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::book::Book;
+    use crate::moves::Move;
+
+    #[test]
+    fn builtin_book() {
+        let book = Book::builtin();
+        assert!(book.len() > 100);
+
+        let b = Board::initial();
+        let first: Vec<Move> = book.moves(&b).iter().map(|(mv, _)| *mv).collect();
+        assert!(first.contains(&mv!("e2-e4")));
+        assert!(first.contains(&mv!("d2-d4")));
+        for _ in 0..20 {
+            assert!(first.contains(&book.pick(&b).unwrap()));
+        }
+
+        // out of the book
+        let b = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(book.moves(&b).is_empty());
+        assert_eq!(book.pick(&b), None);
+    }
+
+    #[test]
+    fn weights_and_transpositions() {
+        let book = Book::from_lines(
+            "1. d4 Nf6 2. c4 e6 3. Nc3\n\
+             1. c4 e6 2. d4 Nf6 3. Nf3 # the same position after two moves\n\
+             \n\
+             d4 d5",
+        )
+        .unwrap();
+        let b = Board::initial();
+        let mut first = book.moves(&b).to_vec();
+        first.sort_by_key(|(_, w)| *w);
+        assert_eq!(first, vec![(mv!("c2-c4"), 1), (mv!("d2-d4"), 2)]);
+        assert_eq!(book.pick_heaviest(&b), Some(mv!("d2-d4")));
+
+        // both lines reach this position, and carry on differently
+        let b = Board::from_fen("rnbqkb1r/pppp1ppp/4pn2/8/2PP4/8/PP2PPPP/RNBQKBNR w KQkq - 0 3")
+            .unwrap();
+        let moves: Vec<Move> = book.moves(&b).iter().map(|(mv, _)| *mv).collect();
+        assert_eq!(moves, vec![mv!("Nb1-c3"), mv!("Ng1-f3")]);
+        assert_eq!(book.pick_heaviest(&b), Some(mv!("Nb1-c3")));
+
+        assert!(Book::from_lines("e4 e5 Ke3").is_err());
+    }
+}
@@ -0,0 +1,369 @@
+use crate::search_limits::MAX_SKILL;
+use crate::transposition_table::DEFAULT_MEGABYTES;
+use crate::util::ChessError;
+
+// what an option holds, and what the interface may set it to
+#[derive(Debug, Clone, PartialEq)]
+pub enum OptionValue {
+    Check(bool),
+    Spin {
+        value: isize,
+        min: isize,
+        max: isize,
+    },
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EngineOption {
+    pub name: &'static str,
+    pub value: OptionValue,
+}
+
+// the options the engine shows its interface, set from the command line and by the gui.
+// the same registry serves xboard's feature option strings and uci's option lines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EngineOptions {
+    options: Vec<EngineOption>,
+    // the temperature random mode replaced, while it's on
+    random: Option<isize>,
+}
+
+pub const HASH: &str = "Hash";
+pub const CONTEMPT: &str = "Contempt";
+pub const OWN_BOOK: &str = "OwnBook";
+pub const PONDER: &str = "Ponder";
+pub const TEMPERATURE: &str = "Temperature";
+pub const SKILL_LEVEL: &str = "Skill Level";
+pub const LIMIT_STRENGTH: &str = "UCI_LimitStrength";
+pub const ELO: &str = "UCI_Elo";
+pub const MTDF: &str = "MTD(f)";
+pub const SEARCH_STATS: &str = "Search Statistics";
+pub const DETERMINISTIC: &str = "Deterministic";
+
+// the temperature xboard's random command turns on
+const RANDOM_TEMPERATURE: isize = 10;
+
+fn spin(name: &'static str, value: isize, min: isize, max: isize) -> EngineOption {
+    EngineOption {
+        name,
+        value: OptionValue::Spin { value, min, max },
+    }
+}
+
+impl EngineOptions {
+    pub fn new() -> EngineOptions {
+        EngineOptions {
+            options: vec![
+                // megabytes of transposition table
+                spin(HASH, DEFAULT_MEGABYTES as isize, 1, 65536),
+                // centipawns to give up to avoid a draw
+                spin(CONTEMPT, 0, -1000, 1000),
+                EngineOption {
+                    name: OWN_BOOK,
+                    value: OptionValue::Check(true),
+                },
+                // think on the opponent's time
+                EngineOption {
+                    name: PONDER,
+                    value: OptionValue::Check(false),
+                },
+                // centipawns below the best a move may be and still be played, picked at
+                // random to vary play
+                spin(TEMPERATURE, 0, 0, 100),
+                spin(SKILL_LEVEL, MAX_SKILL as isize, 0, MAX_SKILL as isize),
+                // play at about UCI_Elo instead of the skill level
+                EngineOption {
+                    name: LIMIT_STRENGTH,
+                    value: OptionValue::Check(false),
+                },
+                spin(ELO, 1500, 800, 2400),
+                // search with mtd(f) rather than aspiration windows
+                EngineOption {
+                    name: MTDF,
+                    value: OptionValue::Check(false),
+                },
+                // debugging: after each search, say how the move ordering and pruning did
+                EngineOption {
+                    name: SEARCH_STATS,
+                    value: OptionValue::Check(false),
+                },
+                // debugging: search on one thread and never pick a move at random, so
+                // the same moves give the same game
+                EngineOption {
+                    name: DETERMINISTIC,
+                    value: OptionValue::Check(false),
+                },
+            ],
+            random: None,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &EngineOption> {
+        self.options.iter()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&OptionValue> {
+        self.options
+            .iter()
+            .find(|o| o.name.eq_ignore_ascii_case(name))
+            .map(|o| &o.value)
+    }
+
+    // the value of a spin option, or 0 if there is none by that name
+    pub fn spin(&self, name: &str) -> isize {
+        match self.get(name) {
+            Some(&OptionValue::Spin { value, .. }) => value,
+            _ => 0,
+        }
+    }
+
+    // set a spin option to the nearest value in its range, as for xboard's memory,
+    // which tells us what we may use rather than what we must
+    pub fn set_clamped(&mut self, name: &str, n: usize) {
+        let option = self
+            .options
+            .iter_mut()
+            .find(|o| o.name.eq_ignore_ascii_case(name));
+        if let Some(EngineOption {
+            value: OptionValue::Spin { value, min, max },
+            ..
+        }) = option
+        {
+            *value = (n.min(*max as usize) as isize).max(*min);
+        }
+    }
+
+    pub fn check(&self, name: &str) -> bool {
+        self.get(name) == Some(&OptionValue::Check(true))
+    }
+
+    // set an option from text, as the interface sends it. checks take 0 and 1 as well as
+    // true and false, and spins must be within their range.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), ChessError> {
+        let option = match self
+            .options
+            .iter_mut()
+            .find(|o| o.name.eq_ignore_ascii_case(name))
+        {
+            Some(option) => option,
+            None => parse_error!("[EngineOptions::set] no option named {}", name),
+        };
+        let value = value.trim();
+        match &mut option.value {
+            OptionValue::Check(b) => {
+                *b = match value {
+                    "1" | "true" => true,
+                    "0" | "false" => false,
+                    _ => parse_error!("[EngineOptions::set] {} takes true or false", name),
+                }
+            }
+            OptionValue::Spin { value: v, min, max } => match value.parse::<isize>() {
+                Ok(n) if *min <= n && n <= *max => *v = n,
+                _ => parse_error!(
+                    "[EngineOptions::set] {} takes a number from {} to {}",
+                    name,
+                    min,
+                    max
+                ),
+            },
+            OptionValue::Text(s) => *s = value.to_string(),
+        }
+        Ok(())
+    }
+
+    // xboard's random: toggle varying our moves
+    pub fn toggle_random(&mut self) {
+        let temperature = match self.random.take() {
+            Some(saved) => saved,
+            None => {
+                self.random = Some(self.spin(TEMPERATURE));
+                RANDOM_TEMPERATURE
+            }
+        };
+        self.set(TEMPERATURE, &temperature.to_string()).unwrap();
+    }
+
+    // xboard's new: random mode lasts only the game, and the book comes back as it was
+    // at the start if it was turned off since
+    pub fn new_game(&mut self, start: &EngineOptions) {
+        if self.random.is_some() {
+            self.toggle_random();
+        }
+        let own_book = start.check(OWN_BOOK).to_string();
+        self.set(OWN_BOOK, &own_book).unwrap();
+    }
+
+    // set from xboard's "option NAME=VALUE", returning the name
+    pub fn set_xboard(&mut self, s: &str) -> Result<&'static str, ChessError> {
+        let (name, value) = match s.split_once('=') {
+            Some(pair) => pair,
+            None => parse_error!("[EngineOptions::set_xboard] no value in {}", s),
+        };
+        self.set(name, value)?;
+        Ok(self
+            .iter()
+            .find(|o| o.name.eq_ignore_ascii_case(name))
+            .unwrap()
+            .name)
+    }
+
+    // the features that tell xboard about the options, one per option
+    pub fn xboard_features(&self) -> Vec<String> {
+        self.iter()
+            .map(|o| match &o.value {
+                OptionValue::Check(b) => {
+                    format!("feature option=\"{} -check {}\"", o.name, *b as u8)
+                }
+                OptionValue::Spin { value, min, max } => {
+                    format!(
+                        "feature option=\"{} -spin {} {} {}\"",
+                        o.name, value, min, max
+                    )
+                }
+                OptionValue::Text(s) => format!("feature option=\"{} -string {}\"", o.name, s),
+            })
+            .collect()
+    }
+
+    // the lines a uci engine answers "uci" with
+    pub fn uci_options(&self) -> Vec<String> {
+        self.iter()
+            .map(|o| match &o.value {
+                OptionValue::Check(b) => {
+                    format!("option name {} type check default {}", o.name, b)
+                }
+                OptionValue::Spin { value, min, max } => format!(
+                    "option name {} type spin default {} min {} max {}",
+                    o.name, value, min, max
+                ),
+                OptionValue::Text(s) => {
+                    format!("option name {} type string default {}", o.name, s)
+                }
+            })
+            .collect()
+    }
+}
+
+impl Default for EngineOptions {
+    fn default() -> EngineOptions {
+        EngineOptions::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine_options::{
+        EngineOptions, CONTEMPT, HASH, OWN_BOOK, SKILL_LEVEL, TEMPERATURE,
+    };
+    use crate::util::ChessError;
+
+    #[test]
+    fn options() {
+        let mut opts = EngineOptions::new();
+        assert_eq!(opts.spin(CONTEMPT), 0);
+        assert!(opts.check(OWN_BOOK));
+
+        assert_eq!(opts.set_xboard("Contempt=25"), Ok(CONTEMPT));
+        assert_eq!(opts.spin(CONTEMPT), 25);
+        assert_eq!(opts.set_xboard("ownbook=0"), Ok(OWN_BOOK));
+        assert_eq!(opts.set_xboard("Skill Level=5"), Ok(SKILL_LEVEL));
+        assert_eq!(opts.spin(SKILL_LEVEL), 5);
+        opts.set(SKILL_LEVEL, "20").unwrap();
+        assert!(!opts.check(OWN_BOOK));
+        opts.set(HASH, "64").unwrap();
+        assert_eq!(opts.spin(HASH), 64);
+
+        for bad in [
+            "Hash=0",
+            "Hash=lots",
+            "OwnBook=maybe",
+            "Ponder=yes",
+            "Contempt",
+            "Skill=1",
+        ]
+        .iter()
+        {
+            match opts.set_xboard(bad) {
+                Err(ChessError::ParseError(_)) => {}
+                res => panic!("{} gave {:?}", bad, res),
+            }
+        }
+        assert_eq!(opts.spin(HASH), 64);
+
+        assert_eq!(
+            opts.xboard_features(),
+            vec![
+                "feature option=\"Hash -spin 64 1 65536\"",
+                "feature option=\"Contempt -spin 25 -1000 1000\"",
+                "feature option=\"OwnBook -check 0\"",
+                "feature option=\"Ponder -check 0\"",
+                "feature option=\"Temperature -spin 0 0 100\"",
+                "feature option=\"Skill Level -spin 20 0 20\"",
+                "feature option=\"UCI_LimitStrength -check 0\"",
+                "feature option=\"UCI_Elo -spin 1500 800 2400\"",
+                "feature option=\"MTD(f) -check 0\"",
+                "feature option=\"Search Statistics -check 0\"",
+                "feature option=\"Deterministic -check 0\"",
+            ]
+        );
+        assert_eq!(
+            opts.uci_options()[0],
+            "option name Hash type spin default 64 min 1 max 65536"
+        );
+    }
+
+    #[test]
+    fn clamped() {
+        let mut opts = EngineOptions::new();
+        opts.set_clamped(HASH, 256);
+        assert_eq!(opts.spin(HASH), 256);
+        opts.set_clamped(HASH, 0);
+        assert_eq!(opts.spin(HASH), 1);
+        opts.set_clamped(HASH, usize::MAX);
+        assert_eq!(opts.spin(HASH), 65536);
+    }
+
+    #[test]
+    fn random_and_new() {
+        let mut opts = EngineOptions::new();
+        opts.toggle_random();
+        assert!(opts.spin(TEMPERATURE) > 0);
+        opts.toggle_random();
+        assert_eq!(opts.spin(TEMPERATURE), 0);
+
+        // random, then new
+        let start = opts.clone();
+        opts.toggle_random();
+        opts.new_game(&start);
+        assert_eq!(opts.spin(TEMPERATURE), 0);
+
+        // a temperature the gui set survives new, and random hands it back
+        opts.set(TEMPERATURE, "30").unwrap();
+        opts.new_game(&start);
+        assert_eq!(opts.spin(TEMPERATURE), 30);
+        opts.toggle_random();
+        assert!(opts.spin(TEMPERATURE) > 0);
+        opts.new_game(&start);
+        assert_eq!(opts.spin(TEMPERATURE), 30);
+        opts.toggle_random();
+        opts.toggle_random();
+        assert_eq!(opts.spin(TEMPERATURE), 30);
+    }
+
+    #[test]
+    fn new_restores_the_book() {
+        let start = EngineOptions::new();
+        let mut opts = start.clone();
+        opts.set(OWN_BOOK, "false").unwrap();
+        opts.new_game(&start);
+        assert!(opts.check(OWN_BOOK));
+
+        // unless it was off from the start, as with --no-book
+        let mut start = EngineOptions::new();
+        start.set(OWN_BOOK, "false").unwrap();
+        let mut opts = start.clone();
+        opts.new_game(&start);
+        assert!(!opts.check(OWN_BOOK));
+    }
+}
@@ -0,0 +1,263 @@
+use crate::board::Board;
+use crate::util::ChessError;
+#[cfg(feature = "std")]
+use crate::util::ParseError;
+
+#[cfg(feature = "std")]
+use std::fs::File;
+#[cfg(feature = "std")]
+use std::io::{BufRead, BufReader};
+#[cfg(feature = "std")]
+use std::path::Path;
+
+#[cfg(not(feature = "std"))]
+use crate::alloc_prelude::*;
+
+// a position from an epd line, or from a plain fen
+#[derive(Debug, Clone)]
+pub struct Epd {
+    pub board: Board,
+    // opcodes and their operands in the order they appear, like ("bm", "Nf3") or
+    // ("id", "WAC.001") with the quotes taken off
+    pub ops: Vec<(String, String)>,
+}
+
+impl Epd {
+    pub fn op(&self, name: &str) -> Option<&str> {
+        self.ops
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    // four fen fields followed by operations, each ended by a semicolon. a full fen,
+    // with the halfmove clock and move number, is read as it is.
+    pub fn parse(line: &str) -> Result<Epd, ChessError> {
+        let mut fields = Vec::with_capacity(4);
+        let mut rest = line.trim();
+        for _ in 0..4 {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            if end == 0 {
+                parse_error!("[epd] expected 4 fields in \"{}\"", line);
+            }
+            fields.push(&rest[..end]);
+            rest = rest[end..].trim_start();
+        }
+
+        let counters = !rest.contains(';')
+            && rest.split_whitespace().count() <= 2
+            && rest.split_whitespace().all(|s| s.parse::<usize>().is_ok());
+        if counters {
+            return Ok(Epd {
+                board: Board::from_fen(line)?,
+                ops: Vec::new(),
+            });
+        }
+
+        let mut board = Board::from_fen(&fields.join(" "))?;
+        let ops = operations(rest)?;
+        for (name, value) in &ops {
+            let field = match name.as_str() {
+                "hmvc" => &mut board.halfmove_clock,
+                "fmvn" => &mut board.move_number,
+                _ => continue,
+            };
+            *field = match value.parse() {
+                Ok(n) => n,
+                Err(_) => parse_error!("[epd] couldn't decode {} \"{}\"", name, value),
+            };
+        }
+        Ok(Epd { board, ops })
+    }
+}
+
+// "bm Nf3 Ng5; id \"a; b\";" as [("bm", "Nf3 Ng5"), ("id", "a; b")]. the last
+// semicolon may be left off.
+fn operations(s: &str) -> Result<Vec<(String, String)>, ChessError> {
+    let mut ops = Vec::new();
+    let mut rest = s.trim_start();
+    while !rest.is_empty() {
+        let end = rest
+            .find(|c: char| c.is_whitespace() || c == ';')
+            .unwrap_or(rest.len());
+        let name = &rest[..end];
+        if name.is_empty() {
+            parse_error!("[epd] operation without an opcode in \"{}\"", s);
+        }
+        rest = rest[end..].trim_start();
+
+        // the operands run to the first semicolon outside quotes
+        let mut quoted = false;
+        let end = rest
+            .char_indices()
+            .find(|&(_, c)| {
+                if c == '"' {
+                    quoted = !quoted;
+                }
+                c == ';' && !quoted
+            })
+            .map(|(i, _)| i);
+        if quoted {
+            parse_error!("[epd] unclosed quote after {}", name);
+        }
+        let value = rest[..end.unwrap_or(rest.len())].trim();
+        let value = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+            Some(v) if !v.contains('"') => v,
+            _ => value,
+        };
+        ops.push((name.to_string(), value.to_string()));
+        rest = match end {
+            Some(i) => rest[i + 1..].trim_start(),
+            None => "",
+        };
+    }
+    Ok(ops)
+}
+
+// reads positions one line at a time, so files of any size can be gone through. blank
+// lines are skipped, and a line with an error is returned as that error, naming the
+// line, before reading goes on with the next one.
+#[cfg(feature = "std")]
+pub struct EpdReader<R> {
+    reader: R,
+    buf: Vec<u8>,
+    nlines: usize,
+    done: bool,
+}
+
+#[cfg(feature = "std")]
+pub fn read_epd<R: BufRead>(reader: R) -> EpdReader<R> {
+    EpdReader {
+        reader,
+        buf: Vec::new(),
+        nlines: 0,
+        done: false,
+    }
+}
+
+#[cfg(feature = "std")]
+impl EpdReader<BufReader<File>> {
+    pub fn open(path: &Path) -> Result<Self, ChessError> {
+        match File::open(path) {
+            Ok(f) => Ok(read_epd(BufReader::new(f))),
+            Err(e) => parse_error!("[epd] can't read {}: {}", path.display(), e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R> EpdReader<R> {
+    // the number of lines read so far, which is the line the last position came from
+    pub fn line(&self) -> usize {
+        self.nlines
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: BufRead> Iterator for EpdReader<R> {
+    type Item = Result<Epd, ChessError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.buf.clear();
+            let msg = match self.reader.read_until(b'\n', &mut self.buf) {
+                Ok(0) => return None,
+                Ok(_) => {
+                    self.nlines += 1;
+                    match core::str::from_utf8(&self.buf) {
+                        Ok(line) if line.trim().is_empty() => continue,
+                        Ok(line) => match Epd::parse(line) {
+                            Ok(epd) => return Some(Ok(epd)),
+                            Err(e) => format!("[epd] line {}: {}", self.nlines, e),
+                        },
+                        Err(_) => format!("[epd] line {}: not utf-8", self.nlines),
+                    }
+                }
+                Err(e) => {
+                    // the reader may keep failing, so stop here
+                    self.done = true;
+                    format!("[epd] after line {}: {}", self.nlines, e)
+                }
+            };
+            return Some(Err(ChessError::ParseError(ParseError(msg))));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "std")]
+    use crate::epd::read_epd;
+    use crate::epd::Epd;
+
+    #[test]
+    fn parse_epd() {
+        let epd = Epd::parse(
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";",
+        )
+        .unwrap();
+        assert_eq!(epd.op("bm"), Some("Qg6"));
+        assert_eq!(epd.op("id"), Some("WAC.001"));
+        assert_eq!(epd.op("am"), None);
+        assert_eq!(epd.board.move_number, 1);
+
+        let epd = Epd::parse(
+            "4k3/8/8/8/8/8/8/4K3 b - - c0 \"a; b\"; bm Kd7 Ke7 ; hmvc 12; fmvn 40; noop",
+        )
+        .unwrap();
+        assert_eq!(
+            epd.ops,
+            vec![
+                ("c0".to_string(), "a; b".to_string()),
+                ("bm".to_string(), "Kd7 Ke7".to_string()),
+                ("hmvc".to_string(), "12".to_string()),
+                ("fmvn".to_string(), "40".to_string()),
+                ("noop".to_string(), "".to_string()),
+            ]
+        );
+        assert_eq!(epd.board.to_fen(), "4k3/8/8/8/8/8/8/4K3 b - - 12 40");
+
+        let fen = "4k3/8/8/8/8/8/8/4K3 w - - 3 9";
+        let epd = Epd::parse(fen).unwrap();
+        assert!(epd.ops.is_empty());
+        assert_eq!(epd.board.to_fen(), fen);
+
+        for bad in [
+            "",
+            "4k3/8/8/8/8/8/8/4K3 w -",
+            "4k3/8/8/8/8/8/8/4K3 w - - id \"open",
+            "4k3/8/8/8/8/8/8/4K3 w - - hmvc x;",
+            "4k3/8/8/8/8/8/8/4K3 w - - ; bm Ke2",
+            "4k3/8/8/8/8/8/8/8 w - - bm Ke2;",
+        ]
+        .iter()
+        {
+            assert!(Epd::parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn read_lines() {
+        let text = "4k3/8/8/8/8/8/8/4K3 w - - bm Kd2;\n\
+                    \n\
+                    4k3/8/8/8/8/8/8/4K3 w - - 0 1\r\n\
+                    4k3/8/8/8/8/8/8/4K4 w - - id \"broken\";\n\
+                    4k3/8/8/8/8/8/8/4K3 b - -";
+        let mut r = read_epd(text.as_bytes());
+        assert_eq!(r.next().unwrap().unwrap().op("bm"), Some("Kd2"));
+        assert_eq!(r.line(), 1);
+        assert!(r.next().unwrap().is_ok());
+        assert_eq!(r.line(), 3);
+        let e = r.next().unwrap().unwrap_err();
+        assert!(e.to_string().starts_with("[epd] line 4: "), "{}", e);
+        assert!(r.next().unwrap().is_ok());
+        assert!(r.next().is_none());
+
+        let mut r = read_epd(&b"4k3/8/8/8/8/8/8/4K3 w - -\n\xff\n4k3/8/8/8/8/8/8/4K3 w - -"[..]);
+        assert!(r.next().unwrap().is_ok());
+        assert!(r.next().unwrap().is_err());
+        assert!(r.next().unwrap().is_ok());
+    }
+}
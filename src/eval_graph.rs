@@ -0,0 +1,174 @@
+use crate::board::{Board, GameStatus};
+use crate::board_alpha_beta::{json_score, mate_in, MATE};
+use crate::game::Game;
+use crate::moves::Move;
+use crate::piece::Color;
+use crate::search_limits::SearchLimits;
+use crate::transposition_table::TranspositionTable;
+use crate::util::ChessError;
+
+use std::sync::Arc;
+
+// the columns of MoveEval::to_csv. a score is either centipawns or mate in moves, with
+// the other column empty.
+pub const CSV_HEADER: &str = "move,color,san,cp,mate,best,best_cp,best_mate";
+
+// how the game stood after one of its moves, for plotting an evaluation graph. scores
+// are white's: positive when white is better, whoever moved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveEval {
+    // the game's move number, shared by white's move and black's reply
+    pub move_number: usize,
+    pub color: Color,
+    pub san: String,
+    pub score: isize,
+    // the move the engine would have played instead and the score after it, when it
+    // wasn't the move played
+    pub best: Option<(String, isize)>,
+}
+
+impl MoveEval {
+    pub fn to_csv(&self) -> String {
+        let (best, best_score) = match &self.best {
+            Some((san, score)) => (san.as_str(), csv_score(*score)),
+            None => ("", ",".to_string()),
+        };
+        format!(
+            "{},{},{},{},{},{}",
+            self.move_number,
+            self.color,
+            self.san,
+            csv_score(self.score),
+            best,
+            best_score
+        )
+    }
+
+    pub fn to_json(&self) -> String {
+        let best = match &self.best {
+            Some((san, score)) => {
+                format!("{{\"san\":\"{}\",\"score\":{}}}", san, json_score(*score))
+            }
+            None => "null".to_string(),
+        };
+        format!(
+            "{{\"move\":{},\"color\":\"{}\",\"san\":\"{}\",\"score\":{},\"best\":{}}}",
+            self.move_number,
+            self.color,
+            self.san,
+            json_score(self.score),
+            best
+        )
+    }
+}
+
+fn csv_score(score: isize) -> String {
+    match mate_in(score) {
+        Some(moves) => format!(",{}", moves),
+        None => format!("{},", score),
+    }
+}
+
+fn for_white(score: isize, to_move: Color) -> isize {
+    match to_move {
+        Color::White => score,
+        Color::Black => -score,
+    }
+}
+
+// the engine's move in b, if the game isn't over, and its score for the side to move
+fn search(
+    b: &Board,
+    limits: &SearchLimits,
+    tt: &Arc<TranspositionTable>,
+) -> Result<(Option<Move>, isize), ChessError> {
+    match b.clone().search(limits, None, Some(tt.clone())) {
+        Ok(res) => Ok((Some(res.mv), res.score)),
+        Err(ChessError::GameOver(GameStatus::Checkmate)) => Ok((None, -MATE)),
+        Err(ChessError::GameOver(_)) => Ok((None, 0)),
+        Err(e) => Err(e),
+    }
+}
+
+// search every position of the game once, calling report with each move's evaluation as
+// it is known. one table serves the whole game, since each position follows the last.
+pub fn evaluate_game(
+    game: &Game,
+    limits: &SearchLimits,
+    report: &mut dyn FnMut(&MoveEval),
+) -> Result<Vec<MoveEval>, ChessError> {
+    let tt = Arc::new(TranspositionTable::new());
+    let positions = game.positions();
+    let mut evals = Vec::new();
+    let (mut best, mut best_score) = search(&positions[0], limits, &tt)?;
+    for (i, mv) in game.moves().iter().enumerate() {
+        let (before, after) = (&positions[i], &positions[i + 1]);
+        let (next_best, score) = search(after, limits, &tt)?;
+        let alternative = best.filter(|best| best != mv).map(|best| {
+            let score = for_white(best_score, before.color_to_move);
+            (best.to_san(before), score)
+        });
+        let eval = MoveEval {
+            move_number: before.move_number,
+            color: before.color_to_move,
+            san: mv.to_san(before),
+            score: for_white(score, after.color_to_move),
+            best: alternative,
+        };
+        report(&eval);
+        evals.push(eval);
+        best = next_best;
+        best_score = score;
+    }
+    Ok(evals)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board_alpha_beta::MATE;
+    use crate::eval_graph::{evaluate_game, MoveEval, CSV_HEADER};
+    use crate::game::Game;
+    use crate::moves::Move;
+    use crate::piece::Color;
+    use crate::search_limits::SearchLimits;
+
+    #[test]
+    fn evaluation_graph() {
+        // white misses the back rank mate, then finds it after black's blunder
+        let mut game = Game::from_fen("6k1/5ppp/8/8/8/8/r4PPP/4R1K1 w - - 0 1").unwrap();
+        for san in ["h3", "Ra3", "Re8#"].iter() {
+            let mv = Move::from_san(game.board(), san).unwrap();
+            game.make_move(&mv).unwrap();
+        }
+        let mut reported = 0;
+        let limits = SearchLimits::depth(2);
+        let evals = evaluate_game(&game, &limits, &mut |_| reported += 1).unwrap();
+        assert_eq!(reported, 3);
+        let sans: Vec<&str> = evals.iter().map(|e| e.san.as_str()).collect();
+        assert_eq!(sans, vec!["h3", "Ra3", "Re8#"]);
+        assert_eq!(evals[0].best, Some(("Re8#".to_string(), MATE - 1)));
+        assert_eq!((evals[1].move_number, evals[1].color), (1, Color::Black));
+        assert_eq!(evals[1].score, MATE - 1);
+        assert!(evals[1].best.is_some());
+        assert_eq!((evals[2].move_number, evals[2].color), (2, Color::White));
+        assert_eq!((evals[2].score, evals[2].best.clone()), (MATE, None));
+
+        let eval = MoveEval {
+            move_number: 12,
+            color: Color::Black,
+            san: "Nf6".to_string(),
+            score: -35,
+            best: Some(("Qh4".to_string(), MATE - 3)),
+        };
+        assert_eq!(
+            CSV_HEADER.split(',').count(),
+            eval.to_csv().split(',').count()
+        );
+        assert_eq!(eval.to_csv(), "12,black,Nf6,-35,,Qh4,,2");
+        assert_eq!(
+            eval.to_json(),
+            "{\"move\":12,\"color\":\"black\",\"san\":\"Nf6\",\"score\":{\"cp\":-35},\
+             \"best\":{\"san\":\"Qh4\",\"score\":{\"mate\":2}}}"
+        );
+    }
+}
@@ -0,0 +1,326 @@
+use crate::util::ChessError;
+
+use std::fs;
+use std::slice;
+use std::sync::RwLock;
+
+// 0 on the edge of the board, up to 3 in the middle
+#[rustfmt::skip]
+const CENTRALITY: [isize; 64] = [
+    0, 0, 0, 0, 0, 0, 0, 0,
+    0, 1, 1, 1, 1, 1, 1, 0,
+    0, 1, 2, 2, 2, 2, 1, 0,
+    0, 1, 2, 3, 3, 2, 1, 0,
+    0, 1, 2, 3, 3, 2, 1, 0,
+    0, 1, 2, 2, 2, 2, 1, 0,
+    0, 1, 1, 1, 1, 1, 1, 0,
+    0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+// every weight the evaluation uses, in centipawns. they can be read from a file of
+// "name = value" lines (a subset of toml), where a value is a number or a list of
+// numbers in brackets, so weights can be tried out without recompiling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvalParams {
+    pub pawn: isize,
+    pub knight: isize,
+    pub bishop: isize,
+    pub rook: isize,
+    pub queen: isize,
+
+    // what each kind of piece is worth on each square, from a8 to h1 as white sees the
+    // board, and mirrored for black. the defaults only reward being central.
+    pub pawn_squares: [isize; 64],
+    pub knight_squares: [isize; 64],
+    pub bishop_squares: [isize; 64],
+    pub rook_squares: [isize; 64],
+    pub queen_squares: [isize; 64],
+    pub king_squares: [isize; 64],
+
+    // centipawns per square a piece can move to, and the number of squares it has in a
+    // typical middlegame position. pieces with fewer are penalized. pawns and kings don't
+    // get a mobility term.
+    pub knight_mobility: [isize; 2],
+    pub bishop_mobility: [isize; 2],
+    pub rook_mobility: [isize; 2],
+    pub queen_mobility: [isize; 2],
+
+    // two bishops cover both colors, and are worth more than a bishop and a knight
+    pub bishop_pair: isize,
+
+    // for each pawn of its own side above or below five, knights gain and rooks lose:
+    // pawns block the files rooks want, and give knights outposts
+    pub knight_pawn_adjustment: isize,
+    pub rook_pawn_adjustment: isize,
+
+    // major pieces doing the same job: a second rook, or a rook next to a queen, adds less
+    pub rook_redundancy: isize,
+    pub queen_rook_redundancy: isize,
+
+    // rooks want files without pawns in the way: most with none at all, less when only
+    // enemy pawns are left on the file
+    pub rook_open_file: isize,
+    pub rook_semi_open_file: isize,
+
+    // a rook on the opponent's second rank attacks pawns still at home and hems in the
+    // king
+    pub rook_on_seventh: isize,
+
+    // rooks on the same rank or file with nothing between them defend each other
+    pub connected_rooks: isize,
+
+    // bonus for a pawn no enemy pawn can stop, by how far it has advanced
+    pub passed_pawn: [isize; 6],
+
+    // per rank advanced, for each square the enemy king is further from the pawn's path
+    // than ours
+    pub passed_pawn_king_distance: isize,
+
+    // a rook behind a passed pawn pushes it on, and keeps guarding it as it goes
+    pub passed_pawn_rook_behind: isize,
+
+    // a knight in the enemy half, guarded by a pawn, where no enemy pawn can ever chase it
+    pub knight_outpost: isize,
+
+    // a knight on the a or h file reaches half the squares it could
+    pub knight_on_rim: isize,
+
+    // per own pawn on the bishop's color, in its way
+    pub bishop_own_pawn: isize,
+
+    // per square closer than 7 to the enemy king, by piece. only while the side has its
+    // queen, since without it there is rarely an attack to join.
+    pub knight_tropism: isize,
+    pub bishop_tropism: isize,
+    pub rook_tropism: isize,
+    pub queen_tropism: isize,
+}
+
+impl EvalParams {
+    pub const DEFAULT: EvalParams = EvalParams {
+        pawn: 100,
+        knight: 300,
+        bishop: 300,
+        rook: 500,
+        queen: 900,
+        pawn_squares: CENTRALITY,
+        knight_squares: CENTRALITY,
+        bishop_squares: CENTRALITY,
+        rook_squares: CENTRALITY,
+        queen_squares: CENTRALITY,
+        king_squares: CENTRALITY,
+        knight_mobility: [4, 4],
+        bishop_mobility: [5, 6],
+        rook_mobility: [3, 6],
+        queen_mobility: [1, 12],
+        bishop_pair: 50,
+        knight_pawn_adjustment: 6,
+        rook_pawn_adjustment: -12,
+        rook_redundancy: -16,
+        queen_rook_redundancy: -8,
+        rook_open_file: 25,
+        rook_semi_open_file: 12,
+        rook_on_seventh: 20,
+        connected_rooks: 10,
+        passed_pawn: [0, 10, 20, 40, 70, 120],
+        passed_pawn_king_distance: 2,
+        passed_pawn_rook_behind: 20,
+        knight_outpost: 20,
+        knight_on_rim: -15,
+        bishop_own_pawn: -4,
+        knight_tropism: 3,
+        bishop_tropism: 2,
+        rook_tropism: 2,
+        queen_tropism: 4,
+    };
+
+    // each parameter by the name it has in a file
+    fn fields(&mut self) -> Vec<(&'static str, &mut [isize])> {
+        vec![
+            ("pawn", slice::from_mut(&mut self.pawn)),
+            ("knight", slice::from_mut(&mut self.knight)),
+            ("bishop", slice::from_mut(&mut self.bishop)),
+            ("rook", slice::from_mut(&mut self.rook)),
+            ("queen", slice::from_mut(&mut self.queen)),
+            ("pawn_squares", &mut self.pawn_squares),
+            ("knight_squares", &mut self.knight_squares),
+            ("bishop_squares", &mut self.bishop_squares),
+            ("rook_squares", &mut self.rook_squares),
+            ("queen_squares", &mut self.queen_squares),
+            ("king_squares", &mut self.king_squares),
+            ("knight_mobility", &mut self.knight_mobility),
+            ("bishop_mobility", &mut self.bishop_mobility),
+            ("rook_mobility", &mut self.rook_mobility),
+            ("queen_mobility", &mut self.queen_mobility),
+            ("bishop_pair", slice::from_mut(&mut self.bishop_pair)),
+            (
+                "knight_pawn_adjustment",
+                slice::from_mut(&mut self.knight_pawn_adjustment),
+            ),
+            (
+                "rook_pawn_adjustment",
+                slice::from_mut(&mut self.rook_pawn_adjustment),
+            ),
+            (
+                "rook_redundancy",
+                slice::from_mut(&mut self.rook_redundancy),
+            ),
+            (
+                "queen_rook_redundancy",
+                slice::from_mut(&mut self.queen_rook_redundancy),
+            ),
+            ("rook_open_file", slice::from_mut(&mut self.rook_open_file)),
+            (
+                "rook_semi_open_file",
+                slice::from_mut(&mut self.rook_semi_open_file),
+            ),
+            (
+                "rook_on_seventh",
+                slice::from_mut(&mut self.rook_on_seventh),
+            ),
+            (
+                "connected_rooks",
+                slice::from_mut(&mut self.connected_rooks),
+            ),
+            ("passed_pawn", &mut self.passed_pawn),
+            (
+                "passed_pawn_king_distance",
+                slice::from_mut(&mut self.passed_pawn_king_distance),
+            ),
+            (
+                "passed_pawn_rook_behind",
+                slice::from_mut(&mut self.passed_pawn_rook_behind),
+            ),
+            ("knight_outpost", slice::from_mut(&mut self.knight_outpost)),
+            ("knight_on_rim", slice::from_mut(&mut self.knight_on_rim)),
+            (
+                "bishop_own_pawn",
+                slice::from_mut(&mut self.bishop_own_pawn),
+            ),
+            ("knight_tropism", slice::from_mut(&mut self.knight_tropism)),
+            ("bishop_tropism", slice::from_mut(&mut self.bishop_tropism)),
+            ("rook_tropism", slice::from_mut(&mut self.rook_tropism)),
+            ("queen_tropism", slice::from_mut(&mut self.queen_tropism)),
+        ]
+    }
+
+    // parameters left out of s keep their defaults
+    pub fn from_toml(s: &str) -> Result<EvalParams, ChessError> {
+        let mut params = EvalParams::default();
+        for (n, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, value) = match line.split_once('=') {
+                Some((name, value)) => (name.trim(), value.trim()),
+                None => parse_error!("[eval params] line {}: expected name = value", n + 1),
+            };
+            let list = value.strip_prefix('[').and_then(|v| v.strip_suffix(']'));
+            let mut values = Vec::new();
+            for v in list.unwrap_or(value).split(',') {
+                match v.trim().parse::<isize>() {
+                    Ok(v) => values.push(v),
+                    Err(_) => parse_error!("[eval params] line {}: bad number {}", n + 1, v),
+                }
+            }
+            let mut fields = params.fields();
+            let field = match fields.iter_mut().find(|(f, _)| *f == name) {
+                Some((_, field)) => field,
+                None => parse_error!("[eval params] line {}: unknown parameter {}", n + 1, name),
+            };
+            if field.len() != values.len() || (field.len() > 1) != list.is_some() {
+                parse_error!(
+                    "[eval params] line {}: {} takes {} values",
+                    n + 1,
+                    name,
+                    field.len()
+                );
+            }
+            field.copy_from_slice(&values);
+        }
+        Ok(params)
+    }
+
+    pub fn load(path: &str) -> Result<EvalParams, ChessError> {
+        match fs::read_to_string(path) {
+            Ok(s) => EvalParams::from_toml(s.as_str()),
+            Err(e) => parse_error!("[eval params] can't read {}: {}", path, e),
+        }
+    }
+
+    // in the format from_toml reads
+    pub fn to_toml(&self) -> String {
+        let mut params = *self;
+        let mut s = String::new();
+        for (name, values) in params.fields() {
+            let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+            if values.len() == 1 {
+                s += &format!("{} = {}\n", name, values[0]);
+            } else {
+                s += &format!("{} = [{}]\n", name, values.join(", "));
+            }
+        }
+        s
+    }
+}
+
+impl Default for EvalParams {
+    fn default() -> EvalParams {
+        EvalParams::DEFAULT
+    }
+}
+
+// the parameters Board::score uses
+static PARAMS: RwLock<EvalParams> = RwLock::new(EvalParams::DEFAULT);
+
+pub fn eval_params() -> EvalParams {
+    *PARAMS.read().unwrap()
+}
+
+// for the whole process. a search reads them once, when it starts
+pub fn set_eval_params(params: EvalParams) {
+    *PARAMS.write().unwrap() = params;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eval_params::EvalParams;
+
+    #[test]
+    fn params_from_toml() {
+        let params = EvalParams::from_toml(
+            "# experiment\n\
+             knight = 325\n\
+             \n\
+             rook_mobility = [2, 7]  # cheaper rooks\n\
+             passed_pawn = [0, 5, 10, 30, 60, 150]\n",
+        )
+        .unwrap();
+        assert_eq!(params.knight, 325);
+        assert_eq!(params.rook_mobility, [2, 7]);
+        assert_eq!(params.passed_pawn, [0, 5, 10, 30, 60, 150]);
+        assert_eq!(params.bishop, EvalParams::DEFAULT.bishop);
+
+        let mut squares = vec!["0"; 64];
+        squares[45] = "30";
+        let params =
+            EvalParams::from_toml(&format!("knight_squares = [{}]", squares.join(", "))).unwrap();
+        assert_eq!(params.knight_squares[45], 30);
+        assert_eq!(params.knight_squares[36], 0);
+        assert_eq!(params.king_squares, EvalParams::DEFAULT.king_squares);
+        assert!(EvalParams::from_toml("knight_squares = [1, 2, 3]").is_err());
+
+        let defaults = EvalParams::default().to_toml();
+        assert_eq!(
+            EvalParams::from_toml(&defaults).unwrap(),
+            EvalParams::DEFAULT
+        );
+
+        assert!(EvalParams::from_toml("kinght = 300").is_err());
+        assert!(EvalParams::from_toml("knight 300").is_err());
+        assert!(EvalParams::from_toml("knight = [300, 1]").is_err());
+        assert!(EvalParams::from_toml("rook_mobility = 3").is_err());
+        assert!(EvalParams::from_toml("queen = nine hundred").is_err());
+    }
+}
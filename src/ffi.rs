@@ -0,0 +1,464 @@
+// a c interface to the rules and the engine, for guis and apps in other languages. the
+// declarations are in include/combustion.h, which cbindgen can regenerate from this file.
+//
+// boards and engines are opaque handles, made and freed here. strings going in are nul
+// terminated utf-8; strings coming out are allocated here and given back to
+// combustion_string_free. moves are in coordinate notation, like e2e4 or e7e8q, and san
+// is accepted going in. a function that fails returns NULL or -1, and
+// combustion_last_error says why.
+
+// the safety requirements are the usual ones for c pointers, spelled out in the header
+#![allow(clippy::missing_safety_doc)]
+
+use crate::board::{Board, Draw, GameStatus};
+use crate::moves::{coordinates, Move};
+use crate::util::ChessError;
+
+#[cfg(feature = "engine")]
+use crate::board_alpha_beta::SearchResult;
+#[cfg(feature = "engine")]
+use crate::moves::line_coordinates;
+#[cfg(feature = "engine")]
+use crate::search_limits::SearchLimits;
+#[cfg(feature = "engine")]
+use crate::transposition_table::TranspositionTable;
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+#[cfg(feature = "engine")]
+use std::os::raw::c_void;
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+#[cfg(feature = "engine")]
+use std::sync::{Arc, RwLock};
+#[cfg(feature = "engine")]
+use std::time::Duration;
+
+pub struct CombustionBoard(Board);
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CombustionStatus {
+    InProgress = 0,
+    Checkmate = 1,
+    Stalemate = 2,
+    Check = 3,
+    DrawByFiftyMove = 4,
+    DrawByInsufficientMaterial = 5,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(e: impl ToString) {
+    let msg = CString::new(e.to_string().replace('\0', "")).unwrap();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(msg));
+}
+
+fn to_c_string(s: String) -> *mut c_char {
+    CString::new(s).unwrap().into_raw()
+}
+
+unsafe fn from_c_string<'a>(s: *const c_char) -> Result<&'a str, ChessError> {
+    if s.is_null() {
+        parse_error!("[ffi] null string");
+    }
+    match CStr::from_ptr(s).to_str() {
+        Ok(s) => Ok(s),
+        Err(e) => parse_error!("[ffi] {}", e),
+    }
+}
+
+// the legal move in b given in coordinate notation or san
+pub(crate) fn parse_move(b: &Board, s: &str) -> Result<Move, ChessError> {
+    let legal = b.legal_moves();
+    let s = s.trim();
+    if let Some(mv) = legal.iter().find(|mv| coordinates(mv, b) == s) {
+        return Ok(*mv);
+    }
+    Move::from_san(b, s)
+}
+
+// the message for the last call on this thread that failed, or NULL. it stays valid
+// until the next failure on this thread.
+#[no_mangle]
+pub extern "C" fn combustion_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |s| s.as_ptr()))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn combustion_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn combustion_board_new() -> *mut CombustionBoard {
+    Box::into_raw(Box::new(CombustionBoard(Board::initial())))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn combustion_board_from_fen(fen: *const c_char) -> *mut CombustionBoard {
+    match from_c_string(fen).and_then(Board::from_fen) {
+        Ok(b) => Box::into_raw(Box::new(CombustionBoard(b))),
+        Err(e) => {
+            set_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn combustion_board_clone(
+    board: *const CombustionBoard,
+) -> *mut CombustionBoard {
+    match board.as_ref() {
+        Some(CombustionBoard(b)) => Box::into_raw(Box::new(CombustionBoard(b.clone()))),
+        None => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn combustion_board_free(board: *mut CombustionBoard) {
+    if !board.is_null() {
+        drop(Box::from_raw(board));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn combustion_board_fen(board: *const CombustionBoard) -> *mut c_char {
+    match board.as_ref() {
+        Some(CombustionBoard(b)) => to_c_string(b.to_fen()),
+        None => ptr::null_mut(),
+    }
+}
+
+// the legal moves, in coordinate notation separated by spaces
+#[no_mangle]
+pub unsafe extern "C" fn combustion_board_legal_moves(
+    board: *const CombustionBoard,
+) -> *mut c_char {
+    match board.as_ref() {
+        Some(CombustionBoard(b)) => {
+            let moves: Vec<String> = b
+                .legal_moves()
+                .iter()
+                .map(|mv| coordinates(mv, b))
+                .collect();
+            to_c_string(moves.join(" "))
+        }
+        None => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn combustion_board_make_move(
+    board: *mut CombustionBoard,
+    mv: *const c_char,
+) -> c_int {
+    let CombustionBoard(b) = match board.as_mut() {
+        Some(board) => board,
+        None => return -1,
+    };
+    let res = from_c_string(mv)
+        .and_then(|mv| parse_move(b, mv))
+        .and_then(|mv| b.make_move(&mv));
+    match res {
+        Ok(next) => {
+            *b = next;
+            0
+        }
+        Err(e) => {
+            set_error(e);
+            -1
+        }
+    }
+}
+
+// a legal move in the board, given in coordinate notation or san, written in san
+#[no_mangle]
+pub unsafe extern "C" fn combustion_board_san(
+    board: *const CombustionBoard,
+    mv: *const c_char,
+) -> *mut c_char {
+    let CombustionBoard(b) = match board.as_ref() {
+        Some(board) => board,
+        None => return ptr::null_mut(),
+    };
+    match from_c_string(mv).and_then(|mv| parse_move(b, mv)) {
+        Ok(mv) => to_c_string(mv.to_san(b)),
+        Err(e) => {
+            set_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn combustion_board_status(
+    board: *const CombustionBoard,
+) -> CombustionStatus {
+    match board.as_ref().map(|CombustionBoard(b)| b.status()) {
+        Some(GameStatus::Check) => CombustionStatus::Check,
+        Some(GameStatus::Checkmate) => CombustionStatus::Checkmate,
+        Some(GameStatus::Stalemate) => CombustionStatus::Stalemate,
+        Some(GameStatus::DrawBy(Draw::FiftyMove)) => CombustionStatus::DrawByFiftyMove,
+        Some(GameStatus::DrawBy(Draw::InsufficientMaterial)) => {
+            CombustionStatus::DrawByInsufficientMaterial
+        }
+        // a board alone can't tell a repetition
+        Some(GameStatus::DrawBy(Draw::Repetition)) | Some(GameStatus::InProgress) | None => {
+            CombustionStatus::InProgress
+        }
+    }
+}
+
+// a search engine keeps its transposition table between searches, and can be stopped
+// from another thread while it searches
+#[cfg(feature = "engine")]
+pub struct CombustionEngine {
+    table: Arc<TranspositionTable>,
+    abort: Arc<RwLock<bool>>,
+}
+
+// when to stop searching, at whichever comes first. zero means no limit, and with no
+// limits at all the search goes on until combustion_engine_stop.
+#[cfg(feature = "engine")]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CombustionLimits {
+    pub depth: u32,
+    pub nodes: u64,
+    pub movetime_ms: u64,
+    // look only for a mate in at most this many moves
+    pub mate: u32,
+}
+
+// the progress of a search after each iteration. pv is only valid during the callback.
+#[cfg(feature = "engine")]
+#[repr(C)]
+#[derive(Debug)]
+pub struct CombustionInfo {
+    pub depth: u32,
+    pub seldepth: u32,
+    // centipawns for the side to move, or +-(100000 + n) for mate in n moves
+    pub score: i32,
+    pub nodes: u64,
+    pub time_ms: u64,
+    pub nps: u64,
+    pub pv: *const c_char,
+}
+
+#[cfg(feature = "engine")]
+pub type CombustionInfoCallback = extern "C" fn(info: *const CombustionInfo, user: *mut c_void);
+
+#[cfg(feature = "engine")]
+impl CombustionLimits {
+    fn to_search_limits(self) -> SearchLimits {
+        let mut limits = SearchLimits::default();
+        if self.depth > 0 {
+            // depth counts the root moves, as the info does, and the search doesn't
+            limits.depth = Some((self.depth as usize).saturating_sub(1));
+        }
+        if self.nodes > 0 {
+            limits.nodes = Some(self.nodes);
+        }
+        if self.movetime_ms > 0 {
+            limits.time = SearchLimits::move_time(Duration::from_millis(self.movetime_ms)).time;
+        }
+        if self.mate > 0 {
+            limits.mate = Some(self.mate as usize);
+        }
+        limits.infinite = self.depth == 0 && self.nodes == 0 && self.movetime_ms == 0;
+        limits
+    }
+}
+
+#[cfg(feature = "engine")]
+#[no_mangle]
+pub extern "C" fn combustion_engine_new() -> *mut CombustionEngine {
+    Box::into_raw(Box::new(CombustionEngine {
+        table: Arc::new(TranspositionTable::new()),
+        abort: Arc::new(RwLock::new(false)),
+    }))
+}
+
+#[cfg(feature = "engine")]
+#[no_mangle]
+pub unsafe extern "C" fn combustion_engine_free(engine: *mut CombustionEngine) {
+    if !engine.is_null() {
+        drop(Box::from_raw(engine));
+    }
+}
+
+// forget everything learned in earlier searches, as for a new game
+#[cfg(feature = "engine")]
+#[no_mangle]
+pub unsafe extern "C" fn combustion_engine_clear(engine: *const CombustionEngine) {
+    if let Some(engine) = engine.as_ref() {
+        engine.table.clear();
+    }
+}
+
+// the best move in the board, in coordinate notation. info, if not NULL, is called with
+// user after each iteration. blocks until a limit is reached or combustion_engine_stop.
+#[cfg(feature = "engine")]
+#[no_mangle]
+pub unsafe extern "C" fn combustion_engine_search(
+    engine: *const CombustionEngine,
+    board: *const CombustionBoard,
+    limits: *const CombustionLimits,
+    info: Option<CombustionInfoCallback>,
+    user: *mut c_void,
+) -> *mut c_char {
+    let (engine, CombustionBoard(b)) = match (engine.as_ref(), board.as_ref()) {
+        (Some(engine), Some(board)) => (engine, board),
+        _ => return ptr::null_mut(),
+    };
+    let limits = limits
+        .as_ref()
+        .copied()
+        .unwrap_or_default()
+        .to_search_limits();
+    *engine.abort.write().unwrap() = false;
+    engine.table.new_search();
+
+    let mut report = |res: &SearchResult| {
+        if let Some(info) = info {
+            let pv = CString::new(line_coordinates(b, &res.pv)).unwrap();
+            let res = CombustionInfo {
+                depth: res.depth as u32 + 1,
+                seldepth: res.seldepth as u32,
+                score: res.xboard_score() as i32,
+                nodes: res.nodes,
+                time_ms: res.time.as_millis() as u64,
+                nps: res.nps(),
+                pv: pv.as_ptr(),
+            };
+            info(&res, user);
+        }
+    };
+    let mut b = b.clone();
+    let res = b.search_with(
+        &limits,
+        Some(engine.abort.clone()),
+        Some(engine.table.clone()),
+        &mut report,
+    );
+    match res {
+        Ok(res) => to_c_string(coordinates(&res.mv, &b)),
+        Err(e) => {
+            set_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+// makes a search running on another thread return its best move so far
+#[cfg(feature = "engine")]
+#[no_mangle]
+pub unsafe extern "C" fn combustion_engine_stop(engine: *const CombustionEngine) {
+    if let Some(engine) = engine.as_ref() {
+        *engine.abort.write().unwrap() = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn c(s: &str) -> CString {
+        CString::new(s).unwrap()
+    }
+
+    unsafe fn take(s: *mut c_char) -> String {
+        assert!(!s.is_null());
+        let owned = CStr::from_ptr(s).to_str().unwrap().to_string();
+        combustion_string_free(s);
+        owned
+    }
+
+    #[test]
+    fn board_handles() {
+        unsafe {
+            let b = combustion_board_new();
+            assert_eq!(take(combustion_board_legal_moves(b)).split(' ').count(), 20);
+            assert_eq!(combustion_board_make_move(b, c("e2e4").as_ptr()), 0);
+            assert_eq!(combustion_board_make_move(b, c("c5").as_ptr()), 0);
+            assert_eq!(take(combustion_board_san(b, c("g1f3").as_ptr())), "Nf3");
+            assert_eq!(
+                take(combustion_board_fen(b)),
+                "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 0 2"
+            );
+
+            assert_eq!(combustion_board_make_move(b, c("e4e6").as_ptr()), -1);
+            assert!(!combustion_last_error().is_null());
+            let copy = combustion_board_clone(b);
+            combustion_board_free(b);
+            assert_eq!(combustion_board_status(copy), CombustionStatus::InProgress);
+            combustion_board_free(copy);
+
+            assert!(combustion_board_from_fen(c("not a fen").as_ptr()).is_null());
+            let mated = combustion_board_from_fen(c("4k3/4Q3/3P4/8/8/8/8/K7 b - - 0 1").as_ptr());
+            assert_eq!(combustion_board_status(mated), CombustionStatus::Checkmate);
+            combustion_board_free(mated);
+        }
+    }
+
+    #[cfg(feature = "engine")]
+    extern "C" fn count_iterations(info: *const CombustionInfo, user: *mut c_void) {
+        unsafe {
+            assert!(!CStr::from_ptr((*info).pv).to_bytes().is_empty());
+            *(user as *mut u32) += 1;
+        }
+    }
+
+    #[cfg(feature = "engine")]
+    extern "C" fn last_depth(info: *const CombustionInfo, user: *mut c_void) {
+        unsafe {
+            *(user as *mut u32) = (*info).depth;
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "engine")]
+    fn engine_search() {
+        unsafe {
+            let engine = combustion_engine_new();
+            let b = combustion_board_from_fen(c("4k3/8/3P4/6Q1/8/8/8/K7 w - - 0 1").as_ptr());
+            let limits = CombustionLimits {
+                depth: 3,
+                ..CombustionLimits::default()
+            };
+            let mut iterations = 0u32;
+            let mv = combustion_engine_search(
+                engine,
+                b,
+                &limits,
+                Some(count_iterations),
+                &mut iterations as *mut u32 as *mut c_void,
+            );
+            assert_eq!(take(mv), "g5e7");
+            assert!(iterations > 0);
+
+            // the depth reported is the depth asked for
+            let mut depth = 0u32;
+            let mv = combustion_engine_search(
+                engine,
+                b,
+                &limits,
+                Some(last_depth),
+                &mut depth as *mut u32 as *mut c_void,
+            );
+            take(mv);
+            assert_eq!(depth, limits.depth);
+
+            assert_eq!(combustion_board_make_move(b, c("Qe7#").as_ptr()), 0);
+            assert!(combustion_engine_search(engine, b, &limits, None, ptr::null_mut()).is_null());
+            combustion_board_free(b);
+            combustion_engine_free(engine);
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use crate::board::{Board, Draw, GameStatus};
+use crate::moves::Move;
+use crate::piece::Color;
+use crate::util::ChessError;
+
+use core::fmt;
+
+#[cfg(not(feature = "std"))]
+use crate::alloc_prelude::*;
+
+// how a game ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    // the winner
+    Checkmate(Color),
+    Stalemate,
+    FiftyMoveRule,
+    Repetition,
+    InsufficientMaterial,
+}
+
+impl GameResult {
+    // "1-0", "0-1" or "1/2-1/2"
+    pub fn score(&self) -> &'static str {
+        match *self {
+            GameResult::Checkmate(Color::White) => "1-0",
+            GameResult::Checkmate(Color::Black) => "0-1",
+            _ => "1/2-1/2",
+        }
+    }
+
+    pub fn reason(&self) -> &'static str {
+        match *self {
+            GameResult::Checkmate(_) => "Checkmate",
+            GameResult::Stalemate => "Stalemate",
+            GameResult::FiftyMoveRule => "Fifty move rule",
+            GameResult::Repetition => "Threefold repetition",
+            GameResult::InsufficientMaterial => "Insufficient material",
+        }
+    }
+}
+
+// as xboard wants it: 1-0 {Checkmate}
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {{{}}}", self.score(), self.reason())
+    }
+}
+
+// a game from some start position: every position reached, so moves can be taken back
+// and repetitions found without replaying from the start
+#[derive(Debug, Clone)]
+pub struct Game {
+    moves: Vec<Move>,
+    // positions[0] is the start, positions[i] the one after moves[i - 1]
+    positions: Vec<Board>,
+    hashes: Vec<u64>,
+}
+
+impl Game {
+    pub fn new() -> Game {
+        Game::from_board(Board::initial())
+    }
+
+    pub fn from_board(b: Board) -> Game {
+        Game {
+            moves: Vec::new(),
+            hashes: vec![b.zobrist()],
+            positions: vec![b],
+        }
+    }
+
+    pub fn from_fen(fen: &str) -> Result<Game, ChessError> {
+        Ok(Game::from_board(Board::from_fen(fen)?))
+    }
+
+    // the current position
+    pub fn board(&self) -> &Board {
+        self.positions.last().unwrap()
+    }
+
+    pub fn start(&self) -> &Board {
+        &self.positions[0]
+    }
+
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    // the start and every position after it, one more than there are moves
+    pub fn positions(&self) -> &[Board] {
+        &self.positions
+    }
+
+    // mv only has to name a legal move: the generator's version of it is played, with
+    // its flags, since moves parsed from elsewhere may miss some (like en passant)
+    pub fn make_move(&mut self, mv: &Move) -> Result<(), ChessError> {
+        let legal = self.board().legal_moves();
+        let mv = match legal.iter().find(|m| {
+            m.castle == mv.castle
+                && (mv.castle.is_some()
+                    || m.from == mv.from && m.to == mv.to && m.promotion == mv.promotion)
+        }) {
+            Some(m) => *m,
+            None => illegal_move_error!("[Game::make_move] {} is not a legal move", mv),
+        };
+        let b = self.board().make_move(&mv)?;
+        self.moves.push(mv);
+        self.hashes.push(b.zobrist());
+        self.positions.push(b);
+        Ok(())
+    }
+
+    // takes back the last move, if there is one
+    pub fn undo(&mut self) -> Option<Move> {
+        let mv = self.moves.pop()?;
+        self.positions.pop();
+        self.hashes.pop();
+        Some(mv)
+    }
+
+    // how many times the current position occurred before. only positions since the
+    // last capture or pawn move can be the same.
+    pub fn repetitions(&self) -> usize {
+        let current = *self.hashes.last().unwrap();
+        self.hashes
+            .iter()
+            .rev()
+            .take(self.board().halfmove_clock + 1)
+            .skip(2)
+            .step_by(2)
+            .filter(|&&h| h == current)
+            .count()
+    }
+
+    // the current position has occurred three times, so a draw can be claimed
+    pub fn threefold_repetition(&self) -> bool {
+        self.repetitions() >= 2
+    }
+
+    pub fn fifty_move_draw(&self) -> bool {
+        self.board().fifty_move_draw()
+    }
+
+    // the board's status, with draws by repetition, which only the game can see
+    pub fn status(&self) -> GameStatus {
+        match self.board().status() {
+            GameStatus::InProgress | GameStatus::Check if self.threefold_repetition() => {
+                GameStatus::DrawBy(Draw::Repetition)
+            }
+            status => status,
+        }
+    }
+
+    // how the game has ended, counting draws that can be claimed, or None if it goes on
+    pub fn result(&self) -> Option<GameResult> {
+        match self.status() {
+            GameStatus::Checkmate => {
+                Some(GameResult::Checkmate(self.board().color_to_move.other()))
+            }
+            GameStatus::Stalemate => Some(GameResult::Stalemate),
+            GameStatus::DrawBy(Draw::FiftyMove) => Some(GameResult::FiftyMoveRule),
+            GameStatus::DrawBy(Draw::Repetition) => Some(GameResult::Repetition),
+            GameStatus::DrawBy(Draw::InsufficientMaterial) => {
+                Some(GameResult::InsufficientMaterial)
+            }
+            GameStatus::InProgress | GameStatus::Check => None,
+        }
+    }
+}
+
+impl Default for Game {
+    fn default() -> Game {
+        Game::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::{Draw, GameStatus};
+    use crate::game::{Game, GameResult};
+    use crate::moves::Move;
+    use crate::piece::Color;
+    use crate::position::Pos;
+
+    #[test]
+    fn repetition_and_undo() {
+        let mut g = Game::new();
+        let shuffle = ["Ng1-f3", "Ng8-f6", "Nf3-g1", "Nf6-g8"];
+        for mv in shuffle.iter() {
+            g.make_move(&mv!(*mv)).unwrap();
+        }
+        assert_eq!(g.repetitions(), 1);
+        assert_eq!(g.result(), None);
+        for mv in shuffle.iter() {
+            g.make_move(&mv!(*mv)).unwrap();
+        }
+        assert!(g.threefold_repetition());
+        assert_eq!(g.result(), Some(GameResult::Repetition));
+        assert_eq!(g.moves().len(), 8);
+
+        // taking moves back returns to earlier positions
+        assert_eq!(g.undo(), Some(mv!("Nf6-g8")));
+        assert_eq!(g.repetitions(), 1);
+        assert_eq!(g.result(), None);
+        while g.undo().is_some() {}
+        assert!(g.board() == g.start());
+
+        // illegal moves are refused and change nothing
+        assert!(g.make_move(&mv!("Ke1-e3")).is_err());
+        assert!(g.moves().is_empty());
+    }
+
+    #[test]
+    fn moves_from_xboard() {
+        let mut g = Game::from_fen("4k3/8/8/3pP3/8/8/8/4K2R w K d6 0 1").unwrap();
+        let ep = Move::from_xboard_format("e5d6", g.board()).unwrap();
+        g.make_move(&ep).unwrap();
+        assert!(g.moves()[0].en_passant);
+        assert!(!g.board().occupied(pos!("d5")));
+        g.undo();
+        let castle = Move::from_xboard_format("e1g1", g.board()).unwrap();
+        g.make_move(&castle).unwrap();
+        assert!(g.board().occupied(pos!("f1")));
+    }
+
+    #[test]
+    fn results() {
+        let mut g = Game::from_fen("4k3/8/3P4/6Q1/8/8/8/K7 w - - 0 1").unwrap();
+        g.make_move(&mv!("Qg5-e7")).unwrap();
+        let res = g.result().unwrap();
+        assert_eq!(res, GameResult::Checkmate(Color::White));
+        assert_eq!(res.to_string(), "1-0 {Checkmate}");
+
+        let g = Game::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(g.result(), Some(GameResult::Stalemate));
+
+        let g = Game::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 100 80").unwrap();
+        assert_eq!(g.result().unwrap().to_string(), "1/2-1/2 {Fifty move rule}");
+
+        let g = Game::from_fen("4k3/8/8/8/8/2B5/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(g.status(), GameStatus::DrawBy(Draw::InsufficientMaterial));
+        assert_eq!(g.result(), Some(GameResult::InsufficientMaterial));
+
+        // a repetition with check is still a draw
+        let mut g = Game::from_fen("5k2/8/8/8/8/8/8/r6K w - - 0 1").unwrap();
+        for _ in 0..2 {
+            assert_eq!(g.status(), GameStatus::Check);
+            for mv in ["Kh1-h2", "Ra1-a2", "Kh2-h1", "Ra2-a1"].iter() {
+                g.make_move(&mv!(*mv)).unwrap();
+            }
+        }
+        assert_eq!(g.board().status(), GameStatus::Check);
+        assert_eq!(g.status(), GameStatus::DrawBy(Draw::Repetition));
+    }
+}
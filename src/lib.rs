@@ -1,17 +1,102 @@
+//! Chess rules and the combustion engine.
+//!
+//! The rules (boards, move generation, FEN, SAN, PGN and games) have no dependencies
+//! and can be used alone by turning off the default `engine` feature:
+//!
+//! ```toml
+//! combustion = { version = "0.1", default-features = false }
+//! ```
+//!
+//! The [`prelude`] has the types most programs need. The `engine` feature adds the
+//! search, evaluation, opening book and the programs built on them.
+//!
+//! Turning off the `std` feature as well leaves the rules needing only `alloc`, for
+//! embedded targets and sandboxes. Tablebases and the C interface need `std`.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+
+// the parts of the std prelude the rules use, for building without std
+#[cfg(not(feature = "std"))]
+mod alloc_prelude {
+    pub use alloc::string::{String, ToString};
+    pub use alloc::vec::Vec;
+    pub use alloc::{format, vec};
+}
+
 #[macro_use]
 pub mod macros;
 
-pub mod clock;
+// the rules
+pub mod epd;
+pub mod game;
+pub mod mailbox;
+pub mod move_list;
 pub mod moves;
+pub mod pgn;
 pub mod piece;
 pub mod position;
-pub mod threadpool;
-pub mod transposition_table;
+pub mod prelude;
+#[cfg(feature = "std")]
+pub mod tablebase;
 pub mod util;
+pub mod zobrist;
 
 pub mod board;
-pub mod board_alpha_beta;
+pub mod board_do_move;
 pub mod board_from_fen;
+pub mod board_legal;
 pub mod board_moves;
+pub mod board_perft;
+pub mod board_render;
+pub mod board_svg;
 pub mod board_tests;
 pub mod board_threatens;
+pub mod board_validate;
+
+#[cfg(feature = "std")]
+pub mod ffi;
+
+// the engine
+#[cfg(feature = "engine")]
+pub mod adjourned;
+#[cfg(feature = "engine")]
+pub mod adjudication;
+#[cfg(feature = "engine")]
+pub mod bench;
+#[cfg(feature = "engine")]
+pub mod book;
+#[cfg(feature = "engine")]
+pub mod clock;
+#[cfg(feature = "engine")]
+pub mod engine_options;
+#[cfg(feature = "engine")]
+pub mod eval_graph;
+#[cfg(feature = "engine")]
+pub mod eval_params;
+#[cfg(feature = "engine")]
+pub mod persona;
+#[cfg(feature = "engine")]
+pub mod search_limits;
+#[cfg(feature = "engine")]
+pub mod search_trace;
+#[cfg(feature = "engine")]
+pub mod sprt;
+#[cfg(feature = "engine")]
+pub mod threadpool;
+#[cfg(feature = "engine")]
+pub mod time_manager;
+#[cfg(feature = "engine")]
+pub mod transposition_table;
+
+#[cfg(feature = "engine")]
+pub mod board_alpha_beta;
+#[cfg(feature = "engine")]
+pub mod board_evaluate;
+#[cfg(feature = "engine")]
+pub mod board_pn_search;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -16,7 +16,7 @@ macro_rules! pos {
 macro_rules! parse_error(
     ($($arg:tt)*) => { {
         let s = format!($($arg)*);
-        return Err(ChessError::ParseError(s));
+        return Err($crate::util::ChessError::ParseError($crate::util::ParseError(s)));
     } }
 );
 
@@ -24,7 +24,7 @@ macro_rules! parse_error(
 macro_rules! illegal_move_error(
     ($($arg:tt)*) => { {
         let s = format!($($arg)*);
-        return Err(ChessError::IllegalMove(s));
+        return Err($crate::util::ChessError::IllegalMove($crate::util::MoveError(s)));
     } }
 );
 
@@ -32,7 +32,7 @@ macro_rules! illegal_move_error(
 macro_rules! board_state_error(
     ($($arg:tt)*) => { {
         let s = format!($($arg)*);
-        return Err(ChessError::BadBoardState(s));
+        return Err($crate::util::ChessError::BadBoardState(s));
     } }
 );
 
@@ -55,8 +55,8 @@ macro_rules! send(
         let mut stdout = ::std::io::stdout();
         // let mut stderr = ::std::io::stderr();
         let s = format!($($arg)*);
-        stdout.write(s.as_str().as_bytes()).expect("failed printing to stdout");
-        stdout.write("\n".as_bytes()).expect("failed printing to stdout");
+        stdout.write_all(s.as_str().as_bytes()).expect("failed printing to stdout");
+        stdout.write_all("\n".as_bytes()).expect("failed printing to stdout");
         // let debug = "sent message: \"".to_string() + &s + "\"\n";
         // stderr.write(debug.as_str().as_bytes()).expect("failed printing to stderr");
         // stderr.flush().expect("failed flushing stderr");
@@ -0,0 +1,127 @@
+use crate::piece::{Color, Piece, PieceType};
+use crate::position::Pos;
+
+// the pieces on the board, a byte for each square from a8 to h1. a byte holds the
+// piece type plus one in its low three bits and the color in the fourth, with 0 for an
+// empty square, so the whole board is 64 bytes and copies cheaply when making moves.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Mailbox([u8; 64]);
+
+const KINDS: [PieceType; 6] = [
+    PieceType::Pawn,
+    PieceType::Bishop,
+    PieceType::Knight,
+    PieceType::Rook,
+    PieceType::Queen,
+    PieceType::King,
+];
+
+const BLACK: u8 = 8;
+
+// every byte a square can hold, decoded
+static UNPACKED: [Option<Piece>; 16] = {
+    let mut table = [None; 16];
+    let mut i = 0;
+    while i < 6 {
+        table[i + 1] = Some(Piece {
+            kind: KINDS[i],
+            color: Color::White,
+        });
+        table[i + 1 + BLACK as usize] = Some(Piece {
+            kind: KINDS[i],
+            color: Color::Black,
+        });
+        i += 1;
+    }
+    table
+};
+
+#[inline]
+const fn pack(p: Option<Piece>) -> u8 {
+    match p {
+        None => 0,
+        Some(Piece { kind, color }) => {
+            let color = match color {
+                Color::White => 0,
+                Color::Black => BLACK,
+            };
+            kind as u8 + 1 + color
+        }
+    }
+}
+
+impl Mailbox {
+    pub const fn empty() -> Mailbox {
+        Mailbox([0; 64])
+    }
+
+    #[inline]
+    pub fn get(&self, pos: Pos) -> Option<Piece> {
+        UNPACKED[self.0[pos.index()] as usize & 15]
+    }
+
+    #[inline]
+    pub fn set(&mut self, pos: Pos, p: Option<Piece>) {
+        self.0[pos.index()] = pack(p);
+    }
+
+    // empties the square, returning what was on it
+    #[inline]
+    pub fn take(&mut self, pos: Pos) -> Option<Piece> {
+        let p = self.get(pos);
+        self.0[pos.index()] = 0;
+        p
+    }
+
+    // moves whatever is on from to to, returning what was on to before
+    #[inline]
+    pub fn shift(&mut self, from: Pos, to: Pos) -> Option<Piece> {
+        let captured = self.get(to);
+        self.0[to.index()] = self.0[from.index()];
+        self.0[from.index()] = 0;
+        captured
+    }
+
+    // every square with what is on it, from a8 to h1
+    pub fn iter(&self) -> impl Iterator<Item = (Pos, Option<Piece>)> + '_ {
+        Pos::all().map(move |pos| (pos, self.get(pos)))
+    }
+}
+
+impl Default for Mailbox {
+    fn default() -> Mailbox {
+        Mailbox::empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mailbox::{pack, Mailbox, UNPACKED};
+    use crate::piece::{Color, Piece, PieceType};
+    use crate::position::Pos;
+
+    #[test]
+    fn packing() {
+        assert_eq!(pack(None), 0);
+        assert_eq!(UNPACKED[0], None);
+        for c in Color::both() {
+            for kind in PieceType::all() {
+                let p = Some(Piece { kind, color: c });
+                assert_eq!(UNPACKED[pack(p) as usize], p);
+            }
+        }
+
+        let mut b = Mailbox::empty();
+        let rook = Some(Piece {
+            kind: PieceType::Rook,
+            color: Color::Black,
+        });
+        b.set(Pos::A8, rook);
+        assert_eq!(b.get(Pos::A8), rook);
+        assert_eq!(b.shift(Pos::A8, Pos::A1), None);
+        assert_eq!((b.get(Pos::A8), b.get(Pos::A1)), (None, rook));
+        assert_eq!(b.take(Pos::A1), rook);
+        assert_eq!(b, Mailbox::empty());
+        assert_eq!(core::mem::size_of::<Mailbox>(), 64);
+    }
+}
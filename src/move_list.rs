@@ -0,0 +1,102 @@
+use crate::moves::Move;
+use crate::piece::PieceType;
+use crate::position::Pos;
+
+use core::ops::{Deref, DerefMut};
+
+// no position has more than 218 legal moves, so this leaves room for pseudo-legal ones
+pub const MAX_MOVES: usize = 256;
+
+const EMPTY: Move = Move::normal(PieceType::Pawn, Pos::zero(), Pos::zero());
+
+// fixed capacity list of moves that lives on the stack, so generating moves in the search
+// doesn't allocate
+#[derive(Clone, Copy)]
+pub struct MoveList {
+    moves: [Move; MAX_MOVES],
+    len: usize,
+}
+
+impl MoveList {
+    pub fn new() -> MoveList {
+        MoveList {
+            moves: [EMPTY; MAX_MOVES],
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, mv: Move) {
+        assert!(self.len < MAX_MOVES, "[MoveList::push] list is full!");
+        self.moves[self.len] = mv;
+        self.len += 1;
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    // keep only the moves for which f is true, preserving their order
+    pub fn retain<F: FnMut(&Move) -> bool>(&mut self, mut f: F) {
+        let mut kept = 0;
+        for i in 0..self.len {
+            if f(&self.moves[i]) {
+                self.moves[kept] = self.moves[i];
+                kept += 1;
+            }
+        }
+        self.len = kept;
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> MoveList {
+        MoveList::new()
+    }
+}
+
+impl Deref for MoveList {
+    type Target = [Move];
+
+    fn deref(&self) -> &[Move] {
+        &self.moves[..self.len]
+    }
+}
+
+impl DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut [Move] {
+        &mut self.moves[..self.len]
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = core::slice::Iter<'a, Move>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::move_list::MoveList;
+    use crate::piece::PieceType;
+
+    #[test]
+    fn generate_into_move_list() {
+        let b = Board::initial();
+        let mut moves = MoveList::new();
+        b.generate_legal_moves(&mut moves);
+        assert_eq!(moves.len(), 20);
+        assert_eq!(moves.to_vec(), b.legal_moves());
+
+        // generating again starts from scratch
+        b.generate_legal_moves(&mut moves);
+        assert_eq!(moves.len(), 20);
+
+        moves.retain(|mv| mv.kind == PieceType::Knight);
+        assert_eq!(moves.len(), 4);
+        assert!(moves.iter().all(|mv| b.make_move(mv).is_ok()));
+    }
+}
@@ -1,10 +1,13 @@
-use crate::board::Board;
+use crate::board::{Board, GameStatus};
 use crate::piece::{Color, PieceType};
-use crate::position::Pos;
+use crate::position::{File, Pos, Rank};
 use crate::util::ChessError;
 
-use std::cmp::Ordering;
-use std::fmt;
+use core::cmp::Ordering;
+use core::fmt;
+
+#[cfg(not(feature = "std"))]
+use crate::alloc_prelude::*;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Castle {
@@ -24,28 +27,66 @@ pub struct Move {
 }
 
 impl Move {
+    // a piece moving to an empty square
+    pub const fn normal(kind: PieceType, from: Pos, to: Pos) -> Move {
+        Move {
+            kind,
+            from,
+            to,
+            takes: false,
+            en_passant: false,
+            promotion: None,
+            castle: None,
+        }
+    }
+
+    pub const fn capture(kind: PieceType, from: Pos, to: Pos) -> Move {
+        Move::normal(kind, from, to).capturing()
+    }
+
+    // a pawn moving to the last rank, without capturing
+    pub const fn promotion(from: Pos, to: Pos, promotion: PieceType) -> Move {
+        Move::normal(PieceType::Pawn, from, to).promoting(promotion)
+    }
+
+    // to is the square the pawn lands on, behind the one it takes
+    pub const fn en_passant(from: Pos, to: Pos) -> Move {
+        Move {
+            en_passant: true,
+            ..Move::capture(PieceType::Pawn, from, to)
+        }
+    }
+
+    // castling has no squares, since they depend on the side castling. see squares().
+    pub const fn castle(side: Castle) -> Move {
+        Move {
+            castle: Some(side),
+            ..Move::normal(PieceType::King, Pos::zero(), Pos::zero())
+        }
+    }
+
+    // the same move, capturing what is on its target square, as in
+    // Move::promotion(from, to, PieceType::Queen).capturing()
+    pub const fn capturing(self) -> Move {
+        Move {
+            takes: true,
+            ..self
+        }
+    }
+
+    pub const fn promoting(self, kind: PieceType) -> Move {
+        Move {
+            promotion: Some(kind),
+            ..self
+        }
+    }
+
     #[allow(dead_code)]
     pub fn from_algebra(s: &str) -> Result<Move, ChessError> {
         if s == "O-O" {
-            Ok(Move {
-                kind: PieceType::King,
-                from: Pos::zero(),
-                to: Pos::zero(),
-                takes: false,
-                en_passant: false,
-                promotion: None,
-                castle: Some(Castle::Kingside),
-            })
+            Ok(Move::castle(Castle::Kingside))
         } else if s == "O-O-O" {
-            Ok(Move {
-                kind: PieceType::King,
-                from: Pos::zero(),
-                to: Pos::zero(),
-                takes: false,
-                en_passant: false,
-                promotion: None,
-                castle: Some(Castle::Queenside),
-            })
+            Ok(Move::castle(Castle::Queenside))
         } else {
             let mut cs: Vec<char> = s.chars().collect();
             let kind = match cs[0] {
@@ -121,6 +162,17 @@ impl Move {
         )
     }
 
+    // the squares the piece moves from and to, the king's when castling
+    pub fn squares(&self, c: Color) -> (Pos, Pos) {
+        match (self.castle, c) {
+            (Some(Castle::Kingside), Color::White) => (Pos::E1, Pos::G1),
+            (Some(Castle::Queenside), Color::White) => (Pos::E1, Pos::C1),
+            (Some(Castle::Kingside), Color::Black) => (Pos::E8, Pos::G8),
+            (Some(Castle::Queenside), Color::Black) => (Pos::E8, Pos::C8),
+            (None, _) => (self.from, self.to),
+        }
+    }
+
     pub fn from_xboard_format(s: &str, b: &Board) -> Result<Move, ChessError> {
         let from = Pos::from_algebra(&s[0..2])?;
         let to = Pos::from_algebra(&s[2..4])?;
@@ -148,11 +200,9 @@ impl Move {
             }
         }
         let castle = if p.kind == PieceType::King {
-            if from == pos!("e1") && to == pos!("g1") || from == pos!("e8") && to == pos!("g8") {
+            if from == Pos::E1 && to == Pos::G1 || from == Pos::E8 && to == Pos::G8 {
                 Some(Castle::Kingside)
-            } else if from == pos!("e1") && to == pos!("c1")
-                || from == pos!("e8") && to == pos!("c8")
-            {
+            } else if from == Pos::E1 && to == Pos::C1 || from == Pos::E8 && to == Pos::C8 {
                 Some(Castle::Queenside)
             } else {
                 None
@@ -172,6 +222,161 @@ impl Move {
         };
         Ok(m)
     }
+
+    // the legal move in b written in standard algebraic notation, like Nf3, exd5, Rad1,
+    // e8=Q or O-O+. check and annotation suffixes are ignored.
+    pub fn from_san(b: &Board, s: &str) -> Result<Move, ChessError> {
+        let san = s;
+        let mut s = s.trim_end_matches(|c| "+#!?".contains(c));
+        let legal = b.legal_moves();
+        let castle = match s {
+            "O-O" | "0-0" => Some(Castle::Kingside),
+            "O-O-O" | "0-0-0" => Some(Castle::Queenside),
+            _ => None,
+        };
+        if castle.is_some() {
+            return match legal.into_iter().find(|mv| mv.castle == castle) {
+                Some(mv) => Ok(mv),
+                None => illegal_move_error!("[Move::from_san] {}: can't castle", san),
+            };
+        }
+
+        s = s.trim_end_matches("e.p.");
+        let kind = match s.chars().next() {
+            Some('B') => PieceType::Bishop,
+            Some('N') => PieceType::Knight,
+            Some('R') => PieceType::Rook,
+            Some('Q') => PieceType::Queen,
+            Some('K') => PieceType::King,
+            _ => PieceType::Pawn,
+        };
+        if kind != PieceType::Pawn {
+            s = &s[1..];
+        }
+        // the = is sometimes left out: e8Q
+        let promotion = match s.chars().last() {
+            Some('B') => Some(PieceType::Bishop),
+            Some('N') => Some(PieceType::Knight),
+            Some('R') => Some(PieceType::Rook),
+            Some('Q') => Some(PieceType::Queen),
+            _ => None,
+        };
+        if promotion.is_some() {
+            s = s[..s.len() - 1].trim_end_matches('=');
+        }
+        if s.len() < 2 || !s.is_char_boundary(s.len() - 2) {
+            parse_error!("[Move::from_san] no destination square: \"{}\"", san);
+        }
+        let to = Pos::from_algebra(&s[s.len() - 2..])?;
+        // whatever is left before the destination says where the piece comes from
+        let mut from = Vec::new();
+        for c in s[..s.len() - 2].chars() {
+            match c {
+                'a'..='h' | '1'..='8' => from.push(c),
+                'x' | ':' | '-' => {}
+                _ => parse_error!("[Move::from_san] unexpected {} in \"{}\"", c, san),
+            }
+        }
+        let candidates: Vec<Move> = legal
+            .into_iter()
+            .filter(|mv| {
+                mv.castle.is_none()
+                    && mv.kind == kind
+                    && mv.to == to
+                    && mv.promotion == promotion
+                    && from.iter().all(|&c| match c {
+                        'a'..='h' => File::from_char(c) == Some(mv.from.file()),
+                        _ => Rank::from_char(c) == Some(mv.from.rank()),
+                    })
+            })
+            .collect();
+        match candidates.len() {
+            1 => Ok(candidates[0]),
+            0 => illegal_move_error!("[Move::from_san] {}: no such legal move", san),
+            _ => illegal_move_error!("[Move::from_san] {}: ambiguous", san),
+        }
+    }
+
+    // in standard algebraic notation, as played in b: only as much of the source square
+    // as tells it apart from other legal moves, and + or # if it checks
+    pub fn to_san(&self, b: &Board) -> String {
+        let mut s = match self.castle {
+            Some(Castle::Kingside) => "O-O".to_string(),
+            Some(Castle::Queenside) => "O-O-O".to_string(),
+            None => {
+                let mut s = match self.kind {
+                    PieceType::Bishop => "B",
+                    PieceType::Knight => "N",
+                    PieceType::Rook => "R",
+                    PieceType::Queen => "Q",
+                    PieceType::King => "K",
+                    PieceType::Pawn => "",
+                }
+                .to_string();
+                let from = self.from.to_algebra();
+                if self.kind == PieceType::Pawn {
+                    if self.takes {
+                        s += &from[..1];
+                    }
+                } else {
+                    // the same kind of piece could also move there
+                    let others: Vec<Move> = b
+                        .legal_moves()
+                        .into_iter()
+                        .filter(|m| m.kind == self.kind && m.to == self.to && m.from != self.from)
+                        .collect();
+                    if others.iter().any(|m| m.from.file() == self.from.file()) {
+                        if others.iter().any(|m| m.from.rank() == self.from.rank()) {
+                            s += &from;
+                        } else {
+                            s += &from[1..];
+                        }
+                    } else if !others.is_empty() {
+                        s += &from[..1];
+                    }
+                }
+                if self.takes {
+                    s.push('x');
+                }
+                s += &self.to.to_algebra();
+                match self.promotion {
+                    Some(PieceType::Bishop) => s += "=B",
+                    Some(PieceType::Knight) => s += "=N",
+                    Some(PieceType::Rook) => s += "=R",
+                    Some(PieceType::Queen) => s += "=Q",
+                    _ => {}
+                }
+                s
+            }
+        };
+        if let Ok(after) = b.make_move(self) {
+            match after.status() {
+                GameStatus::Checkmate => s.push('#'),
+                _ if after.in_check(after.color_to_move) => s.push('+'),
+                _ => {}
+            }
+        }
+        s
+    }
+}
+
+// a move in coordinate notation, like e2e4, e1g1 or e7e8q
+pub fn coordinates(mv: &Move, b: &Board) -> String {
+    mv.to_xboard_format(b.color_to_move).replace("e.p.", "")
+}
+
+// a line of moves played from b, in coordinate notation separated by spaces
+pub fn line_coordinates(b: &Board, line: &[Move]) -> String {
+    let mut b = b.clone();
+    let mut moves = Vec::new();
+    for mv in line {
+        moves.push(coordinates(mv, &b));
+        b = match b.make_move(mv) {
+            Ok(next) => next,
+            Err(_) => break,
+        };
+    }
+    moves.join(" ")
 }
 
 impl fmt::Display for Move {
@@ -230,3 +435,39 @@ impl Ord for Move {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::moves::{Castle, Move};
+    use crate::piece::{Color, PieceType};
+    use crate::position::Pos;
+
+    #[test]
+    fn constructors() {
+        let (e2, e4, d5) = (pos!("e2"), pos!("e4"), pos!("d5"));
+        assert_eq!(Move::normal(PieceType::Pawn, e2, e4), mv!("e2-e4"));
+        assert_eq!(Move::capture(PieceType::Pawn, e4, d5), mv!("e4xd5"));
+        assert_eq!(
+            Move::capture(PieceType::Knight, pos!("f3"), e4),
+            mv!("Nf3xe4")
+        );
+        assert_eq!(
+            Move::promotion(pos!("a7"), pos!("a8"), PieceType::Queen),
+            mv!("a7-a8=Q")
+        );
+        assert_eq!(
+            Move::promotion(pos!("a7"), pos!("b8"), PieceType::Knight).capturing(),
+            mv!("a7xb8=N")
+        );
+        assert_eq!(
+            Move::normal(PieceType::Pawn, pos!("h2"), pos!("h1")).promoting(PieceType::Rook),
+            mv!("h2-h1=R")
+        );
+        assert_eq!(Move::en_passant(pos!("e5"), pos!("d6")), mv!("e5xd6e.p."));
+        assert_eq!(Move::castle(Castle::Queenside), mv!("O-O-O"));
+        assert_eq!(
+            Move::castle(Castle::Kingside).squares(Color::Black),
+            (pos!("e8"), pos!("g8"))
+        );
+    }
+}
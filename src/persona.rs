@@ -0,0 +1,158 @@
+use crate::board::{Board, GameStatus};
+use crate::board_alpha_beta::MATE;
+use crate::moves::Move;
+use crate::piece::PieceType;
+use crate::util::ChessError;
+use rand::{self, Rng};
+
+use std::fmt;
+
+// ways of picking a move without searching, from weakest to strongest. they make
+// opponents for beginners and baselines to test the engine against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Persona {
+    // any legal move
+    Random,
+    // takes the biggest piece it can, otherwise any move
+    Capturer,
+    // the move that leaves the best evaluation, looking no further
+    Greedy,
+    // greedy, but never lets itself be mated on the next move if it can help it
+    Careful,
+}
+
+impl Persona {
+    pub fn all() -> impl Iterator<Item = Persona> {
+        [
+            Persona::Random,
+            Persona::Capturer,
+            Persona::Greedy,
+            Persona::Careful,
+        ]
+        .iter()
+        .copied()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Persona::Random => "random",
+            Persona::Capturer => "capturer",
+            Persona::Greedy => "greedy",
+            Persona::Careful => "careful",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Persona, ChessError> {
+        match Persona::all().find(|p| p.name().eq_ignore_ascii_case(s.trim())) {
+            Some(p) => Ok(p),
+            None => parse_error!(
+                "[Persona::parse] {} isn't one of random, capturer, greedy or careful",
+                s
+            ),
+        }
+    }
+
+    // the move to play and what it thinks it is worth, for the side to move
+    pub fn pick(&self, b: &Board) -> Result<(Move, isize), ChessError> {
+        let ms = b.legal_moves();
+        if ms.is_empty() {
+            return Err(ChessError::GameOver(b.status()));
+        }
+        let scored: Vec<(Move, isize)> = match *self {
+            Persona::Random => ms.iter().map(|&mv| (mv, 0)).collect(),
+            Persona::Capturer => ms.iter().map(|&mv| (mv, victim(b, &mv))).collect(),
+            Persona::Greedy => ms.iter().map(|&mv| (mv, one_ply(b, &mv))).collect(),
+            Persona::Careful => {
+                let safe: Vec<(Move, isize)> = ms
+                    .iter()
+                    .filter(|mv| !allows_mate(b, mv))
+                    .map(|&mv| (mv, one_ply(b, &mv)))
+                    .collect();
+                if safe.is_empty() {
+                    ms.iter().map(|&mv| (mv, -MATE)).collect()
+                } else {
+                    safe
+                }
+            }
+        };
+
+        // any of the best, so the same position doesn't always get the same move
+        let best = scored.iter().map(|&(_, score)| score).max().unwrap();
+        let best: Vec<&(Move, isize)> = scored.iter().filter(|&&(_, s)| s == best).collect();
+        let i = rand::thread_rng().gen::<usize>() % best.len();
+        Ok(*best[i])
+    }
+}
+
+impl fmt::Display for Persona {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// what the move takes, in pawns
+fn victim(b: &Board, mv: &Move) -> isize {
+    if mv.en_passant {
+        return 1;
+    }
+    match b.piece(mv.to).map(|p| p.kind) {
+        Some(PieceType::Pawn) => 1,
+        Some(PieceType::Knight) | Some(PieceType::Bishop) => 3,
+        Some(PieceType::Rook) => 5,
+        Some(PieceType::Queen) => 9,
+        _ => 0,
+    }
+}
+
+// the evaluation after the move, for the side making it
+fn one_ply(b: &Board, mv: &Move) -> isize {
+    let after = b.make_move(mv).unwrap();
+    match after.status() {
+        GameStatus::Checkmate => MATE,
+        GameStatus::Stalemate | GameStatus::DrawBy(_) => 0,
+        _ => after.score(b.color_to_move),
+    }
+}
+
+// whether the opponent can mate straight after the move
+fn allows_mate(b: &Board, mv: &Move) -> bool {
+    let after = b.make_move(mv).unwrap();
+    after
+        .legal_moves()
+        .iter()
+        .any(|reply| after.make_move(reply).unwrap().status() == GameStatus::Checkmate)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::moves::Move;
+    use crate::persona::Persona;
+    use crate::util::ChessError;
+
+    #[test]
+    fn personas() {
+        for p in Persona::all() {
+            assert_eq!(Persona::parse(&p.to_string()), Ok(p));
+        }
+        match Persona::parse("grandmaster") {
+            Err(ChessError::ParseError(_)) => {}
+            res => panic!("parsed {:?}", res),
+        }
+
+        // the knight can take a pawn or the queen
+        let b = Board::from_fen("4k3/8/3q1p2/8/4N3/8/8/4K3 w - - 0 1").unwrap();
+        for _ in 0..10 {
+            assert_eq!(Persona::Capturer.pick(&b).unwrap().0, mv!("Ne4xd6"));
+            let legal = Persona::Random.pick(&b).unwrap().0;
+            assert!(b.legal_moves().contains(&legal));
+        }
+
+        // greedy takes the knight and is mated on the back rank, careful sees it coming
+        let b = Board::from_fen("4r1k1/5ppp/8/3n4/8/8/5PPP/3R2K1 w - - 0 1").unwrap();
+        assert_eq!(Persona::Greedy.pick(&b).unwrap().0, mv!("Rd1xd5"));
+        let (mv, score) = Persona::Careful.pick(&b).unwrap();
+        assert_ne!(mv, mv!("Rd1xd5"));
+        assert!(score < 100);
+    }
+}
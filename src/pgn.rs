@@ -0,0 +1,549 @@
+use crate::board::Board;
+use crate::game::Game;
+use crate::moves::Move;
+use crate::piece::Color;
+use crate::util::{ChessError, ParseError};
+
+use core::fmt;
+use core::iter::Peekable;
+
+#[cfg(not(feature = "std"))]
+use crate::alloc_prelude::*;
+
+// a game read from pgn
+#[derive(Debug, Clone)]
+pub struct PgnGame {
+    // tag pairs in the order they appear
+    pub headers: Vec<(String, String)>,
+    // the main line played out
+    pub game: Game,
+    // the main line again, with the comments, annotations and variations around it
+    pub line: PgnLine,
+    // as written after the moves: 1-0, 0-1, 1/2-1/2 or *
+    pub result: String,
+}
+
+// a sequence of moves from some position, the main line or a variation
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PgnLine {
+    // comments before the first move
+    pub comments: Vec<String>,
+    pub moves: Vec<PgnMove>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgnMove {
+    pub mv: Move,
+    // numeric annotation glyphs: $1 is a good move, $2 a mistake, and so on
+    pub nags: Vec<u32>,
+    // comments after the move
+    pub comments: Vec<String>,
+    // lines played instead of this move, from the position before it
+    pub variations: Vec<PgnLine>,
+}
+
+impl PgnMove {
+    fn new(mv: Move) -> PgnMove {
+        PgnMove {
+            mv,
+            nags: Vec::new(),
+            comments: Vec::new(),
+            variations: Vec::new(),
+        }
+    }
+}
+
+impl PgnGame {
+    // a game played out, to write as pgn. a fen header is added when it didn't start from
+    // the initial position.
+    pub fn from_game(game: Game, headers: Vec<(String, String)>, result: &str) -> PgnGame {
+        let mut headers = headers;
+        if *game.start() != Board::initial() {
+            headers.push(("SetUp".to_string(), "1".to_string()));
+            headers.push(("FEN".to_string(), game.start().to_fen()));
+        }
+        let line = PgnLine {
+            comments: Vec::new(),
+            moves: game.moves().iter().map(|&mv| PgnMove::new(mv)).collect(),
+        };
+        PgnGame {
+            headers,
+            game,
+            line,
+            result: result.to_string(),
+        }
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    // every position of the game, the start first, with the move played from it or None
+    // for the last
+    pub fn positions(&self) -> impl Iterator<Item = (&Board, Option<&Move>)> {
+        let moves = self.game.moves().iter().map(Some).chain(Some(None));
+        self.game.positions().iter().zip(moves)
+    }
+}
+
+// in pgn export format, so it reads back the same
+impl fmt::Display for PgnGame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, value) in &self.headers {
+            writeln!(f, "[{} \"{}\"]", name, value.replace('"', "\\\""))?;
+        }
+        writeln!(f)?;
+        let mut words = Vec::new();
+        write_line(&mut words, self.game.start(), &self.line);
+        words.push(self.result.clone());
+        // lines of at most 80 characters
+        let mut len = 0;
+        for word in words {
+            if len > 0 && len + 1 + word.len() > 80 {
+                writeln!(f)?;
+                len = 0;
+            } else if len > 0 {
+                write!(f, " ")?;
+                len += 1;
+            }
+            write!(f, "{}", word)?;
+            len += word.len();
+        }
+        writeln!(f)
+    }
+}
+
+// moves played from b as numbered san, like "23... Qxf2+ 24. Kh1 Qf1#"
+pub fn san_line(b: &Board, moves: &[Move]) -> String {
+    let line = PgnLine {
+        comments: Vec::new(),
+        moves: moves.iter().map(|&mv| PgnMove::new(mv)).collect(),
+    };
+    let mut words = Vec::new();
+    write_line(&mut words, b, &line);
+    words.join(" ")
+}
+
+// the words of line as pgn movetext, with the move numbers
+fn write_line(words: &mut Vec<String>, b: &Board, line: &PgnLine) {
+    for c in &line.comments {
+        words.push(format!("{{{}}}", c));
+    }
+    let mut b = b.clone();
+    let mut number = true;
+    for m in &line.moves {
+        if b.color_to_move == Color::White {
+            words.push(format!("{}.", b.move_number));
+        } else if number {
+            words.push(format!("{}...", b.move_number));
+        }
+        words.push(m.mv.to_san(&b));
+        words.extend(m.nags.iter().map(|n| format!("${}", n)));
+        words.extend(m.comments.iter().map(|c| format!("{{{}}}", c)));
+        for v in &m.variations {
+            let mut variation = Vec::new();
+            write_line(&mut variation, &b, v);
+            if let Some(first) = variation.first_mut() {
+                first.insert(0, '(');
+                variation.last_mut().unwrap().push(')');
+                words.append(&mut variation);
+            }
+        }
+        // black's move needs its number again after anything in between
+        number = !m.comments.is_empty() || !m.variations.is_empty();
+        b = b.make_move(&m.mv).unwrap();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token<'a> {
+    Header(&'a str, String),
+    Comment(&'a str),
+    Nag(u32),
+    VariationStart,
+    VariationEnd,
+    Move(&'a str),
+    Result(&'a str),
+}
+
+// splits pgn text into tokens, dropping move numbers
+struct Tokens<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn rest(&self) -> &'a str {
+        &self.s[self.pos..]
+    }
+
+    // up to the first c, which is skipped too
+    fn until(&mut self, c: char) -> &'a str {
+        let rest = self.rest();
+        let end = rest.find(c).unwrap_or(rest.len());
+        self.pos += (end + c.len_utf8()).min(rest.len());
+        &rest[..end]
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Result<Token<'a>, ChessError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            let c = trimmed.chars().next()?;
+            match c {
+                '[' => {
+                    self.pos += 1;
+                    let tag = self.until(']').trim();
+                    let (name, value) = match tag.find(char::is_whitespace) {
+                        Some(i) => (&tag[..i], tag[i..].trim()),
+                        None => (tag, ""),
+                    };
+                    let value = value.trim_matches('"').replace("\\\"", "\"");
+                    return Some(Ok(Token::Header(name, value)));
+                }
+                '{' => {
+                    self.pos += 1;
+                    return Some(Ok(Token::Comment(self.until('}').trim())));
+                }
+                ';' => {
+                    self.pos += 1;
+                    return Some(Ok(Token::Comment(self.until('\n').trim())));
+                }
+                // an escaped line
+                '%' if self.pos == 0 || self.s[..self.pos].ends_with('\n') => {
+                    self.until('\n');
+                }
+                '(' => {
+                    self.pos += 1;
+                    return Some(Ok(Token::VariationStart));
+                }
+                ')' => {
+                    self.pos += 1;
+                    return Some(Ok(Token::VariationEnd));
+                }
+                _ => {
+                    let end = trimmed
+                        .find(|c: char| c.is_whitespace() || "[]{}();".contains(c))
+                        .unwrap_or(trimmed.len());
+                    // a closing bracket with nothing open
+                    if end == 0 {
+                        self.pos += c.len_utf8();
+                        return Some(Err(ChessError::ParseError(ParseError(format!(
+                            "[pgn] unexpected {}",
+                            c
+                        )))));
+                    }
+                    let word = &trimmed[..end];
+                    self.pos += end;
+                    if let Some(nag) = word.strip_prefix('$') {
+                        return Some(match nag.parse() {
+                            Ok(n) => Ok(Token::Nag(n)),
+                            Err(_) => Err(ChessError::ParseError(ParseError(format!(
+                                "[pgn] bad annotation: {}",
+                                word
+                            )))),
+                        });
+                    }
+                    if ["1-0", "0-1", "1/2-1/2", "*"].contains(&word) {
+                        return Some(Ok(Token::Result(word)));
+                    }
+                    // move numbers, possibly run into the move: 12. 12... 12.e4. digits
+                    // without a dot are no move number, as in castling written 0-0
+                    let word = match word.find(|c: char| !c.is_ascii_digit()) {
+                        Some(i) if i > 0 && word[i..].starts_with('.') => {
+                            word[i..].trim_start_matches('.')
+                        }
+                        Some(_) => word,
+                        None => "",
+                    };
+                    if !word.is_empty() {
+                        return Some(Ok(Token::Move(word)));
+                    }
+                }
+            }
+        }
+    }
+}
+
+// parses games one after another from pgn text. a game with an error is returned as
+// that error, and reading goes on with the next one.
+pub struct PgnReader<'a> {
+    tokens: Peekable<Tokens<'a>>,
+    ngames: usize,
+}
+
+pub fn read_pgn(s: &str) -> PgnReader<'_> {
+    PgnReader {
+        tokens: Tokens { s, pos: 0 }.peekable(),
+        ngames: 0,
+    }
+}
+
+// the nag a move suffix like ! or ?! stands for
+fn suffix_nag(san: &str) -> Option<u32> {
+    let start = san.find(['!', '?'])?;
+    match &san[start..] {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    }
+}
+
+impl<'a> PgnReader<'a> {
+    fn skip_game(&mut self) {
+        for token in self.tokens.by_ref() {
+            if let Ok(Token::Result(_)) = token {
+                break;
+            }
+        }
+    }
+
+    // the moves from b up to the end of the variation, or of the game if not in one,
+    // and the result that ended the game
+    fn read_line(
+        &mut self,
+        b: &Board,
+        variation: bool,
+    ) -> Result<(PgnLine, Option<&'a str>), ChessError> {
+        let mut line = PgnLine::default();
+        let mut b = b.clone();
+        let mut before = b.clone();
+        loop {
+            let token = match self.tokens.peek() {
+                None if variation => parse_error!("[pgn] unclosed variation"),
+                // the last game, or one followed by the next game's headers, may lack its
+                // result
+                None | Some(Ok(Token::Header(..))) if !variation => {
+                    return Ok((line, Some("*")));
+                }
+                _ => self.tokens.next().unwrap()?,
+            };
+            match token {
+                Token::Move(san) => {
+                    let mut m = PgnMove::new(Move::from_san(&b, san)?);
+                    m.nags.extend(suffix_nag(san));
+                    before = b;
+                    b = before.make_move(&m.mv)?;
+                    line.moves.push(m);
+                }
+                Token::Comment(c) => match line.moves.last_mut() {
+                    Some(m) => m.comments.push(c.to_string()),
+                    None => line.comments.push(c.to_string()),
+                },
+                Token::Nag(n) => match line.moves.last_mut() {
+                    Some(m) => m.nags.push(n),
+                    None => parse_error!("[pgn] ${} before any move", n),
+                },
+                Token::VariationStart => {
+                    let (v, _) = self.read_line(&before, true)?;
+                    match line.moves.last_mut() {
+                        Some(m) => m.variations.push(v),
+                        None => parse_error!("[pgn] variation before any move"),
+                    }
+                }
+                Token::VariationEnd if variation => return Ok((line, None)),
+                Token::VariationEnd => parse_error!("[pgn] unmatched )"),
+                Token::Result(_) if variation => parse_error!("[pgn] result in a variation"),
+                Token::Result(result) => return Ok((line, Some(result))),
+                Token::Header(name, _) => parse_error!("[pgn] {} tag in a variation", name),
+            }
+        }
+    }
+
+    fn next_game(&mut self) -> Option<Result<PgnGame, ChessError>> {
+        let mut headers = Vec::new();
+        while let Some(Ok(Token::Header(..))) = self.tokens.peek() {
+            if let Some(Ok(Token::Header(name, value))) = self.tokens.next() {
+                headers.push((name.to_string(), value));
+            }
+        }
+        if headers.is_empty() && self.tokens.peek().is_none() {
+            return None;
+        }
+        Some(self.game_after(headers))
+    }
+
+    fn game_after(&mut self, headers: Vec<(String, String)>) -> Result<PgnGame, ChessError> {
+        let mut game = match headers.iter().find(|(n, _)| n == "FEN") {
+            Some((_, fen)) => Game::from_fen(fen)?,
+            None => Game::new(),
+        };
+        let (line, result) = self.read_line(game.board(), false)?;
+        for m in &line.moves {
+            game.make_move(&m.mv)?;
+        }
+        Ok(PgnGame {
+            headers,
+            game,
+            line,
+            result: result.unwrap_or("*").to_string(),
+        })
+    }
+}
+
+impl<'a> Iterator for PgnReader<'a> {
+    type Item = Result<PgnGame, ChessError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let res = match self.next_game()? {
+            Ok(game) => Ok(game),
+            Err(e) => {
+                self.skip_game();
+                Err(ChessError::ParseError(ParseError(format!(
+                    "[pgn] game {}: {}",
+                    self.ngames + 1,
+                    e
+                ))))
+            }
+        };
+        self.ngames += 1;
+        Some(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::game::Game;
+    use crate::moves::Move;
+    use crate::pgn::{read_pgn, san_line, PgnGame};
+
+    const PGN: &str = r#"[Event "Casual"]
+[White "Morphy"]
+[Black "Duke of Brunswick and Count Isouard"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 d6 3. d4 Bg4 {the bishop is soon lost} 4. dxe5 Bxf3 5. Qxf3 dxe5
+6. Bc4 Nf6 7. Qb3 Qe7 8. Nc3 c6 9. Bg5 b5 $2 10. Nxb5 cxb5 11. Bxb5+ Nbd7 12. O-O-O
+Rd8 13. Rxd7 Rxd7 14. Rd1 Qe6 15. Bxd7+ Nxd7 (15... Qxd7 16. Qb8+) 16. Qb8+ Nxb8
+17. Rd8# 1-0
+
+[Event "Setup"]
+[SetUp "1"]
+[FEN "4k3/P7/8/8/8/8/8/4K3 w - - 0 1"]
+
+1. a8=Q+ Kd7 *
+
+[Event "Broken"]
+
+1. e4 Nf6 2. Ke3 1/2-1/2
+
+1.d4 d5 2.c4"#;
+
+    #[test]
+    fn read_games() {
+        let games: Vec<_> = read_pgn(PGN).collect();
+        assert_eq!(games.len(), 4);
+
+        let opera = games[0].as_ref().unwrap();
+        assert_eq!(opera.header("White"), Some("Morphy"));
+        assert_eq!(opera.result, "1-0");
+        assert_eq!(opera.game.moves().len(), 33);
+        assert_eq!(opera.game.moves()[22], Move::from_algebra("O-O-O").unwrap());
+        assert_eq!(opera.positions().count(), 34);
+        assert!(opera.game.result().is_some());
+
+        let setup = games[1].as_ref().unwrap();
+        assert_eq!(setup.result, "*");
+        assert_eq!(
+            setup.game.moves()[0].promotion,
+            Some(crate::piece::PieceType::Queen)
+        );
+
+        assert!(games[2].is_err());
+        let unfinished = games[3].as_ref().unwrap();
+        assert_eq!(unfinished.game.moves().len(), 3);
+    }
+
+    #[test]
+    fn stray_closing_brackets() {
+        let games: Vec<_> = read_pgn("1. e4 } e5 *\n\n1. d4 ] d5 *\n\n1. c4 c5 *").collect();
+        assert_eq!(games.len(), 3);
+        assert!(games[0].is_err());
+        assert!(games[1].is_err());
+        assert_eq!(games[2].as_ref().unwrap().game.moves().len(), 2);
+    }
+
+    #[test]
+    fn castling_spellings() {
+        for &(short, long) in [("O-O", "O-O-O"), ("0-0", "0-0-0")].iter() {
+            let pgn = format!(
+                "1. e4 e5 2. Nf3 Nf6 3. Bc4 Bc5 4. {} {} *\n\n\
+                 1. d4 d5 2. Nc3 Nc6 3. Bf4 Bf5 4. Qd2 Qd7 5. {} {} *",
+                short, short, long, long
+            );
+            let games: Vec<_> = read_pgn(&pgn).collect();
+            let kingside = games[0].as_ref().unwrap().game.moves();
+            assert_eq!(kingside[6..], [Move::from_algebra("O-O").unwrap(); 2]);
+            let queenside = games[1].as_ref().unwrap().game.moves();
+            assert_eq!(queenside[8..], [Move::from_algebra("O-O-O").unwrap(); 2]);
+        }
+    }
+
+    #[test]
+    fn annotations_and_variations() {
+        let pgn = r#"[Event "Annotated"]
+
+{Open games} 1. e4! e5 2. Nf3 (2. f4 exf4 (2... d5) 3. Nf3 $5 {the king's gambit})
+2... Nc6 $1 {developing} 3. Bb5 a6?! 1/2-1/2"#;
+        let game = read_pgn(pgn).next().unwrap().unwrap();
+        let line = &game.line;
+        assert_eq!(line.comments, vec!["Open games"]);
+        assert_eq!(line.moves.len(), 6);
+        assert_eq!(line.moves[0].nags, vec![1]);
+        assert_eq!(line.moves[5].nags, vec![6]);
+        assert_eq!(line.moves[3].comments, vec!["developing"]);
+
+        let gambit = &line.moves[2].variations[0];
+        assert_eq!(gambit.moves.len(), 3);
+        assert_eq!(gambit.moves[0].mv, mv!("f2-f4"));
+        assert_eq!(gambit.moves[1].variations[0].moves[0].mv, mv!("d7-d5"));
+        assert_eq!(gambit.moves[2].nags, vec![5]);
+        assert_eq!(gambit.moves[2].comments, vec!["the king's gambit"]);
+        assert_eq!(game.game.moves().len(), 6);
+
+        // writing the game out and reading it back loses nothing
+        let written = game.to_string();
+        let again = read_pgn(&written).next().unwrap().unwrap();
+        assert_eq!(again.headers, game.headers);
+        assert_eq!(again.line, game.line);
+        assert_eq!(again.result, "1/2-1/2");
+        assert!(written
+            .replace('\n', " ")
+            .contains("2. Nf3 (2. f4 exf4 (2... d5) 3. Nf3 $5 {the king's gambit}) 2... Nc6"));
+
+        // a game played out writes the same way
+        let played = PgnGame::from_game(game.game.clone(), game.headers.clone(), "1/2-1/2");
+        let again = read_pgn(&played.to_string()).next().unwrap().unwrap();
+        assert_eq!(again.game.moves(), game.game.moves());
+        let setup = Game::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        let written = PgnGame::from_game(setup, Vec::new(), "*").to_string();
+        assert!(written.starts_with("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/4K2R w K - 0 1\"]"));
+        assert_eq!(read_pgn(&written).next().unwrap().unwrap().result, "*");
+
+        // unbalanced variations are errors
+        assert!(read_pgn("1. e4 (1. d4 *").next().unwrap().is_err());
+        assert!(read_pgn("1. e4 ) *").next().unwrap().is_err());
+    }
+
+    #[test]
+    fn san_lines() {
+        let b = Board::from_fen("6k1/5ppp/8/8/8/8/5PPP/R5K1 b - - 3 20").unwrap();
+        let moves = [mv!("h7-h6"), mv!("Ra1-a8"), mv!("Kg8-h7")];
+        assert_eq!(san_line(&b, &moves), "20... h6 21. Ra8+ Kh7");
+        assert_eq!(san_line(&Board::initial(), &[mv!("e2-e4")]), "1. e4");
+        assert_eq!(san_line(&b, &[]), "");
+    }
+}
@@ -1,5 +1,5 @@
-use std::cmp::Ordering;
-use std::fmt;
+use core::cmp::Ordering;
+use core::fmt;
 
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
 pub enum Color {
@@ -8,6 +8,11 @@ pub enum Color {
 }
 
 impl Color {
+    // white then black
+    pub fn both() -> impl Iterator<Item = Color> {
+        [Color::White, Color::Black].iter().copied()
+    }
+
     pub fn other(&self) -> Color {
         match *self {
             Color::White => Color::Black,
@@ -96,7 +101,61 @@ impl fmt::Display for Piece {
     }
 }
 
+impl Piece {
+    // a letter as in fen, like N for a white knight, or a unicode chess symbol
+    pub fn from_char(c: char) -> Option<Piece> {
+        let (kind, color) = match c {
+            'K' | '♔' => (PieceType::King, Color::White),
+            'Q' | '♕' => (PieceType::Queen, Color::White),
+            'R' | '♖' => (PieceType::Rook, Color::White),
+            'B' | '♗' => (PieceType::Bishop, Color::White),
+            'N' | '♘' => (PieceType::Knight, Color::White),
+            'P' | '♙' => (PieceType::Pawn, Color::White),
+            'k' | '♚' => (PieceType::King, Color::Black),
+            'q' | '♛' => (PieceType::Queen, Color::Black),
+            'r' | '♜' => (PieceType::Rook, Color::Black),
+            'b' | '♝' => (PieceType::Bishop, Color::Black),
+            'n' | '♞' => (PieceType::Knight, Color::Black),
+            'p' | '♟' => (PieceType::Pawn, Color::Black),
+            _ => return None,
+        };
+        Some(Piece { kind, color })
+    }
+
+    // the unicode chess symbol, like ♘ for a white knight
+    pub fn glyph(&self) -> char {
+        match (self.color, self.kind) {
+            (Color::White, PieceType::King) => '♔',
+            (Color::White, PieceType::Queen) => '♕',
+            (Color::White, PieceType::Rook) => '♖',
+            (Color::White, PieceType::Bishop) => '♗',
+            (Color::White, PieceType::Knight) => '♘',
+            (Color::White, PieceType::Pawn) => '♙',
+            (Color::Black, PieceType::King) => '♚',
+            (Color::Black, PieceType::Queen) => '♛',
+            (Color::Black, PieceType::Rook) => '♜',
+            (Color::Black, PieceType::Bishop) => '♝',
+            (Color::Black, PieceType::Knight) => '♞',
+            (Color::Black, PieceType::Pawn) => '♟',
+        }
+    }
+}
+
 impl PieceType {
+    // from the pawn up to the king
+    pub fn all() -> impl Iterator<Item = PieceType> {
+        [
+            PieceType::Pawn,
+            PieceType::Knight,
+            PieceType::Bishop,
+            PieceType::Rook,
+            PieceType::Queen,
+            PieceType::King,
+        ]
+        .iter()
+        .copied()
+    }
+
     // used for sorting moves
     fn order(&self) -> usize {
         match *self {
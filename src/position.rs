@@ -1,16 +1,327 @@
+use crate::piece::Color;
 use crate::util::{from_algebra, to_algebra, ChessError};
 
-use std::fmt;
+use core::fmt;
 
+#[cfg(not(feature = "std"))]
+use crate::alloc_prelude::*;
+
+// a square, as its index from 0 for a8 to 63 for h1
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
 pub struct Pos(usize);
 
+// the columns of the board, from white's left
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub enum File {
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+}
+
+// the rows of the board, from white's side
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+pub enum Rank {
+    First,
+    Second,
+    Third,
+    Fourth,
+    Fifth,
+    Sixth,
+    Seventh,
+    Eighth,
+}
+
+impl File {
+    pub const ALL: [File; 8] = [
+        File::A,
+        File::B,
+        File::C,
+        File::D,
+        File::E,
+        File::F,
+        File::G,
+        File::H,
+    ];
+
+    // 0 for the a file
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn from_index(ix: usize) -> Option<File> {
+        File::ALL.get(ix).copied()
+    }
+
+    pub fn from_char(c: char) -> Option<File> {
+        match c {
+            'a'..='h' => File::from_index(c as usize - 'a' as usize),
+            _ => None,
+        }
+    }
+
+    pub fn to_char(self) -> char {
+        (b'a' + self as u8) as char
+    }
+
+    // the file on the other side of the board, h for a
+    pub fn flip(self) -> File {
+        File::ALL[7 - self.index()]
+    }
+
+    // the squares of the file, from the first rank up
+    pub fn squares(self) -> impl Iterator<Item = Pos> {
+        Rank::ALL.iter().map(move |&rank| Pos::at(self, rank))
+    }
+}
+
+impl Rank {
+    pub const ALL: [Rank; 8] = [
+        Rank::First,
+        Rank::Second,
+        Rank::Third,
+        Rank::Fourth,
+        Rank::Fifth,
+        Rank::Sixth,
+        Rank::Seventh,
+        Rank::Eighth,
+    ];
+
+    // 0 for the first rank
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn from_index(ix: usize) -> Option<Rank> {
+        Rank::ALL.get(ix).copied()
+    }
+
+    pub fn from_char(c: char) -> Option<Rank> {
+        match c {
+            '1'..='8' => Rank::from_index(c as usize - '1' as usize),
+            _ => None,
+        }
+    }
+
+    pub fn to_char(self) -> char {
+        (b'1' + self as u8) as char
+    }
+
+    // the rank on the other side of the board, the eighth for the first
+    pub fn flip(self) -> Rank {
+        Rank::ALL[7 - self.index()]
+    }
+
+    // the rank as c sees it, so the seventh is where either side's pawns promote from
+    pub fn relative_to(self, c: Color) -> Rank {
+        match c {
+            Color::White => self,
+            Color::Black => self.flip(),
+        }
+    }
+
+    // the squares of the rank, from the a file across
+    pub fn squares(self) -> impl Iterator<Item = Pos> {
+        File::ALL.iter().map(move |&file| Pos::at(file, self))
+    }
+}
+
+impl fmt::Display for File {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+impl fmt::Display for Rank {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+// a set of squares, bit i for the square with index i
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Squares(u64);
+
+impl Squares {
+    pub fn insert(&mut self, pos: Pos) {
+        self.0 |= 1 << pos.0;
+    }
+
+    pub fn contains(&self, pos: Pos) -> bool {
+        self.0 & 1 << pos.0 != 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+}
+
+// in order of index, so from a8 to h1
+impl Iterator for Squares {
+    type Item = Pos;
+
+    fn next(&mut self) -> Option<Pos> {
+        if self.0 == 0 {
+            return None;
+        }
+        let ix = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+        Some(Pos(ix))
+    }
+}
+
+// the step from a towards b, if they share a rank, file or diagonal
+const fn step(a: usize, b: usize) -> Option<(isize, isize)> {
+    let dv = (b / 8) as isize - (a / 8) as isize;
+    let dh = (b % 8) as isize - (a % 8) as isize;
+    if a == b || (dv != 0 && dh != 0 && dv.abs() != dh.abs()) {
+        None
+    } else {
+        Some((dv.signum(), dh.signum()))
+    }
+}
+
+const fn on_board(rank: isize, file: isize) -> bool {
+    rank >= 0 && rank < 8 && file >= 0 && file < 8
+}
+
+// squares strictly between each pair of aligned squares
+static BETWEEN: [[u64; 64]; 64] = {
+    let mut table = [[0; 64]; 64];
+    let mut a = 0;
+    while a < 64 {
+        let mut b = 0;
+        while b < 64 {
+            if let Some((dv, dh)) = step(a, b) {
+                let (mut rank, mut file) = ((a / 8) as isize + dv, (a % 8) as isize + dh);
+                while (rank * 8 + file) as usize != b {
+                    table[a][b] |= 1 << (rank * 8 + file);
+                    rank += dv;
+                    file += dh;
+                }
+            }
+            b += 1;
+        }
+        a += 1;
+    }
+    table
+};
+
+// the whole line through each pair of aligned squares, from edge to edge
+static LINE: [[u64; 64]; 64] = {
+    let mut table = [[0; 64]; 64];
+    let mut a = 0;
+    while a < 64 {
+        let mut b = 0;
+        while b < 64 {
+            if let Some((dv, dh)) = step(a, b) {
+                let (mut rank, mut file) = ((a / 8) as isize, (a % 8) as isize);
+                while on_board(rank - dv, file - dh) {
+                    rank -= dv;
+                    file -= dh;
+                }
+                while on_board(rank, file) {
+                    table[a][b] |= 1 << (rank * 8 + file);
+                    rank += dv;
+                    file += dh;
+                }
+            }
+            b += 1;
+        }
+        a += 1;
+    }
+    table
+};
+
 impl Pos {
+    pub const A8: Pos = Pos(0);
+    pub const B8: Pos = Pos(1);
+    pub const C8: Pos = Pos(2);
+    pub const D8: Pos = Pos(3);
+    pub const E8: Pos = Pos(4);
+    pub const F8: Pos = Pos(5);
+    pub const G8: Pos = Pos(6);
+    pub const H8: Pos = Pos(7);
+    pub const A7: Pos = Pos(8);
+    pub const B7: Pos = Pos(9);
+    pub const C7: Pos = Pos(10);
+    pub const D7: Pos = Pos(11);
+    pub const E7: Pos = Pos(12);
+    pub const F7: Pos = Pos(13);
+    pub const G7: Pos = Pos(14);
+    pub const H7: Pos = Pos(15);
+    pub const A6: Pos = Pos(16);
+    pub const B6: Pos = Pos(17);
+    pub const C6: Pos = Pos(18);
+    pub const D6: Pos = Pos(19);
+    pub const E6: Pos = Pos(20);
+    pub const F6: Pos = Pos(21);
+    pub const G6: Pos = Pos(22);
+    pub const H6: Pos = Pos(23);
+    pub const A5: Pos = Pos(24);
+    pub const B5: Pos = Pos(25);
+    pub const C5: Pos = Pos(26);
+    pub const D5: Pos = Pos(27);
+    pub const E5: Pos = Pos(28);
+    pub const F5: Pos = Pos(29);
+    pub const G5: Pos = Pos(30);
+    pub const H5: Pos = Pos(31);
+    pub const A4: Pos = Pos(32);
+    pub const B4: Pos = Pos(33);
+    pub const C4: Pos = Pos(34);
+    pub const D4: Pos = Pos(35);
+    pub const E4: Pos = Pos(36);
+    pub const F4: Pos = Pos(37);
+    pub const G4: Pos = Pos(38);
+    pub const H4: Pos = Pos(39);
+    pub const A3: Pos = Pos(40);
+    pub const B3: Pos = Pos(41);
+    pub const C3: Pos = Pos(42);
+    pub const D3: Pos = Pos(43);
+    pub const E3: Pos = Pos(44);
+    pub const F3: Pos = Pos(45);
+    pub const G3: Pos = Pos(46);
+    pub const H3: Pos = Pos(47);
+    pub const A2: Pos = Pos(48);
+    pub const B2: Pos = Pos(49);
+    pub const C2: Pos = Pos(50);
+    pub const D2: Pos = Pos(51);
+    pub const E2: Pos = Pos(52);
+    pub const F2: Pos = Pos(53);
+    pub const G2: Pos = Pos(54);
+    pub const H2: Pos = Pos(55);
+    pub const A1: Pos = Pos(56);
+    pub const B1: Pos = Pos(57);
+    pub const C1: Pos = Pos(58);
+    pub const D1: Pos = Pos(59);
+    pub const E1: Pos = Pos(60);
+    pub const F1: Pos = Pos(61);
+    pub const G1: Pos = Pos(62);
+    pub const H1: Pos = Pos(63);
+
+    // by row from the top, 0 for the eighth rank, and column from the left
     pub fn new(rank: usize, file: usize) -> Pos {
         Pos(rank * 8 + file)
     }
 
-    pub fn zero() -> Pos {
+    pub fn at(file: File, rank: Rank) -> Pos {
+        Pos((7 - rank.index()) * 8 + file.index())
+    }
+
+    // every square, in order of index from a8 to h1
+    pub fn all() -> impl Iterator<Item = Pos> {
+        (0..64).map(Pos)
+    }
+
+    pub const fn zero() -> Pos {
         Pos(0)
     }
 
@@ -30,35 +341,33 @@ impl Pos {
         to_algebra(self.0).unwrap()
     }
 
-    pub fn file(&self) -> usize {
-        self.0 % 8
-    }
-
-    pub fn rank(&self) -> usize {
-        (self.0 - self.file()) / 8
+    pub fn file(&self) -> File {
+        File::ALL[self.0 % 8]
     }
 
-    pub fn file_is(&self, c: char) -> bool {
-        let file = c as usize - 'a' as usize;
-        self.file() == file
-    }
-
-    pub fn rank_is(&self, c: usize) -> bool {
-        let rank = 8 - c;
-        self.rank() == rank
+    pub fn rank(&self) -> Rank {
+        Rank::ALL[7 - self.0 / 8]
     }
 
     // north is negative, west is negative
     pub fn mv(&self, vertical: isize, horizontal: isize) -> Option<Pos> {
-        let rank = self.rank() as isize + vertical;
-        let file = self.file() as isize + horizontal;
-        if rank >= 0 && rank < 8 && file >= 0 && file < 8 {
-            Some(Pos::new(rank as usize, file as usize))
+        let row = (self.0 / 8) as isize + vertical;
+        let col = (self.0 % 8) as isize + horizontal;
+        if on_board(row, col) {
+            Some(Pos::new(row as usize, col as usize))
         } else {
             None
         }
     }
 
+    // the (vertical, horizontal) that mv takes to get from here to other
+    pub fn offset(&self, other: Pos) -> (isize, isize) {
+        (
+            (other.0 / 8) as isize - (self.0 / 8) as isize,
+            (other.0 % 8) as isize - (self.0 % 8) as isize,
+        )
+    }
+
     pub fn north(&self, d: isize) -> Option<Pos> {
         self.mv(-d, 0)
     }
@@ -84,18 +393,31 @@ impl Pos {
         self.mv(d, -d)
     }
 
+    // like a8 and h1, rather than a1 and h8
+    pub fn is_light(&self) -> bool {
+        !(self.file().index() + self.rank().index()).is_multiple_of(2)
+    }
+
+    // 0 on the edge of the board, up to 3 in the middle
     pub fn value(&self) -> isize {
-        if self.rank_is(1) || self.file_is('a') || self.rank_is(8) || self.file_is('h') {
-            return 0;
-        }
-        if self.rank_is(2) || self.file_is('b') || self.rank_is(7) || self.file_is('g') {
-            return 1;
-        }
-        if self.rank_is(3) || self.file_is('c') || self.rank_is(6) || self.file_is('f') {
-            return 2;
-        } else {
-            return 3;
-        }
+        let edge = |ix: usize| ix.min(7 - ix);
+        edge(self.file().index()).min(edge(self.rank().index())) as isize
+    }
+
+    // the squares strictly between the two, if they share a rank, file or diagonal
+    pub fn between(&self, other: Pos) -> Squares {
+        Squares(BETWEEN[self.0][other.0])
+    }
+
+    // the rank, file or diagonal through both squares, from edge to edge, if there is one
+    pub fn line(&self, other: Pos) -> Squares {
+        Squares(LINE[self.0][other.0])
+    }
+
+    // number of king moves between the two squares
+    pub fn distance(&self, other: Pos) -> usize {
+        let (vertical, horizontal) = self.offset(other);
+        vertical.unsigned_abs().max(horizontal.unsigned_abs())
     }
 }
 
@@ -104,3 +426,92 @@ impl fmt::Display for Pos {
         write!(f, "{}", to_algebra(self.0).unwrap())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::piece::Color;
+    use crate::position::{File, Pos, Rank};
+
+    fn squares(s: &str) -> Vec<Pos> {
+        s.split_whitespace().map(|s| pos!(s)).collect()
+    }
+
+    #[test]
+    fn between_and_line() {
+        assert_eq!(
+            pos!("a1").between(pos!("a4")).collect::<Vec<_>>(),
+            squares("a3 a2")
+        );
+        assert_eq!(
+            pos!("f6").between(pos!("c3")).collect::<Vec<_>>(),
+            squares("e5 d4")
+        );
+        assert!(pos!("b1").between(pos!("c3")).is_empty());
+        assert!(pos!("b1").between(pos!("b2")).is_empty());
+        assert_eq!(pos!("h8").between(pos!("a1")).len(), 6);
+        assert_eq!(
+            pos!("a1").between(pos!("h8")),
+            pos!("h8").between(pos!("a1"))
+        );
+
+        assert_eq!(
+            pos!("c2").line(pos!("d3")).collect::<Vec<_>>(),
+            squares("h7 g6 f5 e4 d3 c2 b1")
+        );
+        assert_eq!(pos!("e4").line(pos!("e5")).len(), 8);
+        assert!(pos!("e4").line(pos!("e4")).is_empty());
+        assert!(pos!("a1").line(pos!("b3")).is_empty());
+        assert!(!pos!("d1").line(pos!("h5")).contains(pos!("c2")));
+        assert!(pos!("d1").line(pos!("h5")).contains(pos!("e2")));
+    }
+
+    #[test]
+    fn files_and_ranks() {
+        let e4 = pos!("e4");
+        assert_eq!(e4, Pos::E4);
+        assert_eq!((e4.file(), e4.rank()), (File::E, Rank::Fourth));
+        assert_eq!(Pos::at(File::E, Rank::Fourth), e4);
+        assert_eq!(Pos::at(File::A, Rank::Eighth).index(), 0);
+        assert_eq!(Pos::at(File::H, Rank::First), Pos::H1);
+        for ix in 0..64 {
+            let pos = Pos::from_index(ix);
+            assert_eq!(Pos::at(pos.file(), pos.rank()), pos);
+            let name = format!("{}{}", pos.file(), pos.rank());
+            assert_eq!(name, pos.to_algebra());
+        }
+
+        assert_eq!(File::from_char('c'), Some(File::C));
+        assert_eq!(File::from_char('i'), None);
+        assert_eq!(Rank::from_char('8'), Some(Rank::Eighth));
+        assert_eq!(Rank::from_char('0'), None);
+        assert_eq!(File::from_index(8), None);
+        assert_eq!(File::B.flip(), File::G);
+        assert_eq!(Rank::Second.relative_to(Color::White), Rank::Second);
+        assert_eq!(Rank::Second.relative_to(Color::Black), Rank::Seventh);
+
+        // one square north and one east
+        assert_eq!(e4.offset(Pos::F5), (-1, 1));
+        assert_eq!(e4.mv(-1, 1), Some(Pos::F5));
+        assert!(Pos::A8.is_light() && Pos::H1.is_light() && !Pos::A1.is_light());
+        assert_eq!(Pos::D4.value(), 3);
+        assert_eq!(Pos::B7.value(), 1);
+    }
+
+    #[test]
+    fn iterators() {
+        assert_eq!(Pos::all().count(), 64);
+        assert_eq!(Pos::all().next(), Some(Pos::A8));
+        assert_eq!(Pos::all().last(), Some(Pos::H1));
+        let file: Vec<Pos> = File::C.squares().collect();
+        assert_eq!(file.len(), 8);
+        assert_eq!((file[0], file[7]), (Pos::C1, Pos::C8));
+        let rank: Vec<Pos> = Rank::Fifth.squares().collect();
+        assert_eq!((rank[0], rank[7]), (Pos::A5, Pos::H5));
+        assert!(rank.iter().all(|p| p.rank() == Rank::Fifth));
+        assert_eq!(
+            Color::both().collect::<Vec<_>>(),
+            [Color::White, Color::Black]
+        );
+        assert_eq!(crate::piece::PieceType::all().count(), 6);
+    }
+}
@@ -0,0 +1,22 @@
+//! The types for playing by the rules, to import all at once:
+//!
+//! ```
+//! use combustion::prelude::*;
+//!
+//! let mut game = Game::new();
+//! let mv = Move::from_san(game.board(), "e4")?;
+//! game.make_move(&mv)?;
+//! assert_eq!(game.board().status(), GameStatus::InProgress);
+//! # Ok::<(), ChessError>(())
+//! ```
+
+pub use crate::board::{Board, Draw, GameStatus};
+#[cfg(feature = "std")]
+pub use crate::epd::read_epd;
+pub use crate::epd::Epd;
+pub use crate::game::{Game, GameResult};
+pub use crate::moves::{Castle, Move};
+pub use crate::pgn::{read_pgn, san_line, PgnGame, PgnLine, PgnMove};
+pub use crate::piece::{Color, Piece, PieceType};
+pub use crate::position::{File, Pos, Rank};
+pub use crate::util::{ChessError, MoveError, ParseError};
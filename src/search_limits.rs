@@ -0,0 +1,149 @@
+use crate::moves::Move;
+use crate::time_manager::Budget;
+
+use std::time::Duration;
+
+// no search goes deeper than this many plies below the root moves
+pub const MAX_DEPTH: usize = 64;
+
+// when a search should stop. it stops at whichever limit comes first, and at MAX_DEPTH
+// if there are none.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchLimits {
+    // plies below the root moves
+    pub depth: Option<usize>,
+    pub nodes: Option<u64>,
+    pub time: Option<Budget>,
+    // ignore depth and time, and search until aborted
+    pub infinite: bool,
+    // only consider these root moves, or all of them if empty
+    pub search_moves: Vec<Move>,
+    // look only for a forced mate in at most this many moves, instead of the best move
+    pub mate: Option<usize>,
+    // count time in nodes searched at this many a second rather than by the clock on the
+    // wall, for matches that don't depend on the hardware
+    pub nps: Option<u64>,
+    // find the scores of every root move within this many centipawns of the best, so
+    // one can be picked at random to vary play
+    pub margin: isize,
+    // add up to this many centipawns either way to every evaluation, to play weaker.
+    // the noise for a position is fixed by the seed, so it is the same wherever it is
+    // met in the search.
+    pub noise: isize,
+    pub noise_seed: u64,
+    // find each iteration's score with mtd(f)'s zero window searches instead of an
+    // aspiration window, to compare the two
+    pub mtdf: bool,
+    // search the same way every time, for debugging and regression tests: on one thread,
+    // with no margin to pick a move from at random, and keeping the noise seed given.
+    // only a time limit can still make two searches differ.
+    pub deterministic: bool,
+}
+
+impl SearchLimits {
+    pub fn depth(depth: usize) -> SearchLimits {
+        SearchLimits {
+            depth: Some(depth),
+            ..SearchLimits::default()
+        }
+    }
+
+    pub fn nodes(nodes: u64) -> SearchLimits {
+        SearchLimits {
+            nodes: Some(nodes),
+            ..SearchLimits::default()
+        }
+    }
+
+    // spend exactly t on the move
+    pub fn move_time(t: Duration) -> SearchLimits {
+        SearchLimits {
+            time: Some(Budget { soft: t, hard: t }),
+            ..SearchLimits::default()
+        }
+    }
+
+    // evaluate only the given candidate moves
+    pub fn search_moves(moves: &[Move]) -> SearchLimits {
+        SearchLimits {
+            search_moves: moves.to_vec(),
+            ..SearchLimits::default()
+        }
+    }
+
+    pub fn mate(moves: usize) -> SearchLimits {
+        SearchLimits {
+            mate: Some(moves),
+            ..SearchLimits::default()
+        }
+    }
+
+    pub fn infinite() -> SearchLimits {
+        SearchLimits {
+            infinite: true,
+            ..SearchLimits::default()
+        }
+    }
+
+    pub fn max_depth(&self) -> usize {
+        match self.depth {
+            Some(depth) if !self.infinite => depth.min(MAX_DEPTH),
+            _ => MAX_DEPTH,
+        }
+    }
+
+    pub fn budget(&self) -> Option<Budget> {
+        if self.infinite {
+            None
+        } else {
+            self.time
+        }
+    }
+}
+
+// the strongest skill level, which is full strength
+pub const MAX_SKILL: usize = 20;
+
+// a weaker engine for people to play, from level 0 up to MAX_SKILL. lower levels search
+// shallower and fewer nodes, misjudge positions more, and more often play a move that is
+// not quite the best.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Skill(usize);
+
+impl Skill {
+    pub fn level(level: usize) -> Skill {
+        Skill(level.min(MAX_SKILL))
+    }
+
+    // the level for elo: a straight line from 800 at level 0 to 2400 at full strength,
+    // 80 a level. a guess, not measured against rated players.
+    pub fn from_elo(elo: usize) -> Skill {
+        Skill::level(elo.saturating_sub(800) / 80)
+    }
+
+    pub fn is_full_strength(&self) -> bool {
+        self.0 == MAX_SKILL
+    }
+
+    // limits weakened to this level
+    pub fn limit(&self, limits: SearchLimits) -> SearchLimits {
+        if self.is_full_strength() {
+            return limits;
+        }
+        let weakness = (MAX_SKILL - self.0) as isize;
+        let depth = 1 + self.0 / 2;
+        let nodes = 1000 << (self.0 / 2);
+        SearchLimits {
+            depth: Some(limits.depth.map_or(depth, |d| d.min(depth))),
+            nodes: Some(limits.nodes.map_or(nodes, |n| n.min(nodes))),
+            margin: limits.margin.max(weakness * 5),
+            noise: limits.noise.max(weakness * 10),
+            noise_seed: if limits.deterministic {
+                limits.noise_seed
+            } else {
+                rand::random()
+            },
+            ..limits
+        }
+    }
+}
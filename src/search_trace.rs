@@ -0,0 +1,295 @@
+use crate::board_alpha_beta::json_score;
+use crate::moves::Move;
+
+use std::fmt::Write;
+
+// why a node returned the score it did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    // a draw by the fifty move rule
+    Draw,
+    // mate or stalemate, with no moves left
+    GameOver,
+    Tablebase,
+    // the transposition table had a good enough score
+    Table,
+    // the static score, at the horizon or once the search was stopped
+    Horizon,
+    Razor,
+    NullMove,
+    ProbCut,
+    // futility left a quiet move unsearched
+    Futility,
+    // searched every move, or until one failed high
+    FailHigh,
+    FailLow,
+    Exact,
+}
+
+impl Reason {
+    fn from_window(score: isize, alpha: isize, beta: isize) -> Reason {
+        if score >= beta {
+            Reason::FailHigh
+        } else if score <= alpha {
+            Reason::FailLow
+        } else {
+            Reason::Exact
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Reason::Draw => "draw",
+            Reason::GameOver => "game over",
+            Reason::Tablebase => "tablebase",
+            Reason::Table => "table",
+            Reason::Horizon => "horizon",
+            Reason::Razor => "razor",
+            Reason::NullMove => "null move",
+            Reason::ProbCut => "probcut",
+            Reason::Futility => "futility",
+            Reason::FailHigh => "fail high",
+            Reason::FailLow => "fail low",
+            Reason::Exact => "exact",
+        }
+    }
+}
+
+// a node of the search tree, from the point of view of the side to move there. the root
+// of each pass over the root moves has no move, and nor does a null move.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceNode {
+    pub mv: Option<Move>,
+    pub depth: usize,
+    pub alpha: isize,
+    pub beta: isize,
+    pub score: isize,
+    pub reason: Reason,
+    pub children: Vec<TraceNode>,
+}
+
+// the tree a search went through, down to max_ply plies and until max_nodes nodes have
+// been recorded. the rest of the search goes on as usual, unrecorded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchTrace {
+    pub max_ply: usize,
+    pub max_nodes: usize,
+    // a tree for each pass over the root moves: every iteration, and every re-search
+    // when a score falls outside the aspiration window
+    pub roots: Vec<TraceNode>,
+    recorded: usize,
+    // the nodes being searched, None where they aren't recorded
+    stack: Vec<Option<TraceNode>>,
+}
+
+impl SearchTrace {
+    pub fn new(max_ply: usize, max_nodes: usize) -> SearchTrace {
+        SearchTrace {
+            max_ply,
+            max_nodes,
+            roots: Vec::new(),
+            recorded: 0,
+            stack: Vec::new(),
+        }
+    }
+
+    pub(crate) fn enter(&mut self, mv: Option<Move>, depth: usize, alpha: isize, beta: isize) {
+        let parent_recorded = self.stack.last().is_none_or(Option::is_some);
+        let node = if parent_recorded
+            && self.stack.len() <= self.max_ply
+            && self.recorded < self.max_nodes
+        {
+            self.recorded += 1;
+            Some(TraceNode {
+                mv,
+                depth,
+                alpha,
+                beta,
+                score: 0,
+                reason: Reason::Exact,
+                children: Vec::new(),
+            })
+        } else {
+            None
+        };
+        self.stack.push(node);
+    }
+
+    // the node entered last is done. without a reason it is judged by its window.
+    pub(crate) fn leave(&mut self, score: isize, reason: Option<Reason>) {
+        if let Some(Some(mut node)) = self.stack.pop() {
+            node.score = score;
+            node.reason =
+                reason.unwrap_or_else(|| Reason::from_window(score, node.alpha, node.beta));
+            self.attach(node);
+        }
+    }
+
+    // a move that was never searched, with the score it was given instead
+    pub(crate) fn pruned(&mut self, mv: Move, depth: usize, score: isize, reason: Reason) {
+        if let Some(Some(parent)) = self.stack.last() {
+            if self.stack.len() <= self.max_ply && self.recorded < self.max_nodes {
+                self.recorded += 1;
+                let (alpha, beta) = (-parent.beta, -parent.alpha);
+                self.attach(TraceNode {
+                    mv: Some(mv),
+                    depth,
+                    alpha,
+                    beta,
+                    score,
+                    reason,
+                    children: Vec::new(),
+                });
+            }
+        }
+    }
+
+    fn attach(&mut self, node: TraceNode) {
+        match self.stack.last_mut() {
+            Some(Some(parent)) => parent.children.push(node),
+            _ => self.roots.push(node),
+        }
+    }
+
+    // a line for each node, indented two spaces a ply
+    pub fn to_text(&self) -> String {
+        let mut s = String::new();
+        for root in self.roots.iter() {
+            text_lines(&mut s, root, 0);
+        }
+        s
+    }
+
+    // the roots as an array, each node with its children
+    pub fn to_json(&self) -> String {
+        let mut s = String::from("[");
+        for (i, root) in self.roots.iter().enumerate() {
+            if i > 0 {
+                s.push(',');
+            }
+            json_node(&mut s, root);
+        }
+        s.push(']');
+        s
+    }
+}
+
+// a bound of the window, which may be infinite
+fn window(bound: isize) -> Option<isize> {
+    if bound.abs() >= isize::MAX - 1 {
+        None
+    } else {
+        Some(bound)
+    }
+}
+
+fn text_bound(bound: isize) -> String {
+    match window(bound) {
+        Some(b) => b.to_string(),
+        None if bound > 0 => "inf".to_string(),
+        None => "-inf".to_string(),
+    }
+}
+
+fn json_bound(bound: isize) -> String {
+    window(bound).map_or("null".to_string(), |b| b.to_string())
+}
+
+fn text_lines(s: &mut String, node: &TraceNode, indent: usize) {
+    let mv = match node.mv {
+        Some(mv) => mv.to_string(),
+        None if indent == 0 => "root".to_string(),
+        None => "null".to_string(),
+    };
+    let _ = writeln!(
+        s,
+        "{:indent$}{} depth {} [{}, {}] {} {}",
+        "",
+        mv,
+        node.depth,
+        text_bound(node.alpha),
+        text_bound(node.beta),
+        text_bound(node.score),
+        node.reason.name(),
+        indent = indent
+    );
+    for child in node.children.iter() {
+        text_lines(s, child, indent + 2);
+    }
+}
+
+fn json_node(s: &mut String, node: &TraceNode) {
+    let mv = node
+        .mv
+        .map_or("null".to_string(), |mv| format!("\"{}\"", mv));
+    let _ = write!(
+        s,
+        "{{\"move\":{},\"depth\":{},\"alpha\":{},\"beta\":{},\"score\":{},\
+         \"reason\":\"{}\",\"children\":[",
+        mv,
+        node.depth,
+        json_bound(node.alpha),
+        json_bound(node.beta),
+        json_score(node.score),
+        node.reason.name()
+    );
+    for (i, child) in node.children.iter().enumerate() {
+        if i > 0 {
+            s.push(',');
+        }
+        json_node(s, child);
+    }
+    s.push_str("]}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+    use crate::search_limits::SearchLimits;
+
+    #[test]
+    fn trace_records_the_tree() {
+        let mut b =
+            Board::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3")
+                .unwrap();
+        let mut trace = SearchTrace::new(2, 100_000);
+        let res = b
+            .search_traced(&SearchLimits::depth(2), None, &mut trace, &mut |_| {})
+            .unwrap();
+
+        // a root for each pass, with a child for each of the 27 moves
+        assert!(trace.roots.len() >= 3);
+        let last = trace.roots.last().unwrap();
+        assert_eq!(last.mv, None);
+        assert_eq!(last.depth, 3);
+        assert_eq!(last.children.len(), 27);
+        assert!(last.children.iter().any(|child| child.mv == Some(res.mv)));
+        // two plies below the root and no further
+        assert!(last.children.iter().any(|child| !child.children.is_empty()));
+        assert!(last
+            .children
+            .iter()
+            .flat_map(|child| child.children.iter())
+            .all(|grandchild| grandchild.children.is_empty()));
+
+        let text = trace.to_text();
+        assert!(text.starts_with("root depth 1 [-inf, inf]"));
+        assert!(text.lines().any(|line| line.starts_with("    ")));
+        let json = trace.to_json();
+        assert!(json.starts_with("[{\"move\":null,\"depth\":1,\"alpha\":null,\"beta\":null,"));
+        assert!(json.ends_with("]}]"));
+    }
+
+    #[test]
+    fn trace_stops_recording_at_max_nodes() {
+        let mut b = Board::initial();
+        let mut trace = SearchTrace::new(10, 50);
+        b.search_traced(&SearchLimits::depth(3), None, &mut trace, &mut |_| {})
+            .unwrap();
+        fn count(node: &TraceNode) -> usize {
+            1 + node.children.iter().map(count).sum::<usize>()
+        }
+        assert_eq!(trace.roots.iter().map(count).sum::<usize>(), 50);
+    }
+}
@@ -0,0 +1,201 @@
+use std::fmt;
+
+// games won, drawn and lost by one side of a match
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatchScore {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MatchScore {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    // points per game, from 0 to 1
+    pub fn fraction(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.5;
+        }
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+
+    // the rating difference that would give this score
+    pub fn elo(&self) -> f64 {
+        elo(self.fraction())
+    }
+
+    // half the width of the 95% confidence interval around elo()
+    pub fn elo_margin(&self) -> f64 {
+        let n = self.games() as f64;
+        if n == 0.0 {
+            return 0.0;
+        }
+        let s = self.fraction();
+        let deviation = (self.wins as f64 * (1.0 - s).powi(2)
+            + self.draws as f64 * (0.5 - s).powi(2)
+            + self.losses as f64 * s.powi(2))
+            / n;
+        let error = 1.959_964 * (deviation / n).sqrt();
+        // infinite when the interval reaches a score of 0 or 1
+        (elo((s + error).min(1.0)) - elo((s - error).max(0.0))) / 2.0
+    }
+
+    // likelihood of superiority: how sure we can be the side is the stronger, from its
+    // wins and losses. draws say nothing about it.
+    pub fn los(&self) -> f64 {
+        let decisive = (self.wins + self.losses) as f64;
+        if decisive == 0.0 {
+            return 0.5;
+        }
+        0.5 * (1.0 + erf((self.wins as f64 - self.losses as f64) / (2.0 * decisive).sqrt()))
+    }
+}
+
+// like 12 - 5 - 8, wins then losses then draws
+impl fmt::Display for MatchScore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} - {} - {}", self.wins, self.losses, self.draws)
+    }
+}
+
+fn elo(fraction: f64) -> f64 {
+    -400.0 * (1.0 / fraction - 1.0).log10()
+}
+
+fn expected_fraction(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+// abramowitz and stegun 7.1.26, good to about 1e-7
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.327_591_1 * x.abs());
+    let poly = t
+        * (0.254_829_592
+            + t * (-0.284_496_736
+                + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    let y = 1.0 - poly * (-x * x).exp();
+    if x < 0.0 {
+        -y
+    } else {
+        y
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hypothesis {
+    // the change is worth elo0 or less
+    H0,
+    // it is worth elo1 or more
+    H1,
+}
+
+// a sequential probability ratio test: play games until the score shows, with error
+// rates alpha and beta, whether one side is elo0 or elo1 stronger than the other
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Sprt {
+    pub fn new(elo0: f64, elo1: f64) -> Sprt {
+        Sprt {
+            elo0,
+            elo1,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+
+    // the log likelihood ratios at which h0 and h1 are accepted
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    // the log likelihood ratio of h1 to h0, treating the score of each game as normally
+    // distributed with the variance the games so far show
+    pub fn llr(&self, score: &MatchScore) -> f64 {
+        let n = score.games() as f64;
+        if n == 0.0 {
+            return 0.0;
+        }
+        let s = score.fraction();
+        let square = (score.wins as f64 + score.draws as f64 / 4.0) / n;
+        let variance = square - s * s;
+        if variance <= 0.0 {
+            return 0.0;
+        }
+        let (s0, s1) = (expected_fraction(self.elo0), expected_fraction(self.elo1));
+        n * (s1 - s0) * (2.0 * s - s0 - s1) / (2.0 * variance)
+    }
+
+    // the hypothesis accepted, or None if more games are needed
+    pub fn result(&self, score: &MatchScore) -> Option<Hypothesis> {
+        let llr = self.llr(score);
+        let (lower, upper) = self.bounds();
+        if llr <= lower {
+            Some(Hypothesis::H0)
+        } else if llr >= upper {
+            Some(Hypothesis::H1)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sprt::{Hypothesis, MatchScore, Sprt};
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 0.05
+    }
+
+    #[test]
+    fn match_statistics() {
+        let even = MatchScore {
+            wins: 10,
+            draws: 20,
+            losses: 10,
+        };
+        assert!(close(even.elo(), 0.0));
+        assert!(close(even.los(), 0.5));
+        assert_eq!(even.to_string(), "10 - 10 - 20");
+
+        let better = MatchScore {
+            wins: 60,
+            draws: 20,
+            losses: 20,
+        };
+        assert!(close(better.elo(), 147.19));
+        assert!(better.elo_margin() > 30.0 && better.elo_margin() < 100.0);
+        assert!(better.los() > 0.99);
+
+        let sprt = Sprt::new(0.0, 5.0);
+        let (lower, upper) = sprt.bounds();
+        assert!(close(lower, -2.94) && close(upper, 2.94));
+        assert_eq!(sprt.result(&MatchScore::default()), None);
+        // not enough games to be sure, until there are ten times as many
+        assert_eq!(sprt.result(&better), None);
+        let better = MatchScore {
+            wins: 600,
+            draws: 200,
+            losses: 200,
+        };
+        assert_eq!(sprt.result(&better), Some(Hypothesis::H1));
+        let worse = MatchScore {
+            wins: better.losses,
+            draws: better.draws,
+            losses: better.wins,
+        };
+        assert_eq!(sprt.result(&worse), Some(Hypothesis::H0));
+        assert_eq!(sprt.result(&even), None);
+    }
+}
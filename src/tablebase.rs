@@ -0,0 +1,651 @@
+use crate::board::Board;
+use crate::piece::{Color, PieceType};
+use crate::util::ChessError;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+// the endings tbgen makes by default, each after the ones it needs for promotions
+pub const ENDINGS: [&str; 4] = ["KQK", "KRK", "KPK", "KBNK"];
+
+// distance to mate in plies, for the side to move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dtm {
+    Win(usize),
+    Loss(usize),
+    Draw,
+}
+
+// a piece that has been captured, in place of its square
+const GONE: usize = 64;
+
+const KING_STEPS: [(isize, isize); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+const ROOK_STEPS: [(isize, isize); 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+const BISHOP_STEPS: [(isize, isize); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+const KNIGHT_JUMPS: [(isize, isize); 8] = [
+    (-2, -1),
+    (-2, 1),
+    (-1, -2),
+    (-1, 2),
+    (1, -2),
+    (1, 2),
+    (2, -1),
+    (2, 1),
+];
+
+fn step(sq: usize, (dr, df): (isize, isize)) -> Option<usize> {
+    let rank = (sq / 8) as isize + dr;
+    let file = (sq % 8) as isize + df;
+    if (0..8).contains(&rank) && (0..8).contains(&file) {
+        Some((rank * 8 + file) as usize)
+    } else {
+        None
+    }
+}
+
+// the directions a piece moves in, and whether it keeps going along them
+fn directions(kind: PieceType) -> (&'static [(isize, isize)], bool) {
+    match kind {
+        PieceType::King => (&KING_STEPS, false),
+        PieceType::Queen => (&KING_STEPS, true),
+        PieceType::Rook => (&ROOK_STEPS, true),
+        PieceType::Bishop => (&BISHOP_STEPS, true),
+        PieceType::Knight => (&KNIGHT_JUMPS, false),
+        PieceType::Pawn => (&[], false),
+    }
+}
+
+// positions in a table are the squares of the white king, the black king and white's
+// other pieces, in the order the table lists them. the weak side only has its king,
+// so it is always black: positions with black as the strong side are flipped first.
+#[derive(Debug, Clone, Copy)]
+struct Setup<'a> {
+    kinds: &'a [PieceType],
+    squares: [usize; 4],
+    white_to_move: bool,
+}
+
+impl<'a> Setup<'a> {
+    fn color(i: usize) -> Color {
+        if i == 1 {
+            Color::Black
+        } else {
+            Color::White
+        }
+    }
+
+    fn occupied(&self, sq: usize) -> bool {
+        self.squares[..self.kinds.len()].contains(&sq)
+    }
+
+    // does piece i attack sq
+    fn attacks(&self, i: usize, sq: usize) -> bool {
+        let from = self.squares[i];
+        if from == GONE {
+            return false;
+        }
+        if self.kinds[i] == PieceType::Pawn {
+            return step(from, (-1, -1)) == Some(sq) || step(from, (-1, 1)) == Some(sq);
+        }
+        let (dirs, slides) = directions(self.kinds[i]);
+        for &dir in dirs {
+            let mut to = from;
+            while let Some(next) = step(to, dir) {
+                if next == sq {
+                    return true;
+                }
+                if !slides || self.occupied(next) {
+                    break;
+                }
+                to = next;
+            }
+        }
+        false
+    }
+
+    fn in_check(&self, white: bool) -> bool {
+        let king = self.squares[if white { 0 } else { 1 }];
+        (0..self.kinds.len())
+            .any(|i| (Setup::color(i) == Color::White) != white && self.attacks(i, king))
+    }
+
+    // could this position come up in a game
+    fn valid(&self) -> bool {
+        let n = self.kinds.len();
+        for i in 0..n {
+            if self.squares[i + 1..n].contains(&self.squares[i]) {
+                return false;
+            }
+            let rank = self.squares[i] / 8;
+            if self.kinds[i] == PieceType::Pawn && (rank == 0 || rank == 7) {
+                return false;
+            }
+        }
+        !self.in_check(!self.white_to_move)
+    }
+
+    fn index(&self) -> usize {
+        let mut ix = 0;
+        for &sq in self.squares[..self.kinds.len()].iter().rev() {
+            ix = ix * 64 + sq;
+        }
+        ix * 2 + if self.white_to_move { 0 } else { 1 }
+    }
+
+    fn from_index(kinds: &'a [PieceType], ix: usize) -> Setup<'a> {
+        let mut squares = [GONE; 4];
+        let mut rest = ix / 2;
+        for sq in squares[..kinds.len()].iter_mut() {
+            *sq = rest % 64;
+            rest /= 64;
+        }
+        Setup {
+            kinds,
+            squares,
+            white_to_move: ix.is_multiple_of(2),
+        }
+    }
+
+    fn moved(&self, i: usize, to: usize) -> Setup<'a> {
+        let mut s = *self;
+        s.squares[i] = to;
+        s.white_to_move = !s.white_to_move;
+        s
+    }
+
+    // squares piece i can move to, capturing or not, before checking for check
+    fn targets(&self, i: usize, out: &mut Vec<usize>) {
+        let from = self.squares[i];
+        if self.kinds[i] == PieceType::Pawn {
+            if let Some(to) = step(from, (-1, 0)).filter(|&to| !self.occupied(to)) {
+                out.push(to);
+                if from / 8 == 6 {
+                    out.extend(step(to, (-1, 0)).filter(|&to| !self.occupied(to)));
+                }
+            }
+            return;
+        }
+        let (dirs, slides) = directions(self.kinds[i]);
+        for &dir in dirs {
+            let mut to = from;
+            while let Some(next) = step(to, dir) {
+                let blocker = (0..self.kinds.len()).find(|&j| self.squares[j] == next);
+                match blocker {
+                    Some(j) if Setup::color(j) == Setup::color(i) => break,
+                    Some(_) => {
+                        out.push(next);
+                        break;
+                    }
+                    None => out.push(next),
+                }
+                if !slides {
+                    break;
+                }
+                to = next;
+            }
+        }
+    }
+
+    // squares piece i could have come from to get here without capturing
+    fn sources(&self, i: usize, out: &mut Vec<usize>) {
+        let to = self.squares[i];
+        if self.kinds[i] == PieceType::Pawn {
+            if let Some(from) = step(to, (1, 0)).filter(|&from| !self.occupied(from)) {
+                if from / 8 < 7 {
+                    out.push(from);
+                }
+                if to / 8 == 4 {
+                    out.extend(step(from, (1, 0)).filter(|&from| !self.occupied(from)));
+                }
+            }
+            return;
+        }
+        let (dirs, slides) = directions(self.kinds[i]);
+        for &dir in dirs {
+            let mut from = to;
+            while let Some(next) = step(from, dir) {
+                if self.occupied(next) {
+                    break;
+                }
+                out.push(next);
+                if !slides {
+                    break;
+                }
+                from = next;
+            }
+        }
+    }
+}
+
+// what a move leads to
+enum Successor<'a> {
+    // a position in the same table
+    Inside,
+    // the weak king took piece j, leaving a smaller ending
+    Capture(usize, Setup<'a>),
+    Promotion(Setup<'a>),
+}
+
+fn successors<'a>(s: &Setup<'a>, promoted: &'a [Vec<PieceType>], out: &mut Vec<Successor<'a>>) {
+    let mut targets = Vec::new();
+    for i in 0..s.kinds.len() {
+        if (Setup::color(i) == Color::White) != s.white_to_move {
+            continue;
+        }
+        targets.clear();
+        s.targets(i, &mut targets);
+        for &to in targets.iter() {
+            let mut next = s.moved(i, to);
+            let captured = (0..s.kinds.len()).find(|&j| j != i && s.squares[j] == to);
+            if let Some(j) = captured {
+                next.squares[j] = GONE;
+            }
+            if next.in_check(s.white_to_move) {
+                continue;
+            }
+            if let Some(j) = captured {
+                out.push(Successor::Capture(j, next));
+            } else if s.kinds[i] == PieceType::Pawn && to / 8 == 0 {
+                for kinds in promoted {
+                    out.push(Successor::Promotion(Setup { kinds, ..next }));
+                }
+            } else {
+                out.push(Successor::Inside);
+            }
+        }
+    }
+}
+
+fn name(kinds: &[PieceType]) -> String {
+    let letters: String = kinds[2..]
+        .iter()
+        .map(|kind| match kind {
+            PieceType::Queen => 'Q',
+            PieceType::Rook => 'R',
+            PieceType::Bishop => 'B',
+            PieceType::Knight => 'N',
+            PieceType::Pawn => 'P',
+            PieceType::King => 'K',
+        })
+        .collect();
+    format!("K{}K", letters)
+}
+
+// kings first, then the strong side's pieces by value, as tables order them
+fn sort_pieces(kinds: &mut [PieceType]) {
+    kinds[2..].sort_by_key(|kind| match kind {
+        PieceType::Queen => 0,
+        PieceType::Rook => 1,
+        PieceType::Bishop => 2,
+        PieceType::Knight => 3,
+        _ => 4,
+    });
+}
+
+// the pieces of an ending named like KBNK, in the order its table uses
+fn kinds(name: &str) -> Result<Vec<PieceType>, ChessError> {
+    let inner = name.strip_prefix('K').and_then(|s| s.strip_suffix('K'));
+    let inner = match inner {
+        Some(inner) if !inner.is_empty() && inner.len() <= 2 => inner,
+        _ => parse_error!("[tablebase] {}: expected K, one or two pieces and K", name),
+    };
+    let mut kinds = vec![PieceType::King, PieceType::King];
+    for c in inner.chars() {
+        kinds.push(match c {
+            'Q' => PieceType::Queen,
+            'R' => PieceType::Rook,
+            'B' => PieceType::Bishop,
+            'N' => PieceType::Knight,
+            'P' => PieceType::Pawn,
+            _ => parse_error!("[tablebase] {}: unknown piece {}", name, c),
+        });
+    }
+    sort_pieces(&mut kinds);
+    Ok(kinds)
+}
+
+// distance to mate for every position of one ending
+#[derive(Debug, Clone)]
+pub struct Table {
+    kinds: Vec<PieceType>,
+    // 0 for a draw or a position that can't come up, otherwise 1 + plies to mate: the
+    // side to move wins when that is odd and loses when it is even
+    values: Vec<u8>,
+}
+
+impl Table {
+    pub fn name(&self) -> String {
+        name(&self.kinds)
+    }
+
+    fn get(&self, s: &Setup) -> Dtm {
+        match self.values[s.index()] {
+            0 => Dtm::Draw,
+            v => {
+                let plies = v as usize - 1;
+                if plies % 2 == 1 {
+                    Dtm::Win(plies)
+                } else {
+                    Dtm::Loss(plies)
+                }
+            }
+        }
+    }
+
+    // retrograde analysis: start from the mates and work backwards through the moves
+    // that lead to them, one ply at a time, so every position is reached first by its
+    // shortest mate. tables for any promotions must already be in tb.
+    pub fn generate(ending: &str, tb: &Tablebase) -> Result<Table, ChessError> {
+        let kinds = kinds(ending)?;
+        let n = kinds.len();
+        let size = 2 << (6 * n);
+        let needed = |k: &[PieceType]| match tb.tables.get(&name(k)) {
+            Some(table) if table.kinds == k => Ok(table),
+            _ => board_state_error!("[tablebase] {} needs {} first", ending, name(k)),
+        };
+
+        // what pawns can become, and the tables that say how that ends
+        let mut promoted = Vec::new();
+        let mut promotion_tables = Vec::new();
+        if let Some(pawn) = kinds.iter().position(|&k| k == PieceType::Pawn) {
+            for &kind in [PieceType::Queen, PieceType::Rook].iter() {
+                let mut k = kinds.clone();
+                k[pawn] = kind;
+                sort_pieces(&mut k);
+                promotion_tables.push(needed(&k)?);
+                promoted.push(k);
+            }
+        }
+        // and what is left after each piece is taken. a king alone, or with one minor
+        // piece, can't mate.
+        let mut remains = vec![None; n];
+        for (j, left) in remains.iter_mut().enumerate().skip(2) {
+            let mut k = kinds.clone();
+            k.remove(j);
+            if k.len() == 3 && k[2] != PieceType::Bishop && k[2] != PieceType::Knight {
+                *left = Some((needed(&k)?, j));
+            }
+        }
+
+        let mut values = vec![0u8; size];
+        // moves not yet known to lose, among those staying in the table
+        let mut remaining = vec![0u8; size];
+        // for moves leaving the table: ESCAPE if one of them doesn't lose, otherwise 1 +
+        // the longest loss through them
+        const ESCAPE: u8 = u8::MAX;
+        let mut exits = vec![0u8; size];
+        // positions to settle at each ply count
+        let mut levels: Vec<Vec<u32>> = vec![Vec::new(); u8::MAX as usize];
+
+        let mut next = Vec::new();
+        for ix in 0..size {
+            let s = Setup::from_index(&kinds, ix);
+            if !s.valid() {
+                continue;
+            }
+            next.clear();
+            successors(&s, &promoted, &mut next);
+            if next.is_empty() {
+                if s.in_check(s.white_to_move) {
+                    levels[0].push(ix as u32);
+                }
+                continue;
+            }
+            for succ in next.iter() {
+                let dtm = match succ {
+                    Successor::Inside => {
+                        remaining[ix] += 1;
+                        continue;
+                    }
+                    Successor::Capture(j, next) => match remains[*j] {
+                        Some((table, j)) => {
+                            let mut squares = [GONE; 4];
+                            let left = next.squares[..n].iter().enumerate();
+                            let left = left.filter(|&(i, _)| i != j).map(|(_, &sq)| sq);
+                            for (sq, from) in squares.iter_mut().zip(left) {
+                                *sq = from;
+                            }
+                            table.get(&Setup {
+                                kinds: &table.kinds,
+                                squares,
+                                white_to_move: next.white_to_move,
+                            })
+                        }
+                        None => Dtm::Draw,
+                    },
+                    Successor::Promotion(p) => {
+                        let i = promoted.iter().position(|k| k == p.kinds).unwrap();
+                        promotion_tables[i].get(p)
+                    }
+                };
+                match dtm {
+                    Dtm::Loss(plies) => {
+                        levels[plies + 1].push(ix as u32);
+                        exits[ix] = ESCAPE;
+                    }
+                    Dtm::Draw => exits[ix] = ESCAPE,
+                    Dtm::Win(plies) if exits[ix] != ESCAPE => {
+                        exits[ix] = exits[ix].max(plies as u8 + 1)
+                    }
+                    Dtm::Win(_) => {}
+                }
+            }
+            if remaining[ix] == 0 && exits[ix] != ESCAPE {
+                levels[exits[ix] as usize].push(ix as u32);
+            }
+        }
+
+        let mut sources = Vec::new();
+        for level in 0..levels.len() {
+            let settled = std::mem::take(&mut levels[level]);
+            for ix in settled {
+                let ix = ix as usize;
+                if values[ix] != 0 {
+                    continue;
+                }
+                values[ix] = level as u8 + 1;
+                let s = Setup::from_index(&kinds, ix);
+                for i in 0..n {
+                    if (Setup::color(i) == Color::White) == s.white_to_move {
+                        continue;
+                    }
+                    sources.clear();
+                    s.sources(i, &mut sources);
+                    for &from in sources.iter() {
+                        let prev = s.moved(i, from);
+                        let p = prev.index();
+                        if values[p] != 0 || !prev.valid() {
+                            continue;
+                        }
+                        if level % 2 == 0 {
+                            // moving here wins
+                            levels[level + 1].push(p as u32);
+                        } else {
+                            remaining[p] -= 1;
+                            if remaining[p] == 0 && exits[p] != ESCAPE {
+                                let longest = (level + 1).max(exits[p] as usize);
+                                levels[longest].push(p as u32);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(Table { kinds, values })
+    }
+
+    // the number of positions the side to move wins, and the longest of those mates
+    pub fn wins(&self) -> (usize, usize) {
+        let wins = self.values.iter().filter(|&&v| v % 2 == 0 && v > 0);
+        (
+            wins.clone().count(),
+            wins.max().map_or(0, |&v| v as usize - 1),
+        )
+    }
+
+    // a line naming the ending, then a byte per position
+    pub fn save(&self, path: &Path) -> Result<(), ChessError> {
+        let mut bytes = format!("combustion tablebase {}\n", self.name()).into_bytes();
+        bytes.extend_from_slice(&self.values);
+        match fs::write(path, bytes) {
+            Ok(()) => Ok(()),
+            Err(e) => board_state_error!("[tablebase] can't write {}: {}", path.display(), e),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Table, ChessError> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) => parse_error!("[tablebase] can't read {}: {}", path.display(), e),
+        };
+        let end = bytes.iter().position(|&b| b == b'\n').unwrap_or(0);
+        let header = String::from_utf8_lossy(&bytes[..end]);
+        let kinds = match header.strip_prefix("combustion tablebase ") {
+            Some(name) => kinds(name)?,
+            None => parse_error!("[tablebase] {} is not a table", path.display()),
+        };
+        let values = bytes[end + 1..].to_vec();
+        if values.len() != 2 << (6 * kinds.len()) {
+            parse_error!("[tablebase] {} is cut short", path.display());
+        }
+        Ok(Table { kinds, values })
+    }
+}
+
+// the tables at hand, by ending
+#[derive(Debug, Clone, Default)]
+pub struct Tablebase {
+    tables: HashMap<String, Table>,
+}
+
+impl Tablebase {
+    pub fn new() -> Tablebase {
+        Tablebase::default()
+    }
+
+    pub fn insert(&mut self, table: Table) {
+        self.tables.insert(table.name(), table);
+    }
+
+    pub fn get(&self, ending: &str) -> Option<&Table> {
+        self.tables.get(ending)
+    }
+
+    // every NAME.tb file in dir
+    pub fn load_dir(dir: &Path) -> Result<Tablebase, ChessError> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => parse_error!("[tablebase] can't read {}: {}", dir.display(), e),
+        };
+        let mut tb = Tablebase::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "tb") {
+                tb.insert(Table::load(&path)?);
+            }
+        }
+        Ok(tb)
+    }
+
+    // the distance to mate in b, if it has a table
+    pub fn probe(&self, b: &Board) -> Option<Dtm> {
+        let mut pieces = Vec::new();
+        for (pos, p) in b.iter_pieces() {
+            if pieces.len() == 4 {
+                return None;
+            }
+            pieces.push((pos.index(), p));
+        }
+        let strong = pieces
+            .iter()
+            .find(|(_, p)| p.kind != PieceType::King)?
+            .1
+            .color;
+        if pieces
+            .iter()
+            .any(|(_, p)| p.color != strong && p.kind != PieceType::King)
+        {
+            return None;
+        }
+        if strong == Color::Black {
+            return self.probe(&b.flipped());
+        }
+
+        let mut kinds = vec![PieceType::King, PieceType::King];
+        kinds.extend(
+            pieces
+                .iter()
+                .map(|(_, p)| p.kind)
+                .filter(|&k| k != PieceType::King),
+        );
+        sort_pieces(&mut kinds);
+        let table = self.tables.get(&name(&kinds))?;
+        let mut squares = [GONE; 4];
+        let mut used = [false; 4];
+        for (i, &kind) in table.kinds.iter().enumerate() {
+            let color = Setup::color(i);
+            let j = (0..pieces.len())
+                .find(|&j| !used[j] && pieces[j].1.kind == kind && pieces[j].1.color == color)?;
+            used[j] = true;
+            squares[i] = pieces[j].0;
+        }
+        Some(table.get(&Setup {
+            kinds: &table.kinds,
+            squares,
+            white_to_move: b.color_to_move == Color::White,
+        }))
+    }
+}
+
+// the tables searches probe
+static TABLEBASE: RwLock<Option<Arc<Tablebase>>> = RwLock::new(None);
+
+pub fn tablebase() -> Option<Arc<Tablebase>> {
+    TABLEBASE.read().unwrap().clone()
+}
+
+// for the whole process, like the evaluation parameters
+pub fn set_tablebase(tb: Tablebase) {
+    *TABLEBASE.write().unwrap() = Some(Arc::new(tb));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::tablebase::{Dtm, Table, Tablebase};
+
+    #[test]
+    fn kqk() {
+        let mut tb = Tablebase::new();
+        tb.insert(Table::generate("KQK", &tb).unwrap());
+        assert!(Table::generate("KPK", &tb).is_err());
+        let probe = |fen| tb.probe(&Board::from_fen(fen).unwrap()).unwrap();
+
+        // the longest win takes ten moves
+        assert_eq!(tb.get("KQK").unwrap().wins().1, 19);
+        assert_eq!(probe("k7/8/1K6/8/8/8/7Q/8 w - - 0 1"), Dtm::Win(1));
+        assert_eq!(probe("k6Q/8/1K6/8/8/8/8/8 b - - 0 1"), Dtm::Loss(0));
+        // stalemate, and the queen left hanging
+        assert_eq!(probe("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1"), Dtm::Draw);
+        assert_eq!(probe("8/8/8/8/8/8/1q6/K6k w - - 0 1"), Dtm::Draw);
+        // the same position with colors reversed
+        assert_eq!(probe("8/7q/8/8/8/1k6/8/K7 b - - 0 1"), Dtm::Win(1));
+
+        assert_eq!(tb.probe(&Board::initial()), None);
+        assert_eq!(
+            tb.probe(&Board::from_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1").unwrap()),
+            None
+        );
+    }
+}
@@ -1,10 +1,13 @@
 use crate::board::Board;
+use crate::board_alpha_beta::SearchResult;
 use crate::moves::Move;
+use crate::search_limits::SearchLimits;
 use crate::transposition_table::TranspositionTable;
 use crate::util::ChessError;
 
-use rand::{self, Rng};
+use std::io::Write;
 use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::{Arc, Condvar, Mutex, RwLock};
 use std::thread;
@@ -12,25 +15,40 @@ use std::time::Duration;
 
 type Worker = thread::JoinHandle<()>;
 
-struct Job {
-    mv: Move,
-    board: Board,
-    depth: usize,
-    table: Arc<TranspositionTable>,
+enum Job {
+    Search {
+        thread: usize,
+        board: Board,
+        limits: SearchLimits,
+        table: Arc<TranspositionTable>,
+        // the deepest finished iteration of any thread
+        published: Arc<Mutex<Option<SearchResult>>>,
+    },
+    Perft {
+        mv: Move,
+        board: Board,
+        depth: usize,
+    },
+    // the worker that takes this exits
+    Quit,
 }
 
 enum JobResult {
-    Done { mv: Move, val: isize },
+    Done(Result<SearchResult, ChessError>),
+    Perft { mv: Move, nodes: u64 },
 }
 
 pub struct Threadpool {
     handles: Vec<Worker>,
+    result_tx: Sender<JobResult>,
     result_chan: Arc<Mutex<Receiver<JobResult>>>,
     jobs: Arc<JobQueue>,
     abort: Arc<RwLock<bool>>,
     main_signal: Arc<Condvar>,
-    result_mutex: Arc<Mutex<Option<Result<(Move, isize), ChessError>>>>,
+    result_mutex: Arc<Mutex<Option<Result<SearchResult, ChessError>>>>,
     thinking: Arc<Mutex<bool>>,
+    table: Arc<TranspositionTable>,
+    post: Arc<AtomicBool>,
 }
 
 struct JobQueue {
@@ -78,20 +96,51 @@ impl JobQueue {
     }
 }
 
-fn worker(s: Sender<JobResult>, q: Arc<JobQueue>, abort: Arc<RwLock<bool>>) -> Worker {
+fn worker(
+    s: Sender<JobResult>,
+    q: Arc<JobQueue>,
+    abort: Arc<RwLock<bool>>,
+    post: Arc<AtomicBool>,
+) -> Worker {
     thread::spawn(move || {
         loop {
             // get next job
             match q.next_job() {
-                Job {
-                    mv,
-                    board,
-                    depth,
+                Job::Search {
+                    thread,
+                    mut board,
+                    limits,
                     table,
+                    published,
                 } => {
-                    let val = board.alpha_beta(depth, Some(abort.clone()), Some(table.clone()));
-                    s.send(JobResult::Done { mv: mv, val: val }).unwrap();
+                    // the first thread to finish an iteration publishes it, and its
+                    // thinking output goes straight to xboard
+                    let mut report = |res: &SearchResult| {
+                        let mut published = published.lock().unwrap();
+                        if published.as_ref().is_some_and(|p| p.depth >= res.depth) {
+                            return;
+                        }
+                        *published = Some(res.clone());
+                        if post.load(Ordering::SeqCst) {
+                            send!("{}", res.to_xboard_post());
+                        }
+                    };
+                    let res = board.search_thread(
+                        thread,
+                        &limits,
+                        Some(abort.clone()),
+                        Some(table),
+                        &mut report,
+                    );
+                    // one thread done is all done
+                    *abort.write().unwrap() = true;
+                    s.send(JobResult::Done(res)).unwrap();
+                }
+                Job::Perft { mv, board, depth } => {
+                    let nodes = board.perft(depth);
+                    s.send(JobResult::Perft { mv, nodes }).unwrap();
                 }
+                Job::Quit => break,
             }
         }
     })
@@ -103,23 +152,35 @@ impl Threadpool {
         let (result_tx, result_rx) = channel();
         let q = Arc::new(JobQueue::new());
         let abort = Arc::new(RwLock::new(false));
+        let post = Arc::new(AtomicBool::new(false));
 
         for _ in 0..nthreads {
-            hs.push(worker(result_tx.clone(), q.clone(), abort.clone()));
+            hs.push(worker(
+                result_tx.clone(),
+                q.clone(),
+                abort.clone(),
+                post.clone(),
+            ));
         }
 
         Threadpool {
             handles: hs,
+            result_tx,
             result_chan: Arc::new(Mutex::new(result_rx)),
             jobs: q,
             abort: abort,
             main_signal: main_signal,
             result_mutex: Arc::new(Mutex::new(None)),
             thinking: Arc::new(Mutex::new(false)),
+            table: Arc::new(TranspositionTable::new()),
+            post,
         }
     }
 
     pub fn close(&mut self) {
+        for _ in 0..self.handles.len() {
+            self.jobs.add_job(Job::Quit);
+        }
         loop {
             match self.handles.pop() {
                 Some(h) => h.join().unwrap(),
@@ -132,36 +193,64 @@ impl Threadpool {
         *self.abort.write().unwrap() = true;
     }
 
+    // whether to send thinking output while searching
+    pub fn set_post(&self, post: bool) {
+        self.post.store(post, Ordering::SeqCst);
+    }
+
+    // search with nthreads workers from now on, for xboard's cores command. the old
+    // workers are stopped and new ones started, so this must not be called while
+    // thinking.
+    pub fn set_threads(&mut self, nthreads: usize) {
+        self.close();
+        for _ in 0..nthreads.max(1) {
+            self.handles.push(worker(
+                self.result_tx.clone(),
+                self.jobs.clone(),
+                self.abort.clone(),
+                self.post.clone(),
+            ));
+        }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.handles.len()
+    }
+
+    // replace the transposition table with an empty one of mb megabytes. must not be
+    // called while thinking.
+    pub fn set_hash_size(&mut self, mb: usize) {
+        self.table = Arc::new(TranspositionTable::with_megabytes(mb));
+    }
+
     pub fn thinking(&self) -> bool {
         *self.thinking.lock().unwrap()
     }
 
-    pub fn find_best_move(&mut self, b: &Board, depth: usize) {
+    pub fn find_best_move(&mut self, b: &Board, limits: &SearchLimits) {
         *self.thinking.lock().unwrap() = true;
         *self.abort.write().unwrap() = false; // initialize abort flag
 
-        // initialize transposition table
-        let transposition_table = Arc::new(TranspositionTable::new(20));
-
-        let nmoves = match b.legal_moves() {
-            Ok(moves) => {
-                for mv in moves.iter() {
-                    self.jobs.add_job(Job {
-                        mv: *mv,
-                        board: b.make_move(mv).unwrap(),
-                        depth: depth,
-                        table: transposition_table.clone(),
-                    });
-                }
-                moves.len()
-            }
+        // the transposition table is kept between searches, older entries just age
+        self.table.new_search();
 
-            Err(e) => {
-                *self.result_mutex.lock().unwrap() = Some(Err(e));
-                *self.thinking.lock().unwrap() = false;
-                return;
-            }
+        // lazy smp: every thread searches the whole tree, sharing the table. threads
+        // racing for the table would make the search different every time.
+        let nthreads = if limits.deterministic {
+            self.handles.len().min(1)
+        } else {
+            self.handles.len()
         };
+        let published = Arc::new(Mutex::new(None));
+        for thread in 0..nthreads {
+            self.jobs.add_job(Job::Search {
+                thread,
+                board: b.clone(),
+                limits: limits.clone(),
+                table: self.table.clone(),
+                published: published.clone(),
+            });
+        }
 
         // bending over backwards to use a thread to clean up
         let rx = self.result_chan.clone();
@@ -169,31 +258,67 @@ impl Threadpool {
         let thinking = self.thinking.clone();
         let main_signal = self.main_signal.clone();
         thread::spawn(move || {
-            let mut rng = rand::thread_rng();
-            let mut best_score = isize::min_value();
-            let mut best_move = None;
-            for _ in 0..nmoves {
-                match rx.lock().unwrap().recv().unwrap() {
-                    JobResult::Done { mv, val } => {
-                        if val > best_score || (val == best_score && rng.gen()) {
-                            best_move = Some(mv);
-                            best_score = val;
-                        }
-                    }
+            let mut result = None;
+            let mut ndone = 0;
+            while ndone < nthreads {
+                if let JobResult::Done(res) = rx.lock().unwrap().recv().unwrap() {
+                    // every thread sees the same mate or stalemate; otherwise prefer the
+                    // deepest published result
+                    result = match (result, res) {
+                        (None, res) | (Some(Ok(_)), res @ Err(_)) => Some(res),
+                        (result, _) => result,
+                    };
+                    ndone += 1;
                 }
             }
-            *result_mutex.lock().unwrap() = Some(Ok((best_move.unwrap(), best_score)));
+            let result = match (published.lock().unwrap().take(), result) {
+                (Some(res), Some(Ok(_))) => Some(Ok(res)),
+                (_, result) => result,
+            };
+            *result_mutex.lock().unwrap() = result;
 
             *thinking.lock().unwrap() = false;
             main_signal.notify_all();
         });
     }
 
+    // forget what was learned in previous searches, e.g. when starting a new game
+    pub fn clear_table(&self) {
+        self.table.clear();
+    }
+
+    // perft_divide with the root moves split among the workers. blocks until done, and
+    // must not be called while thinking.
+    pub fn perft_divide(&self, b: &Board, depth: usize) -> Vec<(Move, u64)> {
+        let moves = b.legal_moves();
+        if moves.is_empty() || depth == 0 {
+            return Vec::new();
+        }
+        for mv in moves.iter() {
+            self.jobs.add_job(Job::Perft {
+                mv: *mv,
+                board: b.make_move(mv).unwrap(),
+                depth: depth - 1,
+            });
+        }
+        let mut res: Vec<(Move, u64)> = moves.iter().map(|&mv| (mv, 0)).collect();
+        let rx = self.result_chan.lock().unwrap();
+        let mut nresults = 0;
+        while nresults < moves.len() {
+            if let JobResult::Perft { mv, nodes } = rx.recv().unwrap() {
+                let ix = moves.iter().position(|&m| m == mv).unwrap();
+                res[ix].1 = nodes;
+                nresults += 1;
+            }
+        }
+        res
+    }
+
     pub fn has_result(&self) -> bool {
         self.result_mutex.lock().unwrap().is_some()
     }
 
-    pub fn take_result(&self) -> Option<Result<(Move, isize), ChessError>> {
+    pub fn take_result(&self) -> Option<Result<SearchResult, ChessError>> {
         mem::replace(&mut *self.result_mutex.lock().unwrap(), None)
     }
 
@@ -205,3 +330,93 @@ impl Threadpool {
         let _ = self.take_result();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::board::{Board, GameStatus};
+    use crate::moves::Move;
+    use crate::search_limits::SearchLimits;
+    use crate::threadpool::Threadpool;
+    use crate::util::ChessError;
+
+    use std::sync::{Arc, Condvar};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn lazy_smp_search() {
+        let mut pool = Threadpool::new(3, Arc::new(Condvar::new()));
+        let b = Board::from_fen("4k3/8/3P4/6Q1/8/8/8/K7 w - - 0 1").unwrap();
+        pool.find_best_move(&b, &SearchLimits::depth(3));
+        while !pool.has_result() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let res = pool.take_result().unwrap().unwrap();
+        assert_eq!(res.mv, mv!("Qg5-e7"));
+        assert_eq!(res.depth, 3);
+        assert!(!pool.thinking());
+
+        // all threads agree there is nothing to search
+        let b = Board::from_fen("4k3/4Q3/3P4/8/8/8/8/K7 b - - 0 1").unwrap();
+        pool.find_best_move(&b, &SearchLimits::depth(3));
+        while !pool.has_result() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        match pool.take_result() {
+            Some(Err(ChessError::GameOver(GameStatus::Checkmate))) => {}
+            _ => panic!("expected checkmate"),
+        }
+    }
+
+    #[test]
+    fn resized() {
+        let mut pool = Threadpool::new(3, Arc::new(Condvar::new()));
+        pool.set_threads(1);
+        assert_eq!(pool.threads(), 1);
+        pool.set_threads(4);
+        assert_eq!(pool.threads(), 4);
+        let b = Board::from_fen("4k3/8/3P4/6Q1/8/8/8/K7 w - - 0 1").unwrap();
+        pool.find_best_move(&b, &SearchLimits::depth(3));
+        while !pool.has_result() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(pool.take_result().unwrap().unwrap().mv, mv!("Qg5-e7"));
+        pool.close();
+        assert_eq!(pool.threads(), 0);
+    }
+
+    #[test]
+    fn deterministic_search() {
+        let mut pool = Threadpool::new(4, Arc::new(Condvar::new()));
+        let b = Board::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3")
+            .unwrap();
+        let limits = SearchLimits {
+            depth: Some(4),
+            margin: 50,
+            noise: 30,
+            noise_seed: 7,
+            deterministic: true,
+            ..SearchLimits::default()
+        };
+        let mut results = Vec::new();
+        for _ in 0..2 {
+            pool.clear_table();
+            pool.find_best_move(&b, &limits);
+            while !pool.has_result() {
+                thread::sleep(Duration::from_millis(10));
+            }
+            let res = pool.take_result().unwrap().unwrap();
+            assert!(res.near_best.is_empty());
+            results.push((res.mv, res.score, res.pv, res.nodes, res.stats));
+        }
+        assert_eq!(results[0], results[1]);
+    }
+
+    #[test]
+    fn threaded_perft() {
+        let b =
+            Board::from_fen("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8").unwrap();
+        let pool = Threadpool::new(3, Arc::new(Condvar::new()));
+        assert_eq!(pool.perft_divide(&b, 3), b.perft_divide(3));
+    }
+}
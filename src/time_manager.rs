@@ -0,0 +1,148 @@
+use std::time::Duration;
+
+// std's Instant panics in the browser, so there the time comes from javascript
+#[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+pub use std::time::Instant;
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Instant(f64);
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+impl Instant {
+    pub fn now() -> Instant {
+        Instant(js_sys::Date::now())
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_secs_f64((js_sys::Date::now() - self.0).max(0.0) / 1000.0)
+    }
+}
+
+// moves we expect to still have to make in sudden death
+const SUDDEN_DEATH_MOVES: usize = 30;
+
+// kept back for communication delays, in centiseconds
+const SAFETY_MARGIN: isize = 5;
+
+// how long a search may take. no new iteration is started after soft has passed, and
+// the search is stopped at hard.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Budget {
+    pub soft: Duration,
+    pub hard: Duration,
+}
+
+// turns the time control into a budget for each move. times are in centiseconds, like
+// the clock.
+#[derive(Debug, Clone)]
+pub struct TimeManager {
+    // moves in each time control, 0 for the whole game
+    moves_per_session: usize,
+    increment: isize,
+    // exact time per move, from st
+    move_time: Option<isize>,
+}
+
+fn from_csecs(csecs: isize) -> Duration {
+    Duration::from_millis(csecs.max(0) as u64 * 10)
+}
+
+impl TimeManager {
+    pub fn new() -> TimeManager {
+        TimeManager {
+            moves_per_session: 0,
+            increment: 0,
+            move_time: None,
+        }
+    }
+
+    // from xboard's level command
+    pub fn set_level(&mut self, moves_per_session: usize, increment: isize) {
+        self.moves_per_session = moves_per_session;
+        self.increment = increment;
+        self.move_time = None;
+    }
+
+    // from xboard's st command
+    pub fn set_move_time(&mut self, csecs: isize) {
+        self.move_time = Some(csecs);
+    }
+
+    // moves per session and increment, as set_level takes them
+    pub fn level(&self) -> (usize, isize) {
+        (self.moves_per_session, self.increment)
+    }
+
+    pub fn move_time(&self) -> Option<isize> {
+        self.move_time
+    }
+
+    // moves_made is how many moves we have made this game
+    pub fn budget(&self, time_left: isize, moves_made: usize) -> Budget {
+        if let Some(t) = self.move_time {
+            let t = from_csecs(t - SAFETY_MARGIN);
+            return Budget { soft: t, hard: t };
+        }
+        let moves_to_go = if self.moves_per_session > 0 {
+            self.moves_per_session - moves_made % self.moves_per_session
+        } else {
+            SUDDEN_DEATH_MOVES
+        };
+        let available = (time_left - SAFETY_MARGIN).max(0);
+        let soft = available / moves_to_go as isize + self.increment * 3 / 4;
+        // a bad iteration may overrun by a lot, but never by more than a third of what
+        // is left
+        let hard = (soft * 4).min(available / 3).max(soft.min(available));
+        Budget {
+            soft: from_csecs(soft.min(available)),
+            hard: from_csecs(hard),
+        }
+    }
+}
+
+impl Default for TimeManager {
+    fn default() -> TimeManager {
+        TimeManager::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::time_manager::{Budget, TimeManager};
+
+    use std::time::Duration;
+
+    #[test]
+    fn budgets() {
+        let mut tm = TimeManager::new();
+
+        // 5 minutes sudden death
+        let b = tm.budget(30000, 0);
+        assert_eq!(b.soft, Duration::from_millis(9990));
+        assert!(b.hard > b.soft && b.hard <= Duration::from_secs(100));
+
+        // 40 moves in 5 minutes: the last move before the control gets everything
+        tm.set_level(40, 0);
+        let early = tm.budget(30000, 0);
+        let last = tm.budget(1000, 39);
+        assert!(early.soft < Duration::from_secs(8));
+        assert_eq!(last.soft, Duration::from_millis(9950));
+        assert!(last.hard <= Duration::from_millis(9950));
+
+        // the increment is mostly spent
+        tm.set_level(0, 200);
+        assert_eq!(tm.budget(0, 10).soft, Duration::from_millis(0));
+        assert!(tm.budget(3000, 10).soft > Duration::from_secs(1));
+
+        tm.set_move_time(500);
+        let exact = Duration::from_millis(4950);
+        assert_eq!(
+            tm.budget(100, 3),
+            Budget {
+                soft: exact,
+                hard: exact
+            }
+        );
+    }
+}
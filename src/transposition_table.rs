@@ -1,29 +1,205 @@
 use crate::board::Board;
 
-use std::collections::HashMap;
-use std::sync::RwLock;
+use std::mem;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+// number of entries
+pub const DEFAULT_CAPACITY: usize = 1 << 18;
+
+// the size of a table of DEFAULT_CAPACITY
+pub const DEFAULT_MEGABYTES: usize = (DEFAULT_CAPACITY * mem::size_of::<Entry>()) >> 20;
+
+// what a stored score means, given that the search fails soft outside its window
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    // the real score is at least this
+    Lower,
+    // the real score is at most this
+    Upper,
+}
+
+// generations are stored modulo this
+const GENERATION_MASK: u64 = (1 << 46) - 1;
+
+// one slot of the table, shared by all search threads without locking. the check word is
+// the key xored with the other two, so an entry torn by a concurrent write doesn't
+// verify and is treated as missing.
+struct Entry {
+    check: AtomicU64,
+    score: AtomicU64,
+    // depth in bits 0-15, bound in 16-17, generation in 18-63
+    info: AtomicU64,
+}
+
+fn pack_info(depth: usize, bound: Bound, generation: usize) -> u64 {
+    let bound = match bound {
+        Bound::Exact => 1,
+        Bound::Lower => 2,
+        Bound::Upper => 3,
+    };
+    (depth as u64 & 0xffff) | (bound << 16) | ((generation as u64 & GENERATION_MASK) << 18)
+}
+
+// (depth, bound, generation), or None for an empty slot
+fn unpack_info(info: u64) -> Option<(usize, Bound, u64)> {
+    let bound = match (info >> 16) & 3 {
+        1 => Bound::Exact,
+        2 => Bound::Lower,
+        3 => Bound::Upper,
+        _ => return None,
+    };
+    Some(((info & 0xffff) as usize, bound, info >> 18))
+}
 
 pub struct TranspositionTable {
-    depth_tables: Vec<RwLock<HashMap<String, isize>>>,
+    entries: Vec<Entry>,
+    generation: AtomicUsize,
 }
 
 impl TranspositionTable {
-    pub fn new(max_depth: usize) -> TranspositionTable {
-        let mut tabs = Vec::with_capacity(max_depth);
-        for _ in 0..max_depth {
-            tabs.push(RwLock::new(HashMap::new()));
+    pub fn new() -> TranspositionTable {
+        TranspositionTable::with_capacity(DEFAULT_CAPACITY)
+    }
+
+    // as many entries as fit in mb megabytes, for xboard's memory command
+    pub fn with_megabytes(mb: usize) -> TranspositionTable {
+        TranspositionTable::with_capacity((mb << 20) / mem::size_of::<Entry>())
+    }
+
+    pub fn with_capacity(capacity: usize) -> TranspositionTable {
+        let mut entries = Vec::with_capacity(capacity);
+        for _ in 0..capacity.max(1) {
+            entries.push(Entry {
+                check: AtomicU64::new(0),
+                score: AtomicU64::new(0),
+                info: AtomicU64::new(0),
+            });
+        }
+        TranspositionTable {
+            entries,
+            generation: AtomicUsize::new(0),
+        }
+    }
+
+    // bump the generation: everything currently in the table becomes stale, and will be
+    // the first to be replaced
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    // forget everything, for when the game changes under us
+    pub fn clear(&self) {
+        for e in self.entries.iter() {
+            e.info.store(0, Ordering::Relaxed);
+            e.score.store(0, Ordering::Relaxed);
+            e.check.store(0, Ordering::Relaxed);
         }
-        TranspositionTable { depth_tables: tabs }
     }
 
-    pub fn get(&self, b: &Board, depth: usize) -> Option<isize> {
-        let ref map = self.depth_tables[depth].read().unwrap();
-        map.get(&b.to_fen()).map(|res| res.clone())
+    fn entry(&self, key: u64) -> &Entry {
+        &self.entries[(key % self.entries.len() as u64) as usize]
+    }
+
+    // depth is the number of plies searched below b. an entry searched deeper will do.
+    pub fn get(&self, b: &Board, depth: usize) -> Option<(isize, Bound)> {
+        let key = b.zobrist();
+        let e = self.entry(key);
+        let info = e.info.load(Ordering::Relaxed);
+        let score = e.score.load(Ordering::Relaxed);
+        if e.check.load(Ordering::Relaxed) ^ score ^ info != key {
+            return None;
+        }
+        match unpack_info(info) {
+            Some((d, bound, _)) if d >= depth => Some((score as i64 as isize, bound)),
+            _ => None,
+        }
+    }
+
+    // stored unless the slot holds a deeper result from this search
+    pub fn insert(&self, b: &Board, depth: usize, result: isize, bound: Bound) {
+        let key = b.zobrist();
+        let generation = self.generation();
+        let e = self.entry(key);
+        let old = e.info.load(Ordering::Relaxed);
+        if let Some((d, _, g)) = unpack_info(old) {
+            let same =
+                e.check.load(Ordering::Relaxed) ^ e.score.load(Ordering::Relaxed) ^ old == key;
+            if g == generation as u64 & GENERATION_MASK && d > depth && !same {
+                return;
+            }
+        }
+        let info = pack_info(depth, bound, generation);
+        let score = result as i64 as u64;
+        e.check.store(key ^ score ^ info, Ordering::Relaxed);
+        e.score.store(score, Ordering::Relaxed);
+        e.info.store(info, Ordering::Relaxed);
+    }
+
+    // number of occupied entries
+    pub fn len(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|e| unpack_info(e.info.load(Ordering::Relaxed)).is_some())
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> TranspositionTable {
+        TranspositionTable::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::transposition_table::{Bound, TranspositionTable};
+
+    #[test]
+    fn stale_entries_are_replaced() {
+        let tt = TranspositionTable::with_capacity(1);
+        let a = Board::initial();
+        let b = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+
+        tt.insert(&a, 5, 1, Bound::Exact);
+        assert_eq!(tt.get(&a, 5), Some((1, Bound::Exact)));
+        assert_eq!(tt.get(&a, 3), Some((1, Bound::Exact)));
+        assert_eq!(tt.get(&a, 6), None);
+        assert_eq!(tt.get(&b, 0), None);
+
+        // a shallower result from the same search doesn't push out a deeper one
+        tt.insert(&b, 2, 2, Bound::Lower);
+        assert_eq!(tt.get(&b, 2), None);
+        assert_eq!(tt.get(&a, 5), Some((1, Bound::Exact)));
+
+        // after a new search begins, the old entry makes way
+        tt.new_search();
+        tt.insert(&b, 2, -2, Bound::Lower);
+        assert_eq!(tt.get(&b, 2), Some((-2, Bound::Lower)));
+        assert_eq!(tt.get(&a, 5), None);
+        assert_eq!(tt.len(), 1);
+
+        tt.clear();
+        assert!(tt.is_empty());
     }
 
-    pub fn insert(&self, b: &Board, depth: usize, result: isize) {
-        let ref tab = self.depth_tables[depth];
-        let ref mut map = tab.write().unwrap();
-        map.insert(b.to_fen(), result);
+    #[test]
+    fn sized_in_megabytes() {
+        // 24 bytes an entry
+        assert_eq!(TranspositionTable::with_megabytes(3).capacity(), 1 << 17);
+        assert_eq!(TranspositionTable::with_megabytes(0).capacity(), 1);
     }
 }
@@ -1,32 +1,83 @@
-use std::fmt;
+use crate::board::GameStatus;
 
-#[derive(Debug)]
+use core::error::Error;
+use core::fmt;
+
+#[cfg(not(feature = "std"))]
+use crate::alloc_prelude::*;
+
+// text that isn't valid fen, algebraic notation, pgn or the like
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError(pub String);
+
+// a move that can't be made in the position it was given for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveError(pub String);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ChessError {
-    ParseError(String),
-    IllegalMove(String),
+    ParseError(ParseError),
+    IllegalMove(MoveError),
     BadBoardState(String),
-    Stalemate,
-    Checkmate,
+    // a move was wanted, but the game is over
+    GameOver(GameStatus),
 }
 
 impl ChessError {
     pub fn msg(&self) -> String {
         match *self {
-            ChessError::ParseError(ref s) => s.clone(),
-            ChessError::IllegalMove(ref s) => s.clone(),
+            ChessError::ParseError(ref e) => e.0.clone(),
+            ChessError::IllegalMove(ref e) => e.0.clone(),
             ChessError::BadBoardState(ref s) => s.clone(),
-            ChessError::Stalemate => "stalemate".to_string(),
-            ChessError::Checkmate => "checkmate".to_string(),
+            ChessError::GameOver(status) => format!("game over: {}", status),
         }
     }
 }
 
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 impl fmt::Display for ChessError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.msg())
     }
 }
 
+impl Error for ParseError {}
+
+impl Error for MoveError {}
+
+impl Error for ChessError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ChessError::ParseError(ref e) => Some(e),
+            ChessError::IllegalMove(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ParseError> for ChessError {
+    fn from(e: ParseError) -> ChessError {
+        ChessError::ParseError(e)
+    }
+}
+
+impl From<MoveError> for ChessError {
+    fn from(e: MoveError) -> ChessError {
+        ChessError::IllegalMove(e)
+    }
+}
+
 pub fn to_algebra(coord: usize) -> Result<String, ChessError> {
     if coord >= 64 {
         parse_error!("[to_algebra] coordinate out of bounds! got \"{}\"", coord);
@@ -43,7 +94,8 @@ pub fn to_algebra(coord: usize) -> Result<String, ChessError> {
 
 pub fn from_algebra(s: &str) -> Result<usize, ChessError> {
     let cs: Vec<char> = s.chars().collect();
-    if (cs[1] as usize) < '1' as usize
+    if cs.len() != 2
+        || (cs[1] as usize) < '1' as usize
         || cs[1] as usize > '8' as usize
         || (cs[0] as usize) < 'a' as usize
         || cs[0] as usize > 'h' as usize
@@ -57,8 +109,10 @@ pub fn from_algebra(s: &str) -> Result<usize, ChessError> {
 
 #[cfg(test)]
 mod tests {
-    use crate::util::{from_algebra, to_algebra};
+    use crate::board::{Board, GameStatus};
+    use crate::util::{from_algebra, to_algebra, ChessError, ParseError};
     use rand::{self, Rng};
+    use std::error::Error;
 
     #[test]
     fn coordinates_to_algebra() {
@@ -75,4 +129,25 @@ mod tests {
             assert_eq!(x, from_algebra(&to_algebra(x).unwrap()).unwrap());
         }
     }
+
+    #[test]
+    fn error_kinds() {
+        let e = Board::from_fen("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+            .err()
+            .unwrap();
+        assert!(matches!(e, ChessError::ParseError(_)));
+        assert!(e.source().unwrap().is::<ParseError>());
+        assert_eq!(e.source().unwrap().to_string(), e.to_string());
+
+        let mated = Board::from_fen("4k3/4Q3/3P4/8/8/8/8/K7 b - - 0 1").unwrap();
+        assert_eq!(mated.status(), GameStatus::Checkmate);
+        assert!(mated.legal_moves().is_empty());
+        let e = ChessError::GameOver(mated.status());
+        assert_eq!(e.to_string(), "game over: checkmate");
+        assert!(e.source().is_none());
+
+        let stalemated = Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(stalemated.status(), GameStatus::Stalemate);
+        assert_eq!(Board::initial().status(), GameStatus::InProgress);
+    }
 }
@@ -0,0 +1,139 @@
+// the javascript api, built as in the readme with the wasm feature and wasm-bindgen
+//
+//     const engine = new Engine();
+//     engine.setPosition("startpos", ["e2e4", "c5"]);
+//     engine.legalMoves();  // ["a2a3", "b1c3", ...]
+//     const mv = engine.search(1000, info => console.log(info.depth, info.score, info.pv));
+//
+// moves are in coordinate notation, like e2e4 or e7e8q, and san is accepted going in. the
+// search runs on the calling thread, so it belongs in a web worker.
+
+use crate::board::Board;
+use crate::board_alpha_beta::SearchResult;
+use crate::ffi::parse_move;
+use crate::moves::{coordinates, line_coordinates};
+use crate::search_limits::SearchLimits;
+use crate::transposition_table::TranspositionTable;
+use crate::util::ChessError;
+
+use js_sys::{Function, Object, Reflect};
+use std::sync::Arc;
+use std::time::Duration;
+use wasm_bindgen::prelude::*;
+
+fn js_error(e: ChessError) -> JsValue {
+    JsValue::from_str(&e.to_string())
+}
+
+#[wasm_bindgen]
+pub struct Engine {
+    board: Board,
+    table: Arc<TranspositionTable>,
+}
+
+#[wasm_bindgen]
+impl Engine {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Engine {
+        Engine {
+            board: Board::initial(),
+            table: Arc::new(TranspositionTable::new()),
+        }
+    }
+
+    // fen is a fen or "startpos", and moves are played from there
+    #[wasm_bindgen(js_name = setPosition)]
+    pub fn set_position(&mut self, fen: &str, moves: Vec<String>) -> Result<(), JsValue> {
+        let mut b = if fen == "startpos" {
+            Board::initial()
+        } else {
+            Board::from_fen(fen).map_err(js_error)?
+        };
+        for mv in moves {
+            let mv = parse_move(&b, &mv).map_err(js_error)?;
+            b = b.make_move(&mv).map_err(js_error)?;
+        }
+        self.board = b;
+        Ok(())
+    }
+
+    pub fn fen(&self) -> String {
+        self.board.to_fen()
+    }
+
+    #[wasm_bindgen(js_name = legalMoves)]
+    pub fn legal_moves(&self) -> Vec<String> {
+        let b = &self.board;
+        b.legal_moves()
+            .iter()
+            .map(|mv| coordinates(mv, b))
+            .collect()
+    }
+
+    #[wasm_bindgen(js_name = makeMove)]
+    pub fn make_move(&mut self, mv: &str) -> Result<(), JsValue> {
+        let mv = parse_move(&self.board, mv).map_err(js_error)?;
+        self.board = self.board.make_move(&mv).map_err(js_error)?;
+        Ok(())
+    }
+
+    // a legal move given in coordinate notation or san, written in san
+    pub fn san(&self, mv: &str) -> Result<String, JsValue> {
+        let mv = parse_move(&self.board, mv).map_err(js_error)?;
+        Ok(mv.to_san(&self.board))
+    }
+
+    // like "in progress", "check", "checkmate" or "draw by insufficient material"
+    pub fn status(&self) -> String {
+        self.board.status().to_string()
+    }
+
+    // forget everything learned in earlier searches, as for a new game
+    pub fn clear(&self) {
+        self.table.clear();
+    }
+
+    // the best move found in millis milliseconds. progress, if given, is called after
+    // each iteration with { depth, seldepth, score, nodes, time, nps, pv }, where score
+    // is in centipawns, or +-(100000 + n) for mate in n moves.
+    pub fn search(&mut self, millis: u32, progress: Option<Function>) -> Result<String, JsValue> {
+        let limits = SearchLimits::move_time(Duration::from_millis(millis as u64));
+        let root = self.board.clone();
+        let mut report = |res: &SearchResult| {
+            if let Some(progress) = &progress {
+                // a throwing callback shouldn't stop the search
+                let _ = progress.call1(&JsValue::NULL, &info(&root, res));
+            }
+        };
+        self.table.new_search();
+        let res = self
+            .board
+            .clone()
+            .search_with(&limits, None, Some(self.table.clone()), &mut report)
+            .map_err(js_error)?;
+        Ok(coordinates(&res.mv, &root))
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Engine {
+        Engine::new()
+    }
+}
+
+fn info(root: &Board, res: &SearchResult) -> JsValue {
+    let info = Object::new();
+    let fields = [
+        ("depth", JsValue::from(res.depth as u32 + 1)),
+        ("seldepth", JsValue::from(res.seldepth as u32)),
+        ("score", JsValue::from(res.xboard_score() as i32)),
+        ("nodes", JsValue::from(res.nodes as f64)),
+        ("time", JsValue::from(res.time.as_millis() as f64)),
+        ("nps", JsValue::from(res.nps() as f64)),
+        ("pv", JsValue::from(line_coordinates(root, &res.pv))),
+    ];
+    for (key, value) in fields.iter() {
+        let _ = Reflect::set(&info, &JsValue::from_str(key), value);
+    }
+    info.into()
+}
@@ -0,0 +1,225 @@
+use crate::board::Board;
+use crate::piece::{Color, Piece, PieceType};
+use crate::position::Pos;
+
+// random keys for each feature of a position, xored together to hash it. generated at
+// compile time from a fixed seed so hashes are the same on every run.
+struct Keys {
+    pieces: [[u64; 64]; 12],
+    black_to_move: u64,
+    castle_rights: [u64; 4],
+    en_passant_file: [u64; 8],
+}
+
+// splitmix64: returns the next state and its output
+const fn splitmix(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    (state, z ^ (z >> 31))
+}
+
+const fn generate_keys() -> Keys {
+    let mut keys = Keys {
+        pieces: [[0; 64]; 12],
+        black_to_move: 0,
+        castle_rights: [0; 4],
+        en_passant_file: [0; 8],
+    };
+    let mut state = 0x636f_6d62_7573_7469; // "combusti"
+    let mut key;
+
+    let mut p = 0;
+    while p < 12 {
+        let mut ix = 0;
+        while ix < 64 {
+            (state, key) = splitmix(state);
+            keys.pieces[p][ix] = key;
+            ix += 1;
+        }
+        p += 1;
+    }
+    (state, key) = splitmix(state);
+    keys.black_to_move = key;
+    let mut i = 0;
+    while i < 4 {
+        (state, key) = splitmix(state);
+        keys.castle_rights[i] = key;
+        i += 1;
+    }
+    let mut i = 0;
+    while i < 8 {
+        (state, key) = splitmix(state);
+        keys.en_passant_file[i] = key;
+        i += 1;
+    }
+    keys
+}
+
+const KEYS: Keys = generate_keys();
+
+fn piece_index(p: Piece) -> usize {
+    let kind = match p.kind {
+        PieceType::Pawn => 0,
+        PieceType::Bishop => 1,
+        PieceType::Knight => 2,
+        PieceType::Rook => 3,
+        PieceType::Queen => 4,
+        PieceType::King => 5,
+    };
+    match p.color {
+        Color::White => kind,
+        Color::Black => kind + 6,
+    }
+}
+
+fn piece_key(p: Option<Piece>, pos: Pos) -> u64 {
+    p.map_or(0, |p| KEYS.pieces[piece_index(p)][pos.index()])
+}
+
+impl Board {
+    // zobrist hash of everything that affects which moves follow: the pieces, side to
+    // move, castling rights and en passant target. the move counters are left out. it is
+    // kept up to date as moves are made, so this is free.
+    pub fn zobrist(&self) -> u64 {
+        self.key
+    }
+
+    // the hash worked out from scratch, which zobrist always agrees with
+    pub fn compute_zobrist(&self) -> u64 {
+        let mut h = self.state_key();
+        for (pos, p) in self.iter_pieces() {
+            h ^= piece_key(Some(p), pos);
+        }
+        h
+    }
+
+    // for boards built by setting their fields
+    pub(crate) fn rehash(&mut self) {
+        self.key = self.compute_zobrist();
+    }
+
+    // the part of the hash that isn't the pieces. moves xor out this before and in this
+    // after, since it is quicker than following each change to the rights.
+    pub(crate) fn state_key(&self) -> u64 {
+        let mut h = 0;
+        if self.color_to_move == Color::Black {
+            h ^= KEYS.black_to_move;
+        }
+        for (i, &right) in self.castle_rights.iter().enumerate() {
+            if right {
+                h ^= KEYS.castle_rights[i];
+            }
+        }
+        if let Some(ep) = self.en_passant_target {
+            h ^= KEYS.en_passant_file[ep.file().index()];
+        }
+        h
+    }
+
+    // puts p on pos, or empties it, updating the hash. returns what was there.
+    pub(crate) fn set_piece(&mut self, pos: Pos, p: Option<Piece>) -> Option<Piece> {
+        let old = self.board.get(pos);
+        self.key ^= piece_key(old, pos) ^ piece_key(p, pos);
+        self.board.set(pos, p);
+        old
+    }
+
+    pub(crate) fn take_piece(&mut self, pos: Pos) -> Option<Piece> {
+        self.set_piece(pos, None)
+    }
+
+    // moves whatever is on from to to, returning what was on to
+    pub(crate) fn move_piece(&mut self, from: Pos, to: Pos) -> Option<Piece> {
+        let p = self.take_piece(from);
+        self.set_piece(to, p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::moves::Move;
+    use crate::piece::Color;
+
+    #[test]
+    fn zobrist_follows_position() {
+        let b = Board::initial();
+        let after = b.make_move(&mv!("Nb1-c3")).unwrap();
+        assert_ne!(b.zobrist(), after.zobrist());
+
+        // the same position reached by a different route hashes the same
+        let a = after
+            .make_move(&mv!("Ng8-f6"))
+            .unwrap()
+            .make_move(&mv!("Nc3-b1"))
+            .unwrap()
+            .make_move(&mv!("Nf6-g8"))
+            .unwrap();
+        assert_eq!(a.zobrist(), b.zobrist());
+
+        // side to move and castling rights count
+        let w = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let bl = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1").unwrap();
+        let nc = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w Qkq - 0 1").unwrap();
+        assert_ne!(w.zobrist(), bl.zobrist());
+        assert_ne!(w.zobrist(), nc.zobrist());
+    }
+
+    #[test]
+    fn incremental_keys() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        ]
+        .iter()
+        {
+            let mut b = Board::from_fen(fen).unwrap();
+            assert_eq!(b.zobrist(), b.compute_zobrist());
+            for ply in 0..60 {
+                let moves = b.legal_moves();
+                if moves.is_empty() {
+                    break;
+                }
+                // every move from here, made and taken back
+                for mv in moves.iter() {
+                    let after = b.make_move(mv).unwrap();
+                    assert_eq!(after.zobrist(), after.compute_zobrist(), "{}", mv);
+                    let mut c = b.clone();
+                    let undo = c.do_move(mv);
+                    assert_eq!(c.zobrist(), after.zobrist(), "{}", mv);
+                    c.undo_move(undo);
+                    assert_eq!(c.zobrist(), b.zobrist(), "{}", mv);
+                }
+                let mut c = b.clone();
+                let ep = c.do_null_move();
+                assert_eq!(c.zobrist(), c.compute_zobrist());
+                c.undo_null_move(ep);
+                assert_eq!(c.zobrist(), b.zobrist());
+
+                b = b.make_move(&moves[(ply * 7 + 3) % moves.len()]).unwrap();
+            }
+        }
+        // and so do flipped and mirrored boards
+        let fen = "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3";
+        let b = Board::from_fen(fen).unwrap();
+        for c in [b.flipped(), b.mirrored()].iter() {
+            assert_eq!(c.zobrist(), c.compute_zobrist());
+        }
+    }
+
+    #[test]
+    fn setters_keep_the_key() {
+        let mut b = Board::from_fen("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+        b.set_castle_rights([false, true, true, false]);
+        b.set_en_passant_target(None);
+        b.set_color_to_move(Color::Black);
+        let want = Board::from_fen("r3k2r/8/8/3pP3/8/8/8/R3K2R b Qk - 0 1").unwrap();
+        assert_eq!(b.zobrist(), b.compute_zobrist());
+        assert_eq!(b.zobrist(), want.zobrist());
+        assert_eq!(b, want);
+    }
+}